@@ -1819,6 +1819,19 @@ class ApiGateway {
     }
   }
 
+  /**
+   * Cancels queries issued by the SQL API request, used when the SQL client
+   * cancels the query or disconnects before the result is ready.
+   */
+  public async sqlApiCancel({ context }: { context: RequestContext }) {
+    const adapterApi = await this.getAdapterApi(context);
+    await adapterApi.cancelQueriesByRequestId(context.requestId);
+
+    this.log({
+      type: 'SQL API Query Cancelled',
+    }, context);
+  }
+
   public async subscribeQueueEvents({ context, signedWithPlaygroundAuthSecret, connectionId, res }) {
     if (this.enforceSecurityChecks && !signedWithPlaygroundAuthSecret) {
       throw new CubejsHandlerError(
@@ -145,6 +145,11 @@ export class SQLServer {
           }
         });
       },
+      sqlApiCancel: async ({ request, session }) => {
+        const context = await contextByRequest(request, session);
+
+        await this.apiGateway.sqlApiCancel({ context });
+      },
       sql: async ({ request, session, query, memberToAlias, expressionParams }) => {
         const context = await contextByRequest(request, session);
 
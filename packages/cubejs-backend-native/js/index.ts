@@ -64,6 +64,12 @@ export interface SqlApiLoadPayload {
   streaming: boolean,
 }
 
+export interface SqlApiCancelPayload {
+  request: Request<LoadRequestMeta>,
+  session: SessionContext,
+  queryKey: any,
+}
+
 export interface LogLoadEventPayload {
   request: Request<LoadRequestMeta>,
   session: SessionContext,
@@ -92,6 +98,7 @@ export type SQLInterfaceOptions = {
   meta: (payload: MetaPayload) => unknown | Promise<unknown>,
   stream: (payload: LoadPayload) => unknown | Promise<unknown>,
   sqlApiLoad: (payload: SqlApiLoadPayload) => unknown | Promise<unknown>,
+  sqlApiCancel: (payload: SqlApiCancelPayload) => unknown | Promise<unknown>,
   logLoadEvent: (payload: LogLoadEventPayload) => unknown | Promise<unknown>,
   sqlGenerators: (paramsJson: string) => unknown | Promise<unknown>,
   canSwitchUserForSession: (payload: CanSwitchUserPayload) => unknown | Promise<unknown>,
@@ -304,6 +311,10 @@ export const registerInterface = async (options: SQLInterfaceOptions): Promise<S
     throw new Error('options.sqlApiLoad must be a function');
   }
 
+  if (typeof options.sqlApiCancel !== 'function') {
+    throw new Error('options.sqlApiCancel must be a function');
+  }
+
   if (typeof options.sqlGenerators !== 'function') {
     throw new Error('options.sqlGenerators must be a function');
   }
@@ -321,6 +332,7 @@ export const registerInterface = async (options: SQLInterfaceOptions): Promise<S
     meta: wrapNativeFunctionWithChannelCallback(options.meta),
     stream: wrapNativeFunctionWithStream(options.stream),
     sqlApiLoad: wrapNativeFunctionWithStream(options.sqlApiLoad),
+    sqlApiCancel: wrapRawNativeFunctionWithChannelCallback(options.sqlApiCancel),
     sqlGenerators: wrapRawNativeFunctionWithChannelCallback(options.sqlGenerators),
     logLoadEvent: wrapRawNativeFunctionWithChannelCallback(options.logLoadEvent),
     canSwitchUserForSession: wrapRawNativeFunctionWithChannelCallback(options.canSwitchUserForSession),
@@ -113,6 +113,9 @@ fn register_interface(mut cx: FunctionContext) -> JsResult<JsPromise> {
     let transport_sql_api_load = options
         .get::<JsFunction, _, _>(&mut cx, "sqlApiLoad")?
         .root(&mut cx);
+    let transport_sql_api_cancel = options
+        .get::<JsFunction, _, _>(&mut cx, "sqlApiCancel")?
+        .root(&mut cx);
     let transport_sql = options
         .get::<JsFunction, _, _>(&mut cx, "sql")?
         .root(&mut cx);
@@ -162,6 +165,7 @@ fn register_interface(mut cx: FunctionContext) -> JsResult<JsPromise> {
     let transport_service = NodeBridgeTransport::new(
         cx.channel(),
         transport_sql_api_load,
+        transport_sql_api_cancel,
         transport_sql,
         transport_meta,
         transport_log_load_event,
@@ -31,6 +31,7 @@ use crate::{
 pub struct NodeBridgeTransport {
     channel: Arc<Channel>,
     on_sql_api_load: Arc<Root<JsFunction>>,
+    on_sql_api_cancel: Arc<Root<JsFunction>>,
     on_sql: Arc<Root<JsFunction>>,
     on_meta: Arc<Root<JsFunction>>,
     log_load_event: Arc<Root<JsFunction>>,
@@ -42,6 +43,7 @@ impl NodeBridgeTransport {
     pub fn new(
         channel: Channel,
         on_sql_api_load: Root<JsFunction>,
+        on_sql_api_cancel: Root<JsFunction>,
         on_sql: Root<JsFunction>,
         on_meta: Root<JsFunction>,
         log_load_event: Root<JsFunction>,
@@ -51,6 +53,7 @@ impl NodeBridgeTransport {
         Self {
             channel: Arc::new(channel),
             on_sql_api_load: Arc::new(on_sql_api_load),
+            on_sql_api_cancel: Arc::new(on_sql_api_cancel),
             on_sql: Arc::new(on_sql),
             on_meta: Arc::new(on_meta),
             log_load_event: Arc::new(log_load_event),
@@ -90,6 +93,14 @@ struct LoadRequest {
     query_key: Option<serde_json::Value>,
 }
 
+#[derive(Debug, Serialize)]
+struct CancelRequest {
+    request: TransportRequest,
+    session: SessionContext,
+    #[serde(rename = "queryKey", skip_serializing_if = "Option::is_none")]
+    query_key: Option<serde_json::Value>,
+}
+
 #[derive(Debug, Serialize)]
 struct LogEvent {
     request: TransportRequest,
@@ -481,6 +492,49 @@ impl TransportService for NodeBridgeTransport {
         }
     }
 
+    async fn cancel_load(
+        &self,
+        span_id: Option<Arc<SpanId>>,
+        ctx: AuthContextRef,
+        meta_fields: LoadRequestMeta,
+    ) -> Result<(), CubeError> {
+        // Without span there is no request id to match the queries on the Cube side
+        let span_id = match span_id {
+            Some(span_id) => span_id,
+            None => return Ok(()),
+        };
+
+        trace!("[transport] Cancel -> {}", span_id.span_id);
+
+        let native_auth = ctx
+            .as_any()
+            .downcast_ref::<NativeAuthContext>()
+            .expect("Unable to cast AuthContext to NativeAuthContext");
+
+        call_raw_js_with_channel_as_callback(
+            self.channel.clone(),
+            self.on_sql_api_cancel.clone(),
+            CancelRequest {
+                request: TransportRequest {
+                    id: format!("{}-span-1", span_id.span_id),
+                    meta: Some(meta_fields),
+                },
+                session: SessionContext {
+                    user: native_auth.user.clone(),
+                    superuser: native_auth.superuser,
+                    security_context: native_auth.security_context.clone(),
+                },
+                query_key: Some(span_id.query_key.clone()),
+            },
+            Box::new(|cx, v| match NodeObjSerializer::serialize(&v, cx) {
+                Ok(res) => Ok(res),
+                Err(e) => cx.throw_error(format!("Can't serialize to node obj: {}", e)),
+            }),
+            Box::new(move |_, _| Ok(())),
+        )
+        .await
+    }
+
     async fn can_switch_user_for_session(
         &self,
         ctx: AuthContextRef,
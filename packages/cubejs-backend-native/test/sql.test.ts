@@ -153,6 +153,7 @@ describe('SQLInterface', () => {
       checkAuth,
       load,
       sqlApiLoad,
+      sqlApiCancel: (_payload) => true,
       sql,
       meta,
       stream,
@@ -0,0 +1,60 @@
+// automatically generated by the FlatBuffers compiler, do not modify
+
+import * as flatbuffers from 'flatbuffers';
+
+export class HttpCacheEvent {
+  bb: flatbuffers.ByteBuffer|null = null;
+  bb_pos = 0;
+  __init(i:number, bb:flatbuffers.ByteBuffer):HttpCacheEvent {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+}
+
+static getRootAsHttpCacheEvent(bb:flatbuffers.ByteBuffer, obj?:HttpCacheEvent):HttpCacheEvent {
+  return (obj || new HttpCacheEvent()).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+}
+
+static getSizePrefixedRootAsHttpCacheEvent(bb:flatbuffers.ByteBuffer, obj?:HttpCacheEvent):HttpCacheEvent {
+  bb.setPosition(bb.position() + flatbuffers.SIZE_PREFIX_LENGTH);
+  return (obj || new HttpCacheEvent()).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+}
+
+event():string|null
+event(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+event(optionalEncoding?:any):string|Uint8Array|null {
+  const offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+}
+
+path():string|null
+path(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+path(optionalEncoding?:any):string|Uint8Array|null {
+  const offset = this.bb!.__offset(this.bb_pos, 6);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+}
+
+static startHttpCacheEvent(builder:flatbuffers.Builder) {
+  builder.startObject(2);
+}
+
+static addEvent(builder:flatbuffers.Builder, eventOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(0, eventOffset, 0);
+}
+
+static addPath(builder:flatbuffers.Builder, pathOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(1, pathOffset, 0);
+}
+
+static endHttpCacheEvent(builder:flatbuffers.Builder):flatbuffers.Offset {
+  const offset = builder.endObject();
+  return offset;
+}
+
+static createHttpCacheEvent(builder:flatbuffers.Builder, eventOffset:flatbuffers.Offset, pathOffset:flatbuffers.Offset):flatbuffers.Offset {
+  HttpCacheEvent.startHttpCacheEvent(builder);
+  HttpCacheEvent.addEvent(builder, eventOffset);
+  HttpCacheEvent.addPath(builder, pathOffset);
+  return HttpCacheEvent.endHttpCacheEvent(builder);
+}
+}
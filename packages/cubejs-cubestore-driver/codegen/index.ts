@@ -1,5 +1,6 @@
 // automatically generated by the FlatBuffers compiler, do not modify
 
+export { HttpCacheEvent } from './http-cache-event.js';
 export { HttpColumnValue } from './http-column-value.js';
 export { HttpCommand } from './http-command.js';
 export { HttpError } from './http-error.js';
@@ -483,6 +483,20 @@ export class QueryCache {
     }
   }
 
+  /**
+   * Cancel queued and running queries which were issued by the request with the given `requestId`.
+   */
+  public async cancelQueriesByRequestId(requestId: string) {
+    return Promise.all(Object.values(this.queue).map(async (queue) => {
+      const queries = <any[]> await queue.getQueries();
+      return Promise.all(
+        queries
+          .filter((query) => query.requestId === requestId)
+          .map((query) => queue.cancelQuery(query.queryKey, null))
+      );
+    }));
+  }
+
   public async getQueue(dataSource = 'default') {
     if (!this.queue[dataSource]) {
       const queueOptions = await this.options.queueOptions(dataSource);
@@ -470,6 +470,10 @@ export class QueryOrchestrator {
     return this.preAggregations.cancelQueriesFromQueue(queryKeys, dataSource);
   }
 
+  public async cancelQueriesByRequestId(requestId: string) {
+    return this.queryCache.cancelQueriesByRequestId(requestId);
+  }
+
   public async subscribeQueueEvents(id, callback) {
     return this.getQueueEventsBus().subscribe(id, callback);
   }
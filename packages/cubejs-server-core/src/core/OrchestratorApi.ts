@@ -297,6 +297,10 @@ export class OrchestratorApi {
     return this.orchestrator.cancelPreAggregationQueriesFromQueue(queryKeys, dataSource);
   }
 
+  public async cancelQueriesByRequestId(requestId: string) {
+    return this.orchestrator.cancelQueriesByRequestId(requestId);
+  }
+
   public async subscribeQueueEvents(id, callback) {
     return this.orchestrator.subscribeQueueEvents(id, callback);
   }
@@ -618,6 +618,12 @@ impl ExecutionPlan for CubeScanExecutionPlan {
         );
 
         if stream_mode {
+            let mut cancel_guard = CubeScanCancelGuard::new(
+                self.transport.clone(),
+                self.span_id.clone(),
+                self.auth_context.clone(),
+                meta.clone(),
+            );
             let result = self
                 .transport
                 .load_stream(
@@ -630,8 +636,12 @@ impl ExecutionPlan for CubeScanExecutionPlan {
                     self.member_fields.clone(),
                 )
                 .await;
-            let stream = result.map_err(|err| DataFusionError::Execution(err.to_string()))?;
-            let main_stream = CubeScanMemoryStream::new(stream);
+            let stream = result.map_err(|err| {
+                cancel_guard.complete();
+
+                DataFusionError::Execution(err.to_string())
+            })?;
+            let main_stream = CubeScanMemoryStream::new(stream, cancel_guard);
 
             return Ok(Box::pin(CubeScanStreamRouter::new(
                 Some(main_stream),
@@ -746,20 +756,88 @@ impl CubeScanOneShotStream {
 
 struct CubeScanMemoryStream {
     receiver: CubeStreamReceiver,
+    cancel_guard: CubeScanCancelGuard,
 }
 
 impl CubeScanMemoryStream {
-    pub fn new(receiver: CubeStreamReceiver) -> Self {
-        Self { receiver }
+    pub fn new(receiver: CubeStreamReceiver, cancel_guard: CubeScanCancelGuard) -> Self {
+        Self {
+            receiver,
+            cancel_guard,
+        }
     }
 
     fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<ArrowResult<RecordBatch>>> {
-        self.receiver.poll_recv(cx).map(|res| match res {
+        let next = self.receiver.poll_recv(cx).map(|res| match res {
             Some(Some(Ok(chunk))) => Some(Ok(chunk)),
             Some(Some(Err(err))) => Some(Err(ArrowError::ComputeError(err.to_string()))),
             Some(None) => None,
             None => None,
-        })
+        });
+        // Stream is finished or failed, there is nothing to cancel in Cube
+        if let Poll::Ready(None | Some(Err(_))) = &next {
+            self.cancel_guard.complete();
+        }
+
+        next
+    }
+}
+
+/// Cancels the load in Cube when it's dropped before completion. It happens when the query
+/// was cancelled by the client (or KILL QUERY) or the connection was closed while waiting.
+struct CubeScanCancelGuard {
+    transport: Arc<dyn TransportService>,
+    span_id: Option<Arc<SpanId>>,
+    auth_context: AuthContextRef,
+    meta: LoadRequestMeta,
+    completed: bool,
+}
+
+impl CubeScanCancelGuard {
+    pub fn new(
+        transport: Arc<dyn TransportService>,
+        span_id: Option<Arc<SpanId>>,
+        auth_context: AuthContextRef,
+        meta: LoadRequestMeta,
+    ) -> Self {
+        Self {
+            transport,
+            span_id,
+            auth_context,
+            meta,
+            completed: false,
+        }
+    }
+
+    pub fn complete(&mut self) {
+        self.completed = true;
+    }
+}
+
+impl Drop for CubeScanCancelGuard {
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+
+        // Drop can't be async, cancellation is sent in the background
+        let handle = match tokio::runtime::Handle::try_current() {
+            Ok(handle) => handle,
+            Err(_) => {
+                warn!("Unable to cancel Cube load: no tokio runtime");
+                return;
+            }
+        };
+
+        let transport = self.transport.clone();
+        let span_id = self.span_id.clone();
+        let auth_context = self.auth_context.clone();
+        let meta = self.meta.clone();
+        handle.spawn(async move {
+            if let Err(err) = transport.cancel_load(span_id, auth_context, meta).await {
+                warn!("Unable to cancel Cube load: {}", err);
+            }
+        });
     }
 }
 
@@ -856,9 +934,17 @@ async fn load_data(
             data,
         )
     } else {
+        let mut cancel_guard = CubeScanCancelGuard::new(
+            transport.clone(),
+            span_id.clone(),
+            auth_context.clone(),
+            meta.clone(),
+        );
         let result = transport
             .load(span_id, request, sql_query, auth_context, meta)
             .await;
+        cancel_guard.complete();
+
         let mut response = result.map_err(|err| ArrowError::ComputeError(err.to_string()))?;
         if let Some(data) = response.results.pop() {
             match (options.max_records, data.data.len()) {
@@ -1314,6 +1400,15 @@ mod tests {
                 panic!("It's a fake transport");
             }
 
+            async fn cancel_load(
+                &self,
+                _span_id: Option<Arc<SpanId>>,
+                _ctx: AuthContextRef,
+                _meta_fields: LoadRequestMeta,
+            ) -> Result<(), CubeError> {
+                Ok(())
+            }
+
             async fn can_switch_user_for_session(
                 &self,
                 _ctx: AuthContextRef,
@@ -1468,4 +1563,116 @@ mod tests {
             .unwrap()
         )
     }
+
+    #[derive(Debug)]
+    struct CancelRecordingTransport {
+        cancelled: tokio::sync::mpsc::UnboundedSender<Option<Arc<SpanId>>>,
+    }
+
+    #[async_trait]
+    impl TransportService for CancelRecordingTransport {
+        async fn meta(&self, _ctx: AuthContextRef) -> Result<Arc<MetaContext>, CubeError> {
+            panic!("It's a fake transport");
+        }
+
+        async fn sql(
+            &self,
+            _span_id: Option<Arc<SpanId>>,
+            _query: V1LoadRequestQuery,
+            _ctx: AuthContextRef,
+            _meta_fields: LoadRequestMeta,
+            _member_to_alias: Option<HashMap<String, String>>,
+            _expression_params: Option<Vec<Option<String>>>,
+        ) -> Result<SqlResponse, CubeError> {
+            panic!("It's a fake transport");
+        }
+
+        async fn load(
+            &self,
+            _span_id: Option<Arc<SpanId>>,
+            _query: V1LoadRequestQuery,
+            _sql_query: Option<SqlQuery>,
+            _ctx: AuthContextRef,
+            _meta_fields: LoadRequestMeta,
+        ) -> Result<V1LoadResponse, CubeError> {
+            panic!("It's a fake transport");
+        }
+
+        async fn load_stream(
+            &self,
+            _span_id: Option<Arc<SpanId>>,
+            _query: V1LoadRequestQuery,
+            _sql_query: Option<SqlQuery>,
+            _ctx: AuthContextRef,
+            _meta_fields: LoadRequestMeta,
+            _schema: SchemaRef,
+            _member_fields: Vec<MemberField>,
+        ) -> Result<CubeStreamReceiver, CubeError> {
+            panic!("It's a fake transport");
+        }
+
+        async fn cancel_load(
+            &self,
+            span_id: Option<Arc<SpanId>>,
+            _ctx: AuthContextRef,
+            _meta_fields: LoadRequestMeta,
+        ) -> Result<(), CubeError> {
+            self.cancelled.send(span_id).unwrap();
+            Ok(())
+        }
+
+        async fn can_switch_user_for_session(
+            &self,
+            _ctx: AuthContextRef,
+            _to_user: String,
+        ) -> Result<bool, CubeError> {
+            panic!("It's a fake transport");
+        }
+
+        async fn log_load_state(
+            &self,
+            _span_id: Option<Arc<SpanId>>,
+            _ctx: AuthContextRef,
+            _meta_fields: LoadRequestMeta,
+            _event: String,
+            _properties: serde_json::Value,
+        ) -> Result<(), CubeError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancel_guard_cancels_load_on_drop() {
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let transport: Arc<dyn TransportService> =
+            Arc::new(CancelRecordingTransport { cancelled: sender });
+        let auth_context: AuthContextRef = Arc::new(HttpAuthContext {
+            access_token: "access_token".to_string(),
+            base_path: "base_path".to_string(),
+        });
+        let guard = |span: &str| {
+            CubeScanCancelGuard::new(
+                transport.clone(),
+                Some(Arc::new(SpanId::new(span.to_string(), json!({})))),
+                auth_context.clone(),
+                get_test_load_meta(DatabaseProtocol::PostgreSQL),
+            )
+        };
+
+        // Completed loads are not cancelled
+        let mut completed = guard("completed");
+        completed.complete();
+        drop(completed);
+
+        drop(guard("dropped"));
+
+        let span_id = tokio::time::timeout(std::time::Duration::from_secs(5), receiver.recv())
+            .await
+            .expect("cancel_load must be called on drop")
+            .unwrap();
+        assert_eq!(span_id.unwrap().span_id, "dropped");
+
+        drop(transport);
+        assert!(receiver.recv().await.is_none());
+    }
 }
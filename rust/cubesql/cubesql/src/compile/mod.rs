@@ -464,10 +464,9 @@ impl QueryPlanner {
                     Box::new(dataframe::DataFrame::new(vec![], vec![])),
                 ))
             }
-            (ast::Statement::Kill { .. }, DatabaseProtocol::MySQL) => Ok(QueryPlan::MetaOk(
-                StatusFlags::empty(),
-                CommandCompletion::Select(0),
-            )),
+            (ast::Statement::Kill { id, .. }, DatabaseProtocol::MySQL) => {
+                self.kill_to_plan(*id).await
+            }
             (ast::Statement::SetRole { role_name, .. }, _) => self.set_role_to_plan(role_name),
             (ast::Statement::SetVariable { key_values }, _) => {
                 self.set_variable_to_plan(&key_values).await
@@ -1051,6 +1050,30 @@ WHERE `TABLE_SCHEMA` = '{}'",
         ))
     }
 
+    async fn kill_to_plan(&self, id: u64) -> Result<QueryPlan, CompilationError> {
+        let session = match u32::try_from(id) {
+            Ok(connection_id) => self.session_manager.get_session(connection_id).await,
+            Err(_) => None,
+        }
+        .ok_or_else(|| CompilationError::user(format!("Unknown thread id: {}", id)))?;
+
+        if session.state.user() != self.state.user() {
+            return Err(CompilationError::user(format!(
+                "You are not owner of thread {}",
+                id
+            )));
+        }
+
+        // Connection can't be closed from another session, both KILL and KILL QUERY
+        // cancel the running query
+        session.state.cancel_query();
+
+        Ok(QueryPlan::MetaOk(
+            StatusFlags::empty(),
+            CommandCompletion::Select(0),
+        ))
+    }
+
     fn set_role_to_plan(
         &self,
         role_name: &Option<ast::Ident>,
@@ -7042,6 +7065,30 @@ limit
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_kill_query() {
+        let meta_context = get_test_tenant_ctx();
+        let session = get_test_session(DatabaseProtocol::MySQL, meta_context.clone()).await;
+        let cancel = session.state.begin_query("SELECT 1".to_string());
+
+        let query = format!("KILL QUERY {}", session.state.connection_id);
+        convert_sql_to_cube_query(&query, meta_context.clone(), session.clone())
+            .await
+            .expect("KILL QUERY must be planned");
+        assert!(cancel.is_cancelled());
+        assert_eq!(session.state.current_query(), None);
+
+        let res =
+            convert_sql_to_cube_query(&"KILL QUERY 100500".to_string(), meta_context, session)
+                .await;
+        assert_eq!(
+            res.err(),
+            Some(CompilationError::user(
+                "Unknown thread id: 100500".to_string()
+            ))
+        );
+    }
+
     #[tokio::test]
     async fn test_show_warnings() -> Result<(), CubeError> {
         insta::assert_snapshot!(
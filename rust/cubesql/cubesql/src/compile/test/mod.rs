@@ -480,6 +480,15 @@ pub fn get_test_transport(meta_context: Arc<MetaContext>) -> Arc<dyn TransportSe
             panic!("It's a fake transport");
         }
 
+        async fn cancel_load(
+            &self,
+            _span_id: Option<Arc<SpanId>>,
+            _ctx: AuthContextRef,
+            _meta_fields: LoadRequestMeta,
+        ) -> Result<(), CubeError> {
+            Ok(())
+        }
+
         async fn can_switch_user_for_session(
             &self,
             _ctx: AuthContextRef,
@@ -105,6 +105,30 @@ impl CubeServices {
     }
 }
 
+/// Password authentication method which is requested from PostgreSQL clients
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostgresAuthMethod {
+    /// The password is sent in clear text (AuthenticationCleartextPassword)
+    Cleartext,
+    /// SASL authentication with SCRAM-SHA-256, the password is never sent over the wire
+    ScramSha256,
+}
+
+impl FromStr for PostgresAuthMethod {
+    type Err = CubeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "password" | "cleartext" => Ok(Self::Cleartext),
+            "scram-sha-256" => Ok(Self::ScramSha256),
+            _ => Err(CubeError::user(format!(
+                "Unknown PostgreSQL auth method: \"{}\", supported methods: password, scram-sha-256",
+                s
+            ))),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Config {
     config_obj: Arc<ConfigObjImpl>,
@@ -116,6 +140,14 @@ pub trait ConfigObj: DIService + Debug {
 
     fn postgres_bind_address(&self) -> &Option<String>;
 
+    fn postgres_auth_method(&self) -> PostgresAuthMethod;
+
+    fn postgres_tls_cert_path(&self) -> &Option<String>;
+
+    fn postgres_tls_key_path(&self) -> &Option<String>;
+
+    fn postgres_tls_required(&self) -> bool;
+
     fn query_timeout(&self) -> u64;
 
     fn nonce(&self) -> &Option<Vec<u8>>;
@@ -139,6 +171,10 @@ pub trait ConfigObj: DIService + Debug {
 pub struct ConfigObjImpl {
     pub bind_address: Option<String>,
     pub postgres_bind_address: Option<String>,
+    pub postgres_auth_method: PostgresAuthMethod,
+    pub postgres_tls_cert_path: Option<String>,
+    pub postgres_tls_key_path: Option<String>,
+    pub postgres_tls_required: bool,
     pub nonce: Option<Vec<u8>>,
     pub query_timeout: u64,
     pub auth_expire_secs: u64,
@@ -167,6 +203,13 @@ impl ConfigObjImpl {
             postgres_bind_address: env::var("CUBESQL_PG_PORT")
                 .ok()
                 .map(|port| format!("0.0.0.0:{}", port.parse::<u16>().unwrap())),
+            postgres_auth_method: env_parse(
+                "CUBESQL_PG_AUTH_METHOD",
+                PostgresAuthMethod::Cleartext,
+            ),
+            postgres_tls_cert_path: env::var("CUBESQL_PG_TLS_CERT").ok(),
+            postgres_tls_key_path: env::var("CUBESQL_PG_TLS_KEY").ok(),
+            postgres_tls_required: env_parse("CUBESQL_PG_TLS_REQUIRED", false),
             nonce: None,
             query_timeout,
             timezone: Some("UTC".to_string()),
@@ -197,6 +240,22 @@ impl ConfigObj for ConfigObjImpl {
         &self.postgres_bind_address
     }
 
+    fn postgres_auth_method(&self) -> PostgresAuthMethod {
+        self.postgres_auth_method
+    }
+
+    fn postgres_tls_cert_path(&self) -> &Option<String> {
+        &self.postgres_tls_cert_path
+    }
+
+    fn postgres_tls_key_path(&self) -> &Option<String> {
+        &self.postgres_tls_key_path
+    }
+
+    fn postgres_tls_required(&self) -> bool {
+        self.postgres_tls_required
+    }
+
     fn nonce(&self) -> &Option<Vec<u8>> {
         &self.nonce
     }
@@ -255,6 +314,10 @@ impl Config {
             config_obj: Arc::new(ConfigObjImpl {
                 bind_address: None,
                 postgres_bind_address: None,
+                postgres_auth_method: PostgresAuthMethod::Cleartext,
+                postgres_tls_cert_path: None,
+                postgres_tls_key_path: None,
+                postgres_tls_required: false,
                 nonce: None,
                 query_timeout,
                 auth_expire_secs: 60,
@@ -353,6 +416,7 @@ impl Config {
                     let config = i.get_service_typed::<dyn ConfigObj>().await;
                     PostgresServer::new(
                         config.postgres_bind_address().as_ref().unwrap().to_string(),
+                        config.clone(),
                         i.get_service_typed().await,
                     )
                 })
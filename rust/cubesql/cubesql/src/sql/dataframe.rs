@@ -20,8 +20,7 @@ use datafusion::arrow::{
     record_batch::RecordBatch,
     temporal_conversions,
 };
-use pg_srv::IntervalValue;
-use rust_decimal::prelude::*;
+use pg_srv::{IntervalValue, NumericValue};
 use std::{
     fmt::{self, Debug, Formatter},
     io,
@@ -271,11 +270,9 @@ impl Decimal128Value {
         Self { n, scale }
     }
 
-    pub fn as_decimal(&self) -> Result<Decimal, CubeError> {
-        Ok(Decimal::try_from_i128_with_scale(
-            self.n,
-            self.scale as u32,
-        )?)
+    pub fn as_numeric(&self) -> NumericValue {
+        // Scale of Decimal128 is limited by the max precision (38)
+        NumericValue::new(self.n, self.scale as u16)
     }
 }
 
@@ -219,7 +219,17 @@ impl MySqlConnection {
                         ctx.state,
                         &plan,
                     );
-                    let batches = df.collect().await?;
+                    let cancel = self.session.state.begin_query(query.clone());
+                    let batches = tokio::select! {
+                        _ = cancel.cancelled() => {
+                            return Err(CubeError::user("Query execution was interrupted".to_string()));
+                        }
+                        res = df.collect() => {
+                            self.session.state.end_query();
+
+                            res?
+                        }
+                    };
                     let response = batch_to_dataframe(&df.schema().into(), &batches)?;
 
                     return Ok(QueryResponse::ResultSet(status, Box::new(response)))
@@ -0,0 +1,541 @@
+//! Support for `COPY (query) TO STDOUT`, which is used by psql (\copy) and ETL tools to export query results.
+//! <https://www.postgresql.org/docs/14/sql-copy.html>
+
+use pg_srv::protocol::{ErrorCode, ErrorResponse};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CopyFormat {
+    Text,
+    Csv,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopyOptions {
+    pub format: CopyFormat,
+    pub delimiter: u8,
+    pub null: String,
+    pub header: bool,
+    pub quote: u8,
+    pub escape: u8,
+}
+
+impl CopyOptions {
+    fn new(format: CopyFormat) -> Self {
+        match format {
+            CopyFormat::Text => Self {
+                format,
+                delimiter: b'\t',
+                null: "\\N".to_string(),
+                header: false,
+                quote: b'"',
+                escape: b'"',
+            },
+            CopyFormat::Csv => Self {
+                format,
+                delimiter: b',',
+                null: "".to_string(),
+                header: false,
+                quote: b'"',
+                escape: b'"',
+            },
+        }
+    }
+
+    /// Encode a single row (with trailing new line) to the output buffer, None is used for NULL
+    pub fn encode_row(&self, values: &[Option<&[u8]>], buf: &mut Vec<u8>) {
+        for (idx, value) in values.iter().enumerate() {
+            if idx > 0 {
+                buf.push(self.delimiter);
+            }
+
+            match value {
+                None => buf.extend_from_slice(self.null.as_bytes()),
+                Some(value) => match self.format {
+                    CopyFormat::Text => self.encode_text_value(value, buf),
+                    CopyFormat::Csv => self.encode_csv_value(value, buf),
+                },
+            }
+        }
+
+        buf.push(b'\n');
+    }
+
+    /// https://github.com/postgres/postgres/blob/REL_14_4/src/backend/commands/copyto.c#L1063
+    fn encode_text_value(&self, value: &[u8], buf: &mut Vec<u8>) {
+        for &c in value {
+            match c {
+                b'\\' => buf.extend_from_slice(b"\\\\"),
+                b'\n' => buf.extend_from_slice(b"\\n"),
+                b'\r' => buf.extend_from_slice(b"\\r"),
+                b'\t' => buf.extend_from_slice(b"\\t"),
+                0x08 => buf.extend_from_slice(b"\\b"),
+                0x0C => buf.extend_from_slice(b"\\f"),
+                0x0B => buf.extend_from_slice(b"\\v"),
+                c if c == self.delimiter => {
+                    buf.push(b'\\');
+                    buf.push(c);
+                }
+                c => buf.push(c),
+            }
+        }
+    }
+
+    /// https://github.com/postgres/postgres/blob/REL_14_4/src/backend/commands/copyto.c#L1216
+    fn encode_csv_value(&self, value: &[u8], buf: &mut Vec<u8>) {
+        let use_quote = value == self.null.as_bytes()
+            // A data value of \. could be mistaken for the end-of-data marker
+            || value == b"\\."
+            || value.iter().any(|&c| {
+                c == self.delimiter || c == self.quote || c == b'\n' || c == b'\r'
+            });
+
+        if !use_quote {
+            buf.extend_from_slice(value);
+            return;
+        }
+
+        buf.push(self.quote);
+        for &c in value {
+            if c == self.quote || c == self.escape {
+                buf.push(self.escape);
+            }
+
+            buf.push(c);
+        }
+        buf.push(self.quote);
+    }
+}
+
+/// Parsed `COPY (query) TO STDOUT [ [ WITH ] ( option [, ...] ) ]`
+#[derive(Debug, PartialEq)]
+pub struct CopyToStatement {
+    pub query: String,
+    pub options: CopyOptions,
+}
+
+#[derive(Debug, PartialEq)]
+enum CopyToken {
+    Word(String),
+    String(String),
+    LParen,
+    RParen,
+    Comma,
+}
+
+fn copy_error(message: String) -> ErrorResponse {
+    ErrorResponse::error(ErrorCode::SyntaxError, message)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Position of the parenthesis which closes the one at `start`, quoted strings and identifiers are skipped
+fn find_closing_paren(query: &str, start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+
+    for (idx, c) in query.char_indices().skip_while(|(idx, _)| *idx < start) {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None => match c {
+                '\'' | '"' => quote = Some(c),
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(idx);
+                    }
+                }
+                _ => {}
+            },
+        }
+    }
+
+    None
+}
+
+fn tokenize_options(input: &str) -> Result<Vec<CopyToken>, ErrorResponse> {
+    let mut tokens = vec![];
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() || c == ';' => {}
+            '(' => tokens.push(CopyToken::LParen),
+            ')' => tokens.push(CopyToken::RParen),
+            ',' => tokens.push(CopyToken::Comma),
+            '\'' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        // '' is an escaped quote
+                        Some('\'') if chars.peek() == Some(&'\'') => {
+                            chars.next();
+                            value.push('\'');
+                        }
+                        Some('\'') => break,
+                        Some(c) => value.push(c),
+                        None => {
+                            return Err(copy_error(
+                                "unterminated quoted string in COPY options".to_string(),
+                            ))
+                        }
+                    }
+                }
+
+                tokens.push(CopyToken::String(value));
+            }
+            c if is_word_char(c) => {
+                let mut value = c.to_string();
+                while let Some(c) = chars.peek().filter(|c| is_word_char(**c)) {
+                    value.push(*c);
+                    chars.next();
+                }
+
+                tokens.push(CopyToken::Word(value.to_uppercase()));
+            }
+            c => {
+                return Err(copy_error(format!(
+                    r#"syntax error at or near "{}" in COPY statement"#,
+                    c
+                )))
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn single_byte_option(name: &str, value: String) -> Result<u8, ErrorResponse> {
+    match value.as_bytes() {
+        [c] => Ok(*c),
+        _ => Err(ErrorResponse::error(
+            ErrorCode::FeatureNotSupported,
+            format!("COPY {} must be a single one-byte character", name),
+        )),
+    }
+}
+
+fn parse_bool_option(value: Option<String>) -> Result<bool, ErrorResponse> {
+    match value.map(|v| v.to_uppercase()).as_deref() {
+        None | Some("TRUE") | Some("ON") | Some("1") => Ok(true),
+        Some("FALSE") | Some("OFF") | Some("0") => Ok(false),
+        Some(other) => Err(copy_error(format!(
+            r#"HEADER requires a Boolean value, actual: "{}""#,
+            other
+        ))),
+    }
+}
+
+#[derive(Default)]
+struct RawCopyOptions {
+    format: Option<String>,
+    delimiter: Option<String>,
+    null: Option<String>,
+    header: Option<bool>,
+    quote: Option<String>,
+    escape: Option<String>,
+}
+
+impl RawCopyOptions {
+    fn set(&mut self, name: &str, value: Option<String>) -> Result<(), ErrorResponse> {
+        let required = |value: Option<String>| {
+            value.ok_or_else(|| copy_error(format!("{} requires a parameter", name)))
+        };
+
+        match name {
+            "FORMAT" => self.format = Some(required(value)?.to_uppercase()),
+            "DELIMITER" => self.delimiter = Some(required(value)?),
+            "NULL" => self.null = Some(required(value)?),
+            "HEADER" => self.header = Some(parse_bool_option(value)?),
+            "QUOTE" => self.quote = Some(required(value)?),
+            "ESCAPE" => self.escape = Some(required(value)?),
+            "ENCODING" => {
+                let encoding = required(value)?;
+                if !encoding.eq_ignore_ascii_case("UTF8") && !encoding.eq_ignore_ascii_case("UTF-8")
+                {
+                    return Err(ErrorResponse::error(
+                        ErrorCode::FeatureNotSupported,
+                        format!(r#"COPY encoding "{}" is not supported"#, encoding),
+                    ));
+                }
+            }
+            other => {
+                return Err(ErrorResponse::error(
+                    ErrorCode::FeatureNotSupported,
+                    format!(r#"COPY option "{}" is not supported"#, other.to_lowercase()),
+                ))
+            }
+        };
+
+        Ok(())
+    }
+
+    fn into_options(self) -> Result<CopyOptions, ErrorResponse> {
+        let format = match self.format.as_deref() {
+            None | Some("TEXT") => CopyFormat::Text,
+            Some("CSV") => CopyFormat::Csv,
+            Some("BINARY") => {
+                return Err(ErrorResponse::error(
+                    ErrorCode::FeatureNotSupported,
+                    "COPY BINARY is not supported".to_string(),
+                ))
+            }
+            Some(other) => {
+                return Err(copy_error(format!(
+                    r#"COPY format "{}" not recognized"#,
+                    other.to_lowercase()
+                )))
+            }
+        };
+
+        let mut options = CopyOptions::new(format);
+        if let Some(delimiter) = self.delimiter {
+            options.delimiter = single_byte_option("delimiter", delimiter)?;
+        }
+        if let Some(null) = self.null {
+            options.null = null;
+        }
+        if let Some(header) = self.header {
+            options.header = header;
+        }
+
+        match format {
+            CopyFormat::Csv => {
+                if let Some(quote) = self.quote {
+                    options.quote = single_byte_option("quote", quote)?;
+                    options.escape = options.quote;
+                }
+                if let Some(escape) = self.escape {
+                    options.escape = single_byte_option("escape", escape)?;
+                }
+            }
+            CopyFormat::Text => {
+                if self.quote.is_some() || self.escape.is_some() {
+                    return Err(ErrorResponse::error(
+                        ErrorCode::FeatureNotSupported,
+                        "COPY quote and escape available only in CSV mode".to_string(),
+                    ));
+                }
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Parse options after TO STDOUT. Both syntaxes are supported:
+/// `WITH (FORMAT csv, HEADER)` and the legacy one, which psql still uses: `WITH CSV HEADER DELIMITER ','`
+fn parse_copy_options(input: &str) -> Result<CopyOptions, ErrorResponse> {
+    let mut tokens = tokenize_options(input)?.into_iter().peekable();
+    let mut raw = RawCopyOptions::default();
+
+    if tokens.peek() == Some(&CopyToken::Word("WITH".to_string())) {
+        tokens.next();
+    }
+
+    if tokens.peek() == Some(&CopyToken::LParen) {
+        tokens.next();
+
+        loop {
+            let name = match tokens.next() {
+                Some(CopyToken::Word(name)) => name,
+                other => {
+                    return Err(copy_error(format!(
+                        "unexpected token in COPY options: {:?}",
+                        other
+                    )))
+                }
+            };
+
+            let value = match tokens.peek() {
+                Some(CopyToken::Word(_)) | Some(CopyToken::String(_)) => match tokens.next() {
+                    Some(CopyToken::Word(v)) | Some(CopyToken::String(v)) => Some(v),
+                    _ => unreachable!(),
+                },
+                _ => None,
+            };
+            raw.set(&name, value)?;
+
+            match tokens.next() {
+                Some(CopyToken::Comma) => continue,
+                Some(CopyToken::RParen) => break,
+                other => {
+                    return Err(copy_error(format!(
+                        "unexpected token in COPY options: {:?}",
+                        other
+                    )))
+                }
+            }
+        }
+    } else {
+        while let Some(token) = tokens.next() {
+            let optional_as = |tokens: &mut std::iter::Peekable<std::vec::IntoIter<CopyToken>>| {
+                if tokens.peek() == Some(&CopyToken::Word("AS".to_string())) {
+                    tokens.next();
+                }
+
+                match tokens.next() {
+                    Some(CopyToken::String(v)) => Ok(Some(v)),
+                    other => Err(copy_error(format!(
+                        "unexpected token in COPY options: {:?}",
+                        other
+                    ))),
+                }
+            };
+
+            match token {
+                CopyToken::Word(word) => match word.as_str() {
+                    "BINARY" | "CSV" => raw.set("FORMAT", Some(word))?,
+                    "HEADER" => raw.set("HEADER", None)?,
+                    "DELIMITER" | "NULL" | "QUOTE" | "ESCAPE" => {
+                        raw.set(&word, optional_as(&mut tokens)?)?
+                    }
+                    other => raw.set(other, None)?,
+                },
+                other => {
+                    return Err(copy_error(format!(
+                        "unexpected token in COPY options: {:?}",
+                        other
+                    )))
+                }
+            }
+        }
+    }
+
+    if let Some(token) = tokens.next() {
+        return Err(copy_error(format!(
+            "unexpected token after COPY options: {:?}",
+            token
+        )));
+    }
+
+    raw.into_options()
+}
+
+lazy_static! {
+    static ref COPY_PREFIX: regex::Regex = regex::Regex::new(r"(?is)^\s*copy\s*\(").unwrap();
+    static ref COPY_TARGET: regex::Regex = regex::Regex::new(r"(?is)^\s*to\s+stdout\b").unwrap();
+}
+
+/// Returns None if the query is not a `COPY (query) TO ...` statement, then it should go through the regular parser
+pub fn parse_copy_to(query: &str) -> Result<Option<CopyToStatement>, ErrorResponse> {
+    let prefix = match COPY_PREFIX.find(query) {
+        Some(prefix) => prefix,
+        None => return Ok(None),
+    };
+
+    let query_start = prefix.end() - 1;
+    let query_end = find_closing_paren(query, query_start)
+        .ok_or_else(|| copy_error("unterminated parenthesis in COPY statement".to_string()))?;
+
+    let tail = &query[query_end + 1..];
+    let target = COPY_TARGET.find(tail).ok_or_else(|| {
+        ErrorResponse::error(
+            ErrorCode::FeatureNotSupported,
+            "COPY (query) is supported only with TO STDOUT".to_string(),
+        )
+    })?;
+
+    Ok(Some(CopyToStatement {
+        query: query[query_start + 1..query_end].trim().to_string(),
+        options: parse_copy_options(&tail[target.end()..])?,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(options: &CopyOptions, values: Vec<Option<&str>>) -> String {
+        let values = values
+            .iter()
+            .map(|v| v.map(|v| v.as_bytes()))
+            .collect::<Vec<_>>();
+        let mut buf = vec![];
+        options.encode_row(&values, &mut buf);
+
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_parse_copy_to() {
+        assert_eq!(parse_copy_to("SELECT 1").unwrap(), None);
+
+        let copy = parse_copy_to("COPY (SELECT a, ')' FROM t WHERE b IN (1, 2)) TO STDOUT")
+            .unwrap()
+            .unwrap();
+        assert_eq!(copy.query, "SELECT a, ')' FROM t WHERE b IN (1, 2)");
+        assert_eq!(copy.options, CopyOptions::new(CopyFormat::Text));
+
+        let copy = parse_copy_to(
+            "copy ( select 1 ) to stdout with (format csv, header true, delimiter ';', null 'NULL');",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(copy.query, "select 1");
+        assert_eq!(
+            copy.options,
+            CopyOptions {
+                format: CopyFormat::Csv,
+                delimiter: b';',
+                null: "NULL".to_string(),
+                header: true,
+                quote: b'"',
+                escape: b'"',
+            }
+        );
+
+        // psql \copy sends the legacy syntax
+        let copy = parse_copy_to("COPY ( SELECT 1 ) TO STDOUT WITH CSV HEADER QUOTE AS '''' ")
+            .unwrap()
+            .unwrap();
+        assert_eq!(copy.options.format, CopyFormat::Csv);
+        assert!(copy.options.header);
+        assert_eq!(copy.options.quote, b'\'');
+        assert_eq!(copy.options.escape, b'\'');
+
+        assert!(parse_copy_to("COPY (SELECT 1) TO '/tmp/file'").is_err());
+        assert!(parse_copy_to("COPY (SELECT 1) TO STDOUT (FORMAT binary)").is_err());
+        assert!(parse_copy_to("COPY (SELECT 1) TO STDOUT (FREEZE)").is_err());
+        assert!(parse_copy_to("COPY (SELECT 1) TO STDOUT (DELIMITER '||')").is_err());
+        assert!(parse_copy_to("COPY (SELECT 1 TO STDOUT").is_err());
+    }
+
+    #[test]
+    fn test_copy_encode_text() {
+        let options = CopyOptions::new(CopyFormat::Text);
+
+        assert_eq!(
+            encode(&options, vec![Some("a"), None, Some("")]),
+            "a\t\\N\t\n"
+        );
+        assert_eq!(
+            encode(&options, vec![Some("tab\there"), Some("new\nline\\")]),
+            "tab\\there\tnew\\nline\\\\\n"
+        );
+    }
+
+    #[test]
+    fn test_copy_encode_csv() {
+        let options = CopyOptions::new(CopyFormat::Csv);
+
+        assert_eq!(
+            encode(&options, vec![Some("a"), None, Some("")]),
+            "a,,\"\"\n"
+        );
+        assert_eq!(
+            encode(
+                &options,
+                vec![
+                    Some("with,comma"),
+                    Some("with \"quote\""),
+                    Some("new\nline")
+                ]
+            ),
+            "\"with,comma\",\"with \"\"quote\"\"\",\"new\nline\"\n"
+        );
+    }
+}
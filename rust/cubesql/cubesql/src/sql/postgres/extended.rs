@@ -1,6 +1,7 @@
 use crate::{
     compile::QueryPlan,
     sql::{
+        copy::CopyOptions,
         dataframe::{batch_to_dataframe, DataFrame, TableValue},
         statement::PostgresStatementParamsBinder,
         temp_tables::TempTable,
@@ -185,6 +186,8 @@ pub enum PortalFrom {
     Simple,
     Fetch,
     Extended,
+    /// COPY (query) TO STDOUT, rows are returned as CopyData
+    Copy(CopyOptions),
 }
 
 pub enum PortalBatch {
@@ -282,6 +285,13 @@ impl Portal {
         }
     }
 
+    pub fn get_copy_options(&self) -> Option<CopyOptions> {
+        match &self.from {
+            PortalFrom::Copy(options) => Some(options.clone()),
+            _ => None,
+        }
+    }
+
     pub fn get_format(&self) -> protocol::Format {
         self.format.clone()
     }
@@ -329,6 +339,9 @@ impl Portal {
             PortalFrom::Fetch => {
                 protocol::PortalCompletion::Complete(protocol::CommandComplete::Fetch(rows))
             }
+            PortalFrom::Copy(_) => {
+                protocol::PortalCompletion::Complete(protocol::CommandComplete::Copy(rows))
+            }
             PortalFrom::Extended => {
                 if has_more {
                     protocol::PortalCompletion::Suspended(PortalSuspended::new())
@@ -340,7 +353,10 @@ impl Portal {
     }
 
     fn dataframe_to_writer(&self, frame: DataFrame) -> Result<BatchWriter, ProtocolError> {
-        let mut writer = BatchWriter::new(self.get_format());
+        let mut writer = match &self.from {
+            PortalFrom::Copy(options) => BatchWriter::new_copy(options.clone()),
+            _ => BatchWriter::new(self.get_format()),
+        };
 
         for row in frame.to_rows().into_iter() {
             for value in row.to_values() {
@@ -1,7 +1,9 @@
+pub(crate) mod copy;
 pub(crate) mod extended;
 pub(crate) mod pg_type;
 pub(crate) mod service;
 pub(crate) mod shim;
+pub(crate) mod stream;
 pub(crate) mod writer;
 
 pub use pg_type::*;
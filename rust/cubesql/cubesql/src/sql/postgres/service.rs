@@ -7,17 +7,21 @@ use tokio::{
 };
 
 use crate::{
-    config::processing_loop::ProcessingLoop,
+    config::{processing_loop::ProcessingLoop, ConfigObj},
     sql::{session::DatabaseProtocol, SessionManager},
     telemetry::{ContextLogger, SessionLogger},
     CubeError,
 };
 
-use super::shim::AsyncPostgresShim;
+use super::{
+    shim::AsyncPostgresShim,
+    stream::{build_tls_acceptor, PostgresStream},
+};
 
 pub struct PostgresServer {
     // options
     address: String,
+    config_obj: Arc<dyn ConfigObj>,
     close_socket_rx: RwLock<watch::Receiver<bool>>,
     close_socket_tx: watch::Sender<bool>,
     // reference
@@ -29,9 +33,14 @@ crate::di_service!(PostgresServer, []);
 #[async_trait]
 impl ProcessingLoop for PostgresServer {
     async fn processing_loop(&self) -> Result<(), CubeError> {
+        let tls_acceptor = build_tls_acceptor(self.config_obj.as_ref())?;
         let listener = TcpListener::bind(self.address.clone()).await?;
 
-        println!("🔗 Cube SQL (pg) is listening on {}", self.address);
+        println!(
+            "🔗 Cube SQL (pg) is listening on {}{}",
+            self.address,
+            if tls_acceptor.is_some() { " (TLS)" } else { "" }
+        );
 
         loop {
             let mut stop_receiver = self.close_socket_rx.write().await;
@@ -85,8 +94,14 @@ impl ProcessingLoop for PostgresServer {
                 session_manager.drop_session(connection_id).await;
             });
 
+            let tls_acceptor = tls_acceptor.clone();
             tokio::spawn(async move {
-                let handler = AsyncPostgresShim::run_on(socket, session.clone(), logger.clone());
+                let handler = AsyncPostgresShim::run_on(
+                    PostgresStream::Tcp(socket),
+                    tls_acceptor,
+                    session.clone(),
+                    logger.clone(),
+                );
                 if let Err(e) = handler.await {
                     logger.error(
                         format!("Error during processing PostgreSQL connection: {}", e).as_str(),
@@ -113,10 +128,15 @@ impl ProcessingLoop for PostgresServer {
 }
 
 impl PostgresServer {
-    pub fn new(address: String, session_manager: Arc<SessionManager>) -> Arc<Self> {
+    pub fn new(
+        address: String,
+        config_obj: Arc<dyn ConfigObj>,
+        session_manager: Arc<SessionManager>,
+    ) -> Arc<Self> {
         let (close_socket_tx, close_socket_rx) = watch::channel(false);
         Arc::new(Self {
             address,
+            config_obj,
             session_manager,
             close_socket_rx: RwLock::new(close_socket_rx),
             close_socket_tx,
@@ -3,7 +3,7 @@ use std::{
     time::SystemTime,
 };
 
-use super::extended::PreparedStatement;
+use super::{extended::PreparedStatement, stream::PostgresStream};
 use crate::{
     compile::{
         convert_statement_to_cube_query,
@@ -11,7 +11,9 @@ use crate::{
         qtrace::Qtrace,
         CompilationError, MetaContext, QueryPlan,
     },
+    config::PostgresAuthMethod,
     sql::{
+        copy::{self, CopyOptions},
         df_type_to_pg_tid,
         extended::{Cursor, Portal, PortalBatch, PortalFrom},
         session::DatabaseProtocol,
@@ -28,15 +30,17 @@ use log::{debug, error, trace};
 use pg_srv::{
     buffer, protocol,
     protocol::{ErrorCode, ErrorResponse, Format, InitialMessage, PortalCompletion},
-    PgType, PgTypeId, ProtocolError,
+    scram, PgType, PgTypeId, ProtocolError,
 };
 use sqlparser::ast::{self, CloseCursor, FetchDirection, Query, SetExpr, Statement, Value};
-use tokio::{io::AsyncWriteExt, net::TcpStream};
+use tokio::io::AsyncWriteExt;
+use tokio_rustls::TlsAcceptor;
 use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
 pub struct AsyncPostgresShim {
-    socket: TcpStream,
+    socket: PostgresStream,
+    tls_acceptor: Option<TlsAcceptor>,
     // Extended query
     cursors: HashMap<String, Cursor>,
     portals: HashMap<String, Portal>,
@@ -225,12 +229,14 @@ impl From<ErrorResponse> for ConnectionError {
 
 impl AsyncPostgresShim {
     pub async fn run_on(
-        socket: TcpStream,
+        socket: PostgresStream,
+        tls_acceptor: Option<TlsAcceptor>,
         session: Arc<Session>,
         logger: Arc<dyn ContextLogger>,
     ) -> Result<(), ConnectionError> {
         let mut shim = Self {
             socket,
+            tls_acceptor,
             cursors: HashMap::new(),
             portals: HashMap::new(),
             session,
@@ -274,16 +280,18 @@ impl AsyncPostgresShim {
             StartupState::Denied | StartupState::CancelRequest => return Ok(()),
         };
 
-        match buffer::read_message(&mut self.socket).await? {
-            protocol::FrontendMessage::PasswordMessage(password_message) => {
-                if !self
-                    .authenticate(password_message, initial_parameters)
-                    .await?
-                {
-                    return Ok(());
+        let authenticated = match self.session.server.config_obj.postgres_auth_method() {
+            PostgresAuthMethod::Cleartext => match buffer::read_message(&mut self.socket).await? {
+                protocol::FrontendMessage::PasswordMessage(password_message) => {
+                    self.authenticate(password_message, initial_parameters).await?
                 }
-            }
-            _ => return Ok(()),
+                _ => false,
+            },
+            PostgresAuthMethod::ScramSha256 => self.authenticate_scram(initial_parameters).await?,
+        };
+
+        if !authenticated {
+            return Ok(());
         }
 
         self.ready().await?;
@@ -432,6 +440,12 @@ impl AsyncPostgresShim {
 
                     continue;
                 }
+                // COPY FROM STDIN is not supported, so copy-in mode is never entered. Like
+                // PostgreSQL, ignore these messages: a client may still be sending data of a
+                // COPY that failed.
+                protocol::FrontendMessage::CopyData
+                | protocol::FrontendMessage::CopyDone
+                | protocol::FrontendMessage::CopyFail(_) => continue,
                 command_id => {
                     return Err(ConnectionError::Protocol(
                         ErrorResponse::error(
@@ -553,6 +567,34 @@ impl AsyncPostgresShim {
         Ok(())
     }
 
+    /// COPY TO STDOUT starts with CopyOutResponse instead of RowDescription, header goes as the first CopyData
+    async fn write_copy_out_start(
+        &mut self,
+        description: protocol::RowDescription,
+        options: &CopyOptions,
+    ) -> Result<(), ConnectionError> {
+        self.write(protocol::CopyOutResponse::new(
+            Format::Text,
+            description.len(),
+        )?)
+        .await?;
+
+        if options.header {
+            let names = description
+                .field_names()
+                .into_iter()
+                .map(|name| Some(name.as_bytes()))
+                .collect::<Vec<_>>();
+
+            let mut line = vec![];
+            options.encode_row(&names, &mut line);
+
+            self.write(protocol::CopyData::new(line)).await?;
+        }
+
+        Ok(())
+    }
+
     pub async fn write<Message: protocol::Serialize>(
         &mut self,
         message: Message,
@@ -569,7 +611,18 @@ impl AsyncPostgresShim {
         match initial_message {
             InitialMessage::Startup(startup) => self.process_startup_message(startup).await,
             InitialMessage::CancelRequest(cancel) => self.process_cancel(cancel).await,
-            InitialMessage::Gssenc | InitialMessage::SslRequest => {
+            InitialMessage::SslRequest => {
+                match self.tls_acceptor.clone() {
+                    Some(tls_acceptor) if !self.socket.is_tls() => {
+                        self.write(protocol::SSLResponse::accepted()).await?;
+                        self.socket.upgrade(&tls_acceptor).await?;
+                    }
+                    _ => self.write(protocol::SSLResponse::new()).await?,
+                }
+
+                return Ok(StartupState::SslRequested);
+            }
+            InitialMessage::Gssenc => {
                 self.write(protocol::SSLResponse::new()).await?;
                 return Ok(StartupState::SslRequested);
             }
@@ -621,6 +674,16 @@ impl AsyncPostgresShim {
             return Ok(StartupState::Denied);
         }
 
+        if self.session.server.config_obj.postgres_tls_required() && !self.socket.is_tls() {
+            let error_response = protocol::ErrorResponse::new(
+                protocol::ErrorSeverity::Fatal,
+                protocol::ErrorCode::InvalidAuthorizationSpecification,
+                "TLS is required for connection, use sslmode=require".to_string(),
+            );
+            buffer::write_message(&mut self.socket, error_response).await?;
+            return Ok(StartupState::Denied);
+        }
+
         let parameters = startup_message.parameters;
         if !parameters.contains_key("user") {
             let error_response = protocol::ErrorResponse::new(
@@ -632,10 +695,13 @@ impl AsyncPostgresShim {
             return Ok(StartupState::Denied);
         }
 
-        self.write(protocol::Authentication::new(
-            protocol::AuthenticationRequest::CleartextPassword,
-        ))
-        .await?;
+        // SCRAM exchange starts after the password lookup, see authenticate_scram
+        if self.session.server.config_obj.postgres_auth_method() == PostgresAuthMethod::Cleartext {
+            self.write(protocol::Authentication::new(
+                protocol::AuthenticationRequest::CleartextPassword,
+            ))
+            .await?;
+        }
 
         Ok(StartupState::Success(parameters))
     }
@@ -671,15 +737,114 @@ impl AsyncPostgresShim {
         };
 
         if !auth_success {
-            let error_response = protocol::ErrorResponse::fatal(
-                protocol::ErrorCode::InvalidPassword,
-                format!("password authentication failed for user \"{}\"", &user),
-            );
-            buffer::write_message(&mut self.socket, error_response).await?;
+            return self.reject_authentication(&user).await;
+        }
+
+        self.accept_authentication(user, parameters, auth_context).await
+    }
+
+    /// SASL authentication with SCRAM-SHA-256. Like MySQL native password, it requires SqlAuthService
+    /// to return the password for the user, because the client never sends it over the wire.
+    /// Unknown users go through a mock exchange and are rejected only at the final step.
+    pub async fn authenticate_scram(
+        &mut self,
+        parameters: HashMap<String, String>,
+    ) -> Result<bool, ConnectionError> {
+        let user = parameters.get("user").unwrap().clone();
+        let (mut scram, auth_context) = match self
+            .session
+            .server
+            .auth
+            .authenticate(Some(user.clone()), None)
+            .await
+        {
+            Ok(authenticate_response) if authenticate_response.skip_password_check => {
+                return self
+                    .accept_authentication(user, parameters, Some(authenticate_response.context))
+                    .await;
+            }
+            Ok(authenticate_response) => match authenticate_response.password {
+                Some(password) => (
+                    scram::ScramSha256::new(&password),
+                    Some(authenticate_response.context),
+                ),
+                None => (scram::ScramSha256::mock(), None),
+            },
+            Err(_) => (scram::ScramSha256::mock(), None),
+        };
+
+        self.write(protocol::Authentication::new(
+            protocol::AuthenticationRequest::SASL(vec![scram::SCRAM_SHA_256.to_string()]),
+        ))
+        .await?;
+
+        let initial_response: protocol::SASLInitialResponse =
+            buffer::read_typed_message(&mut self.socket, b'p').await?;
+        if initial_response.mechanism != scram::SCRAM_SHA_256 {
+            self.write(protocol::ErrorResponse::fatal(
+                protocol::ErrorCode::ProtocolViolation,
+                format!(
+                    "client selected an invalid SASL authentication mechanism \"{}\"",
+                    initial_response.mechanism
+                ),
+            ))
+            .await?;
 
             return Ok(false);
         }
 
+        let server_first =
+            match scram.process_client_first(&initial_response.data.unwrap_or_default()) {
+                Ok(server_first) => server_first,
+                Err(e) => {
+                    self.write(e.to_error_response()).await?;
+
+                    return Ok(false);
+                }
+            };
+
+        self.write(protocol::Authentication::new(
+            protocol::AuthenticationRequest::SASLContinue(server_first),
+        ))
+        .await?;
+
+        let response: protocol::SASLResponse =
+            buffer::read_typed_message(&mut self.socket, b'p').await?;
+        match (scram.process_client_final(&response.data), auth_context) {
+            (Ok(Some(server_final)), Some(auth_context)) => {
+                self.write(protocol::Authentication::new(
+                    protocol::AuthenticationRequest::SASLFinal(server_final),
+                ))
+                .await?;
+
+                self.accept_authentication(user, parameters, Some(auth_context))
+                    .await
+            }
+            (Ok(_), _) => self.reject_authentication(&user).await,
+            (Err(e), _) => {
+                self.write(e.to_error_response()).await?;
+
+                Ok(false)
+            }
+        }
+    }
+
+    async fn reject_authentication(&mut self, user: &str) -> Result<bool, ConnectionError> {
+        let error_response = protocol::ErrorResponse::fatal(
+            protocol::ErrorCode::InvalidPassword,
+            format!("password authentication failed for user \"{}\"", user),
+        );
+        buffer::write_message(&mut self.socket, error_response).await?;
+
+        Ok(false)
+    }
+
+    async fn accept_authentication(
+        &mut self,
+        user: String,
+        parameters: HashMap<String, String>,
+        auth_context: Option<AuthContextRef>,
+    ) -> Result<bool, ConnectionError> {
         let database = parameters
             .get("database")
             .map(|v| v.clone())
@@ -990,8 +1155,15 @@ impl AsyncPostgresShim {
             if let Some(qtrace) = qtrace {
                 qtrace.push_statement(&query);
             }
-            self.prepare_statement(parse.name, query, false, qtrace, span_id.clone())
-                .await?;
+            self.prepare_statement(
+                parse.name,
+                query,
+                parse.param_types,
+                false,
+                qtrace,
+                span_id.clone(),
+            )
+            .await?;
         }
 
         self.write(protocol::ParseComplete::new()).await?;
@@ -1003,6 +1175,7 @@ impl AsyncPostgresShim {
         &mut self,
         name: String,
         query: Statement,
+        param_types: Vec<u32>,
         from_sql: bool,
         qtrace: &mut Option<Qtrace>,
         span_id: Option<Arc<SpanId>>,
@@ -1032,7 +1205,18 @@ impl AsyncPostgresShim {
         let parameters: Vec<PgTypeId> = stmt_finder
             .find(&query)?
             .into_iter()
-            .map(|param| param.coltype.to_pg_tid())
+            .enumerate()
+            .map(|(idx, param)| {
+                // Client can specify types of parameters in Parse (0 is unspecified), it's used to decode
+                // values in Bind. Types, which cannot be decoded, fallback to the type from the query.
+                match param_types
+                    .get(idx)
+                    .and_then(|oid| PgTypeId::from_oid(*oid))
+                {
+                    Some(tid) if tid.is_bind_supported() => tid,
+                    _ => param.coltype.to_pg_tid(),
+                }
+            })
             .collect();
 
         let meta = self
@@ -1109,6 +1293,7 @@ impl AsyncPostgresShim {
     pub async fn handle_simple_query(
         &mut self,
         stmt: ast::Statement,
+        copy: Option<CopyOptions>,
         meta: Arc<MetaContext>,
         qtrace: &mut Option<Qtrace>,
         span_id: Option<Arc<SpanId>>,
@@ -1128,7 +1313,7 @@ impl AsyncPostgresShim {
 
                 Ok(())
             },
-            res = self.process_simple_query(stmt, meta, cancel.clone(), qtrace, span_id) => {
+            res = self.process_simple_query(stmt, copy, meta, cancel.clone(), qtrace, span_id) => {
                 self.session.state.end_query();
 
                 if cancel.is_cancelled() {
@@ -1146,11 +1331,31 @@ impl AsyncPostgresShim {
     pub async fn process_simple_query(
         &mut self,
         stmt: ast::Statement,
+        copy: Option<CopyOptions>,
         meta: Arc<MetaContext>,
         cancel: CancellationToken,
         qtrace: &mut Option<Qtrace>,
         span_id: Option<Arc<SpanId>>,
     ) -> Result<(), ConnectionError> {
+        if let Some(options) = copy {
+            let plan = convert_statement_to_cube_query(
+                &stmt,
+                meta.clone(),
+                self.session.clone(),
+                qtrace,
+                span_id.clone(),
+            )
+            .await?;
+
+            return self
+                .write_portal(
+                    &mut Portal::new(plan, Format::Text, PortalFrom::Copy(options), span_id),
+                    0,
+                    cancel,
+                )
+                .await;
+        }
+
         match stmt {
             Statement::StartTransaction { .. } => {
                 if !self.session.state.begin_transaction() {
@@ -1548,8 +1753,15 @@ impl AsyncPostgresShim {
                     _ => *statement,
                 };
 
-                self.prepare_statement(name.value, statement, true, qtrace, span_id.clone())
-                    .await?;
+                self.prepare_statement(
+                    name.value,
+                    statement,
+                    vec![],
+                    true,
+                    qtrace,
+                    span_id.clone(),
+                )
+                .await?;
 
                 let plan = QueryPlan::MetaOk(StatusFlags::empty(), CommandCompletion::Prepare);
 
@@ -1588,6 +1800,7 @@ impl AsyncPostgresShim {
         max_rows: usize,
         cancel: CancellationToken,
     ) -> Result<(), ConnectionError> {
+        let copy_options = portal.get_copy_options();
         let mut portal = Pin::new(portal);
         let stream = portal.execute(max_rows);
         pin_mut!(stream);
@@ -1605,23 +1818,54 @@ impl AsyncPostgresShim {
                     };
 
                     match chunk {
-                        PortalBatch::Description(description) => match description.len() {
-                            // Special handling for special queries, such as DISCARD ALL.
-                            0 => self.write(protocol::NoData::new()).await?,
-                            _ => self.write(description).await?,
+                        PortalBatch::Description(description) => match &copy_options {
+                            Some(options) => self.write_copy_out_start(description, options).await?,
+                            None => match description.len() {
+                                // Special handling for special queries, such as DISCARD ALL.
+                                0 => self.write(protocol::NoData::new()).await?,
+                                _ => self.write(description).await?,
+                            },
                         },
                         PortalBatch::Rows(writer) => {
                             if writer.has_data() {
                                 buffer::write_direct(&mut self.socket, writer).await?
                             }
                         }
-                        PortalBatch::Completion(completion) => return self.write_completion(completion).await,
+                        PortalBatch::Completion(completion) => {
+                            if copy_options.is_some() {
+                                self.write(protocol::CopyDone::new()).await?;
+                            }
+
+                            return self.write_completion(completion).await;
+                        },
                     }
                 }
             }
         }
     }
 
+    /// COPY (query) TO STDOUT is not supported by SQL parser, that's why it's recognized before parsing
+    /// and the inner query goes through the simple query pipeline with COPY output
+    fn parse_copy_to(
+        &self,
+        query: &str,
+        qtrace: &mut Option<Qtrace>,
+    ) -> Result<Option<(ast::Statement, CopyOptions)>, ConnectionError> {
+        let copy = match copy::parse_copy_to(query)? {
+            Some(copy) => copy,
+            None => return Ok(None),
+        };
+
+        match parse_sql_to_statement(&copy.query, DatabaseProtocol::PostgreSQL, qtrace)? {
+            statement @ Statement::Query(_) => Ok(Some((statement, copy.options))),
+            _ => Err(protocol::ErrorResponse::error(
+                protocol::ErrorCode::FeatureNotSupported,
+                "COPY query must be a SELECT".to_string(),
+            )
+            .into()),
+        }
+    }
+
     /// Pipeline of Execution
     /// process_query -> (&str)
     ///     execute_query -> (&str)
@@ -1641,8 +1885,13 @@ impl AsyncPostgresShim {
             .meta(self.auth_context()?, self.session.state.protocol.clone())
             .await?;
 
-        let statements =
-            parse_sql_to_statements(&query.to_string(), DatabaseProtocol::PostgreSQL, qtrace)?;
+        let (statements, copy) = match self.parse_copy_to(query, qtrace)? {
+            Some((statement, options)) => (vec![statement], Some(options)),
+            None => (
+                parse_sql_to_statements(&query.to_string(), DatabaseProtocol::PostgreSQL, qtrace)?,
+                None,
+            ),
+        };
 
         if statements.len() == 0 {
             self.write(protocol::EmptyQuery::new()).await?;
@@ -1653,6 +1902,7 @@ impl AsyncPostgresShim {
                 }
                 match std::panic::AssertUnwindSafe(self.handle_simple_query(
                     statement,
+                    copy.clone(),
                     meta.clone(),
                     qtrace,
                     span_id.clone(),
@@ -1751,3 +2001,112 @@ impl AsyncPostgresShim {
             .ok_or(CubeError::internal("must be auth".to_string()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        compile::test::{get_test_tenant_ctx, get_test_transport},
+        config::ConfigObjImpl,
+        sql::{
+            compiler_cache::CompilerCacheImpl, AuthenticateResponse, HttpAuthContext,
+            ServerManager, SessionManager, SqlAuthService,
+        },
+        telemetry::SessionLogger,
+    };
+    use async_trait::async_trait;
+    use tokio::net::TcpListener;
+    use tokio_postgres::{error::SqlState, NoTls};
+
+    #[derive(Debug)]
+    struct ScramTestAuth {}
+
+    #[async_trait]
+    impl SqlAuthService for ScramTestAuth {
+        async fn authenticate(
+            &self,
+            user: Option<String>,
+            _password: Option<String>,
+        ) -> Result<AuthenticateResponse, CubeError> {
+            if user.as_deref() != Some("ovr") {
+                return Err(CubeError::user("unknown user".to_string()));
+            }
+
+            Ok(AuthenticateResponse {
+                context: Arc::new(HttpAuthContext {
+                    access_token: "fake".to_string(),
+                    base_path: "fake".to_string(),
+                }),
+                password: Some("pencil".to_string()),
+                skip_password_check: false,
+            })
+        }
+    }
+
+    /// Start a shim with SCRAM-SHA-256 authentication for a single connection
+    async fn start_scram_server() -> u16 {
+        let mut config = ConfigObjImpl::default();
+        config.postgres_auth_method = PostgresAuthMethod::ScramSha256;
+        let config = Arc::new(config);
+
+        let transport = get_test_transport(get_test_tenant_ctx());
+        let server = Arc::new(ServerManager::new(
+            Arc::new(ScramTestAuth {}),
+            transport.clone(),
+            Arc::new(CompilerCacheImpl::new(config.clone(), transport)),
+            None,
+            config,
+        ));
+        let session_manager = Arc::new(SessionManager::new(server));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let session = session_manager
+                .create_session(DatabaseProtocol::PostgreSQL, "127.0.0.1".to_string(), 1234)
+                .await;
+            let logger = Arc::new(SessionLogger::new(session.state.clone()));
+            let _ =
+                AsyncPostgresShim::run_on(PostgresStream::Tcp(socket), None, session, logger).await;
+        });
+
+        port
+    }
+
+    async fn connect_scram(user: &str, password: &str) -> Result<(), tokio_postgres::Error> {
+        let port = start_scram_server().await;
+        let (_client, connection) = tokio_postgres::connect(
+            &format!(
+                "host=127.0.0.1 port={} user={} password={} dbname=db",
+                port, user, password
+            ),
+            NoTls,
+        )
+        .await?;
+        tokio::spawn(connection);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_scram_handshake() {
+        connect_scram("ovr", "pencil").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_scram_rejects_wrong_password_and_unknown_user() {
+        let wrong_password = connect_scram("ovr", "wrong").await.unwrap_err();
+        let unknown_user = connect_scram("unknown", "pencil").await.unwrap_err();
+
+        // Unknown users go through the whole exchange and fail the same way as a wrong password
+        for (err, user) in [(wrong_password, "ovr"), (unknown_user, "unknown")] {
+            let err = err.as_db_error().unwrap();
+            assert_eq!(err.code(), &SqlState::INVALID_PASSWORD);
+            assert_eq!(
+                err.message(),
+                format!("password authentication failed for user \"{}\"", user)
+            );
+        }
+    }
+}
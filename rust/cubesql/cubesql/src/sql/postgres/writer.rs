@@ -1,6 +1,7 @@
 use crate::sql::{
     dataframe::{Decimal128Value, ListValue, TimestampValue},
     df_type_to_pg_tid,
+    postgres::copy::CopyOptions,
 };
 use bytes::{BufMut, BytesMut};
 use chrono::{
@@ -21,9 +22,8 @@ use datafusion::arrow::{
 use pg_srv::{
     protocol,
     protocol::{ErrorCode, ErrorResponse, Format, Serialize},
-    PgTypeId, ProtocolError, ToProtocolValue,
+    ProtocolError, ToProtocolValue,
 };
-use postgres_types::{ToSql, Type};
 use std::{convert::TryFrom, io, io::Error};
 
 // POSTGRES_EPOCH_JDATE
@@ -113,16 +113,7 @@ impl ToProtocolValue for Decimal128Value {
     }
 
     fn to_binary(&self, buf: &mut BytesMut) -> Result<(), ProtocolError> {
-        let mut tmp = postgres_types::private::BytesMut::new();
-        self.as_decimal()
-            .map_err(|err| ErrorResponse::error(ErrorCode::InternalError, err.to_string()))?
-            .to_sql(&Type::from_oid(PgTypeId::NUMERIC as u32).unwrap(), &mut tmp)
-            .map_err(|err| ErrorResponse::error(ErrorCode::InternalError, err.to_string()))?;
-
-        buf.put_i32(tmp.len() as i32);
-        buf.extend_from_slice(&tmp[..]);
-
-        Ok(())
+        self.as_numeric().to_binary(buf)
     }
 }
 
@@ -261,6 +252,8 @@ pub struct BatchWriter {
     current: u32,
     rows: u32,
     row: BytesMut,
+    // Rows are written as CopyData lines instead of DataRow messages
+    copy: Option<CopyOptions>,
 }
 
 impl BatchWriter {
@@ -271,6 +264,14 @@ impl BatchWriter {
             row: BytesMut::new(),
             current: 0,
             rows: 0,
+            copy: None,
+        }
+    }
+
+    pub fn new_copy(options: CopyOptions) -> Self {
+        Self {
+            copy: Some(options),
+            ..Self::new(Format::Text)
         }
     }
 
@@ -286,8 +287,20 @@ impl BatchWriter {
     }
 
     pub fn end_row(&mut self) -> Result<(), ProtocolError> {
-        self.data.extend_from_slice(&b'D'.to_be_bytes());
         let buffer = self.row.split();
+        if let Some(options) = &self.copy {
+            let line = Self::encode_copy_line(options, &buffer)?;
+
+            self.data.extend_from_slice(&b'd'.to_be_bytes());
+            self.data.put_i32(line.len() as i32 + 4);
+            self.data.extend(line);
+            self.current = 0;
+            self.rows += 1;
+
+            return Ok(());
+        }
+
+        self.data.extend_from_slice(&b'D'.to_be_bytes());
 
         self.data.put_i32(buffer.len() as i32 + 4 + 2);
 
@@ -301,6 +314,43 @@ impl BatchWriter {
         Ok(())
     }
 
+    /// Values in the row buffer are prefixed by length (-1 for NULL), the same way as in DataRow
+    fn encode_copy_line(options: &CopyOptions, buffer: &[u8]) -> Result<Vec<u8>, ProtocolError> {
+        let mut values: Vec<Option<&[u8]>> = vec![];
+        let mut offset = 0;
+
+        while offset < buffer.len() {
+            let len = buffer
+                .get(offset..offset + 4)
+                .map(|len| i32::from_be_bytes([len[0], len[1], len[2], len[3]]))
+                .ok_or_else(|| {
+                    ErrorResponse::error(
+                        ErrorCode::InternalError,
+                        "Malformed row buffer for COPY".to_string(),
+                    )
+                })?;
+            offset += 4;
+
+            if len < 0 {
+                values.push(None);
+            } else {
+                let end = offset + len as usize;
+                values.push(Some(buffer.get(offset..end).ok_or_else(|| {
+                    ErrorResponse::error(
+                        ErrorCode::InternalError,
+                        "Malformed row buffer for COPY".to_string(),
+                    )
+                })?));
+                offset = end;
+            }
+        }
+
+        let mut line = Vec::with_capacity(buffer.len());
+        options.encode_row(&values, &mut line);
+
+        Ok(line)
+    }
+
     pub fn num_rows(&self) -> u32 {
         self.rows
     }
@@ -325,6 +375,7 @@ impl<'a> Serialize for BatchWriter {
 mod tests {
     use crate::sql::{
         dataframe::{Decimal128Value, ListValue, TimestampValue},
+        postgres::copy::parse_copy_to,
         shim::ConnectionError,
         writer::{BatchWriter, ToProtocolValue},
     };
@@ -465,6 +516,28 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_backend_writer_copy() -> Result<(), ConnectionError> {
+        let mut cursor = Cursor::new(vec![]);
+        let mut writer = BatchWriter::new_copy(
+            parse_copy_to("COPY (SELECT 1) TO STDOUT WITH CSV")
+                .unwrap()
+                .unwrap()
+                .options,
+        );
+
+        writer.write_value("a,b".to_string())?;
+        writer.write_value(None::<i64>)?;
+        writer.write_value(1_i64)?;
+        writer.end_row()?;
+
+        buffer::write_direct(&mut cursor, writer).await?;
+
+        assert_eq!(cursor.get_ref()[0..], b"d\x00\x00\x00\x0d\"a,b\",,1\n"[..]);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_backend_writer_binary_int8_array() -> Result<(), ConnectionError> {
         let mut cursor = Cursor::new(vec![]);
@@ -90,6 +90,9 @@ impl SessionManager {
     pub async fn drop_session(&self, connection_id: u32) {
         let mut guard = self.sessions.write().await;
 
-        guard.remove(&connection_id);
+        if let Some(session) = guard.remove(&connection_id) {
+            // Client is gone, there is no reason to continue the query which was started for it
+            session.state.cancel_query();
+        }
     }
 }
@@ -657,6 +657,17 @@ impl<'ast> Visitor<'ast, ConnectionError> for PostgresStatementParamsBinder {
                     BindValue::Float64(v) => {
                         *value = ast::Value::Number(v.to_string(), *v < 0_f64);
                     }
+                    BindValue::Numeric(v) => {
+                        *value = ast::Value::Number(v.to_string(), v.value < 0);
+                    }
+                    BindValue::Date(v) => {
+                        *value = ast::Value::SingleQuotedString(v.format("%Y-%m-%d").to_string());
+                    }
+                    BindValue::Timestamp(v) => {
+                        *value = ast::Value::SingleQuotedString(
+                            v.format("%Y-%m-%d %H:%M:%S%.f").to_string(),
+                        );
+                    }
                     BindValue::Null => {
                         *value = ast::Value::Null;
                     }
@@ -722,6 +733,17 @@ impl<'ast> Visitor<'ast, ConnectionError> for MysqlStatementParamsBinder {
                     BindValue::Float64(v) => {
                         *value = ast::Value::Number(v.to_string(), *v < 0_f64);
                     }
+                    BindValue::Numeric(v) => {
+                        *value = ast::Value::Number(v.to_string(), v.value < 0);
+                    }
+                    BindValue::Date(v) => {
+                        *value = ast::Value::SingleQuotedString(v.format("%Y-%m-%d").to_string());
+                    }
+                    BindValue::Timestamp(v) => {
+                        *value = ast::Value::SingleQuotedString(
+                            v.format("%Y-%m-%d %H:%M:%S%.f").to_string(),
+                        );
+                    }
                     BindValue::Null => {
                         *value = ast::Value::Null;
                     }
@@ -1158,6 +1180,8 @@ impl<'ast> Visitor<'ast, ConnectionError> for SensitiveDataSanitizer {
 mod tests {
     use super::*;
     use crate::{compile::parser::MySqlDialectWithBackTicks, CubeError};
+    use chrono::NaiveDate;
+    use pg_srv::NumericValue;
     use sqlparser::{dialect::PostgreSqlDialect, parser::Parser};
 
     fn run_cast_replacer(input: &str, output: &str) -> Result<(), CubeError> {
@@ -1333,6 +1357,26 @@ mod tests {
             vec![BindValue::String("test1".to_string())],
         )?;
 
+        // Values, which are decoded from binary parameters
+        run_pg_binder(
+            r#"
+                SELECT *
+                FROM testdata
+                WHERE fieldA = $1 AND fieldB >= $2 AND fieldC < $3
+            "#,
+            "SELECT * FROM testdata WHERE fieldA = -1.50 AND fieldB >= '2022-04-25' AND fieldC < '2022-04-25 15:36:49.397050'",
+            vec![
+                BindValue::Numeric(NumericValue::new(-150, 2)),
+                BindValue::Date(NaiveDate::from_ymd_opt(2022, 4, 25).unwrap()),
+                BindValue::Timestamp(
+                    NaiveDate::from_ymd_opt(2022, 4, 25)
+                        .unwrap()
+                        .and_hms_micro_opt(15, 36, 49, 397050)
+                        .unwrap(),
+                ),
+            ],
+        )?;
+
         Ok(())
     }
 
@@ -147,6 +147,17 @@ pub trait TransportService: Send + Sync + Debug {
         member_fields: Vec<MemberField>,
     ) -> Result<CubeStreamReceiver, CubeError>;
 
+    // Cancel load queries started for the span, called when the query is cancelled by
+    // the client (CancelRequest, KILL QUERY) or the connection is closed before completion.
+    // Implementations without a way to cancel the load in Cube must return an error, the load
+    // future is dropped anyway
+    async fn cancel_load(
+        &self,
+        span_id: Option<Arc<SpanId>>,
+        ctx: AuthContextRef,
+        meta_fields: LoadRequestMeta,
+    ) -> Result<(), CubeError>;
+
     async fn can_switch_user_for_session(
         &self,
         ctx: AuthContextRef,
@@ -304,6 +315,20 @@ impl TransportService for HttpTransport {
         panic!("Does not work for standalone mode yet");
     }
 
+    async fn cancel_load(
+        &self,
+        _span_id: Option<Arc<SpanId>>,
+        _ctx: AuthContextRef,
+        _meta_fields: LoadRequestMeta,
+    ) -> Result<(), CubeError> {
+        // REST API doesn't have an endpoint to cancel the query. Dropping the load future closes
+        // the HTTP request, but the query that was already sent to the data source keeps running
+        // there until it finishes
+        Err(CubeError::internal(
+            "Cancelling a load is not supported in the standalone mode".to_string(),
+        ))
+    }
+
     async fn can_switch_user_for_session(
         &self,
         _ctx: AuthContextRef,
@@ -36,6 +36,9 @@ pub async fn read_message<Reader: AsyncReadExt + Unpin + Send>(
         b'X' => FrontendMessage::Terminate,
         b'H' => FrontendMessage::Flush,
         b'S' => FrontendMessage::Sync,
+        b'd' => FrontendMessage::CopyData,
+        b'c' => FrontendMessage::CopyDone,
+        b'f' => FrontendMessage::CopyFail(protocol::CopyFail::deserialize(cursor).await?),
         identifier => {
             return Err(ErrorResponse::error(
                 ErrorCode::DataException,
@@ -50,6 +53,30 @@ pub async fn read_message<Reader: AsyncReadExt + Unpin + Send>(
     Ok(message)
 }
 
+/// Read message with the expected identifier and decode it as the specified type.
+/// It's used for messages which cannot be identified by the identifier alone, for example,
+/// PasswordMessage, SASLInitialResponse and SASLResponse share `p`, only the connection state tells them apart.
+pub async fn read_typed_message<Reader: AsyncReadExt + Unpin + Send, Message: Deserialize>(
+    reader: &mut Reader,
+    expected_tag: u8,
+) -> Result<Message, ProtocolError> {
+    let message_tag = reader.read_u8().await?;
+    if message_tag != expected_tag {
+        return Err(ErrorResponse::error(
+            ErrorCode::ProtocolViolation,
+            format!(
+                "Unexpected message identifier: {:X?}, expected: {:X?}",
+                message_tag, expected_tag
+            ),
+        )
+        .into());
+    }
+
+    let cursor = read_contents(reader, message_tag).await?;
+
+    Message::deserialize(cursor).await
+}
+
 pub async fn read_contents<Reader: AsyncReadExt + Unpin>(
     reader: &mut Reader,
     message_tag: u8,
@@ -1,11 +1,17 @@
 //! Decoding values from the Protocol representation
 
 use crate::{
+    encoding::{
+        NumericValue, NUMERIC_DEC_DIGITS, NUMERIC_DSCALE_MAX, NUMERIC_NAN, NUMERIC_NBASE,
+        NUMERIC_NEG, NUMERIC_POS,
+    },
     protocol::{ErrorCode, ErrorResponse, Format},
     ProtocolError,
 };
 use byteorder::{BigEndian, ByteOrder};
-use std::backtrace::Backtrace;
+#[cfg(feature = "with-chrono")]
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use std::{backtrace::Backtrace, convert::TryFrom};
 
 /// This trait explains how to decode values from the protocol
 /// It's used in the Bind message
@@ -32,6 +38,35 @@ pub trait FromProtocolValue {
         Self: Sized;
 }
 
+fn decoding_error(message: String) -> ProtocolError {
+    ProtocolError::ErrorResponse {
+        source: ErrorResponse::error(ErrorCode::ProtocolViolation, message),
+        backtrace: Backtrace::capture(),
+    }
+}
+
+fn text_from_raw(raw: &Vec<u8>) -> Result<&str, ProtocolError> {
+    std::str::from_utf8(&raw[..]).map_err(|err| decoding_error(err.to_string()))
+}
+
+/// Binary representation of fixed length types must have an exact size
+fn check_binary_length(
+    raw: &Vec<u8>,
+    expected: usize,
+    type_name: &str,
+) -> Result<(), ProtocolError> {
+    if raw.len() != expected {
+        return Err(decoding_error(format!(
+            "Unable to decode {} from binary: expected {} bytes, actual: {}",
+            type_name,
+            expected,
+            raw.len()
+        )));
+    }
+
+    Ok(())
+}
+
 impl FromProtocolValue for String {
     fn from_text(raw: &Vec<u8>) -> Result<Self, ProtocolError> {
         String::from_utf8(raw.clone()).map_err(|err| ProtocolError::ErrorResponse {
@@ -48,27 +83,58 @@ impl FromProtocolValue for String {
     }
 }
 
-impl FromProtocolValue for i64 {
-    fn from_text(raw: &Vec<u8>) -> Result<Self, ProtocolError> {
-        let as_str =
-            String::from_utf8(raw.clone()).map_err(|err| ProtocolError::ErrorResponse {
-                source: ErrorResponse::error(ErrorCode::ProtocolViolation, err.to_string()),
-                backtrace: Backtrace::capture(),
-            })?;
+macro_rules! impl_primitive {
+    ($type: ident, $read: ident) => {
+        impl FromProtocolValue for $type {
+            fn from_text(raw: &Vec<u8>) -> Result<Self, ProtocolError> {
+                text_from_raw(raw)?
+                    .trim()
+                    .parse::<$type>()
+                    .map_err(|err| decoding_error(err.to_string()))
+            }
 
-        as_str
-            .parse::<i64>()
-            .map_err(|err| ProtocolError::ErrorResponse {
-                source: ErrorResponse::error(ErrorCode::ProtocolViolation, err.to_string()),
-                backtrace: Backtrace::capture(),
-            })
-    }
+            fn from_binary(raw: &Vec<u8>) -> Result<Self, ProtocolError> {
+                check_binary_length(raw, std::mem::size_of::<$type>(), stringify!($type))?;
 
-    fn from_binary(raw: &Vec<u8>) -> Result<Self, ProtocolError> {
-        Ok(BigEndian::read_i64(&raw[..]))
-    }
+                Ok(BigEndian::$read(&raw[..]))
+            }
+        }
+    };
 }
 
+impl_primitive!(i16, read_i16);
+impl_primitive!(i32, read_i32);
+impl_primitive!(i64, read_i64);
+
+macro_rules! impl_float {
+    ($type: ident, $read: ident) => {
+        impl FromProtocolValue for $type {
+            // float8in_internal - https://github.com/postgres/postgres/blob/REL_14_4/src/backend/utils/adt/float.c#L380
+            fn from_text(raw: &Vec<u8>) -> Result<Self, ProtocolError> {
+                let as_str = text_from_raw(raw)?.trim();
+
+                match as_str.to_lowercase().as_str() {
+                    "nan" => Ok($type::NAN),
+                    "infinity" | "+infinity" | "inf" | "+inf" => Ok($type::INFINITY),
+                    "-infinity" | "-inf" => Ok($type::NEG_INFINITY),
+                    _ => as_str
+                        .parse::<$type>()
+                        .map_err(|err| decoding_error(err.to_string())),
+                }
+            }
+
+            fn from_binary(raw: &Vec<u8>) -> Result<Self, ProtocolError> {
+                check_binary_length(raw, std::mem::size_of::<$type>(), stringify!($type))?;
+
+                Ok(BigEndian::$read(&raw[..]))
+            }
+        }
+    };
+}
+
+impl_float!(f32, read_f32);
+impl_float!(f64, read_f64);
+
 impl FromProtocolValue for bool {
     fn from_text(raw: &Vec<u8>) -> Result<Self, ProtocolError> {
         match raw[0] {
@@ -99,12 +165,268 @@ impl FromProtocolValue for bool {
     }
 }
 
+impl FromProtocolValue for NumericValue {
+    // numeric_in - https://github.com/postgres/postgres/blob/REL_14_4/src/backend/utils/adt/numeric.c#L617
+    fn from_text(raw: &Vec<u8>) -> Result<Self, ProtocolError> {
+        let as_str = text_from_raw(raw)?.trim();
+        let invalid = || {
+            decoding_error(format!(
+                r#"Unable to decode numeric from text: "{}""#,
+                as_str
+            ))
+        };
+
+        let (mantissa, exponent) = match as_str.find(['e', 'E']) {
+            Some(pos) => (
+                &as_str[..pos],
+                as_str[pos + 1..].parse::<i32>().map_err(|_| invalid())?,
+            ),
+            None => (as_str, 0),
+        };
+
+        let (negative, mantissa) = match mantissa.as_bytes().first() {
+            Some(b'-') => (true, &mantissa[1..]),
+            Some(b'+') => (false, &mantissa[1..]),
+            _ => (false, mantissa),
+        };
+
+        let (int_part, frac_part) = match mantissa.split_once('.') {
+            Some((int_part, frac_part)) => (int_part, frac_part),
+            None => (mantissa, ""),
+        };
+
+        if int_part.is_empty() && frac_part.is_empty()
+            || !int_part
+                .bytes()
+                .chain(frac_part.bytes())
+                .all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        // Checked before building the string, so a huge exponent can't allocate unbounded memory
+        let mut scale = i32::try_from(frac_part.len())
+            .ok()
+            .and_then(|len| len.checked_sub(exponent))
+            .filter(|scale| scale.unsigned_abs() <= NUMERIC_DSCALE_MAX as u32)
+            .ok_or_else(invalid)?;
+        let mut value = format!("{}{}", int_part, frac_part);
+        if scale < 0 {
+            value.push_str(&"0".repeat((-scale) as usize));
+            scale = 0;
+        }
+
+        let value = value.parse::<i128>().map_err(|_| invalid())?;
+        let scale = u16::try_from(scale).map_err(|_| invalid())?;
+
+        Ok(NumericValue::new(
+            if negative { -value } else { value },
+            scale,
+        ))
+    }
+
+    // numeric_recv - https://github.com/postgres/postgres/blob/REL_14_4/src/backend/utils/adt/numeric.c#L1026
+    fn from_binary(raw: &Vec<u8>) -> Result<Self, ProtocolError> {
+        if raw.len() < 8 {
+            return Err(decoding_error(format!(
+                "Unable to decode numeric from binary: expected at least 8 bytes, actual: {}",
+                raw.len()
+            )));
+        }
+
+        let ndigits = BigEndian::read_i16(&raw[0..2]);
+        let weight = BigEndian::read_i16(&raw[2..4]) as i32;
+        let sign = BigEndian::read_u16(&raw[4..6]);
+        let dscale = BigEndian::read_u16(&raw[6..8]);
+
+        match sign {
+            NUMERIC_POS | NUMERIC_NEG => {}
+            NUMERIC_NAN => {
+                return Err(ErrorResponse::error(
+                    ErrorCode::FeatureNotSupported,
+                    "NaN is not supported for numeric parameters".to_string(),
+                )
+                .into())
+            }
+            other => {
+                return Err(decoding_error(format!(
+                    "Unable to decode numeric from binary: invalid sign: {}",
+                    other
+                )))
+            }
+        }
+
+        if ndigits < 0 {
+            return Err(decoding_error(format!(
+                "Unable to decode numeric from binary: invalid number of digits: {}",
+                ndigits
+            )));
+        }
+
+        if dscale > NUMERIC_DSCALE_MAX {
+            return Err(decoding_error(format!(
+                "Unable to decode numeric from binary: invalid scale: {}",
+                dscale
+            )));
+        }
+
+        check_binary_length(raw, 8 + 2 * ndigits as usize, "numeric")?;
+
+        let digits = raw[8..]
+            .chunks(2)
+            .map(BigEndian::read_i16)
+            .collect::<Vec<_>>();
+        if let Some(digit) = digits.iter().find(|d| !(0..NUMERIC_NBASE).contains(*d)) {
+            return Err(decoding_error(format!(
+                "Unable to decode numeric from binary: invalid digit: {}",
+                digit
+            )));
+        }
+        let digit_at = |idx: i32| -> i16 {
+            if idx < 0 {
+                0
+            } else {
+                digits.get(idx as usize).cloned().unwrap_or(0)
+            }
+        };
+
+        let mut as_str = String::new();
+        if sign == NUMERIC_NEG {
+            as_str.push('-');
+        }
+
+        if weight < 0 {
+            as_str.push('0');
+        } else {
+            for idx in 0..=weight {
+                if idx == 0 {
+                    as_str.push_str(&digit_at(idx).to_string());
+                } else {
+                    as_str.push_str(&format!("{:04}", digit_at(idx)));
+                }
+            }
+        }
+
+        if dscale > 0 {
+            let frac_digits = (dscale as usize).div_ceil(NUMERIC_DEC_DIGITS);
+            let frac = (1..=frac_digits as i32)
+                .map(|idx| format!("{:04}", digit_at(weight + idx)))
+                .collect::<String>();
+
+            as_str.push('.');
+            as_str.push_str(&frac[..dscale as usize]);
+        }
+
+        Self::from_text(&as_str.into_bytes())
+    }
+}
+
+// POSTGRES_EPOCH_JDATE
+#[cfg(feature = "with-chrono")]
+fn pg_base_date_epoch() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2000, 1, 1)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+}
+
+#[cfg(feature = "with-chrono")]
+impl FromProtocolValue for NaiveDate {
+    // date_in - https://github.com/postgres/postgres/blob/REL_14_4/src/backend/utils/adt/date.c#L111
+    fn from_text(raw: &Vec<u8>) -> Result<Self, ProtocolError> {
+        let as_str = text_from_raw(raw)?.trim();
+
+        NaiveDate::parse_from_str(as_str, "%Y-%m-%d").map_err(|err| {
+            decoding_error(format!(
+                r#"Unable to decode date from text: "{}", error: {}"#,
+                as_str, err
+            ))
+        })
+    }
+
+    // date_recv - https://github.com/postgres/postgres/blob/REL_14_4/src/backend/utils/adt/date.c#L201
+    fn from_binary(raw: &Vec<u8>) -> Result<Self, ProtocolError> {
+        check_binary_length(raw, 4, "date")?;
+
+        let days = BigEndian::read_i32(&raw[..]);
+        pg_base_date_epoch()
+            .date()
+            .checked_add_signed(Duration::days(days as i64))
+            .ok_or_else(|| decoding_error(format!("date out of range: {}", days)))
+    }
+}
+
+#[cfg(feature = "with-chrono")]
+fn timestamp_from_pg_microseconds(raw: &Vec<u8>) -> Result<NaiveDateTime, ProtocolError> {
+    check_binary_length(raw, 8, "timestamp")?;
+
+    let microseconds = BigEndian::read_i64(&raw[..]);
+    pg_base_date_epoch()
+        .checked_add_signed(Duration::microseconds(microseconds))
+        .ok_or_else(|| decoding_error(format!("timestamp out of range: {}", microseconds)))
+}
+
+#[cfg(feature = "with-chrono")]
+impl FromProtocolValue for NaiveDateTime {
+    // timestamp_in - https://github.com/postgres/postgres/blob/REL_14_4/src/backend/utils/adt/timestamp.c#L145
+    fn from_text(raw: &Vec<u8>) -> Result<Self, ProtocolError> {
+        let as_str = text_from_raw(raw)?.trim();
+
+        NaiveDateTime::parse_from_str(as_str, "%Y-%m-%d %H:%M:%S%.f")
+            .or_else(|_| NaiveDateTime::parse_from_str(as_str, "%Y-%m-%dT%H:%M:%S%.f"))
+            .or_else(|_| {
+                NaiveDate::parse_from_str(as_str, "%Y-%m-%d")
+                    .map(|date| date.and_hms_opt(0, 0, 0).unwrap())
+            })
+            .map_err(|err| {
+                decoding_error(format!(
+                    r#"Unable to decode timestamp from text: "{}", error: {}"#,
+                    as_str, err
+                ))
+            })
+    }
+
+    // timestamp_recv - https://github.com/postgres/postgres/blob/REL_14_4/src/backend/utils/adt/timestamp.c#L253
+    fn from_binary(raw: &Vec<u8>) -> Result<Self, ProtocolError> {
+        timestamp_from_pg_microseconds(raw)
+    }
+}
+
+#[cfg(feature = "with-chrono")]
+impl FromProtocolValue for DateTime<Utc> {
+    // timestamptz_in - https://github.com/postgres/postgres/blob/REL_14_4/src/backend/utils/adt/timestamp.c#L403
+    fn from_text(raw: &Vec<u8>) -> Result<Self, ProtocolError> {
+        let as_str = text_from_raw(raw)?.trim();
+
+        DateTime::parse_from_str(as_str, "%Y-%m-%d %H:%M:%S%.f%#z")
+            .or_else(|_| DateTime::parse_from_str(as_str, "%Y-%m-%dT%H:%M:%S%.f%#z"))
+            .map(|dt| dt.with_timezone(&Utc))
+            .or_else(|err| {
+                // Timestamp without time zone is interpreted as UTC, which is the only TimeZone for sessions
+                NaiveDateTime::from_text(raw)
+                    .map(|ndt| Utc.from_utc_datetime(&ndt))
+                    .map_err(|_| {
+                        decoding_error(format!(
+                            r#"Unable to decode timestamptz from text: "{}", error: {}"#,
+                            as_str, err
+                        ))
+                    })
+            })
+    }
+
+    // timestamptz_recv - https://github.com/postgres/postgres/blob/REL_14_4/src/backend/utils/adt/timestamp.c#L798
+    fn from_binary(raw: &Vec<u8>) -> Result<Self, ProtocolError> {
+        timestamp_from_pg_microseconds(raw).map(|ndt| Utc.from_utc_datetime(&ndt))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::*;
 
     use crate::protocol::Format;
     use bytes::BytesMut;
+    use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
 
     fn assert_test_decode<T: ToProtocolValue + FromProtocolValue + std::cmp::PartialEq>(
         value: T,
@@ -122,6 +444,23 @@ mod tests {
         Ok(())
     }
 
+    /// Value must be the same after decoding from both formats
+    fn assert_round_trip<T: ToProtocolValue + FromProtocolValue + std::cmp::PartialEq + Clone>(
+        value: T,
+    ) -> Result<(), ProtocolError> {
+        assert_test_decode(value.clone(), Format::Text)?;
+        assert_test_decode(value, Format::Binary)?;
+
+        Ok(())
+    }
+
+    fn test_timestamp() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2022, 4, 25)
+            .unwrap()
+            .and_hms_micro_opt(15, 36, 49, 397050)
+            .unwrap()
+    }
+
     #[test]
     fn test_text_decoders() -> Result<(), ProtocolError> {
         assert_test_decode("test".to_string(), Format::Text)?;
@@ -143,4 +482,117 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_scalar_round_trip() -> Result<(), ProtocolError> {
+        assert_round_trip(-5_i16)?;
+        assert_round_trip(i32::MAX)?;
+        assert_round_trip(i64::MIN)?;
+        assert_round_trip(1.5_f32)?;
+        assert_round_trip(-0.25_f64)?;
+        assert_round_trip(f64::INFINITY)?;
+        assert_round_trip(f64::NEG_INFINITY)?;
+
+        assert_round_trip(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap())?;
+        assert_round_trip(NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())?;
+        assert_round_trip(NaiveDate::from_ymd_opt(2099, 12, 31).unwrap())?;
+
+        assert_round_trip(test_timestamp())?;
+        assert_round_trip(
+            NaiveDate::from_ymd_opt(1960, 2, 29)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+        )?;
+        assert_round_trip(Utc.from_utc_datetime(&test_timestamp()))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_numeric_round_trip() -> Result<(), ProtocolError> {
+        for (value, scale) in [
+            (0, 0),
+            (0, 5),
+            (1, 0),
+            (-1, 4),
+            (12345678, 3),
+            (-12345678, 3),
+            (10000000000, 0),
+            (10000000000, 10),
+            (5, 20),
+            (i128::MAX / 10, 0),
+            (-(i128::MAX / 10), 38),
+        ] {
+            assert_round_trip(NumericValue::new(value, scale))?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_numeric_text_decoder() -> Result<(), ProtocolError> {
+        let decode = |s: &str| NumericValue::from_text(&s.as_bytes().to_vec());
+
+        assert_eq!(decode("123.45")?, NumericValue::new(12345, 2));
+        assert_eq!(decode("-0.5")?, NumericValue::new(-5, 1));
+        assert_eq!(decode("+.5")?, NumericValue::new(5, 1));
+        assert_eq!(decode("1.5e3")?, NumericValue::new(1500, 0));
+        assert_eq!(decode("1.5E-3")?, NumericValue::new(15, 4));
+        assert!(decode("NaN").is_err());
+        assert!(decode("1.2.3").is_err());
+        assert!(decode("").is_err());
+        assert!(decode("1e-2147483648").is_err());
+        assert!(decode("1e900000000").is_err());
+        assert!(decode("1e16384").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_timestamp_text_decoders() -> Result<(), ProtocolError> {
+        let decode = |s: &str| DateTime::<Utc>::from_text(&s.as_bytes().to_vec());
+
+        assert_eq!(
+            decode("2022-04-25 18:36:49.39705+03")?,
+            Utc.from_utc_datetime(&test_timestamp())
+        );
+        assert_eq!(
+            decode("2022-04-25T15:36:49.39705")?,
+            Utc.from_utc_datetime(&test_timestamp())
+        );
+        assert_eq!(
+            NaiveDateTime::from_text(&b"2022-04-25".to_vec())?,
+            NaiveDate::from_ymd_opt(2022, 4, 25)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_binary_decoders_length() {
+        assert!(i64::from_binary(&vec![0, 1]).is_err());
+        assert!(NaiveDate::from_binary(&vec![0, 0, 0, 0, 0]).is_err());
+        assert!(NumericValue::from_binary(&vec![0, 2, 0, 0, 0, 0, 0, 0, 0, 1]).is_err());
+    }
+
+    #[test]
+    fn test_numeric_binary_decoder_invalid() {
+        // ndigits, weight, sign, dscale, digits
+        let numeric = |dscale: u16, digit: i16| {
+            let mut raw = vec![0, 1, 0, 0, 0, 0];
+            raw.extend_from_slice(&dscale.to_be_bytes());
+            raw.extend_from_slice(&digit.to_be_bytes());
+            NumericValue::from_binary(&raw)
+        };
+
+        assert_eq!(numeric(0, 9999).unwrap(), NumericValue::new(9999, 0));
+        assert!(numeric(0, 10000).is_err());
+        assert!(numeric(0, -1).is_err());
+        assert!(numeric(NUMERIC_DSCALE_MAX + 1, 1).is_err());
+        assert!(NumericValue::from_binary(&vec![0xFF, 0xFF, 0, 0, 0, 0, 0, 0]).is_err());
+    }
 }
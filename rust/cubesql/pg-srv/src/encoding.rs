@@ -3,7 +3,7 @@
 use crate::{protocol::Format, ProtocolError};
 use bytes::{BufMut, BytesMut};
 #[cfg(feature = "with-chrono")]
-use chrono::{NaiveDate, NaiveDateTime};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use std::io::{Error, ErrorKind};
 
 /// This trait explains how to encode values to the protocol format
@@ -104,8 +104,155 @@ impl_primitive!(i8);
 impl_primitive!(i16);
 impl_primitive!(i32);
 impl_primitive!(i64);
-impl_primitive!(f32);
-impl_primitive!(f64);
+
+macro_rules! impl_float {
+    ($type: ident) => {
+        impl ToProtocolValue for $type {
+            // float8out_internal - https://github.com/postgres/postgres/blob/REL_14_4/src/backend/utils/adt/float.c#L545
+            fn to_text(&self, buf: &mut BytesMut) -> Result<(), ProtocolError> {
+                if self.is_nan() {
+                    "NaN".to_string().to_text(buf)
+                } else if self.is_infinite() {
+                    if self.is_sign_negative() {
+                        "-Infinity".to_string().to_text(buf)
+                    } else {
+                        "Infinity".to_string().to_text(buf)
+                    }
+                } else {
+                    self.to_string().to_text(buf)
+                }
+            }
+
+            fn to_binary(&self, buf: &mut BytesMut) -> Result<(), ProtocolError> {
+                buf.extend_from_slice(&(std::mem::size_of::<$type>() as u32).to_be_bytes());
+                buf.extend_from_slice(&self.to_be_bytes());
+
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_float!(f32);
+impl_float!(f64);
+
+/// Decimal value, which is represented by the unscaled integer and scale (number of digits after the point).
+/// It's used to encode/decode NUMERIC without losing precision.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NumericValue {
+    pub value: i128,
+    pub scale: u16,
+}
+
+// Constants from https://github.com/postgres/postgres/blob/REL_14_4/src/backend/utils/adt/numeric.c#L167
+pub(crate) const NUMERIC_POS: u16 = 0x0000;
+pub(crate) const NUMERIC_NEG: u16 = 0x4000;
+pub(crate) const NUMERIC_NAN: u16 = 0xC000;
+// NUMERIC is stored in base 10000, every digit holds 4 decimal digits
+pub(crate) const NUMERIC_DEC_DIGITS: usize = 4;
+pub(crate) const NUMERIC_NBASE: i16 = 10000;
+// Maximum display scale, larger values are rejected by numeric_recv
+pub(crate) const NUMERIC_DSCALE_MAX: u16 = 0x3FFF;
+
+impl NumericValue {
+    pub fn new(value: i128, scale: u16) -> Self {
+        Self { value, scale }
+    }
+
+    /// Digits in base 10000 and weight of the first digit
+    fn to_base_digits(self) -> (Vec<i16>, i16) {
+        let scale = self.scale as usize;
+        let abs = self.value.unsigned_abs().to_string();
+        let (int_part, frac_part) = if abs.len() > scale {
+            abs.split_at(abs.len() - scale)
+        } else {
+            ("", abs.as_str())
+        };
+
+        let int_padding =
+            (NUMERIC_DEC_DIGITS - int_part.len() % NUMERIC_DEC_DIGITS) % NUMERIC_DEC_DIGITS;
+        let frac_padding = scale - frac_part.len();
+        let frac_tail = (NUMERIC_DEC_DIGITS - scale % NUMERIC_DEC_DIGITS) % NUMERIC_DEC_DIGITS;
+
+        let decimal_digits = "0".repeat(int_padding)
+            + int_part
+            + &"0".repeat(frac_padding)
+            + frac_part
+            + &"0".repeat(frac_tail);
+
+        let mut digits = decimal_digits
+            .as_bytes()
+            .chunks(NUMERIC_DEC_DIGITS)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .fold(0_i16, |acc, d| acc * 10 + (d - b'0') as i16)
+            })
+            .collect::<Vec<_>>();
+        let mut weight = ((int_padding + int_part.len()) / NUMERIC_DEC_DIGITS) as i16 - 1;
+
+        // Leading and trailing zeros are not stored
+        let leading_zeros = digits.iter().take_while(|d| **d == 0).count();
+        digits.drain(..leading_zeros);
+        weight -= leading_zeros as i16;
+
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+
+        if digits.is_empty() {
+            weight = 0;
+        }
+
+        (digits, weight)
+    }
+}
+
+impl ToString for NumericValue {
+    fn to_string(&self) -> String {
+        let as_str = self.value.unsigned_abs().to_string();
+        let sign = if self.value < 0 { "-" } else { "" };
+        let scale = self.scale as usize;
+
+        if scale == 0 {
+            format!("{}{}", sign, as_str)
+        } else if as_str.len() > scale {
+            let (int_part, frac_part) = as_str.split_at(as_str.len() - scale);
+
+            format!("{}{}.{}", sign, int_part, frac_part)
+        } else {
+            format!("{}0.{:0>width$}", sign, as_str, width = scale)
+        }
+    }
+}
+
+impl ToProtocolValue for NumericValue {
+    // numeric_out - https://github.com/postgres/postgres/blob/REL_14_4/src/backend/utils/adt/numeric.c#L743
+    fn to_text(&self, buf: &mut BytesMut) -> Result<(), ProtocolError> {
+        self.to_string().to_text(buf)
+    }
+
+    // numeric_send - https://github.com/postgres/postgres/blob/REL_14_4/src/backend/utils/adt/numeric.c#L1103
+    fn to_binary(&self, buf: &mut BytesMut) -> Result<(), ProtocolError> {
+        let (digits, weight) = self.to_base_digits();
+
+        buf.put_i32(8 + 2 * digits.len() as i32);
+        buf.put_i16(digits.len() as i16);
+        buf.put_i16(weight);
+        buf.put_u16(if self.value < 0 && !digits.is_empty() {
+            NUMERIC_NEG
+        } else {
+            NUMERIC_POS
+        });
+        buf.put_u16(self.scale);
+
+        for digit in digits {
+            buf.put_i16(digit);
+        }
+
+        Ok(())
+    }
+}
 
 // POSTGRES_EPOCH_JDATE
 #[cfg(feature = "with-chrono")]
@@ -146,6 +293,56 @@ impl ToProtocolValue for NaiveDate {
     }
 }
 
+#[cfg(feature = "with-chrono")]
+fn timestamp_to_pg_microseconds(ndt: &NaiveDateTime) -> Result<i64, ProtocolError> {
+    ndt.signed_duration_since(pg_base_date_epoch())
+        .num_microseconds()
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::Other,
+                format!(
+                    "Unable to extract number of microseconds from timestamp: {}",
+                    ndt
+                ),
+            )
+            .into()
+        })
+}
+
+#[cfg(feature = "with-chrono")]
+impl ToProtocolValue for NaiveDateTime {
+    // timestamp_out - https://github.com/postgres/postgres/blob/REL_14_4/src/backend/utils/adt/timestamp.c#L232
+    fn to_text(&self, buf: &mut BytesMut) -> Result<(), ProtocolError> {
+        self.format("%Y-%m-%d %H:%M:%S%.6f")
+            .to_string()
+            .to_text(buf)
+    }
+
+    // timestamp_send - https://github.com/postgres/postgres/blob/REL_14_4/src/backend/utils/adt/timestamp.c#L285
+    fn to_binary(&self, buf: &mut BytesMut) -> Result<(), ProtocolError> {
+        buf.put_i32(8);
+        buf.put_i64(timestamp_to_pg_microseconds(self)?);
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "with-chrono")]
+impl ToProtocolValue for DateTime<Utc> {
+    // timestamptz_out - https://github.com/postgres/postgres/blob/REL_14_4/src/backend/utils/adt/timestamp.c#L774
+    fn to_text(&self, buf: &mut BytesMut) -> Result<(), ProtocolError> {
+        (self.naive_utc().format("%Y-%m-%d %H:%M:%S%.6f").to_string() + "+00").to_text(buf)
+    }
+
+    // timestamptz_send - https://github.com/postgres/postgres/blob/REL_14_4/src/backend/utils/adt/timestamp.c#L827
+    fn to_binary(&self, buf: &mut BytesMut) -> Result<(), ProtocolError> {
+        buf.put_i32(8);
+        buf.put_i64(timestamp_to_pg_microseconds(&self.naive_utc())?);
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct IntervalValue {
     pub months: i32,
@@ -281,6 +478,7 @@ impl ToProtocolValue for IntervalValue {
 mod tests {
     use crate::*;
     use bytes::BytesMut;
+    use chrono::NaiveDate;
 
     fn assert_text_encode<T: ToProtocolValue>(value: T, expected: &[u8]) {
         let mut buf = BytesMut::new();
@@ -305,10 +503,71 @@ mod tests {
         assert_eq!(&buf.as_ref()[..], expected);
     }
 
+    #[test]
+    fn test_text_float_encoders() -> Result<(), ProtocolError> {
+        assert_text_encode(1.5_f64, b"\0\0\0\x031.5");
+        assert_text_encode(f64::NAN, b"\0\0\0\x03NaN");
+        assert_text_encode(f32::INFINITY, b"\0\0\0\x08Infinity");
+        assert_text_encode(f64::NEG_INFINITY, b"\0\0\0\x09-Infinity");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_text_numeric_encoders() -> Result<(), ProtocolError> {
+        assert_eq!(NumericValue::new(0, 0).to_string(), "0");
+        assert_eq!(NumericValue::new(12345, 2).to_string(), "123.45");
+        assert_eq!(NumericValue::new(-12345, 2).to_string(), "-123.45");
+        assert_eq!(NumericValue::new(-5, 3).to_string(), "-0.005");
+        assert_eq!(NumericValue::new(100, 0).to_string(), "100");
+
+        Ok(())
+    }
+
     #[test]
     fn test_binary_encoders() -> Result<(), ProtocolError> {
         assert_bind_encode(true, &[0, 0, 0, 1, 1]);
         assert_bind_encode(false, &[0, 0, 0, 1, 0]);
+        // 2000-01-01
+        assert_bind_encode(
+            NaiveDate::from_ymd_opt(2000, 1, 2).unwrap(),
+            &[0, 0, 0, 4, 0, 0, 0, 1],
+        );
+        assert_bind_encode(
+            NaiveDate::from_ymd_opt(2000, 1, 1)
+                .unwrap()
+                .and_hms_micro_opt(0, 0, 1, 5)
+                .unwrap(),
+            &[0, 0, 0, 8, 0, 0, 0, 0, 0, 0x0f, 0x42, 0x45],
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_binary_numeric_encoders() -> Result<(), ProtocolError> {
+        // Captured from PostgreSQL: SELECT 0::numeric
+        assert_bind_encode(
+            NumericValue::new(0, 0),
+            &[0, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0],
+        );
+        // SELECT 12345.678::numeric
+        assert_bind_encode(
+            NumericValue::new(12345678, 3),
+            &[
+                0, 0, 0, 14, 0, 3, 0, 1, 0, 0, 0, 3, 0, 1, 0x09, 0x29, 0x1a, 0x7c,
+            ],
+        );
+        // SELECT -0.0001::numeric
+        assert_bind_encode(
+            NumericValue::new(-1, 4),
+            &[0, 0, 0, 10, 0, 1, 0xff, 0xff, 0x40, 0, 0, 4, 0, 1],
+        );
+        // SELECT 10000000000::numeric
+        assert_bind_encode(
+            NumericValue::new(10000000000, 0),
+            &[0, 0, 0, 10, 0, 1, 0, 2, 0, 0, 0, 0, 0, 100],
+        );
 
         Ok(())
     }
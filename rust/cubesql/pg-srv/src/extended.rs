@@ -1,10 +1,20 @@
 //! Implementation for Extended Query
 
+use crate::NumericValue;
+#[cfg(feature = "with-chrono")]
+use chrono::{NaiveDate, NaiveDateTime};
+
 #[derive(Debug, PartialEq)]
 pub enum BindValue {
     String(String),
     Int64(i64),
     Float64(f64),
     Bool(bool),
+    Numeric(NumericValue),
+    #[cfg(feature = "with-chrono")]
+    Date(NaiveDate),
+    /// TIMESTAMPTZ is converted to UTC
+    #[cfg(feature = "with-chrono")]
+    Timestamp(NaiveDateTime),
     Null,
 }
@@ -13,6 +13,7 @@ pub mod buffer;
 pub mod extended;
 pub mod pg_type;
 pub mod protocol;
+pub mod scram;
 
 pub use buffer::*;
 pub use decoding::*;
@@ -2064,4 +2064,25 @@ impl PgTypeId {
     pub fn to_type(self) -> &'static PgType<'static> {
         PgType::get_by_tid(self)
     }
+
+    /// Types which can be decoded from parameters in Bind, see [`crate::protocol::Bind::to_bind_values`]
+    pub fn is_bind_supported(self) -> bool {
+        match self {
+            PgTypeId::TEXT
+            | PgTypeId::VARCHAR
+            | PgTypeId::BPCHAR
+            | PgTypeId::NAME
+            | PgTypeId::BOOL
+            | PgTypeId::INT2
+            | PgTypeId::INT4
+            | PgTypeId::INT8
+            | PgTypeId::FLOAT4
+            | PgTypeId::FLOAT8
+            | PgTypeId::NUMERIC => true,
+            PgTypeId::DATE | PgTypeId::TIMESTAMP | PgTypeId::TIMESTAMPTZ => {
+                cfg!(feature = "with-chrono")
+            }
+            _ => false,
+        }
+    }
 }
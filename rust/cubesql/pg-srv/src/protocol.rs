@@ -14,7 +14,9 @@ use async_trait::async_trait;
 use bytes::BufMut;
 use tokio::io::AsyncReadExt;
 
-use crate::{buffer, BindValue, FromProtocolValue, PgType, PgTypeId, ProtocolError};
+use crate::{buffer, BindValue, FromProtocolValue, NumericValue, PgType, PgTypeId, ProtocolError};
+#[cfg(feature = "with-chrono")]
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 
 const DEFAULT_CAPACITY: usize = 64;
 
@@ -240,11 +242,18 @@ impl Serialize for ErrorResponse {
     }
 }
 
-pub struct SSLResponse {}
+/// (B) Single byte reply for SSLRequest/GSSENCRequest, N - refused, S - the server is ready for TLS handshake.
+pub struct SSLResponse {
+    accepted: bool,
+}
 
 impl SSLResponse {
     pub fn new() -> Self {
-        Self {}
+        Self { accepted: false }
+    }
+
+    pub fn accepted() -> Self {
+        Self { accepted: true }
     }
 }
 
@@ -254,6 +263,14 @@ impl Serialize for SSLResponse {
     fn serialize(&self) -> Option<Vec<u8>> {
         None
     }
+
+    fn code(&self) -> u8 {
+        if self.accepted {
+            b'S'
+        } else {
+            Self::CODE
+        }
+    }
 }
 
 pub struct Authentication {
@@ -439,6 +456,7 @@ pub enum PortalCompletion {
 pub enum CommandComplete {
     Select(u32),
     Fetch(u32),
+    Copy(u32),
     Plain(String),
 }
 
@@ -463,6 +481,9 @@ impl Serialize for CommandComplete {
             CommandComplete::Fetch(rows) => {
                 buffer::write_string(&mut buffer, &format!("FETCH {}", rows))
             }
+            CommandComplete::Copy(rows) => {
+                buffer::write_string(&mut buffer, &format!("COPY {}", rows))
+            }
             CommandComplete::Plain(tag) => buffer::write_string(&mut buffer, &tag),
         }
 
@@ -502,6 +523,79 @@ impl Serialize for EmptyQueryResponse {
     }
 }
 
+/// Start of COPY TO STDOUT, it's sent instead of RowDescription
+pub struct CopyOutResponse {
+    format: Format,
+    columns: i16,
+}
+
+impl CopyOutResponse {
+    pub fn new(format: Format, columns: usize) -> Result<Self, ProtocolError> {
+        let columns = i16::try_from(columns).map_err(|_| {
+            ErrorResponse::error(
+                ErrorCode::ProgramLimitExceeded,
+                format!(
+                    "COPY supports at most {} columns, got {}",
+                    i16::MAX,
+                    columns
+                ),
+            )
+        })?;
+
+        Ok(Self { format, columns })
+    }
+}
+
+impl Serialize for CopyOutResponse {
+    const CODE: u8 = b'H';
+
+    fn serialize(&self) -> Option<Vec<u8>> {
+        let mut buffer = Vec::with_capacity(3 + 2 * self.columns as usize);
+        buffer.push(self.format as u8);
+        buffer.put_i16(self.columns);
+
+        for _ in 0..self.columns {
+            buffer.put_i16(self.format as i16);
+        }
+
+        Some(buffer)
+    }
+}
+
+pub struct CopyData {
+    data: Vec<u8>,
+}
+
+impl CopyData {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self { data }
+    }
+}
+
+impl Serialize for CopyData {
+    const CODE: u8 = b'd';
+
+    fn serialize(&self) -> Option<Vec<u8>> {
+        Some(self.data.clone())
+    }
+}
+
+pub struct CopyDone {}
+
+impl CopyDone {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Serialize for CopyDone {
+    const CODE: u8 = b'c';
+
+    fn serialize(&self) -> Option<Vec<u8>> {
+        Some(vec![])
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ParameterDescription {
     pub parameters: Vec<PgTypeId>,
@@ -546,6 +640,10 @@ impl RowDescription {
     pub fn len(&self) -> usize {
         self.fields.len()
     }
+
+    pub fn field_names(&self) -> Vec<&str> {
+        self.fields.iter().map(|f| f.name.as_str()).collect()
+    }
 }
 
 impl Serialize for RowDescription {
@@ -626,6 +724,66 @@ impl Deserialize for PasswordMessage {
     }
 }
 
+/// (F) Initial response of SASL authentication, it's sent with the same identifier as PasswordMessage.
+#[derive(Debug, PartialEq)]
+pub struct SASLInitialResponse {
+    /// Name of the SASL authentication mechanism that the client selected
+    pub mechanism: String,
+    /// SASL mechanism specific "Initial Response", None if there is no Initial Response
+    pub data: Option<Vec<u8>>,
+}
+
+#[async_trait]
+impl Deserialize for SASLInitialResponse {
+    async fn deserialize(mut buffer: Cursor<Vec<u8>>) -> Result<Self, ProtocolError>
+    where
+        Self: Sized,
+    {
+        let mechanism = buffer::read_string(&mut buffer).await?;
+        let len = buffer.read_i32().await?;
+        let remaining = buffer.get_ref().len() as u64 - buffer.position();
+        let data = match len {
+            -1 => None,
+            // Length comes from the client before authentication, don't trust it for allocation
+            len if len >= 0 && len as u64 <= remaining => {
+                let mut data = vec![0; len as usize];
+                buffer.read_exact(&mut data).await?;
+
+                Some(data)
+            }
+            len => {
+                return Err(ErrorResponse::error(
+                    ErrorCode::ProtocolViolation,
+                    format!("Invalid length of SASL initial response: {}", len),
+                )
+                .into())
+            }
+        };
+
+        Ok(Self { mechanism, data })
+    }
+}
+
+/// (F) Continuation of SASL authentication, it's sent with the same identifier as PasswordMessage.
+#[derive(Debug, PartialEq)]
+pub struct SASLResponse {
+    /// SASL mechanism specific message data
+    pub data: Vec<u8>,
+}
+
+#[async_trait]
+impl Deserialize for SASLResponse {
+    async fn deserialize(mut buffer: Cursor<Vec<u8>>) -> Result<Self, ProtocolError>
+    where
+        Self: Sized,
+    {
+        let mut data = Vec::new();
+        buffer.read_to_end(&mut data).await?;
+
+        Ok(Self { data })
+    }
+}
+
 /// (F) Extended Query. Contains a textual query string, optionally some information about data
 /// types of parameter placeholders, and the name of a destination prepared-statement object
 /// (an empty string selects the unnamed prepared statement)
@@ -764,15 +922,42 @@ impl Bind {
             values.push(match raw_value {
                 None => BindValue::Null,
                 Some(raw_value) => match param_tid {
-                    PgTypeId::TEXT => {
+                    PgTypeId::TEXT | PgTypeId::VARCHAR | PgTypeId::BPCHAR | PgTypeId::NAME => {
                         BindValue::String(String::from_protocol(raw_value, param_format)?)
                     }
                     PgTypeId::BOOL => {
                         BindValue::Bool(bool::from_protocol(raw_value, param_format)?)
                     }
+                    PgTypeId::INT2 => {
+                        BindValue::Int64(i16::from_protocol(raw_value, param_format)? as i64)
+                    }
+                    PgTypeId::INT4 => {
+                        BindValue::Int64(i32::from_protocol(raw_value, param_format)? as i64)
+                    }
                     PgTypeId::INT8 => {
                         BindValue::Int64(i64::from_protocol(raw_value, param_format)?)
                     }
+                    PgTypeId::FLOAT4 => {
+                        BindValue::Float64(f32::from_protocol(raw_value, param_format)? as f64)
+                    }
+                    PgTypeId::FLOAT8 => {
+                        BindValue::Float64(f64::from_protocol(raw_value, param_format)?)
+                    }
+                    PgTypeId::NUMERIC => {
+                        BindValue::Numeric(NumericValue::from_protocol(raw_value, param_format)?)
+                    }
+                    #[cfg(feature = "with-chrono")]
+                    PgTypeId::DATE => {
+                        BindValue::Date(NaiveDate::from_protocol(raw_value, param_format)?)
+                    }
+                    #[cfg(feature = "with-chrono")]
+                    PgTypeId::TIMESTAMP => {
+                        BindValue::Timestamp(NaiveDateTime::from_protocol(raw_value, param_format)?)
+                    }
+                    #[cfg(feature = "with-chrono")]
+                    PgTypeId::TIMESTAMPTZ => BindValue::Timestamp(
+                        DateTime::<Utc>::from_protocol(raw_value, param_format)?.naive_utc(),
+                    ),
                     _ => {
                         return Err(ErrorResponse::error(
                             ErrorCode::FeatureNotSupported,
@@ -898,6 +1083,23 @@ impl Deserialize for Query {
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub struct CopyFail {
+    pub message: String,
+}
+
+#[async_trait]
+impl Deserialize for CopyFail {
+    async fn deserialize(mut buffer: Cursor<Vec<u8>>) -> Result<Self, ProtocolError>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            message: buffer::read_string(&mut buffer).await?,
+        })
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 #[repr(u8)]
 pub enum Format {
@@ -927,6 +1129,12 @@ pub enum FrontendMessage {
     Execute(Execute),
     /// Extended Query. Close Portal/Statement
     Close(Close),
+    /// COPY FROM STDIN data. The contents are not kept, COPY FROM is not supported
+    CopyData,
+    /// End of COPY FROM STDIN data
+    CopyDone,
+    /// COPY FROM STDIN is aborted by the client
+    CopyFail(CopyFail),
 }
 
 /// <https://www.postgresql.org/docs/14/errcodes-appendix.html>
@@ -954,6 +1162,8 @@ pub enum ErrorCode {
     SyntaxError,
     // Class 53 — Insufficient Resources
     ConfigurationLimitExceeded,
+    // Class 54 — Program Limit Exceeded
+    ProgramLimitExceeded,
     // Class 55 — Object Not In Prerequisite State
     ObjectNotInPrerequisiteState,
     // Class 57 - Operator Intervention
@@ -977,6 +1187,7 @@ impl Display for ErrorCode {
             Self::DuplicateCursor => "42P03",
             Self::SyntaxError => "42601",
             Self::ConfigurationLimitExceeded => "53400",
+            Self::ProgramLimitExceeded => "54000",
             Self::ObjectNotInPrerequisiteState => "55000",
             Self::QueryCanceled => "57014",
             Self::InternalError => "XX000",
@@ -1046,17 +1257,40 @@ impl TransactionStatus {
 pub enum AuthenticationRequest {
     Ok,
     CleartextPassword,
+    /// Start of SASL authentication, contains the names of supported mechanisms
+    SASL(Vec<String>),
+    /// SASL challenge (mechanism specific data)
+    SASLContinue(Vec<u8>),
+    /// SASL outcome (mechanism specific data), it's followed by AuthenticationOk
+    SASLFinal(Vec<u8>),
 }
 
 impl AuthenticationRequest {
     pub fn to_bytes(&self) -> Vec<u8> {
-        self.to_code().to_be_bytes().to_vec()
+        let mut buffer = self.to_code().to_be_bytes().to_vec();
+
+        match self {
+            Self::Ok | Self::CleartextPassword => {}
+            Self::SASL(mechanisms) => {
+                for mechanism in mechanisms {
+                    buffer::write_string(&mut buffer, mechanism);
+                }
+
+                buffer.push(0);
+            }
+            Self::SASLContinue(data) | Self::SASLFinal(data) => buffer.extend_from_slice(data),
+        }
+
+        buffer
     }
 
     pub fn to_code(&self) -> u32 {
         match self {
             Self::Ok => 0,
             Self::CleartextPassword => 3,
+            Self::SASL(_) => 10,
+            Self::SASLContinue(_) => 11,
+            Self::SASLFinal(_) => 12,
         }
     }
 }
@@ -1245,6 +1479,50 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_bind_to_bind_values_binary_scalars() -> Result<(), ProtocolError> {
+        let bind = Bind {
+            portal: "".to_string(),
+            statement: "s0".to_string(),
+            parameter_formats: vec![Format::Binary],
+            parameter_values: vec![
+                Some(vec![0, 5]),
+                Some(vec![0x3f, 0xc0, 0, 0]),
+                Some(vec![0, 2, 0, 0, 0, 0, 0, 2, 0, 1, 0x13, 0x88]),
+                Some(vec![0, 0, 0x21, 0xeb]),
+                Some(vec![0, 0x02, 0x80, 0x7b, 0x1c, 0xfb, 0x86, 0x40]),
+                None,
+            ],
+            result_formats: vec![],
+        };
+
+        assert_eq!(
+            bind.to_bind_values(&ParameterDescription::new(vec![
+                PgTypeId::INT2,
+                PgTypeId::FLOAT4,
+                PgTypeId::NUMERIC,
+                PgTypeId::DATE,
+                PgTypeId::TIMESTAMPTZ,
+                PgTypeId::TIMESTAMP,
+            ]))?,
+            vec![
+                BindValue::Int64(5),
+                BindValue::Float64(1.5),
+                BindValue::Numeric(NumericValue::new(150, 2)),
+                BindValue::Date(NaiveDate::from_ymd_opt(2023, 10, 10).unwrap()),
+                BindValue::Timestamp(
+                    NaiveDate::from_ymd_opt(2022, 4, 25)
+                        .unwrap()
+                        .and_hms_opt(15, 36, 49)
+                        .unwrap()
+                ),
+                BindValue::Null,
+            ]
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_frontend_message_parse_describe() -> Result<(), ProtocolError> {
         let buffer = parse_hex_dump(
@@ -1298,6 +1576,111 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_frontend_message_parse_sasl_initial_response() -> Result<(), ProtocolError> {
+        let buffer = parse_hex_dump(
+            r#"
+            70 00 00 00 32 53 43 52 41 4d 2d 53 48 41 2d 32   p...2SCRAM-SHA-2
+            35 36 00 00 00 00 1c 6e 2c 2c 6e 3d 2c 72 3d 72   56.....n,,n=,r=r
+            4f 70 72 4e 47 66 77 45 62 65 52 57 67 62 4e 45   OprNGfwEbeRWgbNE
+            6b 71 4f                                          kqO
+            "#
+            .to_string(),
+        );
+        let mut cursor = Cursor::new(buffer);
+
+        let message: SASLInitialResponse = buffer::read_typed_message(&mut cursor, b'p').await?;
+        assert_eq!(
+            message,
+            SASLInitialResponse {
+                mechanism: "SCRAM-SHA-256".to_string(),
+                data: Some(b"n,,n=,r=rOprNGfwEbeRWgbNEkqO".to_vec()),
+            },
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_frontend_message_parse_sasl_initial_response_invalid_length(
+    ) -> Result<(), ProtocolError> {
+        for len in [-2_i32, 5, i32::MAX] {
+            let mut body = b"SCRAM-SHA-256\0".to_vec();
+            body.extend_from_slice(&len.to_be_bytes());
+            body.extend_from_slice(b"n,,n");
+            let mut buffer = vec![b'p'];
+            buffer.extend_from_slice(&(body.len() as i32 + 4).to_be_bytes());
+            buffer.extend(body);
+            let mut cursor = Cursor::new(buffer);
+
+            let result: Result<SASLInitialResponse, _> =
+                buffer::read_typed_message(&mut cursor, b'p').await;
+            match result {
+                Err(ProtocolError::ErrorResponse { source, .. }) => {
+                    assert!(matches!(source.code, ErrorCode::ProtocolViolation))
+                }
+                other => panic!("Unexpected result for length {}: {:?}", len, other),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_frontend_message_parse_sasl_response() -> Result<(), ProtocolError> {
+        let buffer = parse_hex_dump(
+            r#"
+            70 00 00 00 0e 63 3d 62 69 77 73 2c 72 3d 61      p....c=biws,r=a
+            "#
+            .to_string(),
+        );
+        let mut cursor = Cursor::new(buffer);
+
+        let message: SASLResponse = buffer::read_typed_message(&mut cursor, b'p').await?;
+        assert_eq!(
+            message,
+            SASLResponse {
+                data: b"c=biws,r=a".to_vec(),
+            },
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_authentication_sasl_write() -> Result<(), ProtocolError> {
+        let mut cursor = Cursor::new(vec![]);
+        buffer::write_message(
+            &mut cursor,
+            Authentication::new(AuthenticationRequest::SASL(vec![
+                "SCRAM-SHA-256".to_string()
+            ])),
+        )
+        .await?;
+
+        assert_eq!(
+            cursor.get_ref()[0..],
+            vec![
+                82, 0, 0, 0, 23, 0, 0, 0, 10, 83, 67, 82, 65, 77, 45, 83, 72, 65, 45, 50, 53, 54,
+                0, 0
+            ]
+        );
+
+        let mut cursor = Cursor::new(vec![]);
+        buffer::write_message(
+            &mut cursor,
+            Authentication::new(AuthenticationRequest::SASLFinal(b"v=abc".to_vec())),
+        )
+        .await?;
+
+        assert_eq!(
+            cursor.get_ref()[0..],
+            vec![82, 0, 0, 0, 13, 0, 0, 0, 12, 118, 61, 97, 98, 99]
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_frontend_message_execute() -> Result<(), ProtocolError> {
         let buffer = parse_hex_dump(
@@ -1355,6 +1738,72 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_ssl_response_write() -> Result<(), ProtocolError> {
+        let mut cursor = Cursor::new(vec![]);
+        buffer::write_message(&mut cursor, SSLResponse::new()).await?;
+        buffer::write_message(&mut cursor, SSLResponse::accepted()).await?;
+
+        assert_eq!(cursor.get_ref()[0..], vec![b'N', b'S']);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_copy_out_write() -> Result<(), ProtocolError> {
+        let mut cursor = Cursor::new(vec![]);
+        buffer::write_message(&mut cursor, CopyOutResponse::new(Format::Text, 2)?).await?;
+        buffer::write_message(&mut cursor, CopyData::new(b"1\ta\n".to_vec())).await?;
+        buffer::write_message(&mut cursor, CopyDone::new()).await?;
+        buffer::write_message(&mut cursor, CommandComplete::Copy(1)).await?;
+
+        assert_eq!(
+            cursor.get_ref()[0..],
+            vec![
+                // CopyOutResponse
+                72, 0, 0, 0, 11, 0, 0, 2, 0, 0, 0, 0, //
+                // CopyData
+                100, 0, 0, 0, 8, 49, 9, 97, 10, //
+                // CopyDone
+                99, 0, 0, 0, 4, //
+                // CommandComplete
+                67, 0, 0, 0, 11, 67, 79, 80, 89, 32, 49, 0,
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_out_too_many_columns() {
+        assert!(CopyOutResponse::new(Format::Text, i16::MAX as usize).is_ok());
+        assert!(CopyOutResponse::new(Format::Text, i16::MAX as usize + 1).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_frontend_message_parse_copy() -> Result<(), ProtocolError> {
+        let buffer = parse_hex_dump(
+            r#"
+            64 00 00 00 08 31 09 61 0a                        d....1.a.
+            63 00 00 00 04                                    c....
+            66 00 00 00 09 73 74 6f 70 00                     f....stop.
+            "#
+            .to_string(),
+        );
+        let mut cursor = Cursor::new(buffer);
+
+        assert_eq!(read_message(&mut cursor).await?, FrontendMessage::CopyData);
+        assert_eq!(read_message(&mut cursor).await?, FrontendMessage::CopyDone);
+        assert_eq!(
+            read_message(&mut cursor).await?,
+            FrontendMessage::CopyFail(CopyFail {
+                message: "stop".to_string()
+            })
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_frontend_message_write_row_description() -> Result<(), ProtocolError> {
         let mut cursor = Cursor::new(vec![]);
@@ -0,0 +1,389 @@
+//! Server side of SCRAM-SHA-256 SASL authentication.
+//! RFC 5802 (SCRAM): <https://datatracker.ietf.org/doc/html/rfc5802>
+//! RFC 7677 (SCRAM-SHA-256): <https://datatracker.ietf.org/doc/html/rfc7677>
+//! PostgreSQL specifics: <https://www.postgresql.org/docs/14/sasl-authentication.html>
+
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    protocol::{ErrorCode, ErrorResponse},
+    ProtocolError,
+};
+
+/// Name of the mechanism which is advertised in AuthenticationSASL
+pub const SCRAM_SHA_256: &str = "SCRAM-SHA-256";
+
+/// The same default as PostgreSQL uses (scram_iterations)
+const DEFAULT_ITERATIONS: u32 = 4096;
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 18;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// State which is required between client-first and client-final messages
+struct ScramExchange {
+    gs2_header: String,
+    client_first_bare: String,
+    server_first: String,
+    nonce: String,
+}
+
+/// Server side of a single SCRAM-SHA-256 exchange.
+///
+/// There is no stored verifier for users, it's why the salted password is computed from the plain
+/// password (returned by SqlAuthService) with a random salt for every exchange.
+pub struct ScramSha256 {
+    salt: Vec<u8>,
+    iterations: u32,
+    salted_password: Vec<u8>,
+    exchange: Option<ScramExchange>,
+    /// Mock exchange, the client proof is never accepted
+    doomed: bool,
+}
+
+impl ScramSha256 {
+    pub fn new(password: &str) -> Self {
+        let mut salt = vec![0; SALT_LENGTH];
+        rand::thread_rng().fill_bytes(&mut salt);
+
+        Self::with_salt(password, salt, DEFAULT_ITERATIONS)
+    }
+
+    pub fn with_salt(password: &str, salt: Vec<u8>, iterations: u32) -> Self {
+        let salted_password = hi(password.as_bytes(), &salt, iterations);
+
+        Self {
+            salt,
+            iterations,
+            salted_password,
+            exchange: None,
+            doomed: false,
+        }
+    }
+
+    /// Exchange for an unknown user (or a user without password). For the client it looks the same
+    /// as a real one, but it always fails at the final step, like PostgreSQL does, so it's not
+    /// possible to find out which users exist.
+    pub fn mock() -> Self {
+        let mut password = vec![0; SALT_LENGTH];
+        rand::thread_rng().fill_bytes(&mut password);
+
+        Self {
+            doomed: true,
+            ..Self::new(&base64::encode(password))
+        }
+    }
+
+    /// Handle client-first-message (payload of SASLInitialResponse) and return server-first-message,
+    /// which must be sent to the client with AuthenticationSASLContinue.
+    pub fn process_client_first(&mut self, data: &[u8]) -> Result<Vec<u8>, ProtocolError> {
+        let mut server_nonce = vec![0; NONCE_LENGTH];
+        rand::thread_rng().fill_bytes(&mut server_nonce);
+
+        self.process_client_first_with_nonce(data, &base64::encode(server_nonce))
+    }
+
+    fn process_client_first_with_nonce(
+        &mut self,
+        data: &[u8],
+        server_nonce: &str,
+    ) -> Result<Vec<u8>, ProtocolError> {
+        let message = std::str::from_utf8(data)
+            .map_err(|_| scram_error("malformed SCRAM message: invalid UTF-8"))?;
+
+        // gs2-header = gs2-cbind-flag "," [ authzid ] ","
+        let mut parts = message.splitn(3, ',');
+        let cbind_flag = parts.next().unwrap_or_default();
+        let authzid = parts
+            .next()
+            .ok_or_else(|| scram_error("malformed SCRAM message: missing GS2 header"))?;
+        let client_first_bare = parts
+            .next()
+            .ok_or_else(|| scram_error("malformed SCRAM message: missing GS2 header"))?;
+
+        match cbind_flag {
+            // n - client doesn't support channel binding
+            // y - client supports channel binding, but thinks that the server doesn't
+            "n" | "y" => {}
+            flag if flag.starts_with("p=") => {
+                return Err(scram_error(
+                    "channel binding is not supported, SCRAM-SHA-256-PLUS was not advertised",
+                ))
+            }
+            flag => {
+                return Err(scram_error(&format!(
+                    "malformed SCRAM message: unexpected channel binding flag \"{}\"",
+                    flag
+                )))
+            }
+        }
+
+        if !authzid.is_empty() {
+            return Err(scram_error(
+                "client uses authorization identity, but it is not supported",
+            ));
+        }
+
+        // PostgreSQL ignores the user name in SCRAM, user from the startup message is used instead
+        let mut client_nonce = None;
+        for attribute in client_first_bare.split(',') {
+            match attribute.split_once('=') {
+                Some(("m", _)) => {
+                    return Err(scram_error("SCRAM extensions are not supported"));
+                }
+                Some(("r", value)) if !value.is_empty() => client_nonce = Some(value),
+                Some(("n", _)) => {}
+                _ => {
+                    return Err(scram_error(&format!(
+                        "malformed SCRAM message: unexpected attribute \"{}\"",
+                        attribute
+                    )))
+                }
+            }
+        }
+
+        let client_nonce =
+            client_nonce.ok_or_else(|| scram_error("malformed SCRAM message: missing nonce"))?;
+        let nonce = format!("{}{}", client_nonce, server_nonce);
+        let server_first = format!(
+            "r={},s={},i={}",
+            nonce,
+            base64::encode(&self.salt),
+            self.iterations
+        );
+
+        self.exchange = Some(ScramExchange {
+            gs2_header: format!("{},{},", cbind_flag, authzid),
+            client_first_bare: client_first_bare.to_string(),
+            server_first: server_first.clone(),
+            nonce,
+        });
+
+        Ok(server_first.into_bytes())
+    }
+
+    /// Handle client-final-message (payload of SASLResponse) and verify the client proof.
+    ///
+    /// Returns server-final-message, which must be sent to the client with AuthenticationSASLFinal,
+    /// or None if the proof doesn't match the password.
+    pub fn process_client_final(&self, data: &[u8]) -> Result<Option<Vec<u8>>, ProtocolError> {
+        let exchange = self.exchange.as_ref().ok_or_else(|| {
+            scram_error("client-final-message was sent before client-first-message")
+        })?;
+
+        let message = std::str::from_utf8(data)
+            .map_err(|_| scram_error("malformed SCRAM message: invalid UTF-8"))?;
+        let (client_final_without_proof, proof) = message
+            .rsplit_once(",p=")
+            .ok_or_else(|| scram_error("malformed SCRAM message: missing proof"))?;
+
+        let mut channel_binding = None;
+        let mut nonce = None;
+        for attribute in client_final_without_proof.split(',') {
+            match attribute.split_once('=') {
+                Some(("c", value)) => channel_binding = Some(value),
+                Some(("r", value)) => nonce = Some(value),
+                _ => {
+                    return Err(scram_error(&format!(
+                        "malformed SCRAM message: unexpected attribute \"{}\"",
+                        attribute
+                    )))
+                }
+            }
+        }
+
+        let channel_binding = channel_binding
+            .and_then(|value| base64::decode(value).ok())
+            .ok_or_else(|| scram_error("malformed SCRAM message: invalid channel binding"))?;
+        if channel_binding != exchange.gs2_header.as_bytes() {
+            return Err(scram_error("SCRAM channel binding check failed"));
+        }
+
+        if nonce != Some(exchange.nonce.as_str()) {
+            return Err(scram_error("SCRAM nonce mismatch"));
+        }
+
+        let proof = base64::decode(proof)
+            .map_err(|_| scram_error("malformed SCRAM message: invalid proof"))?;
+        if proof.len() != 32 {
+            return Err(scram_error("malformed SCRAM message: invalid proof"));
+        }
+
+        let auth_message = format!(
+            "{},{},{}",
+            exchange.client_first_bare, exchange.server_first, client_final_without_proof
+        );
+
+        let client_key = hmac(&self.salted_password, b"Client Key");
+        let stored_key = Sha256::digest(&client_key);
+        let client_signature = hmac(&stored_key, auth_message.as_bytes());
+
+        // ClientKey := ClientProof XOR ClientSignature, then H(ClientKey) must be equal to StoredKey
+        let recovered_client_key = proof
+            .iter()
+            .zip(client_signature.iter())
+            .map(|(a, b)| a ^ b)
+            .collect::<Vec<u8>>();
+        if !constant_time_eq(&Sha256::digest(&recovered_client_key), &stored_key) || self.doomed {
+            return Ok(None);
+        }
+
+        let server_key = hmac(&self.salted_password, b"Server Key");
+        let server_signature = hmac(&server_key, auth_message.as_bytes());
+
+        Ok(Some(
+            format!("v={}", base64::encode(server_signature)).into_bytes(),
+        ))
+    }
+}
+
+/// Hi(str, salt, i) from RFC 5802, it's PBKDF2 with HMAC-SHA-256 where the output length equals the hash length
+fn hi(password: &[u8], salt: &[u8], iterations: u32) -> Vec<u8> {
+    let mut block = hmac(password, &[salt, &1_u32.to_be_bytes()].concat());
+    let mut result = block.clone();
+
+    for _ in 1..iterations {
+        block = hmac(password, &block);
+        for (r, b) in result.iter_mut().zip(block.iter()) {
+            *r ^= b;
+        }
+    }
+
+    result
+}
+
+fn hmac(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC can take key of any size");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+fn scram_error(message: &str) -> ProtocolError {
+    ErrorResponse::fatal(ErrorCode::ProtocolViolation, message.to_string()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test vector from https://datatracker.ietf.org/doc/html/rfc7677#section-3
+    fn rfc_server() -> ScramSha256 {
+        ScramSha256::with_salt(
+            "pencil",
+            base64::decode("W22ZaJ0SNY7soEsUEjb6gQ==").unwrap(),
+            4096,
+        )
+    }
+
+    #[test]
+    fn test_scram_sha_256_rfc_exchange() -> Result<(), ProtocolError> {
+        let mut server = rfc_server();
+
+        let server_first = server.process_client_first_with_nonce(
+            b"n,,n=user,r=rOprNGfwEbeRWgbNEkqO",
+            "%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0",
+        )?;
+        assert_eq!(
+            String::from_utf8(server_first).unwrap(),
+            "r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096"
+        );
+
+        let server_final = server.process_client_final(
+            b"c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ=",
+        )?;
+        assert_eq!(
+            String::from_utf8(server_final.unwrap()).unwrap(),
+            "v=6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4="
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scram_sha_256_wrong_password() -> Result<(), ProtocolError> {
+        let mut server = ScramSha256::with_salt(
+            "wrong",
+            base64::decode("W22ZaJ0SNY7soEsUEjb6gQ==").unwrap(),
+            4096,
+        );
+
+        server.process_client_first_with_nonce(
+            b"n,,n=user,r=rOprNGfwEbeRWgbNEkqO",
+            "%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0",
+        )?;
+
+        let server_final = server.process_client_final(
+            b"c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ=",
+        )?;
+        assert_eq!(server_final, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scram_sha_256_mock() -> Result<(), ProtocolError> {
+        let mut server = ScramSha256::mock();
+
+        let server_first = server.process_client_first_with_nonce(
+            b"n,,n=user,r=rOprNGfwEbeRWgbNEkqO",
+            "%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0",
+        )?;
+        assert!(String::from_utf8(server_first)
+            .unwrap()
+            .starts_with("r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,s="));
+
+        // Even a proof computed from the mock's own salted password is rejected
+        let auth_message = "n=user,r=rOprNGfwEbeRWgbNEkqO,".to_string()
+            + &server.exchange.as_ref().unwrap().server_first
+            + ",c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0";
+        let client_key = hmac(&server.salted_password, b"Client Key");
+        let client_signature = hmac(&Sha256::digest(&client_key), auth_message.as_bytes());
+        let proof = client_key
+            .iter()
+            .zip(client_signature.iter())
+            .map(|(a, b)| a ^ b)
+            .collect::<Vec<u8>>();
+
+        let server_final = server.process_client_final(
+            format!(
+                "c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,p={}",
+                base64::encode(proof)
+            )
+            .as_bytes(),
+        )?;
+        assert_eq!(server_final, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scram_sha_256_protocol_violations() {
+        let mut server = rfc_server();
+        assert!(server.process_client_final(b"c=biws,r=abc,p=abc").is_err());
+        assert!(server
+            .process_client_first(b"p=tls-server-end-point,,n=,r=abc")
+            .is_err());
+        assert!(server.process_client_first(b"n,,m=ext,n=,r=abc").is_err());
+        assert!(server.process_client_first(b"n,,n=").is_err());
+
+        server
+            .process_client_first_with_nonce(b"n,,n=,r=client", "server")
+            .unwrap();
+        // nonce must be the same as the server has sent in server-first-message
+        assert!(server
+            .process_client_final(b"c=biws,r=client,p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ=")
+            .is_err());
+        // channel binding must repeat GS2 header from client-first-message
+        assert!(server
+            .process_client_final(
+                b"c=eSws,r=clientserver,p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ="
+            )
+            .is_err());
+    }
+}
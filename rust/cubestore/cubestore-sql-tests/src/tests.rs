@@ -1,7 +1,7 @@
 use crate::files::write_tmp_file;
 use crate::rows::{rows, NULL};
 use crate::SqlClient;
-use async_compression::tokio::write::GzipEncoder;
+use async_compression::tokio::write::{BzEncoder, GzipEncoder, XzEncoder, ZstdEncoder};
 use cubestore::metastore::{Column, ColumnType};
 use cubestore::queryplanner::pretty_printers::{pp_phys_plan, pp_phys_plan_ext, PPOptions};
 use cubestore::queryplanner::MIN_TOPK_STREAM_ROWS;
@@ -88,6 +88,10 @@ pub fn sql_tests() -> Vec<(&'static str, TestFn)> {
         t("join_with_aliases", join_with_aliases),
         t("group_by_without_aggregates", group_by_without_aggregates),
         t("create_table_with_location", create_table_with_location),
+        t(
+            "create_table_with_compressed_locations",
+            create_table_with_compressed_locations,
+        ),
         t(
             "create_table_with_location_messed_order",
             create_table_with_location_messed_order,
@@ -109,6 +113,7 @@ pub fn sql_tests() -> Vec<(&'static str, TestFn)> {
             "create_table_with_csv_no_header_and_delimiter",
             create_table_with_csv_no_header_and_delimiter,
         ),
+        t("create_table_with_ndjson", create_table_with_ndjson),
         t("create_table_with_url", create_table_with_url),
         t("create_table_fail_and_retry", create_table_fail_and_retry),
         t("empty_crash", empty_crash),
@@ -238,7 +243,12 @@ pub fn sql_tests() -> Vec<(&'static str, TestFn)> {
         t("inline_tables", inline_tables),
         t("inline_tables_2x", inline_tables_2x),
         t("build_range_end", build_range_end),
+        t("parquet_options", parquet_options),
+        t("delete_and_update", delete_and_update),
+        t("alter_table", alter_table),
+        t("table_retention", table_retention),
         t("cache_incr", cache_incr),
+        t("cache_hash_list_cas", cache_hash_list_cas),
         t("cache_set_get_rm", cache_set_get_rm),
         t("cache_set_get_set_get", cache_set_get_set_get),
         t("cache_compaction", cache_compaction),
@@ -260,6 +270,7 @@ pub fn sql_tests() -> Vec<(&'static str, TestFn)> {
             queue_multiple_result_blocking,
         ),
         t("queue_custom_orphaned", queue_custom_orphaned),
+        t("queue_prefix_concurrency", queue_prefix_concurrency),
         t("limit_pushdown_group", limit_pushdown_group),
         t("limit_pushdown_group_order", limit_pushdown_group_order),
         t(
@@ -2061,6 +2072,61 @@ async fn create_table_with_location(service: Box<dyn SqlClient>) {
     assert_eq!(result.get_rows(), &vec![Row::new(vec![TableValue::Int(7)])]);
 }
 
+async fn create_table_with_compressed_locations(service: Box<dyn SqlClient>) {
+    let dir = env::temp_dir();
+    let path_1 = dir.clone().join("compressed-1.csv.zst");
+    let path_2 = dir.clone().join("compressed-2.csv.bz2");
+    let path_3 = dir.clone().join("compressed-3.csv.xz");
+    let create = |path: &Path| {
+        let path = path.to_path_buf();
+        async move { BufWriter::new(tokio::fs::File::create(path).await.unwrap()) }
+    };
+
+    let mut file = ZstdEncoder::new(create(&path_1).await);
+    file.write_all("id,city\n1,San Francisco\n2,New York\n".as_bytes())
+        .await
+        .unwrap();
+    file.shutdown().await.unwrap();
+
+    let mut file = BzEncoder::new(create(&path_2).await);
+    file.write_all("id,city\n3,Boston\n".as_bytes())
+        .await
+        .unwrap();
+    file.shutdown().await.unwrap();
+
+    let mut file = XzEncoder::new(create(&path_3).await);
+    file.write_all("id,city\n4,Chicago\n5,Denver\n".as_bytes())
+        .await
+        .unwrap();
+    file.shutdown().await.unwrap();
+
+    service.exec_query("CREATE SCHEMA s").await.unwrap();
+    service
+        .exec_query(&format!(
+            "CREATE TABLE s.cities (id int, city text) LOCATION {}",
+            vec![path_1, path_2, path_3]
+                .into_iter()
+                .map(|p| format!("'{}'", p.to_string_lossy()))
+                .join(",")
+        ))
+        .await
+        .unwrap();
+    let r = service
+        .exec_query("SELECT id, city FROM s.cities ORDER BY id")
+        .await
+        .unwrap();
+    assert_eq!(
+        to_rows(&r),
+        rows(&[
+            (1, "San Francisco"),
+            (2, "New York"),
+            (3, "Boston"),
+            (4, "Chicago"),
+            (5, "Denver")
+        ])
+    );
+}
+
 async fn create_table_with_location_messed_order(service: Box<dyn SqlClient>) {
     let paths = {
         let dir = env::temp_dir();
@@ -2246,6 +2312,57 @@ async fn create_table_with_csv_no_header_and_delimiter(service: Box<dyn SqlClien
     );
 }
 
+async fn create_table_with_ndjson(service: Box<dyn SqlClient>) {
+    let file = write_tmp_file(indoc! {r#"
+        {"fruit": "apple", "number": 2, "price": "1.25"}
+        {"number": 3, "fruit": "banana", "price": 0.5}
+        {"fruit": "cherry"}
+    "#})
+    .unwrap();
+    let path = file.path().to_string_lossy();
+    service
+        .exec_query("CREATE SCHEMA IF NOT EXISTS test")
+        .await
+        .unwrap();
+    service
+        .exec_query(format!("CREATE TABLE test.table (`fruit` text, `number` int, `price` decimal) WITH (input_format = 'ndjson') LOCATION '{}'", path).as_str())
+        .await
+        .unwrap();
+    let result = service
+        .exec_query("SELECT fruit, number, price FROM test.table ORDER BY fruit")
+        .await
+        .unwrap();
+    assert_eq!(
+        to_rows(&result),
+        rows(&[
+            ("apple", 2, Decimal::new(125000)),
+            ("banana", 3, Decimal::new(50000)),
+        ])
+        .into_iter()
+        .chain(vec![vec![
+            TableValue::String("cherry".to_string()),
+            TableValue::Null,
+            TableValue::Null
+        ]])
+        .collect_vec()
+    );
+
+    let file = write_tmp_file(indoc! {r#"
+        {"fruit": "apple", "number": 2}
+    "#})
+    .unwrap();
+    let path = file.path().to_string_lossy();
+    let r = service
+        .exec_query(format!("CREATE TABLE test.bad_types (`fruit` text, `number` boolean) WITH (input_format = 'ndjson') LOCATION '{}'", path).as_str())
+        .await;
+    assert!(r.is_err(), "number can't be imported as boolean");
+
+    let r = service
+        .exec_query(format!("CREATE TABLE test.delimiter (`fruit` text) WITH (input_format = 'ndjson', delimiter = 'tab') LOCATION '{}'", path).as_str())
+        .await;
+    assert!(r.is_err(), "delimiter is only supported by CSV");
+}
+
 async fn create_table_with_url(service: Box<dyn SqlClient>) {
     let url = "https://data.wprdc.org/dataset/0b584c84-7e35-4f4d-a5a2-b01697470c0f/resource/e95dd941-8e47-4460-9bd8-1e51c194370b/download/bikepghpublic.csv";
 
@@ -6630,6 +6747,281 @@ async fn inline_tables_2x(service: Box<dyn SqlClient>) {
     );
 }
 
+async fn parquet_options(service: Box<dyn SqlClient>) {
+    service.exec_query("CREATE SCHEMA s").await.unwrap();
+
+    service
+        .exec_query(
+            "CREATE TABLE s.data(id int, name text, amount decimal) \
+             WITH(compression = 'zstd', dictionary = 'false', \
+             column_compression = 'name:gzip', column_encoding = 'id:delta_binary_packed,name:delta_byte_array')",
+        )
+        .await
+        .unwrap();
+    service
+        .exec_query("CREATE INDEX by_name ON s.data (name)")
+        .await
+        .unwrap();
+    service
+        .exec_query("INSERT INTO s.data(id, name, amount) VALUES (1, 'a', 1.5), (2, 'b', 2.5)")
+        .await
+        .unwrap();
+
+    let r = service
+        .exec_query("SELECT id, name FROM s.data ORDER BY id")
+        .await
+        .unwrap();
+    assert_eq!(to_rows(&r), rows(&[(1, "a"), (2, "b")]));
+
+    let r = service
+        .exec_query("CREATE TABLE s.t1(id int) WITH(column_encoding = 'name:plain')")
+        .await;
+    assert!(r.is_err(), "unknown column must be rejected");
+
+    let r = service
+        .exec_query(
+            "CREATE TABLE s.t2(name text) WITH(column_encoding = 'name:delta_binary_packed')",
+        )
+        .await;
+    assert!(r.is_err(), "encoding must be allowed for the column type");
+
+    let r = service
+        .exec_query("CREATE TABLE s.t3(id int) WITH(compression = 'lzma')")
+        .await;
+    assert!(r.is_err(), "unknown compression must be rejected");
+}
+
+async fn delete_and_update(service: Box<dyn SqlClient>) {
+    service.exec_query("CREATE SCHEMA s").await.unwrap();
+    service
+        .exec_query("CREATE TABLE s.data(id int, name text)")
+        .await
+        .unwrap();
+    service
+        .exec_query("CREATE INDEX by_name ON s.data (name)")
+        .await
+        .unwrap();
+    service
+        .exec_query("INSERT INTO s.data(id, name) VALUES (1, 'a'), (2, 'b'), (3, 'c'), (4, 'd')")
+        .await
+        .unwrap();
+
+    service
+        .exec_query("DELETE FROM s.data WHERE id = 2 OR name = 'd'")
+        .await
+        .unwrap();
+    let r = service
+        .exec_query("SELECT id, name FROM s.data ORDER BY id")
+        .await
+        .unwrap();
+    assert_eq!(to_rows(&r), rows(&[(1, "a"), (3, "c")]));
+
+    service
+        .exec_query("UPDATE s.data SET name = 'x' WHERE id > 1")
+        .await
+        .unwrap();
+    let r = service
+        .exec_query("SELECT id, name FROM s.data ORDER BY id")
+        .await
+        .unwrap();
+    assert_eq!(to_rows(&r), rows(&[(1, "a"), (3, "x")]));
+    let r = service
+        .exec_query("SELECT id FROM s.data WHERE name = 'x'")
+        .await
+        .unwrap();
+    assert_eq!(to_rows(&r), rows(&[3]));
+
+    service
+        .exec_query("UPDATE s.data SET id = 5 WHERE name = 'a'")
+        .await
+        .unwrap();
+    let r = service
+        .exec_query("SELECT id, name FROM s.data ORDER BY id")
+        .await
+        .unwrap();
+    assert_eq!(to_rows(&r), rows(&[(3, "x"), (5, "a")]));
+
+    let r = service
+        .exec_query("UPDATE s.data SET amount = 1 WHERE id = 1")
+        .await;
+    assert!(r.is_err(), "unknown columns can't be updated");
+
+    let r = service
+        .exec_query("UPDATE s.data SET name = id WHERE id = 1")
+        .await;
+    assert!(r.is_err(), "only constants can be assigned");
+
+    service.exec_query("DELETE FROM s.data").await.unwrap();
+    let r = service
+        .exec_query("SELECT count(*) FROM s.data")
+        .await
+        .unwrap();
+    assert_eq!(to_rows(&r), rows(&[0]));
+}
+
+async fn alter_table(service: Box<dyn SqlClient>) {
+    service.exec_query("CREATE SCHEMA s").await.unwrap();
+    service
+        .exec_query("CREATE TABLE s.data(id int, name text)")
+        .await
+        .unwrap();
+    service
+        .exec_query("INSERT INTO s.data(id, name) VALUES (1, 'a'), (2, 'b')")
+        .await
+        .unwrap();
+
+    service
+        .exec_query("ALTER TABLE s.data ADD COLUMN amount int")
+        .await
+        .unwrap();
+    service
+        .exec_query("INSERT INTO s.data(id, name, amount) VALUES (3, 'c', 30)")
+        .await
+        .unwrap();
+    let r = service
+        .exec_query("SELECT id, name, amount FROM s.data ORDER BY id")
+        .await
+        .unwrap();
+    assert_eq!(
+        to_rows(&r),
+        vec![
+            vec![
+                TableValue::Int(1),
+                TableValue::String("a".to_string()),
+                TableValue::Null
+            ],
+            vec![
+                TableValue::Int(2),
+                TableValue::String("b".to_string()),
+                TableValue::Null
+            ],
+            vec![
+                TableValue::Int(3),
+                TableValue::String("c".to_string()),
+                TableValue::Int(30)
+            ],
+        ]
+    );
+    let r = service
+        .exec_query("SELECT sum(amount) FROM s.data WHERE amount IS NOT NULL")
+        .await
+        .unwrap();
+    assert_eq!(to_rows(&r), rows(&[30]));
+
+    let r = service
+        .exec_query("ALTER TABLE s.data ADD COLUMN amount text")
+        .await;
+    assert!(r.is_err(), "column names must be unique");
+
+    service
+        .exec_query("ALTER TABLE s.data DROP COLUMN name")
+        .await
+        .unwrap();
+    let r = service
+        .exec_query("SELECT * FROM s.data ORDER BY id")
+        .await
+        .unwrap();
+    assert_eq!(
+        to_rows(&r),
+        vec![
+            vec![TableValue::Int(1), TableValue::Null],
+            vec![TableValue::Int(2), TableValue::Null],
+            vec![TableValue::Int(3), TableValue::Int(30)],
+        ]
+    );
+    service
+        .exec_query("INSERT INTO s.data(id, amount) VALUES (4, 40)")
+        .await
+        .unwrap();
+    let r = service
+        .exec_query("SELECT id FROM s.data WHERE amount > 10 ORDER BY id")
+        .await
+        .unwrap();
+    assert_eq!(to_rows(&r), rows(&[3, 4]));
+    let r = service.exec_query("SELECT name FROM s.data").await;
+    assert!(r.is_err(), "dropped column can't be selected");
+    let r = service
+        .exec_query("ALTER TABLE s.data DROP COLUMN name")
+        .await;
+    assert!(r.is_err(), "unknown column can't be dropped");
+
+    service
+        .exec_query("ALTER TABLE s.data RENAME TO data_v2")
+        .await
+        .unwrap();
+    let r = service
+        .exec_query("SELECT count(*) FROM s.data_v2")
+        .await
+        .unwrap();
+    assert_eq!(to_rows(&r), rows(&[4]));
+    let r = service.exec_query("SELECT count(*) FROM s.data").await;
+    assert!(r.is_err(), "old table name is gone");
+
+    service
+        .exec_query("CREATE TABLE s.other(id int)")
+        .await
+        .unwrap();
+    let r = service
+        .exec_query("ALTER TABLE s.data_v2 RENAME TO s.other")
+        .await;
+    assert!(r.is_err(), "table names must be unique");
+}
+
+async fn table_retention(service: Box<dyn SqlClient>) {
+    service.exec_query("CREATE SCHEMA s").await.unwrap();
+    service
+        .exec_query(
+            "CREATE TABLE s.data(id int, ts timestamp) \
+             WITH (retention_column = 'ts', retention = '90 days')",
+        )
+        .await
+        .unwrap();
+    service
+        .exec_query(
+            "INSERT INTO s.data(id, ts) VALUES \
+             (1, '2020-01-01T00:00:00.000Z'), (2, NULL)",
+        )
+        .await
+        .unwrap();
+    // Expired rows are removed in background.
+    let r = service
+        .exec_query("SELECT count(*) FROM s.data")
+        .await
+        .unwrap();
+    assert_eq!(to_rows(&r), rows(&[2]));
+
+    let r = service
+        .exec_query("ALTER TABLE s.data DROP COLUMN ts")
+        .await;
+    assert!(r.is_err(), "retention column can't be dropped");
+
+    let r = service
+        .exec_query(
+            "CREATE TABLE s.bad_type(id int, ts timestamp) \
+             WITH (retention_column = 'id', retention = '90 days')",
+        )
+        .await;
+    assert!(r.is_err(), "retention column must be timestamp");
+    let r = service
+        .exec_query(
+            "CREATE TABLE s.bad_column(id int, ts timestamp) \
+             WITH (retention_column = 'created_at', retention = '90 days')",
+        )
+        .await;
+    assert!(r.is_err(), "retention column must exist");
+    let r = service
+        .exec_query(
+            "CREATE TABLE s.bad_period(id int, ts timestamp) \
+             WITH (retention_column = 'ts', retention = '90 fortnights')",
+        )
+        .await;
+    assert!(r.is_err(), "unknown period unit");
+    let r = service
+        .exec_query("CREATE TABLE s.no_period(id int, ts timestamp) WITH (retention_column = 'ts')")
+        .await;
+    assert!(r.is_err(), "retention period is required");
+}
+
 async fn build_range_end(service: Box<dyn SqlClient>) {
     service.exec_query("CREATE SCHEMA s").await.unwrap();
 
@@ -6749,6 +7141,71 @@ async fn cache_incr(service: Box<dyn SqlClient>) {
     );
 }
 
+async fn cache_hash_list_cas(service: Box<dyn SqlClient>) {
+    let r = service
+        .exec_query("CACHE HSET 'hash:1' 'f1' 'v1' 'f2' 'v2'")
+        .await
+        .unwrap();
+    assert_eq!(r.get_rows(), &vec![Row::new(vec![TableValue::Int(2)])]);
+
+    let r = service.exec_query("CACHE HGETALL 'hash:1'").await.unwrap();
+    assert_eq!(
+        r.get_rows(),
+        &vec![
+            Row::new(vec![
+                TableValue::String("f1".to_string()),
+                TableValue::String("v1".to_string())
+            ]),
+            Row::new(vec![
+                TableValue::String("f2".to_string()),
+                TableValue::String("v2".to_string())
+            ]),
+        ]
+    );
+
+    let r = service
+        .exec_query("CACHE HDEL 'hash:1' 'f1'")
+        .await
+        .unwrap();
+    assert_eq!(r.get_rows(), &vec![Row::new(vec![TableValue::Int(1)])]);
+
+    let r = service
+        .exec_query("CACHE HGET 'hash:1' 'f1'")
+        .await
+        .unwrap();
+    assert_eq!(r.get_rows(), &vec![Row::new(vec![TableValue::Null])]);
+
+    let r = service
+        .exec_query("CACHE RPUSH 'list:1' 'a' 'b'")
+        .await
+        .unwrap();
+    assert_eq!(r.get_rows(), &vec![Row::new(vec![TableValue::Int(2)])]);
+
+    let r = service.exec_query("CACHE LPOP 'list:1'").await.unwrap();
+    assert_eq!(
+        r.get_rows(),
+        &vec![Row::new(vec![TableValue::String("a".to_string())])]
+    );
+
+    let r = service
+        .exec_query("CACHE CAS 'lock:1' NULL 'owner1'")
+        .await
+        .unwrap();
+    assert_eq!(
+        r.get_rows(),
+        &vec![Row::new(vec![TableValue::Boolean(true)])]
+    );
+
+    let r = service
+        .exec_query("CACHE CAS 'lock:1' 'owner2' 'owner3'")
+        .await
+        .unwrap();
+    assert_eq!(
+        r.get_rows(),
+        &vec![Row::new(vec![TableValue::Boolean(false)])]
+    );
+}
+
 async fn cache_set_get_rm(service: Box<dyn SqlClient>) {
     service
         .exec_query("CACHE SET 'key_to_rm' 'myvalue';")
@@ -9080,6 +9537,70 @@ async fn queue_retrieve_extended(service: Box<dyn SqlClient>) {
     }
 }
 
+async fn queue_prefix_concurrency(service: Box<dyn SqlClient>) {
+    service
+        .exec_query(r#"QUEUE SET_PREFIX CONCURRENCY 1 MAX_ATTEMPTS 3 "STANDALONE#queue""#)
+        .await
+        .unwrap();
+
+    service
+        .exec_query(r#"QUEUE ADD PRIORITY 1 "STANDALONE#queue:1" "payload1";"#)
+        .await
+        .unwrap();
+
+    service
+        .exec_query(r#"QUEUE ADD PRIORITY 1 "STANDALONE#queue:2" "payload2";"#)
+        .await
+        .unwrap();
+
+    {
+        let retrieve_response = service
+            .exec_query(r#"QUEUE RETRIEVE EXTENDED CONCURRENCY 5 "STANDALONE#queue:1""#)
+            .await
+            .unwrap();
+        assert_queue_retrieve_columns(&retrieve_response);
+        assert_eq!(
+            retrieve_response.get_rows()[0].values()[0],
+            TableValue::String("payload1".to_string()),
+        );
+    }
+
+    {
+        // limit from the prefix configuration wins over the client's one
+        let retrieve_response = service
+            .exec_query(r#"QUEUE RETRIEVE EXTENDED CONCURRENCY 5 "STANDALONE#queue:2""#)
+            .await
+            .unwrap();
+        assert_queue_retrieve_columns(&retrieve_response);
+        assert_eq!(
+            retrieve_response.get_rows(),
+            &vec![Row::new(vec![
+                TableValue::Null,
+                TableValue::Null,
+                TableValue::Int(1),
+                TableValue::String("1".to_string()),
+                TableValue::Null,
+            ]),]
+        );
+    }
+
+    let res = service
+        .exec_query(
+            "SELECT prefix, concurrency, max_attempts, heartbeat_timeout FROM system.queue_prefixes",
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        res.get_rows(),
+        &vec![Row::new(vec![
+            TableValue::String("STANDALONE#queue".to_string()),
+            TableValue::Int(1),
+            TableValue::Int(3),
+            TableValue::Null,
+        ]),]
+    );
+}
+
 async fn queue_ack_then_result_v1(service: Box<dyn SqlClient>) {
     service
         .exec_query(r#"QUEUE ADD PRIORITY 1 "STANDALONE#queue:5555" "payload1";"#)
@@ -13,6 +13,8 @@ use cubestore_sql_tests::{run_sql_tests, TestFn};
 
 const METASTORE_PORT: u16 = 51336;
 const WORKER_PORTS: [u16; 2] = [51337, 51338];
+/// All nodes authenticate connections to each other with this secret.
+const CLUSTER_SECRET: &str = "cluster-test-secret";
 
 #[cfg(not(target_os = "windows"))]
 fn main() {
@@ -69,6 +71,7 @@ impl MultiProcTest for ClusterSqlTest {
             .update_config(|mut c| {
                 c.server_name = format!("localhost:{}", METASTORE_PORT);
                 c.metastore_bind_address = Some(c.server_name.clone());
+                c.cluster_secret = Some(CLUSTER_SECRET.to_string());
                 c.select_workers = WORKER_PORTS
                     .iter()
                     .map(|p| format!("localhost:{}", p))
@@ -102,6 +105,7 @@ impl WorkerProc<WorkerArgs> for WorkerFn {
                 c.select_worker_pool_size = 2;
                 c.server_name = format!("localhost:{}", WORKER_PORTS[id]);
                 c.worker_bind_address = Some(c.server_name.clone());
+                c.cluster_secret = Some(CLUSTER_SECRET.to_string());
                 c.metastore_remote_address = Some(format!("localhost:{}", METASTORE_PORT));
                 c.select_workers = WORKER_PORTS
                     .iter()
@@ -80,3 +80,60 @@ pub static CACHESTORE_EVICTION_REMOVED_KEYS: Counter =
     metrics::counter("cs.cachestore.eviction.removed.keys");
 pub static CACHESTORE_EVICTION_REMOVED_SIZE: Counter =
     metrics::counter("cs.cachestore.eviction.removed.size");
+
+/// Exposes all metrics in Prometheus format from the start, so they read as 0 instead of being
+/// missing until their first update.
+pub fn register_all() {
+    for counter in [
+        &STARTUPS,
+        &DATA_QUERIES,
+        &DATA_QUERIES_CACHE_HIT,
+        &META_QUERIES,
+        &CACHE_QUERIES,
+        &QUEUE_QUERIES,
+        &STREAMING_ROWS_READ,
+        &STREAMING_CHUNKS_READ,
+        &REMOTE_FS_OPERATION_CORE,
+        &CACHESTORE_TTL_PERSIST,
+        &CACHESTORE_EVICTION_REMOVED_EXPIRED_KEYS,
+        &CACHESTORE_EVICTION_REMOVED_EXPIRED_SIZE,
+        &CACHESTORE_EVICTION_REMOVED_KEYS,
+        &CACHESTORE_EVICTION_REMOVED_SIZE,
+    ] {
+        counter.register();
+    }
+    for metric in [
+        &DATA_QUERIES_CACHE_SIZE,
+        &DATA_QUERIES_CACHE_WEIGHT,
+        &DATA_QUERY_TIME_MS,
+        &META_QUERY_TIME_MS,
+        &CACHE_QUERY_TIME_MS,
+        &QUEUE_QUERY_TIME_MS,
+        &STREAMING_LASTOFFSET,
+        &IN_MEMORY_CHUNKS_COUNT,
+        &IN_MEMORY_CHUNKS_ROWS,
+        &IN_MEMORY_CHUNKS_MEMORY,
+        &STREAMING_IMPORT_TIME,
+        &STREAMING_PARTITION_TIME,
+        &STREAMING_UPLOAD_TIME,
+        &STREAMING_ROUNDTRIP_TIME,
+        &STREAMING_ROUNDTRIP_ROWS,
+        &STREAMING_ROUNDTRIP_CHUNKS,
+        &STREAMING_LAG,
+        &METASTORE_QUEUE,
+        &METASTORE_READ_OPERATION,
+        &METASTORE_INNER_READ_OPERATION,
+        &METASTORE_WRITE_OPERATION,
+        &METASTORE_INNER_WRITE_OPERATION,
+        &METASTORE_READ_OUT_QUEUE_OPERATION,
+        &CACHESTORE_ROCKSDB_ESTIMATE_LIVE_DATA_SIZE,
+        &CACHESTORE_ROCKSDB_LIVE_SST_FILES_SIZE,
+        &CACHESTORE_ROCKSDB_CF_DEFAULT_SIZE,
+        &CACHESTORE_SCHEDULER_GC_QUEUE,
+        &REMOTE_FS_FILES_TO_REMOVE,
+        &REMOTE_FS_FILES_SIZE_TO_REMOVE,
+        &CACHESTORE_TTL_BUFFER,
+    ] {
+        metric.register();
+    }
+}
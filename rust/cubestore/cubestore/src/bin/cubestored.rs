@@ -1,5 +1,7 @@
+use chrono::DateTime;
 use cubestore::config::{validate_config, Config, CubeServices};
 use cubestore::http::status::serve_status_probes;
+use cubestore::telemetry::tracing::{init_tracing_telemetry, shutdown_tracing_telemetry};
 use cubestore::telemetry::{init_agent_sender, track_event};
 use cubestore::util::logger::init_cube_logger;
 use cubestore::util::metrics::init_metrics;
@@ -78,8 +80,19 @@ fn main() {
         tokio_builder.worker_threads(var.parse().unwrap());
     }
     let runtime = tokio_builder.build().unwrap();
+    if std::env::args().nth(1).as_deref() == Some("restore") {
+        let args = std::env::args().skip(2).collect::<Vec<_>>();
+        if let Err(e) = runtime.block_on(restore(&config, &args)) {
+            log::error!("Restore failed: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
     runtime.block_on(async move {
         init_agent_sender().await;
+        if let Err(e) = init_tracing_telemetry() {
+            log::error!("Failed to initialize tracing: {}", e);
+        }
 
         validate_config(config.config_obj().as_ref()).report_and_abort_on_errors();
 
@@ -95,9 +108,54 @@ fn main() {
 
         stop_on_ctrl_c(&services).await;
         services.wait_processing_loops().await.unwrap();
+        shutdown_tracing_telemetry();
     });
 }
 
+/// `cubestored restore --snapshot <id> [--until <RFC 3339 timestamp>] [--from <backup location>]`
+async fn restore(config: &Config, args: &[String]) -> Result<(), CubeError> {
+    let usage = "Usage: cubestored restore --snapshot <id> [--until <RFC 3339 timestamp>] [--from <backup location>]";
+    let mut snapshot = None;
+    let mut until = None;
+    let mut location = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| CubeError::user(format!("Missing value for {}. {}", arg, usage)))?;
+        match arg.as_str() {
+            "--snapshot" => {
+                snapshot = Some(value.parse::<u128>().map_err(|e| {
+                    CubeError::user(format!("Invalid snapshot id '{}': {}", value, e))
+                })?)
+            }
+            "--until" => {
+                let time = DateTime::parse_from_rfc3339(value).map_err(|e| {
+                    CubeError::user(format!("Invalid --until timestamp '{}': {}", value, e))
+                })?;
+                until = Some(time.timestamp_millis() as u128)
+            }
+            "--from" => location = Some(value.as_str()),
+            _ => {
+                return Err(CubeError::user(format!(
+                    "Unknown argument {}. {}",
+                    arg, usage
+                )))
+            }
+        }
+    }
+    let snapshot = snapshot.ok_or_else(|| CubeError::user(usage.to_string()))?;
+
+    validate_config(config.config_obj().as_ref()).report_and_abort_on_errors();
+    let restored = config.restore_metastore(snapshot, until, location).await?;
+    log::info!(
+        "Metastore snapshot {} restored as {}. Start the router to use it.",
+        snapshot,
+        restored
+    );
+    Ok(())
+}
+
 async fn stop_on_ctrl_c(s: &CubeServices) {
     let s = s.clone();
     cube_ext::spawn(async move {
@@ -1,12 +1,13 @@
 use crate::cachestore::cache_item::{
     CacheItemRocksIndex, CacheItemRocksTable, CACHE_ITEM_SIZE_WITHOUT_VALUE,
 };
-use crate::cachestore::CacheItem;
+use crate::cachestore::{CacheItem, CacheItemChangeEvent, CacheItemChangeKind};
 use crate::config::ConfigObj;
 use crate::metastore::{
-    BaseRocksSecondaryIndex, IdRow, PackedDateTime, RocksSecondaryIndexValueTTLExtended,
-    RocksSecondaryIndexValueVersionDecoder, RocksSecondaryIndexValueVersionEncoder, RocksStore,
-    RocksTable, SecondaryIndexValueScanIterItem,
+    BaseRocksSecondaryIndex, IdRow, MetaStoreEvent, PackedDateTime,
+    RocksSecondaryIndexValueTTLExtended, RocksSecondaryIndexValueVersionDecoder,
+    RocksSecondaryIndexValueVersionEncoder, RocksStore, RocksTable,
+    SecondaryIndexValueScanIterItem,
 };
 use crate::util::aborting_join_handle::AbortingJoinHandle;
 use crate::util::lock::acquire_lock;
@@ -343,7 +344,9 @@ impl CacheEvictionManager {
                     let current_batch =
                         std::mem::replace(&mut batch, Vec::with_capacity(self.eviction_batch_size));
 
-                    let batch_result = self.delete_batch(current_batch, &store).await?;
+                    let batch_result = self
+                        .delete_batch(current_batch, &store, keys_are_expired)
+                        .await?;
 
                     total_size_removed += batch_result.deleted_size;
                     total_keys_removed += batch_result.deleted_count;
@@ -357,7 +360,9 @@ impl CacheEvictionManager {
         };
 
         if last_batch.len() > 0 {
-            let batch_result = self.delete_batch(last_batch, &store).await?;
+            let batch_result = self
+                .delete_batch(last_batch, &store, keys_are_expired)
+                .await?;
 
             total_size_removed += batch_result.deleted_size;
             total_keys_removed += batch_result.deleted_count;
@@ -385,7 +390,14 @@ impl CacheEvictionManager {
         &self,
         batch: Vec<(u64, u32)>,
         store: &Arc<RocksStore>,
+        keys_are_expired: bool,
     ) -> Result<DeleteBatchResult, CubeError> {
+        let change_kind = if keys_are_expired {
+            CacheItemChangeKind::Expire
+        } else {
+            CacheItemChangeKind::Delete
+        };
+
         let (deleted_count, deleted_size, skipped) = store
             .write_operation(move |db_ref, pipe| {
                 let cache_schema = CacheItemRocksTable::new(db_ref.clone());
@@ -395,7 +407,12 @@ impl CacheEvictionManager {
                 let mut skipped: u32 = 0;
 
                 for (id, raw_size) in batch {
-                    if let Some(_) = cache_schema.try_delete(id, pipe)? {
+                    if let Some(row) = cache_schema.try_delete(id, pipe)? {
+                        pipe.add_event(MetaStoreEvent::CacheItemChange(CacheItemChangeEvent::new(
+                            row.get_row().get_path(),
+                            change_kind,
+                        )));
+
                         deleted_count += 1;
                         deleted_size += raw_size as u64;
                     } else {
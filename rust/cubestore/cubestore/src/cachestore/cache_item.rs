@@ -6,6 +6,63 @@ use chrono::serde::ts_seconds_option;
 use chrono::{DateTime, Duration, Utc};
 use rocksdb::WriteBatch;
 use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+#[repr(u8)]
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Eq, PartialEq, Hash)]
+pub enum CacheItemValueType {
+    String = 0,
+    // JSON object with string values
+    Hash = 1,
+    // JSON array of strings
+    List = 2,
+}
+
+impl ToString for CacheItemValueType {
+    fn to_string(&self) -> String {
+        match self {
+            CacheItemValueType::String => "string".to_string(),
+            CacheItemValueType::Hash => "hash".to_string(),
+            CacheItemValueType::List => "list".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Eq, PartialEq)]
+pub enum CacheListSide {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Eq, PartialEq, Hash)]
+pub enum CacheItemChangeKind {
+    Set,
+    Delete,
+    // Removed by eviction after TTL
+    Expire,
+}
+
+impl ToString for CacheItemChangeKind {
+    fn to_string(&self) -> String {
+        match self {
+            CacheItemChangeKind::Set => "set".to_string(),
+            CacheItemChangeKind::Delete => "delete".to_string(),
+            CacheItemChangeKind::Expire => "expire".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Eq, PartialEq, Hash)]
+pub struct CacheItemChangeEvent {
+    pub path: String,
+    pub kind: CacheItemChangeKind,
+}
+
+impl CacheItemChangeEvent {
+    pub fn new(path: String, kind: CacheItemChangeKind) -> Self {
+        Self { path, kind }
+    }
+}
 
 #[derive(Clone, Serialize, Deserialize, Debug, Eq, PartialEq)]
 pub struct CacheItem {
@@ -14,6 +71,8 @@ pub struct CacheItem {
     pub(crate) value: String,
     #[serde(with = "ts_seconds_option")]
     pub(crate) expire: Option<DateTime<Utc>>,
+    #[serde(default = "CacheItem::value_type_default")]
+    pub(crate) value_type: CacheItemValueType,
 }
 
 // Every RowKey uses 15 bytes
@@ -43,6 +102,15 @@ impl CacheItem {
     }
 
     pub fn new(path: String, ttl: Option<u32>, value: String) -> CacheItem {
+        Self::new_typed(path, ttl, CacheItemValueType::String, value)
+    }
+
+    pub fn new_typed(
+        path: String,
+        ttl: Option<u32>,
+        value_type: CacheItemValueType,
+        value: String,
+    ) -> CacheItem {
         let parts: Vec<&str> = path.rsplitn(2, ":").collect();
 
         let (prefix, key) = match parts.len() {
@@ -55,9 +123,96 @@ impl CacheItem {
             key,
             value,
             expire: ttl.map(|ttl| Utc::now() + Duration::seconds(ttl as i64)),
+            value_type,
         }
     }
 
+    pub fn new_hash(path: String, fields: &BTreeMap<String, String>) -> Result<Self, CubeError> {
+        Ok(Self::new_typed(
+            path,
+            None,
+            CacheItemValueType::Hash,
+            serde_json::to_string(fields)?,
+        ))
+    }
+
+    pub fn new_list(path: String, values: &VecDeque<String>) -> Result<Self, CubeError> {
+        Ok(Self::new_typed(
+            path,
+            None,
+            CacheItemValueType::List,
+            serde_json::to_string(values)?,
+        ))
+    }
+
+    /// Hash/list without elements, it's never stored, because empty collections are removed
+    pub fn new_empty(path: String, value_type: CacheItemValueType) -> Self {
+        let value = match value_type {
+            CacheItemValueType::String => "",
+            CacheItemValueType::Hash => "{}",
+            CacheItemValueType::List => "[]",
+        };
+
+        Self::new_typed(path, None, value_type, value.to_string())
+    }
+
+    pub fn is_empty_collection(&self) -> bool {
+        match self.value_type {
+            CacheItemValueType::String => false,
+            CacheItemValueType::Hash => self.value == "{}",
+            CacheItemValueType::List => self.value == "[]",
+        }
+    }
+
+    fn value_type_default() -> CacheItemValueType {
+        CacheItemValueType::String
+    }
+
+    fn check_value_type(&self, expected: CacheItemValueType) -> Result<(), CubeError> {
+        if self.value_type == expected {
+            Ok(())
+        } else {
+            Err(CubeError::user(format!(
+                "Operation against '{}' key holding the wrong kind of value, expected: {}, actual: {}",
+                self.get_path(),
+                expected.to_string(),
+                self.value_type.to_string()
+            )))
+        }
+    }
+
+    pub fn get_hash(&self) -> Result<BTreeMap<String, String>, CubeError> {
+        self.check_value_type(CacheItemValueType::Hash)?;
+
+        Ok(serde_json::from_str(&self.value)?)
+    }
+
+    pub fn set_hash(&mut self, fields: &BTreeMap<String, String>) -> Result<(), CubeError> {
+        self.check_value_type(CacheItemValueType::Hash)?;
+        self.value = serde_json::to_string(fields)?;
+
+        Ok(())
+    }
+
+    pub fn get_list(&self) -> Result<VecDeque<String>, CubeError> {
+        self.check_value_type(CacheItemValueType::List)?;
+
+        Ok(serde_json::from_str(&self.value)?)
+    }
+
+    pub fn set_list(&mut self, values: &VecDeque<String>) -> Result<(), CubeError> {
+        self.check_value_type(CacheItemValueType::List)?;
+        self.value = serde_json::to_string(values)?;
+
+        Ok(())
+    }
+
+    pub fn get_string(&self) -> Result<&String, CubeError> {
+        self.check_value_type(CacheItemValueType::String)?;
+
+        Ok(&self.value)
+    }
+
     pub fn get_path(&self) -> String {
         if let Some(prefix) = &self.prefix {
             format!("{}:{}", prefix, self.key)
@@ -81,6 +236,10 @@ impl CacheItem {
     pub fn get_value(&self) -> &String {
         &self.value
     }
+
+    pub fn get_value_type(&self) -> &CacheItemValueType {
+        &self.value_type
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -207,4 +366,37 @@ mod tests {
         assert_eq!(row.key, "1".to_string());
         assert_eq!(row.get_path(), "lock:1".to_string());
     }
+
+    #[test]
+    fn test_typed_values() -> Result<(), CubeError> {
+        let mut fields = BTreeMap::new();
+        fields.insert("field".to_string(), "value".to_string());
+
+        let mut row = CacheItem::new_hash("hash:1".to_string(), &fields)?;
+        assert_eq!(row.get_value_type(), &CacheItemValueType::Hash);
+        assert_eq!(row.get_value(), &r#"{"field":"value"}"#.to_string());
+        assert_eq!(row.get_hash()?, fields);
+
+        fields.remove("field");
+        row.set_hash(&fields)?;
+        assert_eq!(row.get_value(), &"{}".to_string());
+        assert!(row.is_empty_collection());
+
+        assert_eq!(
+            row.get_list(),
+            Err(CubeError::user(
+                "Operation against 'hash:1' key holding the wrong kind of value, expected: list, actual: hash".to_string()
+            ))
+        );
+        assert!(row.get_string().is_err());
+
+        let row = CacheItem::new_list(
+            "list:1".to_string(),
+            &VecDeque::from(vec!["a".to_string(), "b".to_string()]),
+        )?;
+        assert_eq!(row.get_value(), &r#"["a","b"]"#.to_string());
+        assert_eq!(row.get_list()?.pop_front(), Some("a".to_string()));
+
+        Ok(())
+    }
 }
@@ -1,13 +1,14 @@
 use crate::cachestore::cache_item::{
-    CacheItem, CacheItemIndexKey, CacheItemRocksIndex, CacheItemRocksTable,
-    CACHE_ITEM_SIZE_WITHOUT_VALUE,
+    CacheItem, CacheItemChangeEvent, CacheItemChangeKind, CacheItemIndexKey, CacheItemRocksIndex,
+    CacheItemRocksTable, CacheItemValueType, CacheListSide, CACHE_ITEM_SIZE_WITHOUT_VALUE,
 };
 use crate::cachestore::queue_item::{
     QueueItem, QueueItemIndexKey, QueueItemRocksIndex, QueueItemRocksTable, QueueItemStatus,
     QueueResultAckEvent, QueueResultAckEventResult, QueueRetrieveResponse,
 };
+use crate::cachestore::queue_prefix::QueuePrefixRocksTable;
 use crate::cachestore::queue_result::{QueueResultRocksIndex, QueueResultRocksTable};
-use crate::cachestore::{compaction, QueueResult};
+use crate::cachestore::{compaction, QueuePrefix, QueueResult};
 use crate::config::injection::DIService;
 use crate::config::{Config, ConfigObj};
 use std::collections::HashMap;
@@ -27,7 +28,7 @@ use rocksdb::{BlockBasedOptions, Cache, Options, DB};
 
 use crate::cachestore::cache_eviction_manager::{CacheEvictionManager, EvictionResult};
 use crate::cachestore::compaction::CompactionPreloadedState;
-use crate::cachestore::listener::RocksCacheStoreListener;
+use crate::cachestore::listener::{RocksCacheStoreChangeListener, RocksCacheStoreListener};
 use crate::table::{Row, TableValue};
 use chrono::{DateTime, Utc};
 use datafusion::cube_ext;
@@ -64,6 +65,7 @@ impl RocksCacheStoreDetails {
         populate_indexes!(CacheItemRocksTable);
         populate_indexes!(QueueItemRocksTable);
         populate_indexes!(QueueResultRocksTable);
+        populate_indexes!(QueuePrefixRocksTable);
 
         CompactionPreloadedState::new(indexes)
     }
@@ -146,6 +148,7 @@ impl RocksStoreDetails for RocksCacheStoreDetails {
         CacheItemRocksTable::new(table_ref.clone()).migrate()?;
         QueueItemRocksTable::new(table_ref.clone()).migrate()?;
         QueueResultRocksTable::new(table_ref.clone()).migrate()?;
+        QueuePrefixRocksTable::new(table_ref.clone()).migrate()?;
 
         table_ref
             .db
@@ -164,9 +167,14 @@ pub struct RocksCacheStore {
     cache_eviction_manager: CacheEvictionManager,
     upload_loop: Arc<WorkerLoop>,
     metrics_loop: Arc<WorkerLoop>,
+    queue_requeue_loop: Arc<WorkerLoop>,
 }
 
 impl RocksCacheStore {
+    pub fn get_change_listener(&self) -> RocksCacheStoreChangeListener {
+        RocksCacheStoreChangeListener::new(self.store.cache_change_sender.subscribe())
+    }
+
     pub async fn get_listener(&self) -> RocksCacheStoreListener {
         let listeners = self.store.listeners.read().await;
 
@@ -201,6 +209,7 @@ impl RocksCacheStore {
             cache_eviction_manager,
             upload_loop: Arc::new(WorkerLoop::new("Cachestore upload")),
             metrics_loop: Arc::new(WorkerLoop::new("Cachestore metrics")),
+            queue_requeue_loop: Arc::new(WorkerLoop::new("Cachestore queue requeue")),
         }))
     }
 
@@ -328,6 +337,31 @@ impl RocksCacheStore {
             log::info!("Not running cachestore eviction loop");
         }
 
+        let requeue_interval = self.store.config.cachestore_queue_requeue_loop_interval();
+        if requeue_interval > 0 {
+            let cachestore = self.clone();
+            loops.push(cube_ext::spawn(async move {
+                cachestore
+                    .queue_requeue_loop
+                    .process(
+                        cachestore.clone(),
+                        async move |_| Ok(Delay::new(Duration::from_secs(requeue_interval)).await),
+                        async move |m, _| {
+                            if let Err(err) = m.queue_requeue_stalled().await {
+                                log::error!("Error while requeueing stalled queue items: {}", err)
+                            };
+
+                            Ok(())
+                        },
+                    )
+                    .await;
+
+                Ok(())
+            }))
+        } else {
+            log::info!("Not running cachestore queue requeue loop");
+        }
+
         loops
     }
 
@@ -365,6 +399,7 @@ impl RocksCacheStore {
         self.cache_eviction_manager.stop_processing_loops();
         self.upload_loop.stop();
         self.metrics_loop.stop();
+        self.queue_requeue_loop.stop();
     }
 
     pub async fn add_listener(&self, listener: Sender<MetaStoreEvent>) {
@@ -464,7 +499,144 @@ impl RocksCacheStore {
     }
 }
 
+/// What happened with the cache row during typed (hash/list) modification
+enum CacheModifyResult {
+    Inserted(IdRow<CacheItem>),
+    Updated(IdRow<CacheItem>),
+    Deleted(u64, usize),
+    Untouched,
+}
+
 impl RocksCacheStore {
+    fn check_cache_entry_size(&self, path: &str, size: usize) -> Result<(), CubeError> {
+        Self::check_cache_entry_size_impl(
+            path,
+            size,
+            self.store.config.cachestore_cache_max_entry_size(),
+        )
+    }
+
+    fn check_cache_entry_size_impl(
+        path: &str,
+        size: usize,
+        max_entry_size: usize,
+    ) -> Result<(), CubeError> {
+        if size >= max_entry_size {
+            return Err(CubeError::user(format!(
+                "Unable to SET cache with '{}' key, exceeds maximum allowed size for payload: {}, max allowed: {}",
+                path,
+                humansize::format_size(size, humansize::DECIMAL),
+                humansize::format_size(max_entry_size, humansize::DECIMAL),
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn add_cache_change_event(batch_pipe: &mut BatchPipe, path: String, kind: CacheItemChangeKind) {
+        batch_pipe.add_event(MetaStoreEvent::CacheItemChange(CacheItemChangeEvent::new(
+            path, kind,
+        )));
+    }
+
+    /// Read-modify-write for hash/list values. It's done inside one write batch, that's why it's
+    /// atomic. Like in Redis, a collection without elements is removed.
+    fn cache_modify_typed_impl<R>(
+        db_ref: DbTableRef,
+        batch_pipe: &mut BatchPipe,
+        path: String,
+        value_type: CacheItemValueType,
+        upsert: bool,
+        max_entry_size: usize,
+        modify: impl FnOnce(&mut CacheItem) -> Result<R, CubeError>,
+    ) -> Result<Option<(R, CacheModifyResult)>, CubeError> {
+        let cache_schema = CacheItemRocksTable::new(db_ref.clone());
+        let index_key = CacheItemIndexKey::ByPath(path.clone());
+        let id_row_opt =
+            cache_schema.get_single_opt_row_by_index(&index_key, &CacheItemRocksIndex::ByPath)?;
+
+        let mut item = match &id_row_opt {
+            Some(id_row) => id_row.get_row().clone(),
+            None if upsert => CacheItem::new_empty(path.clone(), value_type),
+            None => return Ok(None),
+        };
+
+        let result = modify(&mut item)?;
+
+        if item.is_empty_collection() {
+            return if let Some(id_row) = id_row_opt {
+                let row_id = id_row.get_id();
+                let raw_size = id_row.get_row().get_value().len();
+
+                cache_schema.delete_row(id_row, batch_pipe)?;
+                Self::add_cache_change_event(batch_pipe, path, CacheItemChangeKind::Delete);
+
+                Ok(Some((result, CacheModifyResult::Deleted(row_id, raw_size))))
+            } else {
+                Ok(Some((result, CacheModifyResult::Untouched)))
+            };
+        }
+
+        Self::check_cache_entry_size_impl(&path, item.get_value().len(), max_entry_size)?;
+        Self::add_cache_change_event(batch_pipe, path, CacheItemChangeKind::Set);
+
+        let modify_result = if let Some(id_row) = id_row_opt {
+            CacheModifyResult::Updated(cache_schema.update(
+                id_row.id,
+                item,
+                &id_row.row,
+                batch_pipe,
+            )?)
+        } else {
+            CacheModifyResult::Inserted(cache_schema.insert(item, batch_pipe)?)
+        };
+
+        Ok(Some((result, modify_result)))
+    }
+
+    async fn cache_modify_typed<R: Send + Sync + 'static>(
+        &self,
+        path: String,
+        value_type: CacheItemValueType,
+        upsert: bool,
+        modify: impl FnOnce(&mut CacheItem) -> Result<R, CubeError> + Send + Sync + 'static,
+    ) -> Result<Option<R>, CubeError> {
+        let max_entry_size = self.store.config.cachestore_cache_max_entry_size();
+        let result = self
+            .store
+            .write_operation(move |db_ref, batch_pipe| {
+                Self::cache_modify_typed_impl(
+                    db_ref,
+                    batch_pipe,
+                    path,
+                    value_type,
+                    upsert,
+                    max_entry_size,
+                    modify,
+                )
+            })
+            .await?;
+
+        if let Some((result, modify_result)) = result {
+            match modify_result {
+                CacheModifyResult::Inserted(row) => self
+                    .cache_eviction_manager
+                    .notify_insert(row.get_row().get_value().len() as u64)?,
+                CacheModifyResult::Updated(row) => {
+                    self.cache_eviction_manager.notify_lookup(&row)?
+                }
+                CacheModifyResult::Deleted(row_id, raw_size) => self
+                    .cache_eviction_manager
+                    .notify_delete(row_id, raw_size as u64)?,
+                CacheModifyResult::Untouched => {}
+            };
+
+            Ok(Some(result))
+        } else {
+            Ok(None)
+        }
+    }
+
     async fn queue_result_delete_by_id(&self, id: u64) -> Result<(), CubeError> {
         self.store
             .write_operation(move |db_ref, batch_pipe| {
@@ -548,6 +720,117 @@ impl RocksCacheStore {
             .await
     }
 
+    /// Returns stalled active items back to the queue for prefixes with configured heartbeat
+    /// timeout. Items which have used all attempts are moved to the dead letter state instead and
+    /// get an error result, so clients waiting for the result don't wait forever.
+    pub async fn queue_requeue_stalled(&self) -> Result<QueueRequeueResult, CubeError> {
+        self.store
+            .write_operation(move |db_ref, batch_pipe| {
+                let prefix_schema = QueuePrefixRocksTable::new(db_ref.clone());
+                let queue_schema = QueueItemRocksTable::new(db_ref.clone());
+                let result_schema = QueueResultRocksTable::new(db_ref.clone());
+                let now = db_ref.start_time.clone();
+
+                let mut result = QueueRequeueResult {
+                    requeued: 0,
+                    dead_lettered: 0,
+                };
+
+                for prefix_row in prefix_schema.all_rows()? {
+                    let queue_prefix = prefix_row.get_row();
+                    let heartbeat_timeout = match queue_prefix.get_heartbeat_timeout() {
+                        Some(heartbeat_timeout) => *heartbeat_timeout,
+                        None => continue,
+                    };
+
+                    let active = queue_schema.get_rows_by_index(
+                        &QueueItemIndexKey::ByPrefixAndStatus(
+                            queue_prefix.get_prefix().clone(),
+                            QueueItemStatus::Active,
+                        ),
+                        &QueueItemRocksIndex::ByPrefixAndStatus,
+                    )?;
+
+                    for item in Self::filter_to_cancel(now, active, None, Some(heartbeat_timeout)) {
+                        let mut new = item.get_row().clone();
+
+                        if queue_prefix.is_attempts_exhausted(new.get_attempts()) {
+                            new.status = QueueItemStatus::DeadLetter;
+                            result.dead_lettered += 1;
+
+                            Self::queue_dead_letter_result_impl(
+                                &result_schema,
+                                batch_pipe,
+                                item.get_id(),
+                                &new,
+                            )?;
+                        } else {
+                            new.requeue(now);
+                            result.requeued += 1;
+                        }
+
+                        queue_schema.update(item.get_id(), new, item.get_row(), batch_pipe)?;
+                    }
+                }
+
+                Ok(result)
+            })
+            .await
+    }
+
+    /// Stores an error result for the dead letter item and notifies clients which wait for it.
+    fn queue_dead_letter_result_impl(
+        result_schema: &QueueResultRocksTable,
+        batch_pipe: &mut BatchPipe,
+        id: u64,
+        item: &QueueItem,
+    ) -> Result<(), CubeError> {
+        let error = serde_json::json!({
+            "error": format!(
+                "Queue item was moved to the dead letter state after {} attempts",
+                item.get_attempts()
+            )
+        });
+        // QueueResult is a result of QueueItem, it's why we can use row_id of QueueItem
+        let result_row = result_schema.insert_with_pk(
+            id,
+            QueueResult::new(item.get_path(), error.to_string()),
+            batch_pipe,
+        )?;
+
+        batch_pipe.add_event(MetaStoreEvent::AckQueueItem(QueueResultAckEvent {
+            id,
+            path: item.get_path(),
+            result: QueueResultAckEventResult::WithResult {
+                result: result_row.into_row().value,
+            },
+        }));
+
+        Ok(())
+    }
+
+    /// Pending items which are scheduled for the future are not counted and not listed as pending.
+    fn count_pending(
+        queue_schema: &QueueItemRocksTable,
+        prefix: String,
+        now: &DateTime<Utc>,
+    ) -> Result<u64, CubeError> {
+        let pending = queue_schema.count_rows_by_index(
+            &QueueItemIndexKey::ByPrefixAndStatus(prefix.clone(), QueueItemStatus::Pending),
+            &QueueItemRocksIndex::ByPrefixAndStatus,
+        )?;
+        let delayed = queue_schema
+            .get_rows_by_index(
+                &QueueItemIndexKey::ByPrefixAndScheduled(prefix),
+                &QueueItemRocksIndex::ByPrefixAndScheduled,
+            )?
+            .into_iter()
+            .filter(|item| item.get_row().is_delayed(now))
+            .count() as u64;
+
+        Ok(pending - delayed)
+    }
+
     fn filter_to_cancel(
         now: DateTime<Utc>,
         items: Vec<IdRow<QueueItem>>,
@@ -558,12 +841,16 @@ impl RocksCacheStore {
             .into_iter()
             .filter(|item| {
                 if item.get_row().get_status() == &QueueItemStatus::Pending {
+                    if item.get_row().is_delayed(&now) {
+                        return false;
+                    }
+
                     return if let Some(orphaned_timeout) = orphaned_timeout {
                         if let Some(orphaned) = item.get_row().get_orphaned() {
                             return if orphaned < &now { true } else { false };
                         }
 
-                        let elapsed = now - item.get_row().get_created().clone();
+                        let elapsed = now - item.get_row().get_available_from();
                         if elapsed.num_milliseconds() > orphaned_timeout as i64 {
                             true
                         } else {
@@ -613,6 +900,12 @@ pub struct CachestoreInfo {
     pub tables: Vec<RocksTableStats>,
 }
 
+#[derive(Clone, Serialize, Deserialize, Debug, Eq, PartialEq)]
+pub struct QueueRequeueResult {
+    pub requeued: u64,
+    pub dead_lettered: u64,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, Eq, PartialEq)]
 pub struct QueueAddResponse {
     pub id: u64,
@@ -654,6 +947,34 @@ pub trait CacheStore: DIService + Send + Sync {
     async fn cache_get(&self, key: String) -> Result<Option<IdRow<CacheItem>>, CubeError>;
     async fn cache_keys(&self, prefix: String) -> Result<Vec<IdRow<CacheItem>>, CubeError>;
     async fn cache_incr(&self, key: String) -> Result<IdRow<CacheItem>, CubeError>;
+    // Set value only if the current one is equal to expected, None means that key must not exist
+    async fn cache_compare_and_set(
+        &self,
+        item: CacheItem,
+        expected: Option<String>,
+    ) -> Result<bool, CubeError>;
+    // hash, returns the number of added fields
+    async fn cache_hset(
+        &self,
+        path: String,
+        fields: Vec<(String, String)>,
+    ) -> Result<u64, CubeError>;
+    async fn cache_hget(&self, path: String, field: String) -> Result<Option<String>, CubeError>;
+    // returns the number of removed fields
+    async fn cache_hdel(&self, path: String, fields: Vec<String>) -> Result<u64, CubeError>;
+    async fn cache_hgetall(&self, path: String) -> Result<Vec<(String, String)>, CubeError>;
+    // list, returns the length of the list after push
+    async fn cache_list_push(
+        &self,
+        path: String,
+        values: Vec<String>,
+        side: CacheListSide,
+    ) -> Result<u64, CubeError>;
+    async fn cache_list_pop(
+        &self,
+        path: String,
+        side: CacheListSide,
+    ) -> Result<Option<String>, CubeError>;
 
     // queue
     async fn queue_all(&self, limit: Option<usize>) -> Result<Vec<IdRow<QueueItem>>, CubeError>;
@@ -696,6 +1017,17 @@ pub trait CacheStore: DIService + Send + Sync {
         timeout: u64,
     ) -> Result<Option<QueueResultResponse>, CubeError>;
     async fn queue_merge_extra(&self, key: QueueKey, payload: String) -> Result<(), CubeError>;
+    async fn queue_prefix_set(
+        &self,
+        prefix: String,
+        concurrency: Option<u32>,
+        max_attempts: Option<u32>,
+        heartbeat_timeout: Option<u32>,
+    ) -> Result<IdRow<QueuePrefix>, CubeError>;
+    async fn queue_prefixes_all(
+        &self,
+        limit: Option<usize>,
+    ) -> Result<Vec<IdRow<QueuePrefix>>, CubeError>;
 
     // Force compaction for the whole RocksDB
     async fn compaction(&self) -> Result<(), CubeError>;
@@ -724,14 +1056,7 @@ impl CacheStore for RocksCacheStore {
         item: CacheItem,
         update_if_not_exists: bool,
     ) -> Result<bool, CubeError> {
-        if item.get_value().len() >= self.store.config.cachestore_cache_max_entry_size() {
-            return Err(CubeError::user(format!(
-                "Unable to SET cache with '{}' key, exceeds maximum allowed size for payload: {}, max allowed: {}",
-                item.key,
-                humansize::format_size(item.get_value().len(), humansize::DECIMAL),
-                humansize::format_size(self.store.config.cachestore_cache_max_entry_size(), humansize::DECIMAL),
-            )));
-        }
+        self.check_cache_entry_size(&item.key, item.get_value().len())?;
 
         self.cache_eviction_manager
             .before_insert(item.get_value().len() as u64)
@@ -741,15 +1066,18 @@ impl CacheStore for RocksCacheStore {
             .store
             .write_operation(move |db_ref, batch_pipe| {
                 let cache_schema = CacheItemRocksTable::new(db_ref.clone());
-                let index_key = CacheItemIndexKey::ByPath(item.get_path());
+                let path = item.get_path();
+                let index_key = CacheItemIndexKey::ByPath(path.clone());
                 let id_row_opt = cache_schema
                     .get_single_opt_row_by_index(&index_key, &CacheItemRocksIndex::ByPath)?;
 
-                if let Some(id_row) = id_row_opt {
-                    if update_if_not_exists {
-                        return Ok((false, None));
-                    };
+                if id_row_opt.is_some() && update_if_not_exists {
+                    return Ok((false, None));
+                }
+
+                Self::add_cache_change_event(batch_pipe, path, CacheItemChangeKind::Set);
 
+                if let Some(id_row) = id_row_opt {
                     cache_schema.update(id_row.id, item, &id_row.row, batch_pipe)?;
                     Ok((true, None))
                 } else {
@@ -792,7 +1120,7 @@ impl CacheStore for RocksCacheStore {
             .store
             .write_operation(move |db_ref, batch_pipe| {
                 let cache_schema = CacheItemRocksTable::new(db_ref.clone());
-                let index_key = CacheItemIndexKey::ByPath(key);
+                let index_key = CacheItemIndexKey::ByPath(key.clone());
                 let row_opt = cache_schema
                     .get_single_opt_row_by_index(&index_key, &CacheItemRocksIndex::ByPath)?;
 
@@ -801,6 +1129,7 @@ impl CacheStore for RocksCacheStore {
                     let raw_size = row.get_row().get_value().len();
 
                     cache_schema.delete_row(row, batch_pipe)?;
+                    Self::add_cache_change_event(batch_pipe, key, CacheItemChangeKind::Delete);
 
                     Ok(Some((row_id, raw_size)))
                 } else {
@@ -860,6 +1189,8 @@ impl CacheStore for RocksCacheStore {
                 let id_row_opt = cache_schema
                     .get_single_opt_row_by_index(&index_key, &CacheItemRocksIndex::ByPath)?;
 
+                Self::add_cache_change_event(batch_pipe, path.clone(), CacheItemChangeKind::Set);
+
                 // TODO: Merge operator?
                 if let Some(id_row) = id_row_opt {
                     let mut new = id_row.row.clone();
@@ -880,6 +1211,179 @@ impl CacheStore for RocksCacheStore {
         Ok(item)
     }
 
+    async fn cache_compare_and_set(
+        &self,
+        item: CacheItem,
+        expected: Option<String>,
+    ) -> Result<bool, CubeError> {
+        self.check_cache_entry_size(&item.key, item.get_value().len())?;
+
+        self.cache_eviction_manager
+            .before_insert(item.get_value().len() as u64)
+            .await?;
+
+        let (result, inserted) = self
+            .store
+            .write_operation(move |db_ref, batch_pipe| {
+                let cache_schema = CacheItemRocksTable::new(db_ref.clone());
+                let path = item.get_path();
+                let index_key = CacheItemIndexKey::ByPath(path.clone());
+                let id_row_opt = cache_schema
+                    .get_single_opt_row_by_index(&index_key, &CacheItemRocksIndex::ByPath)?;
+
+                match (id_row_opt, expected) {
+                    (Some(id_row), Some(expected)) => {
+                        if id_row.get_row().get_string()? != &expected {
+                            return Ok((false, None));
+                        }
+
+                        cache_schema.update(id_row.id, item, &id_row.row, batch_pipe)?;
+                        Self::add_cache_change_event(batch_pipe, path, CacheItemChangeKind::Set);
+                        Ok((true, None))
+                    }
+                    (None, None) => {
+                        let raw_size = item.get_value().len();
+
+                        cache_schema.insert(item, batch_pipe)?;
+                        Self::add_cache_change_event(batch_pipe, path, CacheItemChangeKind::Set);
+                        Ok((true, Some(raw_size)))
+                    }
+                    (_, _) => Ok((false, None)),
+                }
+            })
+            .await?;
+
+        if let Some(raw_size) = inserted {
+            self.cache_eviction_manager.notify_insert(raw_size as u64)?;
+        }
+
+        Ok(result)
+    }
+
+    async fn cache_hset(
+        &self,
+        path: String,
+        fields: Vec<(String, String)>,
+    ) -> Result<u64, CubeError> {
+        let payload_size: usize = fields.iter().map(|(f, v)| f.len() + v.len()).sum();
+        self.check_cache_entry_size(&path, payload_size)?;
+
+        self.cache_eviction_manager
+            .before_insert(payload_size as u64)
+            .await?;
+
+        let added = self
+            .cache_modify_typed(path, CacheItemValueType::Hash, true, move |item| {
+                let mut hash = item.get_hash()?;
+                let mut added = 0;
+
+                for (field, value) in fields {
+                    if hash.insert(field, value).is_none() {
+                        added += 1;
+                    }
+                }
+
+                item.set_hash(&hash)?;
+
+                Ok(added)
+            })
+            .await?;
+
+        Ok(added.unwrap_or(0))
+    }
+
+    async fn cache_hget(&self, path: String, field: String) -> Result<Option<String>, CubeError> {
+        if let Some(item) = self.cache_get(path).await? {
+            Ok(item.into_row().get_hash()?.remove(&field))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn cache_hdel(&self, path: String, fields: Vec<String>) -> Result<u64, CubeError> {
+        let removed = self
+            .cache_modify_typed(path, CacheItemValueType::Hash, false, move |item| {
+                let mut hash = item.get_hash()?;
+                let mut removed = 0;
+
+                for field in fields {
+                    if hash.remove(&field).is_some() {
+                        removed += 1;
+                    }
+                }
+
+                item.set_hash(&hash)?;
+
+                Ok(removed)
+            })
+            .await?;
+
+        Ok(removed.unwrap_or(0))
+    }
+
+    async fn cache_hgetall(&self, path: String) -> Result<Vec<(String, String)>, CubeError> {
+        if let Some(item) = self.cache_get(path).await? {
+            Ok(item.into_row().get_hash()?.into_iter().collect())
+        } else {
+            Ok(vec![])
+        }
+    }
+
+    async fn cache_list_push(
+        &self,
+        path: String,
+        values: Vec<String>,
+        side: CacheListSide,
+    ) -> Result<u64, CubeError> {
+        let payload_size: usize = values.iter().map(|v| v.len()).sum();
+        self.check_cache_entry_size(&path, payload_size)?;
+
+        self.cache_eviction_manager
+            .before_insert(payload_size as u64)
+            .await?;
+
+        let len = self
+            .cache_modify_typed(path, CacheItemValueType::List, true, move |item| {
+                let mut list = item.get_list()?;
+
+                for value in values {
+                    match side {
+                        CacheListSide::Left => list.push_front(value),
+                        CacheListSide::Right => list.push_back(value),
+                    }
+                }
+
+                item.set_list(&list)?;
+
+                Ok(list.len() as u64)
+            })
+            .await?;
+
+        Ok(len.unwrap_or(0))
+    }
+
+    async fn cache_list_pop(
+        &self,
+        path: String,
+        side: CacheListSide,
+    ) -> Result<Option<String>, CubeError> {
+        let value = self
+            .cache_modify_typed(path, CacheItemValueType::List, false, move |item| {
+                let mut list = item.get_list()?;
+                let value = match side {
+                    CacheListSide::Left => list.pop_front(),
+                    CacheListSide::Right => list.pop_back(),
+                };
+
+                item.set_list(&list)?;
+
+                Ok(value)
+            })
+            .await?;
+
+        Ok(value.flatten())
+    }
+
     async fn queue_all(&self, limit: Option<usize>) -> Result<Vec<IdRow<QueueItem>>, CubeError> {
         self.store
             .read_operation(move |db_ref| Ok(QueueItemRocksTable::new(db_ref).scan_rows(limit)?))
@@ -913,29 +1417,32 @@ impl CacheStore for RocksCacheStore {
         self.store
             .write_operation(move |db_ref, batch_pipe| {
                 let queue_schema = QueueItemRocksTable::new(db_ref.clone());
-                let pending = queue_schema.count_rows_by_index(
-                    &QueueItemIndexKey::ByPrefixAndStatus(
-                        item.get_prefix().clone().unwrap_or("".to_string()),
-                        QueueItemStatus::Pending,
-                    ),
-                    &QueueItemRocksIndex::ByPrefixAndStatus,
+                let pending = Self::count_pending(
+                    &queue_schema,
+                    item.get_prefix().clone().unwrap_or("".to_string()),
+                    &db_ref.start_time,
                 )?;
 
                 let index_key = QueueItemIndexKey::ByPath(item.get_path());
                 let id_row_opt = queue_schema
                     .get_single_opt_row_by_index(&index_key, &QueueItemRocksIndex::ByPath)?;
 
-                let (id, added) = if let Some(row) = id_row_opt {
-                    (row.id, false)
+                let (id, added, delayed) = if let Some(row) = id_row_opt {
+                    (row.id, false, false)
                 } else {
+                    let delayed = item.is_delayed(&db_ref.start_time);
                     let row = queue_schema.insert(item, batch_pipe)?;
-                    (row.id, true)
+                    (row.id, true, delayed)
                 };
 
                 Ok(QueueAddResponse {
                     id,
                     added,
-                    pending: if added { pending + 1 } else { pending },
+                    pending: if added && !delayed {
+                        pending + 1
+                    } else {
+                        pending
+                    },
                 })
             })
             .await
@@ -991,9 +1498,19 @@ impl CacheStore for RocksCacheStore {
                 let queue_schema = QueueItemRocksTable::new(db_ref.clone());
 
                 let items = if let Some(status_filter) = status_filter {
+                    let hide_delayed = status_filter == QueueItemStatus::Pending;
                     let index_key = QueueItemIndexKey::ByPrefixAndStatus(prefix, status_filter);
-                    queue_schema
-                        .get_rows_by_index(&index_key, &QueueItemRocksIndex::ByPrefixAndStatus)?
+                    let items = queue_schema
+                        .get_rows_by_index(&index_key, &QueueItemRocksIndex::ByPrefixAndStatus)?;
+
+                    if hide_delayed {
+                        items
+                            .into_iter()
+                            .filter(|item| !item.get_row().is_delayed(&db_ref.start_time))
+                            .collect()
+                    } else {
+                        items
+                    }
                 } else {
                     let index_key = QueueItemIndexKey::ByPrefix(prefix);
                     queue_schema.get_rows_by_index(&index_key, &QueueItemRocksIndex::ByPrefix)?
@@ -1068,10 +1585,15 @@ impl CacheStore for RocksCacheStore {
                     .0
                     .unwrap_or("".to_string());
 
-                let mut pending = queue_schema.count_rows_by_index(
-                    &QueueItemIndexKey::ByPrefixAndStatus(prefix.clone(), QueueItemStatus::Pending),
-                    &QueueItemRocksIndex::ByPrefixAndStatus,
-                )?;
+                let mut pending =
+                    Self::count_pending(&queue_schema, prefix.clone(), &db_ref.start_time)?;
+
+                // Concurrency from the prefix configuration has priority over the client's one,
+                // it's checked inside write operation to prevent races between clients
+                let allow_concurrency = QueuePrefixRocksTable::new(db_ref.clone())
+                    .get_row_by_prefix(prefix.clone())?
+                    .and_then(|row| row.get_row().get_concurrency().clone())
+                    .unwrap_or(allow_concurrency);
 
                 let mut active: Vec<String> = queue_schema
                     .get_rows_by_index(
@@ -1089,15 +1611,15 @@ impl CacheStore for RocksCacheStore {
                     &QueueItemIndexKey::ByPath(path.clone()),
                     &QueueItemRocksIndex::ByPath,
                 )?;
-                let id_row = if let Some(id_row) = id_row {
-                    id_row
-                } else {
-                    return Ok(QueueRetrieveResponse::NotFound { pending, active });
+                let id_row = match id_row {
+                    Some(id_row) if !id_row.get_row().is_delayed(&db_ref.start_time) => id_row,
+                    _ => return Ok(QueueRetrieveResponse::NotFound { pending, active }),
                 };
 
                 if id_row.get_row().get_status() == &QueueItemStatus::Pending {
                     let mut new = id_row.get_row().clone();
                     new.status = QueueItemStatus::Active;
+                    new.attempts += 1;
                     // It's an important to insert heartbeat, because
                     // without that created datetime will be used for orphaned filtering
                     new.update_heartbeat();
@@ -1136,6 +1658,11 @@ impl CacheStore for RocksCacheStore {
                     if let Some(result) = result {
                         let queue_result = QueueResult::new(path.clone(), result);
                         let result_schema = QueueResultRocksTable::new(db_ref.clone());
+                        // Dead letter items already have an error result, which is replaced by
+                        // the real one when a stalled worker acks the item late.
+                        if let Some(dead_letter_result) = result_schema.get_row(id)? {
+                            result_schema.delete_row(dead_letter_result, batch_pipe)?;
+                        }
                         // QueueResult is a result of QueueItem, it's why we can use row_id of QueueItem
                         let result_row =
                             result_schema.insert_with_pk(id, queue_result, batch_pipe)?;
@@ -1190,7 +1717,7 @@ impl CacheStore for RocksCacheStore {
         let query_key_is_path = key.is_path();
         let fut = tokio::time::timeout(
             Duration::from_millis(timeout),
-            listener.wait_for_queue_ack_by_key(key),
+            listener.wait_for_queue_ack_by_key(key.clone()),
         );
 
         if let Ok(res) = fut.await {
@@ -1213,7 +1740,8 @@ impl CacheStore for RocksCacheStore {
                         }))
                     }
                 },
-                Ok(None) => Ok(None),
+                // Listener lagged behind, the ack could be lost while the result is stored
+                Ok(None) => self.lookup_queue_result_by_key(key).await,
                 Err(e) => Err(e),
             }
         } else {
@@ -1240,13 +1768,66 @@ impl CacheStore for RocksCacheStore {
             .await
     }
 
-    async fn compaction(&self) -> Result<(), CubeError> {
-        self.store
-            .read_operation_out_of_queue(move |db_ref| {
-                let start: Option<&[u8]> = None;
-                let end: Option<&[u8]> = None;
-
-                db_ref.db.compact_range(start, end);
+    async fn queue_prefix_set(
+        &self,
+        prefix: String,
+        concurrency: Option<u32>,
+        max_attempts: Option<u32>,
+        heartbeat_timeout: Option<u32>,
+    ) -> Result<IdRow<QueuePrefix>, CubeError> {
+        if concurrency == Some(0) {
+            return Err(CubeError::user(format!(
+                "Concurrency for queue prefix '{}' must be greater than 0",
+                prefix
+            )));
+        }
+
+        self.store
+            .write_operation(move |db_ref, batch_pipe| {
+                let prefix_schema = QueuePrefixRocksTable::new(db_ref.clone());
+                let id_row_opt = prefix_schema.get_row_by_prefix(prefix.clone())?;
+
+                let mut new = if let Some(id_row) = &id_row_opt {
+                    id_row.get_row().clone()
+                } else {
+                    QueuePrefix::new(prefix)
+                };
+                // Only the specified settings are changed, the rest are kept as is.
+                if concurrency.is_some() {
+                    new.set_concurrency(concurrency);
+                }
+                if max_attempts.is_some() {
+                    new.set_max_attempts(max_attempts);
+                }
+                if heartbeat_timeout.is_some() {
+                    new.set_heartbeat_timeout(heartbeat_timeout);
+                }
+
+                if let Some(id_row) = id_row_opt {
+                    prefix_schema.update(id_row.get_id(), new, id_row.get_row(), batch_pipe)
+                } else {
+                    prefix_schema.insert(new, batch_pipe)
+                }
+            })
+            .await
+    }
+
+    async fn queue_prefixes_all(
+        &self,
+        limit: Option<usize>,
+    ) -> Result<Vec<IdRow<QueuePrefix>>, CubeError> {
+        self.store
+            .read_operation(move |db_ref| Ok(QueuePrefixRocksTable::new(db_ref).scan_rows(limit)?))
+            .await
+    }
+
+    async fn compaction(&self) -> Result<(), CubeError> {
+        self.store
+            .read_operation_out_of_queue(move |db_ref| {
+                let start: Option<&[u8]> = None;
+                let end: Option<&[u8]> = None;
+
+                db_ref.db.compact_range(start, end);
 
                 Ok(())
             })
@@ -1342,6 +1923,51 @@ impl CacheStore for ClusterCacheStoreClient {
         panic!("CacheStore cannot be used on the worker node! cache_incr was used.")
     }
 
+    async fn cache_compare_and_set(
+        &self,
+        _item: CacheItem,
+        _expected: Option<String>,
+    ) -> Result<bool, CubeError> {
+        panic!("CacheStore cannot be used on the worker node! cache_compare_and_set was used.")
+    }
+
+    async fn cache_hset(
+        &self,
+        _path: String,
+        _fields: Vec<(String, String)>,
+    ) -> Result<u64, CubeError> {
+        panic!("CacheStore cannot be used on the worker node! cache_hset was used.")
+    }
+
+    async fn cache_hget(&self, _path: String, _field: String) -> Result<Option<String>, CubeError> {
+        panic!("CacheStore cannot be used on the worker node! cache_hget was used.")
+    }
+
+    async fn cache_hdel(&self, _path: String, _fields: Vec<String>) -> Result<u64, CubeError> {
+        panic!("CacheStore cannot be used on the worker node! cache_hdel was used.")
+    }
+
+    async fn cache_hgetall(&self, _path: String) -> Result<Vec<(String, String)>, CubeError> {
+        panic!("CacheStore cannot be used on the worker node! cache_hgetall was used.")
+    }
+
+    async fn cache_list_push(
+        &self,
+        _path: String,
+        _values: Vec<String>,
+        _side: CacheListSide,
+    ) -> Result<u64, CubeError> {
+        panic!("CacheStore cannot be used on the worker node! cache_list_push was used.")
+    }
+
+    async fn cache_list_pop(
+        &self,
+        _path: String,
+        _side: CacheListSide,
+    ) -> Result<Option<String>, CubeError> {
+        panic!("CacheStore cannot be used on the worker node! cache_list_pop was used.")
+    }
+
     async fn queue_all(&self, _limit: Option<usize>) -> Result<Vec<IdRow<QueueItem>>, CubeError> {
         panic!("CacheStore cannot be used on the worker node! queue_all was used.")
     }
@@ -1426,6 +2052,23 @@ impl CacheStore for ClusterCacheStoreClient {
         panic!("CacheStore cannot be used on the worker node! queue_merge_extra was used.")
     }
 
+    async fn queue_prefix_set(
+        &self,
+        _prefix: String,
+        _concurrency: Option<u32>,
+        _max_attempts: Option<u32>,
+        _heartbeat_timeout: Option<u32>,
+    ) -> Result<IdRow<QueuePrefix>, CubeError> {
+        panic!("CacheStore cannot be used on the worker node! queue_prefix_set was used.")
+    }
+
+    async fn queue_prefixes_all(
+        &self,
+        _limit: Option<usize>,
+    ) -> Result<Vec<IdRow<QueuePrefix>>, CubeError> {
+        panic!("CacheStore cannot be used on the worker node! queue_prefixes_all was used.")
+    }
+
     async fn compaction(&self) -> Result<(), CubeError> {
         panic!("CacheStore cannot be used on the worker node! compaction was used.")
     }
@@ -1497,6 +2140,564 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_cache_typed_values() -> Result<(), CubeError> {
+        init_test_logger().await;
+
+        let (_, cachestore) = RocksCacheStore::prepare_test_cachestore(
+            "cache_typed_values",
+            Config::test("cache_typed_values"),
+        );
+
+        // hash
+        let path = "prefix:hash".to_string();
+        assert_eq!(
+            cachestore
+                .cache_hset(
+                    path.clone(),
+                    vec![
+                        ("f1".to_string(), "v1".to_string()),
+                        ("f2".to_string(), "v2".to_string())
+                    ]
+                )
+                .await?,
+            2
+        );
+        assert_eq!(
+            cachestore
+                .cache_hset(path.clone(), vec![("f1".to_string(), "v3".to_string())])
+                .await?,
+            0
+        );
+        assert_eq!(
+            cachestore
+                .cache_hget(path.clone(), "f1".to_string())
+                .await?,
+            Some("v3".to_string())
+        );
+        assert_eq!(
+            cachestore.cache_hgetall(path.clone()).await?,
+            vec![
+                ("f1".to_string(), "v3".to_string()),
+                ("f2".to_string(), "v2".to_string())
+            ]
+        );
+        assert!(cachestore.cache_incr(path.clone()).await.is_err());
+        assert_eq!(
+            cachestore
+                .cache_hdel(path.clone(), vec!["f1".to_string(), "f2".to_string()])
+                .await?,
+            2
+        );
+        // empty hash is removed
+        assert_eq!(cachestore.cache_get(path.clone()).await?, None);
+
+        // list
+        let path = "prefix:list".to_string();
+        assert_eq!(
+            cachestore
+                .cache_list_push(
+                    path.clone(),
+                    vec!["a".to_string(), "b".to_string()],
+                    CacheListSide::Right
+                )
+                .await?,
+            2
+        );
+        assert_eq!(
+            cachestore
+                .cache_list_push(path.clone(), vec!["c".to_string()], CacheListSide::Left)
+                .await?,
+            3
+        );
+        assert!(cachestore
+            .cache_hget(path.clone(), "a".to_string())
+            .await
+            .is_err());
+        assert_eq!(
+            cachestore
+                .cache_list_pop(path.clone(), CacheListSide::Left)
+                .await?,
+            Some("c".to_string())
+        );
+        assert_eq!(
+            cachestore
+                .cache_list_pop(path.clone(), CacheListSide::Right)
+                .await?,
+            Some("b".to_string())
+        );
+        assert_eq!(
+            cachestore
+                .cache_list_pop(path.clone(), CacheListSide::Right)
+                .await?,
+            Some("a".to_string())
+        );
+        assert_eq!(
+            cachestore
+                .cache_list_pop(path.clone(), CacheListSide::Right)
+                .await?,
+            None
+        );
+
+        // compare-and-set
+        let path = "prefix:lock".to_string();
+        let item = |value: &str| CacheItem::new(path.clone(), None, value.to_string());
+        assert!(cachestore.cache_compare_and_set(item("1"), None).await?);
+        assert!(!cachestore.cache_compare_and_set(item("2"), None).await?);
+        assert!(
+            !cachestore
+                .cache_compare_and_set(item("2"), Some("3".to_string()))
+                .await?
+        );
+        assert!(
+            cachestore
+                .cache_compare_and_set(item("2"), Some("1".to_string()))
+                .await?
+        );
+        assert_eq!(
+            cachestore
+                .cache_get(path.clone())
+                .await?
+                .unwrap()
+                .into_row()
+                .value,
+            "2"
+        );
+
+        RocksCacheStore::cleanup_test_cachestore("cache_typed_values");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cache_change_events() -> Result<(), CubeError> {
+        init_test_logger().await;
+
+        let (_, cachestore) = RocksCacheStore::prepare_test_cachestore(
+            "cache_change_events",
+            Config::test("cache_change_events"),
+        );
+        let mut listener = cachestore.get_change_listener();
+
+        cachestore
+            .cache_set(
+                CacheItem::new("other:key".to_string(), None, "1".to_string()),
+                false,
+            )
+            .await?;
+        cachestore
+            .cache_set(
+                CacheItem::new("invalidation:key".to_string(), None, "1".to_string()),
+                false,
+            )
+            .await?;
+        cachestore
+            .cache_delete("invalidation:key".to_string())
+            .await?;
+        cachestore
+            .cache_hset(
+                "invalidation:hash".to_string(),
+                vec![("f".to_string(), "v".to_string())],
+            )
+            .await?;
+
+        assert_eq!(
+            listener.wait_for_cache_change("invalidation:").await?,
+            CacheItemChangeEvent::new("invalidation:key".to_string(), CacheItemChangeKind::Set)
+        );
+        assert_eq!(
+            listener.wait_for_cache_change("invalidation:").await?,
+            CacheItemChangeEvent::new("invalidation:key".to_string(), CacheItemChangeKind::Delete)
+        );
+        assert_eq!(
+            listener.wait_for_cache_change("invalidation:").await?,
+            CacheItemChangeEvent::new("invalidation:hash".to_string(), CacheItemChangeKind::Set)
+        );
+
+        RocksCacheStore::cleanup_test_cachestore("cache_change_events");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_queue_ack_with_lagging_listener() -> Result<(), CubeError> {
+        init_test_logger().await;
+
+        let (_, cachestore) = RocksCacheStore::prepare_test_cachestore(
+            "queue_ack_with_lagging_listener",
+            Config::test("queue_ack_with_lagging_listener"),
+        );
+        // Cache writes below emit much more events than the channel can hold
+        let (sender, _) = tokio::sync::broadcast::channel(16);
+        cachestore.add_listener(sender).await;
+        let listener = cachestore.get_listener().await;
+
+        cachestore
+            .queue_add(QueueItem::new(
+                "prefix:1".to_string(),
+                "payload".to_string(),
+                QueueItemStatus::Pending,
+                0,
+                None,
+            ))
+            .await?;
+        cachestore
+            .queue_retrieve_by_path("prefix:1".to_string(), 10)
+            .await?;
+
+        for i in 0..100 {
+            cachestore
+                .cache_set(
+                    CacheItem::new(format!("key:{}", i), None, "1".to_string()),
+                    false,
+                )
+                .await?;
+        }
+        cachestore
+            .queue_ack(
+                QueueKey::ByPath("prefix:1".to_string()),
+                Some("result".to_string()),
+            )
+            .await?;
+
+        // Listener lagged behind cache writes, it's reported instead of an error
+        assert_eq!(
+            listener
+                .wait_for_queue_ack_by_key(QueueKey::ByPath("prefix:1".to_string()))
+                .await?,
+            None
+        );
+        assert_eq!(
+            cachestore
+                .queue_result_blocking(QueueKey::ByPath("prefix:1".to_string()), 1000)
+                .await?,
+            Some(QueueResultResponse::Success {
+                value: Some("result".to_string())
+            })
+        );
+
+        RocksCacheStore::cleanup_test_cachestore("queue_ack_with_lagging_listener");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_queue_delayed_items() -> Result<(), CubeError> {
+        init_test_logger().await;
+
+        let (_, cachestore) = RocksCacheStore::prepare_test_cachestore(
+            "queue_delayed_items",
+            Config::test("queue_delayed_items"),
+        );
+
+        let item = |path: &str| {
+            QueueItem::new(
+                path.to_string(),
+                "payload".to_string(),
+                QueueItemStatus::Pending,
+                0,
+                None,
+            )
+        };
+
+        let response = cachestore.queue_add(item("prefix:ready")).await?;
+        assert_eq!(response.pending, 1);
+
+        let response = cachestore
+            .queue_add(
+                item("prefix:delayed").with_scheduled(Utc::now() + chrono::Duration::hours(1)),
+            )
+            .await?;
+        assert_eq!(response.added, true);
+        assert_eq!(response.pending, 1);
+
+        let response = cachestore
+            .queue_add(item("prefix:due").with_scheduled(Utc::now() - chrono::Duration::seconds(1)))
+            .await?;
+        assert_eq!(response.pending, 2);
+
+        let pending = cachestore
+            .queue_list("prefix".to_string(), Some(QueueItemStatus::Pending), true)
+            .await?
+            .into_iter()
+            .map(|item| item.into_row().get_key().clone())
+            .sorted()
+            .collect::<Vec<_>>();
+        assert_eq!(pending, vec!["due".to_string(), "ready".to_string()]);
+
+        let all = cachestore
+            .queue_list("prefix".to_string(), None, true)
+            .await?;
+        assert_eq!(all.len(), 3);
+
+        // Only items which are not due yet are found by the schedule index
+        let delayed = cachestore
+            .store
+            .read_operation(move |db_ref| {
+                Ok(QueueItemRocksTable::new(db_ref).get_rows_by_index(
+                    &QueueItemIndexKey::ByPrefixAndScheduled("prefix".to_string()),
+                    &QueueItemRocksIndex::ByPrefixAndScheduled,
+                )?)
+            })
+            .await?;
+        assert_eq!(delayed.len(), 1);
+        assert_eq!(delayed[0].get_row().get_key(), "delayed");
+
+        match cachestore
+            .queue_retrieve_by_path("prefix:delayed".to_string(), 10)
+            .await?
+        {
+            QueueRetrieveResponse::NotFound { pending, .. } => assert_eq!(pending, 2),
+            other => panic!("Unexpected response: {:?}", other),
+        };
+
+        match cachestore
+            .queue_retrieve_by_path("prefix:due".to_string(), 10)
+            .await?
+        {
+            QueueRetrieveResponse::Success { pending, .. } => assert_eq!(pending, 1),
+            other => panic!("Unexpected response: {:?}", other),
+        };
+
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+        let to_cancel = cachestore
+            .queue_to_cancel("prefix".to_string(), Some(0), None)
+            .await?;
+        assert_eq!(
+            to_cancel
+                .into_iter()
+                .map(|item| item.into_row().get_key().clone())
+                .collect::<Vec<_>>(),
+            vec!["ready".to_string()]
+        );
+
+        RocksCacheStore::cleanup_test_cachestore("queue_delayed_items");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_queue_requeue_and_dead_letter() -> Result<(), CubeError> {
+        init_test_logger().await;
+
+        let (_, cachestore) = RocksCacheStore::prepare_test_cachestore(
+            "queue_requeue_and_dead_letter",
+            Config::test("queue_requeue_and_dead_letter"),
+        );
+
+        cachestore
+            .queue_prefix_set("prefix".to_string(), None, Some(2), Some(0))
+            .await?;
+
+        for path in ["prefix:1", "other:1"] {
+            cachestore
+                .queue_add(QueueItem::new(
+                    path.to_string(),
+                    "payload".to_string(),
+                    QueueItemStatus::Pending,
+                    0,
+                    None,
+                ))
+                .await?;
+        }
+
+        let retrieve = |path: &'static str| {
+            let cachestore = cachestore.clone();
+            async move {
+                cachestore
+                    .queue_retrieve_by_path(path.to_string(), 10)
+                    .await
+            }
+        };
+
+        match retrieve("other:1").await? {
+            QueueRetrieveResponse::Success { .. } => {}
+            other => panic!("Unexpected response: {:?}", other),
+        };
+
+        for attempt in 1..=2 {
+            match retrieve("prefix:1").await? {
+                QueueRetrieveResponse::Success { item, .. } => {
+                    assert_eq!(item.get_attempts(), attempt)
+                }
+                other => panic!("Unexpected response: {:?}", other),
+            };
+
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+            let result = cachestore.queue_requeue_stalled().await?;
+            if attempt < 2 {
+                assert_eq!(
+                    result,
+                    QueueRequeueResult {
+                        requeued: 1,
+                        dead_lettered: 0
+                    }
+                );
+
+                let item = cachestore
+                    .queue_get(QueueKey::ByPath("prefix:1".to_string()))
+                    .await?
+                    .expect("must return item")
+                    .into_row();
+                assert_eq!(item.get_status(), &QueueItemStatus::Pending);
+                assert_eq!(item.get_heartbeat(), &None);
+                // Requeue doesn't delay the item
+                assert_eq!(item.get_scheduled(), &None);
+            } else {
+                assert_eq!(
+                    result,
+                    QueueRequeueResult {
+                        requeued: 0,
+                        dead_lettered: 1
+                    }
+                );
+            }
+        }
+
+        match cachestore
+            .queue_result_by_path("prefix:1".to_string())
+            .await?
+        {
+            Some(QueueResultResponse::Success { value: Some(value) }) => assert_eq!(
+                value,
+                r#"{"error":"Queue item was moved to the dead letter state after 2 attempts"}"#
+            ),
+            other => panic!("Unexpected result: {:?}", other),
+        };
+
+        let dead_letter = cachestore
+            .queue_list(
+                "prefix".to_string(),
+                Some(QueueItemStatus::DeadLetter),
+                false,
+            )
+            .await?;
+        assert_eq!(dead_letter.len(), 1);
+        assert_eq!(dead_letter[0].get_row().get_attempts(), 2);
+
+        match retrieve("prefix:1").await? {
+            QueueRetrieveResponse::LockFailed { pending, .. } => assert_eq!(pending, 0),
+            other => panic!("Unexpected response: {:?}", other),
+        };
+
+        // Prefix without configuration is not touched
+        let active = cachestore
+            .queue_list("other".to_string(), Some(QueueItemStatus::Active), false)
+            .await?;
+        assert_eq!(active.len(), 1);
+
+        RocksCacheStore::cleanup_test_cachestore("queue_requeue_and_dead_letter");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_queue_prefix_concurrency() -> Result<(), CubeError> {
+        init_test_logger().await;
+
+        let (_, cachestore) = RocksCacheStore::prepare_test_cachestore(
+            "queue_prefix_concurrency",
+            Config::test("queue_prefix_concurrency"),
+        );
+
+        for path in ["prefix:1", "prefix:2", "prefix:3"] {
+            cachestore
+                .queue_add(QueueItem::new(
+                    path.to_string(),
+                    "payload".to_string(),
+                    QueueItemStatus::Pending,
+                    0,
+                    None,
+                ))
+                .await?;
+        }
+
+        // Client's concurrency is used without configuration
+        match cachestore
+            .queue_retrieve_by_path("prefix:1".to_string(), 10)
+            .await?
+        {
+            QueueRetrieveResponse::Success { .. } => {}
+            other => panic!("Unexpected response: {:?}", other),
+        };
+
+        cachestore
+            .queue_prefix_set("prefix".to_string(), Some(1), None, None)
+            .await?;
+
+        match cachestore
+            .queue_retrieve_by_path("prefix:2".to_string(), 10)
+            .await?
+        {
+            QueueRetrieveResponse::NotFound { pending, active } => {
+                assert_eq!(pending, 2);
+                assert_eq!(active, vec!["1".to_string()]);
+            }
+            other => panic!("Unexpected response: {:?}", other),
+        };
+
+        cachestore
+            .queue_prefix_set("prefix".to_string(), Some(2), None, None)
+            .await?;
+
+        match cachestore
+            .queue_retrieve_by_path("prefix:2".to_string(), 1)
+            .await?
+        {
+            QueueRetrieveResponse::Success { .. } => {}
+            other => panic!("Unexpected response: {:?}", other),
+        };
+
+        let prefixes = cachestore.queue_prefixes_all(None).await?;
+        assert_eq!(prefixes.len(), 1);
+        assert_eq!(prefixes[0].get_row().get_prefix(), "prefix");
+        assert_eq!(prefixes[0].get_row().get_concurrency(), &Some(2));
+
+        RocksCacheStore::cleanup_test_cachestore("queue_prefix_concurrency");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_queue_prefix_set_partial() -> Result<(), CubeError> {
+        init_test_logger().await;
+
+        let (_, cachestore) = RocksCacheStore::prepare_test_cachestore(
+            "queue_prefix_set_partial",
+            Config::test("queue_prefix_set_partial"),
+        );
+
+        cachestore
+            .queue_prefix_set("prefix".to_string(), Some(3), None, None)
+            .await?;
+        let row = cachestore
+            .queue_prefix_set("prefix".to_string(), None, Some(5), Some(1000))
+            .await?;
+        assert_eq!(row.get_row().get_concurrency(), &Some(3));
+        assert_eq!(row.get_row().get_max_attempts(), &Some(5));
+        assert_eq!(row.get_row().get_heartbeat_timeout(), &Some(1000));
+
+        let err = cachestore
+            .queue_prefix_set("prefix".to_string(), Some(0), None, None)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.message,
+            "Concurrency for queue prefix 'prefix' must be greater than 0"
+        );
+
+        let prefixes = cachestore.queue_prefixes_all(None).await?;
+        assert_eq!(prefixes.len(), 1);
+        assert_eq!(prefixes[0].get_row().get_concurrency(), &Some(3));
+        assert_eq!(prefixes[0].get_row().get_max_attempts(), &Some(5));
+
+        RocksCacheStore::cleanup_test_cachestore("queue_prefix_set_partial");
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_cache_set() -> Result<(), CubeError> {
         init_test_logger().await;
@@ -1,9 +1,10 @@
 use crate::cachestore::cache_eviction_manager::EvictionResult;
 use crate::cachestore::cache_rocksstore::{CachestoreInfo, QueueAddResponse};
+use crate::cachestore::listener::RocksCacheStoreChangeListener;
 use crate::cachestore::queue_item::QueueRetrieveResponse;
 use crate::cachestore::{
-    CacheItem, CacheStore, QueueItem, QueueItemStatus, QueueKey, QueueResult, QueueResultResponse,
-    RocksCacheStore,
+    CacheItem, CacheListSide, CacheStore, QueueItem, QueueItemStatus, QueueKey, QueuePrefix,
+    QueueResult, QueueResultResponse, RocksCacheStore,
 };
 use crate::config::ConfigObj;
 use crate::metastore::{IdRow, MetaStoreEvent, MetaStoreFs, RocksPropertyRow};
@@ -120,6 +121,10 @@ impl LazyRocksCacheStore {
         }
     }
 
+    pub async fn get_change_listener(&self) -> Result<RocksCacheStoreChangeListener, CubeError> {
+        Ok(self.init().await?.get_change_listener())
+    }
+
     pub async fn spawn_processing_loops(self: Arc<Self>) -> Vec<JoinHandle<Result<(), CubeError>>> {
         if let Some(init_signal) = &self.init_signal {
             let _ = init_signal.clone().changed().await;
@@ -204,6 +209,54 @@ impl CacheStore for LazyRocksCacheStore {
         self.init().await?.cache_incr(path).await
     }
 
+    async fn cache_compare_and_set(
+        &self,
+        item: CacheItem,
+        expected: Option<String>,
+    ) -> Result<bool, CubeError> {
+        self.init()
+            .await?
+            .cache_compare_and_set(item, expected)
+            .await
+    }
+
+    async fn cache_hset(
+        &self,
+        path: String,
+        fields: Vec<(String, String)>,
+    ) -> Result<u64, CubeError> {
+        self.init().await?.cache_hset(path, fields).await
+    }
+
+    async fn cache_hget(&self, path: String, field: String) -> Result<Option<String>, CubeError> {
+        self.init().await?.cache_hget(path, field).await
+    }
+
+    async fn cache_hdel(&self, path: String, fields: Vec<String>) -> Result<u64, CubeError> {
+        self.init().await?.cache_hdel(path, fields).await
+    }
+
+    async fn cache_hgetall(&self, path: String) -> Result<Vec<(String, String)>, CubeError> {
+        self.init().await?.cache_hgetall(path).await
+    }
+
+    async fn cache_list_push(
+        &self,
+        path: String,
+        values: Vec<String>,
+        side: CacheListSide,
+    ) -> Result<u64, CubeError> {
+        self.init().await?.cache_list_push(path, values, side).await
+    }
+
+    async fn cache_list_pop(
+        &self,
+        path: String,
+        side: CacheListSide,
+    ) -> Result<Option<String>, CubeError> {
+        self.init().await?.cache_list_pop(path, side).await
+    }
+
     async fn queue_all(&self, limit: Option<usize>) -> Result<Vec<IdRow<QueueItem>>, CubeError> {
         self.init().await?.queue_all(limit).await
     }
@@ -297,6 +350,26 @@ impl CacheStore for LazyRocksCacheStore {
         self.init().await?.queue_merge_extra(key, payload).await
     }
 
+    async fn queue_prefix_set(
+        &self,
+        prefix: String,
+        concurrency: Option<u32>,
+        max_attempts: Option<u32>,
+        heartbeat_timeout: Option<u32>,
+    ) -> Result<IdRow<QueuePrefix>, CubeError> {
+        self.init()
+            .await?
+            .queue_prefix_set(prefix, concurrency, max_attempts, heartbeat_timeout)
+            .await
+    }
+
+    async fn queue_prefixes_all(
+        &self,
+        limit: Option<usize>,
+    ) -> Result<Vec<IdRow<QueuePrefix>>, CubeError> {
+        self.init().await?.queue_prefixes_all(limit).await
+    }
+
     async fn compaction(&self) -> Result<(), CubeError> {
         self.init().await?.compaction().await
     }
@@ -1,6 +1,7 @@
-use crate::cachestore::{QueueKey, QueueResultAckEvent};
+use crate::cachestore::{CacheItemChangeEvent, QueueKey, QueueResultAckEvent};
 use crate::metastore::MetaStoreEvent;
 use crate::CubeError;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::broadcast::Receiver;
 
 pub struct RocksCacheStoreListener {
@@ -12,6 +13,7 @@ impl RocksCacheStoreListener {
         Self { receiver }
     }
 
+    /// Returns None if the listener lagged behind the event channel and the ack could be lost.
     pub async fn wait_for_queue_ack_by_key(
         self,
         key: QueueKey,
@@ -27,7 +29,12 @@ impl RocksCacheStoreListener {
         id: u64,
     ) -> Result<Option<QueueResultAckEvent>, CubeError> {
         loop {
-            let event = self.receiver.recv().await?;
+            let event = match self.receiver.recv().await {
+                Ok(event) => event,
+                // The ack could be among lost events, the result must be looked up in the store
+                Err(RecvError::Lagged(_)) => return Ok(None),
+                Err(e) => return Err(e.into()),
+            };
             if let MetaStoreEvent::AckQueueItem(ack_event) = event {
                 if ack_event.id == id {
                     return Ok(Some(ack_event));
@@ -41,7 +48,12 @@ impl RocksCacheStoreListener {
         path: String,
     ) -> Result<Option<QueueResultAckEvent>, CubeError> {
         loop {
-            let event = self.receiver.recv().await?;
+            let event = match self.receiver.recv().await {
+                Ok(event) => event,
+                // The ack could be among lost events, the result must be looked up in the store
+                Err(RecvError::Lagged(_)) => return Ok(None),
+                Err(e) => return Err(e.into()),
+            };
             if let MetaStoreEvent::AckQueueItem(ack_event) = event {
                 if ack_event.path == path {
                     return Ok(Some(ack_event));
@@ -50,3 +62,28 @@ impl RocksCacheStoreListener {
         }
     }
 }
+
+/// Receives cache key changes, which are sent over a dedicated channel.
+pub struct RocksCacheStoreChangeListener {
+    receiver: Receiver<CacheItemChangeEvent>,
+}
+
+impl RocksCacheStoreChangeListener {
+    pub fn new(receiver: Receiver<CacheItemChangeEvent>) -> Self {
+        Self { receiver }
+    }
+
+    /// Waits for the next set/delete/expire event of a key which path starts with `prefix`.
+    /// Lagging behind the channel is reported as an error, because some events were lost.
+    pub async fn wait_for_cache_change(
+        &mut self,
+        prefix: &str,
+    ) -> Result<CacheItemChangeEvent, CubeError> {
+        loop {
+            let event = self.receiver.recv().await?;
+            if event.path.starts_with(prefix) {
+                return Ok(event);
+            }
+        }
+    }
+}
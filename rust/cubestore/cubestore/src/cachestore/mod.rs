@@ -5,18 +5,22 @@ mod compaction;
 mod lazy;
 mod listener;
 mod queue_item;
+mod queue_prefix;
 mod queue_result;
 mod scheduler;
 
 pub use cache_eviction_manager::{
     CacheEvictionManager, CacheEvictionPolicy, EvictionFinishedResult, EvictionResult,
 };
-pub use cache_item::CacheItem;
+pub use cache_item::{
+    CacheItem, CacheItemChangeEvent, CacheItemChangeKind, CacheItemValueType, CacheListSide,
+};
 pub use cache_rocksstore::{
     CacheStore, CacheStoreRpcClient, CachestoreInfo, ClusterCacheStoreClient, QueueAddResponse,
     QueueKey, QueueResultResponse, RocksCacheStore,
 };
 pub use lazy::LazyRocksCacheStore;
 pub use queue_item::{QueueItem, QueueItemStatus, QueueResultAckEvent, QueueRetrieveResponse};
+pub use queue_prefix::QueuePrefix;
 pub use queue_result::QueueResult;
 pub use scheduler::CacheStoreSchedulerImpl;
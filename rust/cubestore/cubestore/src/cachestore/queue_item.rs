@@ -51,6 +51,8 @@ pub enum QueueItemStatus {
     Pending = 0,
     Active = 1,
     Finished = 2,
+    // Item was retrieved too many times without ack, it's kept for inspection only
+    DeadLetter = 3,
 }
 
 impl ToString for QueueItemStatus {
@@ -59,6 +61,7 @@ impl ToString for QueueItemStatus {
             QueueItemStatus::Pending => "pending".to_string(),
             QueueItemStatus::Active => "active".to_string(),
             QueueItemStatus::Finished => "finished".to_string(),
+            QueueItemStatus::DeadLetter => "dead_letter".to_string(),
         }
     }
 }
@@ -80,6 +83,15 @@ pub struct QueueItem {
     pub(crate) heartbeat: Option<DateTime<Utc>>,
     #[serde(with = "ts_seconds_option")]
     orphaned: Option<DateTime<Utc>>,
+    // Item is hidden from pending views until this time
+    #[serde(with = "ts_seconds_option", default)]
+    scheduled: Option<DateTime<Utc>>,
+    // Last time when the item was returned back to the queue
+    #[serde(with = "ts_seconds_option", default)]
+    requeued: Option<DateTime<Utc>>,
+    // How many times the item was retrieved for processing
+    #[serde(default)]
+    pub(crate) attempts: u32,
 }
 
 impl RocksEntity for QueueItem {
@@ -137,10 +149,24 @@ impl QueueItem {
             } else {
                 None
             },
+            scheduled: None,
+            requeued: None,
+            attempts: 0,
             created,
         }
     }
 
+    /// Delays the item until `scheduled`. Orphaned timeout is shifted too, because it should be
+    /// counted from the moment when the item becomes available for processing.
+    pub fn with_scheduled(mut self, scheduled: DateTime<Utc>) -> Self {
+        if let Some(orphaned) = self.orphaned {
+            self.orphaned = Some(orphaned + (scheduled - self.created));
+        }
+
+        self.scheduled = Some(scheduled);
+        self
+    }
+
     pub fn into_queue_cancel_row(self) -> Row {
         let res = vec![
             TableValue::String(self.value),
@@ -256,6 +282,41 @@ impl QueueItem {
         &self.orphaned
     }
 
+    pub fn get_scheduled(&self) -> &Option<DateTime<Utc>> {
+        &self.scheduled
+    }
+
+    pub fn get_attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// The moment from which the item can be processed: its creation, scheduled time or the last
+    /// time it was requeued.
+    pub fn get_available_from(&self) -> DateTime<Utc> {
+        self.scheduled
+            .into_iter()
+            .chain(self.requeued)
+            .fold(self.created, |a, b| a.max(b))
+    }
+
+    /// Returns an active item back to the pending state without delay. It becomes available from
+    /// `now`, which is why orphaned timeout is shifted in the same way as for scheduled items.
+    pub fn requeue(&mut self, now: DateTime<Utc>) {
+        if let Some(orphaned) = self.orphaned {
+            self.orphaned = Some(orphaned + (now - self.get_available_from()));
+        }
+
+        self.status = QueueItemStatus::Pending;
+        self.heartbeat = None;
+        self.requeued = Some(now);
+    }
+
+    /// Pending item which is not due yet at `now`
+    pub fn is_delayed(&self, now: &DateTime<Utc>) -> bool {
+        self.status == QueueItemStatus::Pending
+            && matches!(&self.scheduled, Some(scheduled) if scheduled > now)
+    }
+
     pub fn status_default() -> QueueItemStatus {
         QueueItemStatus::Pending
     }
@@ -355,6 +416,7 @@ pub(crate) enum QueueItemRocksIndex {
     ByPath = 1,
     ByPrefixAndStatus = 2,
     ByPrefix = 3,
+    ByPrefixAndScheduled = 4,
 }
 
 pub struct QueueItemRocksTable<'a> {
@@ -400,6 +462,7 @@ rocks_table_new!(QueueItem, QueueItemRocksTable, TableId::QueueItems, {
         Box::new(QueueItemRocksIndex::ByPath),
         Box::new(QueueItemRocksIndex::ByPrefixAndStatus),
         Box::new(QueueItemRocksIndex::ByPrefix),
+        Box::new(QueueItemRocksIndex::ByPrefixAndScheduled),
     ]
 });
 
@@ -408,6 +471,8 @@ pub enum QueueItemIndexKey {
     ByPath(String),
     ByPrefixAndStatus(String, QueueItemStatus),
     ByPrefix(String),
+    // Delayed items, the entry expires at the due time of the item, so lookups skip due items
+    ByPrefixAndScheduled(String),
 }
 
 base_rocks_secondary_index!(QueueItem, QueueItemRocksIndex);
@@ -423,6 +488,9 @@ impl RocksSecondaryIndex<QueueItem, QueueItemIndexKey> for QueueItemRocksIndex {
             QueueItemRocksIndex::ByPrefix => {
                 QueueItemIndexKey::ByPrefix(row.get_prefix().clone().unwrap_or("".to_string()))
             }
+            QueueItemRocksIndex::ByPrefixAndScheduled => QueueItemIndexKey::ByPrefixAndScheduled(
+                row.get_prefix().clone().unwrap_or("".to_string()),
+            ),
         }
     }
 
@@ -430,6 +498,7 @@ impl RocksSecondaryIndex<QueueItem, QueueItemIndexKey> for QueueItemRocksIndex {
         match key {
             QueueItemIndexKey::ByPath(s) => s.as_bytes().to_vec(),
             QueueItemIndexKey::ByPrefix(s) => s.as_bytes().to_vec(),
+            QueueItemIndexKey::ByPrefixAndScheduled(s) => s.as_bytes().to_vec(),
             QueueItemIndexKey::ByPrefixAndStatus(prefix, s) => {
                 let mut r = Vec::with_capacity(prefix.len() + 1);
                 r.extend_from_slice(&prefix.as_bytes());
@@ -438,6 +507,7 @@ impl RocksSecondaryIndex<QueueItem, QueueItemIndexKey> for QueueItemRocksIndex {
                     QueueItemStatus::Pending => r.push(0_u8),
                     QueueItemStatus::Active => r.push(1_u8),
                     QueueItemStatus::Finished => r.push(2_u8),
+                    QueueItemStatus::DeadLetter => r.push(3_u8),
                 }
 
                 r
@@ -450,6 +520,7 @@ impl RocksSecondaryIndex<QueueItem, QueueItemIndexKey> for QueueItemRocksIndex {
             QueueItemRocksIndex::ByPath => true,
             QueueItemRocksIndex::ByPrefixAndStatus => false,
             QueueItemRocksIndex::ByPrefix => false,
+            QueueItemRocksIndex::ByPrefixAndScheduled => false,
         }
     }
 
@@ -458,6 +529,7 @@ impl RocksSecondaryIndex<QueueItem, QueueItemIndexKey> for QueueItemRocksIndex {
             QueueItemRocksIndex::ByPath => 1,
             QueueItemRocksIndex::ByPrefixAndStatus => 2,
             QueueItemRocksIndex::ByPrefix => 1,
+            QueueItemRocksIndex::ByPrefixAndScheduled => 2,
         }
     }
 
@@ -466,10 +538,15 @@ impl RocksSecondaryIndex<QueueItem, QueueItemIndexKey> for QueueItemRocksIndex {
     }
 
     fn get_expire(&self, row: &QueueItem) -> Option<DateTime<Utc>> {
+        if let QueueItemRocksIndex::ByPrefixAndScheduled = self {
+            // Items without schedule are due from the start
+            return Some(row.get_scheduled().unwrap_or(row.get_created().clone()));
+        }
+
         if let Some(orphaned) = row.orphaned {
             Some(orphaned.clone() + Duration::hours(1))
         } else {
-            Some(row.get_created().clone() + Duration::hours(2))
+            Some(row.get_available_from() + Duration::hours(2))
         }
     }
 
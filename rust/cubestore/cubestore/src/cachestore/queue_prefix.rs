@@ -0,0 +1,160 @@
+use crate::metastore::{
+    BaseRocksTable, IdRow, IndexId, RocksEntity, RocksSecondaryIndex, RocksTable, TableId,
+    TableInfo,
+};
+use crate::{base_rocks_secondary_index, rocks_table_new, CubeError};
+use rocksdb::WriteBatch;
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Server side configuration for all queue items which share the same prefix
+#[derive(Clone, Serialize, Deserialize, Debug, Eq, PartialEq)]
+pub struct QueuePrefix {
+    prefix: String,
+    // Max number of active items, it overrides concurrency which is passed on retrieve
+    #[serde(default)]
+    concurrency: Option<u32>,
+    // Item is moved to the dead letter state after this number of attempts
+    #[serde(default)]
+    max_attempts: Option<u32>,
+    // In milliseconds, active items without heartbeat for this time are requeued
+    #[serde(default)]
+    heartbeat_timeout: Option<u32>,
+}
+
+impl RocksEntity for QueuePrefix {
+    fn version() -> u32 {
+        1
+    }
+}
+
+impl QueuePrefix {
+    pub fn new(prefix: String) -> Self {
+        QueuePrefix {
+            prefix,
+            concurrency: None,
+            max_attempts: None,
+            heartbeat_timeout: None,
+        }
+    }
+
+    pub fn get_prefix(&self) -> &String {
+        &self.prefix
+    }
+
+    pub fn get_concurrency(&self) -> &Option<u32> {
+        &self.concurrency
+    }
+
+    pub fn get_max_attempts(&self) -> &Option<u32> {
+        &self.max_attempts
+    }
+
+    pub fn get_heartbeat_timeout(&self) -> &Option<u32> {
+        &self.heartbeat_timeout
+    }
+
+    pub fn set_concurrency(&mut self, concurrency: Option<u32>) {
+        self.concurrency = concurrency;
+    }
+
+    pub fn set_max_attempts(&mut self, max_attempts: Option<u32>) {
+        self.max_attempts = max_attempts;
+    }
+
+    pub fn set_heartbeat_timeout(&mut self, heartbeat_timeout: Option<u32>) {
+        self.heartbeat_timeout = heartbeat_timeout;
+    }
+
+    /// Attempts are counted on retrieve, the item which was retrieved `max_attempts` times
+    /// is not returned back to the queue.
+    pub fn is_attempts_exhausted(&self, attempts: u32) -> bool {
+        matches!(self.max_attempts, Some(max_attempts) if attempts >= max_attempts)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum QueuePrefixRocksIndex {
+    ByPrefix = 1,
+}
+
+pub struct QueuePrefixRocksTable<'a> {
+    db: crate::metastore::DbTableRef<'a>,
+}
+
+impl<'a> QueuePrefixRocksTable<'a> {
+    pub fn new(db: crate::metastore::DbTableRef<'a>) -> Self {
+        Self { db }
+    }
+
+    pub fn get_row_by_prefix(
+        &self,
+        prefix: String,
+    ) -> Result<Option<IdRow<QueuePrefix>>, CubeError> {
+        self.get_single_opt_row_by_index(
+            &QueuePrefixIndexKey::ByPrefix(prefix),
+            &QueuePrefixRocksIndex::ByPrefix,
+        )
+    }
+}
+
+impl<'a> BaseRocksTable for QueuePrefixRocksTable<'a> {
+    fn enable_delete_event(&self) -> bool {
+        false
+    }
+
+    fn enable_update_event(&self) -> bool {
+        false
+    }
+
+    fn migrate_table(
+        &self,
+        batch: &mut WriteBatch,
+        _table_info: TableInfo,
+    ) -> Result<(), CubeError> {
+        self.migrate_table_by_truncate(batch)
+    }
+}
+
+rocks_table_new!(
+    QueuePrefix,
+    QueuePrefixRocksTable,
+    TableId::QueuePrefixes,
+    { vec![Box::new(QueuePrefixRocksIndex::ByPrefix)] }
+);
+
+#[derive(Hash, Clone, Debug)]
+pub enum QueuePrefixIndexKey {
+    ByPrefix(String),
+}
+
+base_rocks_secondary_index!(QueuePrefix, QueuePrefixRocksIndex);
+
+impl RocksSecondaryIndex<QueuePrefix, QueuePrefixIndexKey> for QueuePrefixRocksIndex {
+    fn typed_key_by(&self, row: &QueuePrefix) -> QueuePrefixIndexKey {
+        match self {
+            QueuePrefixRocksIndex::ByPrefix => QueuePrefixIndexKey::ByPrefix(row.prefix.clone()),
+        }
+    }
+
+    fn key_to_bytes(&self, key: &QueuePrefixIndexKey) -> Vec<u8> {
+        match key {
+            QueuePrefixIndexKey::ByPrefix(s) => s.as_bytes().to_vec(),
+        }
+    }
+
+    fn is_unique(&self) -> bool {
+        match self {
+            QueuePrefixRocksIndex::ByPrefix => true,
+        }
+    }
+
+    fn version(&self) -> u32 {
+        match self {
+            QueuePrefixRocksIndex::ByPrefix => 1,
+        }
+    }
+
+    fn get_id(&self) -> IndexId {
+        *self as IndexId
+    }
+}
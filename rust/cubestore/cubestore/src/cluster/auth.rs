@@ -0,0 +1,286 @@
+//! Mutual authentication of connections between the router, workers and the meta store.
+//!
+//! When `CUBESTORE_CLUSTER_SECRET` is set, every connection starts with a challenge-response
+//! handshake before any [NetworkMessage](crate::cluster::message::NetworkMessage) is exchanged:
+//!     1. Client sends [AUTH_MAGIC] and a random client nonce.
+//!     2. Server replies with a random server nonce and `HMAC(secret, server label, client nonce, server nonce)`.
+//!     3. Client verifies the server proof and replies with `HMAC(secret, client label, server nonce, client nonce)`.
+//!     4. Server verifies the client proof and replies with a single acknowledgement byte.
+//!
+//! The secret itself never goes over the wire and nonces make replaying old handshakes useless.
+use crate::CubeError;
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Differs from the network message magic so that nodes with mismatched secret settings fail
+/// with a clear error instead of misinterpreting the stream.
+const AUTH_MAGIC: u32 = 94108;
+
+const NONCE_LEN: usize = 32;
+const PROOF_LEN: usize = 32;
+
+const CLIENT_LABEL: &[u8] = b"cubestore-cluster-client";
+const SERVER_LABEL: &[u8] = b"cubestore-cluster-server";
+
+const AUTH_ACCEPTED: u8 = 1;
+const AUTH_REJECTED: u8 = 0;
+
+/// Authenticates the connection on the side that initiated it. No-op if `secret` is not set.
+pub async fn authenticate_client<S: AsyncRead + AsyncWrite + Unpin>(
+    socket: &mut S,
+    secret: &Option<String>,
+    timeout: Duration,
+) -> Result<(), CubeError> {
+    let secret = match secret {
+        Some(s) => s.as_bytes(),
+        None => return Ok(()),
+    };
+    tokio::time::timeout(timeout, client_handshake(socket, secret))
+        .await
+        .map_err(|_| {
+            CubeError::internal("Cluster authentication handshake timed out".to_string())
+        })?
+}
+
+/// Authenticates the connection on the accepting side. No-op if `secret` is not set.
+pub async fn authenticate_server<S: AsyncRead + AsyncWrite + Unpin>(
+    socket: &mut S,
+    secret: &Option<String>,
+    timeout: Duration,
+) -> Result<(), CubeError> {
+    let secret = match secret {
+        Some(s) => s.as_bytes(),
+        None => return Ok(()),
+    };
+    tokio::time::timeout(timeout, server_handshake(socket, secret))
+        .await
+        .map_err(|_| {
+            CubeError::internal("Cluster authentication handshake timed out".to_string())
+        })?
+}
+
+async fn client_handshake<S: AsyncRead + AsyncWrite + Unpin>(
+    socket: &mut S,
+    secret: &[u8],
+) -> Result<(), CubeError> {
+    let client_nonce = rand::random::<[u8; NONCE_LEN]>();
+    socket.write_u32(AUTH_MAGIC).await?;
+    socket.write_all(&client_nonce).await?;
+
+    let mut server_nonce = [0u8; NONCE_LEN];
+    let mut server_proof = [0u8; PROOF_LEN];
+    read_handshake(socket, &mut server_nonce).await?;
+    read_handshake(socket, &mut server_proof).await?;
+    if !verify(
+        secret,
+        SERVER_LABEL,
+        &client_nonce,
+        &server_nonce,
+        &server_proof,
+    )? {
+        return Err(CubeError::user(
+            "Cluster authentication failed: remote node uses a different secret. Please make sure CUBESTORE_CLUSTER_SECRET is the same on all nodes.".to_string(),
+        ));
+    }
+
+    let proof = sign(secret, CLIENT_LABEL, &server_nonce, &client_nonce)?;
+    socket.write_all(&proof).await?;
+
+    let mut ack = [0u8; 1];
+    read_handshake(socket, &mut ack).await?;
+    if ack[0] != AUTH_ACCEPTED {
+        return Err(CubeError::user(
+            "Cluster authentication failed: connection rejected by remote node. Please make sure CUBESTORE_CLUSTER_SECRET is the same on all nodes.".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+async fn server_handshake<S: AsyncRead + AsyncWrite + Unpin>(
+    socket: &mut S,
+    secret: &[u8],
+) -> Result<(), CubeError> {
+    let magic = socket.read_u32().await?;
+    if magic != AUTH_MAGIC {
+        return Err(CubeError::user(
+            "Unauthenticated connection rejected. Please make sure CUBESTORE_CLUSTER_SECRET is set on all nodes.".to_string(),
+        ));
+    }
+    let mut client_nonce = [0u8; NONCE_LEN];
+    socket.read_exact(&mut client_nonce).await?;
+
+    let server_nonce = rand::random::<[u8; NONCE_LEN]>();
+    let proof = sign(secret, SERVER_LABEL, &client_nonce, &server_nonce)?;
+    socket.write_all(&server_nonce).await?;
+    socket.write_all(&proof).await?;
+
+    let mut client_proof = [0u8; PROOF_LEN];
+    socket.read_exact(&mut client_proof).await?;
+    if !verify(
+        secret,
+        CLIENT_LABEL,
+        &server_nonce,
+        &client_nonce,
+        &client_proof,
+    )? {
+        socket.write_u8(AUTH_REJECTED).await?;
+        return Err(CubeError::user(
+            "Cluster authentication failed: remote node uses a different secret".to_string(),
+        ));
+    }
+    socket.write_u8(AUTH_ACCEPTED).await?;
+    Ok(())
+}
+
+async fn read_handshake<S: AsyncRead + Unpin>(
+    socket: &mut S,
+    buf: &mut [u8],
+) -> Result<(), CubeError> {
+    match socket.read_exact(buf).await {
+        Ok(_) => Ok(()),
+        Err(e)
+            if e.kind() == std::io::ErrorKind::UnexpectedEof
+                || e.kind() == std::io::ErrorKind::ConnectionReset =>
+        {
+            Err(CubeError::user(
+            "Cluster authentication failed: connection closed by remote node. Please make sure CUBESTORE_CLUSTER_SECRET is set on all nodes.".to_string(),
+            ))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn new_mac(
+    secret: &[u8],
+    label: &[u8],
+    first: &[u8],
+    second: &[u8],
+) -> Result<Hmac<Sha256>, CubeError> {
+    let mut mac = Hmac::<Sha256>::new_varkey(secret)
+        .map_err(|e| CubeError::internal(format!("Invalid cluster secret: {}", e)))?;
+    mac.update(label);
+    mac.update(first);
+    mac.update(second);
+    Ok(mac)
+}
+
+fn sign(secret: &[u8], label: &[u8], first: &[u8], second: &[u8]) -> Result<Vec<u8>, CubeError> {
+    Ok(new_mac(secret, label, first, second)?
+        .finalize()
+        .into_bytes()
+        .to_vec())
+}
+
+/// Compares in constant time.
+fn verify(
+    secret: &[u8],
+    label: &[u8],
+    first: &[u8],
+    second: &[u8],
+    proof: &[u8],
+) -> Result<bool, CubeError> {
+    Ok(new_mac(secret, label, first, second)?.verify(proof).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cluster::message::NetworkMessage;
+    use crate::cluster::transport::{ClusterTransport, ClusterTransportImpl};
+    use crate::config::Config;
+    use tokio::net::TcpListener;
+
+    /// Starts a worker-like server that answers [NetworkMessage::NotifyJobListeners] after
+    /// authenticating the connection. Returns the bound address.
+    async fn start_worker(secret: Option<String>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let secret = secret.clone();
+                tokio::spawn(async move {
+                    if authenticate_server(&mut socket, &secret, Duration::from_secs(5))
+                        .await
+                        .is_err()
+                    {
+                        return;
+                    }
+                    match NetworkMessage::receive(&mut socket).await {
+                        Ok(NetworkMessage::NotifyJobListeners) => {
+                            NetworkMessage::NotifyJobListenersSuccess
+                                .send(&mut socket)
+                                .await
+                                .unwrap();
+                        }
+                        _ => {}
+                    }
+                });
+            }
+        });
+        address
+    }
+
+    async fn call_worker(
+        test_name: &str,
+        secret: Option<&str>,
+        address: String,
+    ) -> Result<NetworkMessage, CubeError> {
+        let config = Config::test(test_name).update_config(|mut c| {
+            c.cluster_secret = secret.map(|s| s.to_string());
+            c.connection_timeout = 5;
+            c
+        });
+        let transport: std::sync::Arc<dyn ClusterTransport> =
+            ClusterTransportImpl::new(config.config_obj());
+        transport
+            .send_to_worker(address, NetworkMessage::NotifyJobListeners)
+            .await
+    }
+
+    #[tokio::test]
+    async fn router_worker_same_secret() {
+        let address = start_worker(Some("s3cret".to_string())).await;
+        let r = call_worker("auth_same_secret", Some("s3cret"), address).await;
+        assert!(matches!(r, Ok(NetworkMessage::NotifyJobListenersSuccess)));
+    }
+
+    #[tokio::test]
+    async fn router_worker_without_secret() {
+        let address = start_worker(None).await;
+        let r = call_worker("auth_without_secret", None, address).await;
+        assert!(matches!(r, Ok(NetworkMessage::NotifyJobListenersSuccess)));
+    }
+
+    #[tokio::test]
+    async fn router_worker_secret_mismatch() {
+        let address = start_worker(Some("s3cret".to_string())).await;
+        let e = call_worker("auth_secret_mismatch", Some("other"), address)
+            .await
+            .unwrap_err();
+        assert!(e.message.contains("different secret"), "{}", e);
+
+        let address = start_worker(Some("s3cret".to_string())).await;
+        assert!(call_worker("auth_secret_mismatch", None, address)
+            .await
+            .is_err());
+
+        let address = start_worker(None).await;
+        let e = call_worker("auth_secret_mismatch", Some("s3cret"), address)
+            .await
+            .unwrap_err();
+        assert!(e.message.contains("connection closed"), "{}", e);
+    }
+
+    #[tokio::test]
+    async fn handshake_proofs_are_bound_to_nonces() {
+        let secret = b"s3cret";
+        let proof = sign(secret, CLIENT_LABEL, b"server", b"client").unwrap();
+        assert!(verify(secret, CLIENT_LABEL, b"server", b"client", &proof).unwrap());
+        assert!(!verify(secret, SERVER_LABEL, b"server", b"client", &proof).unwrap());
+        assert!(!verify(secret, CLIENT_LABEL, b"client", b"server", &proof).unwrap());
+        assert!(!verify(b"other", CLIENT_LABEL, b"server", b"client", &proof).unwrap());
+    }
+}
@@ -0,0 +1,98 @@
+//! Dynamic worker membership.
+//!
+//! With `CUBESTORE_DYNAMIC_WORKERS` enabled, workers that are not listed in `CUBESTORE_WORKERS`
+//! register themselves by sending heartbeats to the router through the meta store. A new worker
+//! goes through the following states:
+//!     1. `WorkerStatus::Joining` after the first heartbeat.
+//!     2. `WorkerStatus::WarmingUp` while the previous owners persist in-memory chunks of the
+//!        partitions it is going to own and the router sends it
+//!        [NetworkMessage::WarmupDownload](crate::cluster::message::NetworkMessage::WarmupDownload)
+//!        for these partitions.
+//!     3. `WorkerStatus::HandingOver` once warmup is done. The worker takes traffic, but the
+//!        previous owners keep in-memory chunks they received until every node learned about
+//!        the worker with its next heartbeat. These chunks are persisted on the previous owners
+//!        afterwards.
+//!     4. `WorkerStatus::Active` once the handover is done.
+//!
+//! Only handing over and active workers take traffic.
+//!
+//! Workers that miss heartbeats for longer than `CUBESTORE_WORKER_HEARTBEAT_TIMEOUT` are removed.
+//! Partitions are assigned with rendezvous hashing (see [rendezvous_pick]), so adding or
+//! removing a worker only moves the partitions that are owned by that worker.
+//!
+//! Workers are persisted in the meta store (see [crate::metastore::worker::Worker]), so the
+//! membership survives router restarts. [WorkerMembership] caches names of active workers on
+//! every node.
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, RwLock};
+
+#[derive(Debug, Default)]
+pub struct WorkerMembership {
+    /// Sorted names of active dynamic workers. Workers receive it with heartbeat responses.
+    active: RwLock<Arc<Vec<String>>>,
+}
+
+impl WorkerMembership {
+    pub fn set_active_workers(&self, mut active: Vec<String>) {
+        active.sort();
+        let mut current = self.active.write().unwrap();
+        if current.as_ref() != &active {
+            *current = Arc::new(active);
+        }
+    }
+
+    pub fn active_workers(&self) -> Arc<Vec<String>> {
+        self.active.read().unwrap().clone()
+    }
+}
+
+/// Picks the worker with the highest score for `hash` (rendezvous hashing). Unlike taking a
+/// modulo over the list of workers, changing the list only reassigns keys owned by the added or
+/// removed workers.
+pub fn rendezvous_pick<'a>(
+    workers: impl IntoIterator<Item = &'a String>,
+    hash: u64,
+) -> Option<&'a String> {
+    workers.into_iter().max_by_key(|w| {
+        let mut hasher = DefaultHasher::new();
+        w.hash(&mut hasher);
+        hash.hash(&mut hasher);
+        (hasher.finish(), *w)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rendezvous_moves_only_keys_of_new_worker() {
+        let workers = (0..4).map(|i| format!("worker-{}", i)).collect::<Vec<_>>();
+        let mut extended = workers.clone();
+        extended.push("worker-4".to_string());
+
+        let mut moved = 0;
+        for key in 0..10000u64 {
+            let before = rendezvous_pick(workers.iter(), key).unwrap();
+            let after = rendezvous_pick(extended.iter(), key).unwrap();
+            if before != after {
+                assert_eq!(after, "worker-4");
+                moved += 1;
+            }
+        }
+        // Roughly 1/5 of keys should move to the new worker.
+        assert!(moved > 1500 && moved < 2500, "moved {}", moved);
+
+        // Order of workers doesn't matter.
+        let mut reversed = extended.clone();
+        reversed.reverse();
+        for key in 0..1000u64 {
+            assert_eq!(
+                rendezvous_pick(extended.iter(), key),
+                rendezvous_pick(reversed.iter(), key)
+            );
+        }
+        assert_eq!(rendezvous_pick(Vec::<String>::new().iter(), 1), None);
+    }
+}
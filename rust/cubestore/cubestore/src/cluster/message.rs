@@ -1,6 +1,7 @@
-use crate::metastore::{MetaStoreRpcMethodCall, MetaStoreRpcMethodResult};
+use crate::metastore::{MetaStoreRpcMethodCall, MetaStoreRpcMethodResult, PartitionRewrite};
 use crate::queryplanner::query_executor::SerializedRecordBatchStream;
 use crate::queryplanner::serialized_plan::SerializedPlan;
+use crate::telemetry::tracing::TraceContext;
 use crate::CubeError;
 use arrow::datatypes::SchemaRef;
 use serde::{Deserialize, Serialize};
@@ -11,10 +12,10 @@ use tokio::net::TcpStream;
 #[derive(Serialize, Deserialize, Debug)]
 pub enum NetworkMessage {
     /// Route subqueries to other nodes and collect results.
-    RouterSelect(SerializedPlan),
+    RouterSelect(SerializedPlan, Option<TraceContext>),
 
     /// Partial select on the worker.
-    Select(SerializedPlan),
+    Select(SerializedPlan, Option<TraceContext>),
     SelectResult(Result<(SchemaRef, Vec<SerializedRecordBatchStream>), CubeError>),
 
     //Perform explain analyze of worker query part and return it pretty printed physical plan
@@ -23,7 +24,7 @@ pub enum NetworkMessage {
 
     /// Select that sends results in batches. The immediate response is [SelectResultSchema],
     /// followed by a stream of [SelectResultBatch].
-    SelectStart(SerializedPlan),
+    SelectStart(SerializedPlan, Option<TraceContext>),
     /// Response to [SelectStart].
     SelectResultSchema(Result<SchemaRef, CubeError>),
     /// [None] indicates the end of the stream.
@@ -51,11 +52,18 @@ pub enum NetworkMessage {
 
     NotifyJobListeners,
     NotifyJobListenersSuccess,
+
+    /// Rewrites the partition by `DELETE` or `UPDATE` statement on the node that owns it.
+    RewritePartition {
+        partition_id: u64,
+        mutation: String,
+    },
+    RewritePartitionResult(Result<PartitionRewrite, CubeError>),
 }
 
 const MAGIC: u32 = 94107;
 
-const NETWORK_MESSAGE_VERSION: u32 = 1;
+const NETWORK_MESSAGE_VERSION: u32 = 2;
 
 impl NetworkMessage {
     pub fn is_streaming_request(&self) -> bool {
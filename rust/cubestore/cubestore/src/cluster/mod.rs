@@ -1,3 +1,5 @@
+pub mod auth;
+pub mod membership;
 pub mod message;
 
 pub mod transport;
@@ -20,6 +22,7 @@ use crate::cluster::worker_services::{
 };
 
 use crate::ack_error;
+use crate::cluster::membership::rendezvous_pick;
 use crate::cluster::message::NetworkMessage;
 use crate::cluster::rate_limiter::{ProcessRateLimiter, TaskType, TraceIndex};
 use crate::cluster::transport::{ClusterTransport, MetaStoreTransport, WorkerConnection};
@@ -29,9 +32,10 @@ use crate::config::is_router;
 use crate::config::{Config, ConfigObj};
 use crate::metastore::chunks::chunk_file_name;
 use crate::metastore::job::{Job, JobStatus, JobType};
+use crate::metastore::worker::WorkerStatus;
 use crate::metastore::{
-    deactivate_table_on_corrupt_data, Chunk, IdRow, MetaStore, MetaStoreEvent, Partition, RowKey,
-    TableId,
+    deactivate_table_on_corrupt_data, Chunk, IdRow, MetaStore, MetaStoreEvent, Partition,
+    PartitionRewrite, RowKey, TableId,
 };
 use crate::metastore::{
     MetaStoreRpcClientTransport, MetaStoreRpcMethodCall, MetaStoreRpcMethodResult,
@@ -40,8 +44,10 @@ use crate::metastore::{
 use crate::queryplanner::query_executor::{QueryExecutor, SerializedRecordBatchStream};
 use crate::queryplanner::serialized_plan::SerializedPlan;
 use crate::remotefs::RemoteFs;
+use crate::store::compaction::CompactionService;
+use crate::store::mutation::RowsMutation;
 use crate::store::ChunkDataStore;
-use crate::telemetry::tracing::TracingHelper;
+use crate::telemetry::tracing::{TraceContext, TracingHelper};
 use crate::CubeError;
 use arrow::datatypes::SchemaRef;
 use arrow::error::ArrowError;
@@ -63,7 +69,7 @@ use mockall::automock;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::hash_map::DefaultHasher;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::sync::Weak;
 use std::sync::{Arc, Mutex};
@@ -146,6 +152,14 @@ pub trait Cluster: DIService + Send + Sync {
         chunk_names: Vec<String>,
     ) -> Result<(), CubeError>;
 
+    /// Runs [CompactionService::rewrite_partition] on `node_name`, which should own the partition.
+    async fn rewrite_partition(
+        &self,
+        node_name: &str,
+        partition_id: u64,
+        mutation: &RowsMutation,
+    ) -> Result<PartitionRewrite, CubeError>;
+
     fn job_result_listener(&self) -> JobResultListener;
 
     fn node_name_by_partition(&self, p: &IdRow<Partition>) -> String;
@@ -216,6 +230,7 @@ pub enum WorkerMessage {
         HashMap<String, String>,
         HashMap<u64, Vec<SerializedRecordBatchStream>>,
         Option<(u64, u64)>,
+        Option<TraceContext>,
     ),
 }
 
@@ -286,6 +301,7 @@ impl WorkerProcessing for WorkerProcessor {
                 remote_to_local_names,
                 chunk_id_to_record_batches,
                 trace_id_and_span_id,
+                trace_context,
             ) => {
                 let future = async move {
                     let time = SystemTime::now();
@@ -320,13 +336,19 @@ impl WorkerProcessing for WorkerProcessor {
                     let records = SerializedRecordBatchStream::write(schema.as_ref(), records)?;
                     Ok((schema, records, data_loaded_size))
                 };
-                let span = trace_id_and_span_id.map(|(t, s)| {
-                    tracing::info_span!(
+                let span = match (trace_context, trace_id_and_span_id) {
+                    (Some(trace_context), _) => {
+                        let span = tracing::info_span!("Process on select worker");
+                        trace_context.attach(&span);
+                        Some(span)
+                    }
+                    (None, Some((t, s))) => Some(tracing::info_span!(
                         "Process on selec worker",
                         cube_dd_trace_id = t,
                         cube_dd_parent_span_id = s
-                    )
-                });
+                    )),
+                    (None, None) => None,
+                };
                 if let Some(span) = span {
                     future.instrument(span).await
                 } else {
@@ -422,7 +444,10 @@ impl Cluster for ClusterImpl {
         plan: SerializedPlan,
     ) -> Result<(SchemaRef, Vec<SerializedRecordBatchStream>), CubeError> {
         let response = self
-            .send_or_process_locally(&node_name, NetworkMessage::RouterSelect(plan))
+            .send_or_process_locally(
+                &node_name,
+                NetworkMessage::RouterSelect(plan, TraceContext::current()),
+            )
             .await?;
         match response {
             NetworkMessage::SelectResult(r) => r,
@@ -430,14 +455,17 @@ impl Cluster for ClusterImpl {
         }
     }
 
-    #[instrument(level = "trace", skip(self, plan_node))]
+    #[instrument(level = "info", skip(self, plan_node))]
     async fn run_select(
         &self,
         node_name: &str,
         plan_node: SerializedPlan,
     ) -> Result<Vec<RecordBatch>, CubeError> {
         let response = self
-            .send_or_process_locally(node_name, NetworkMessage::Select(plan_node))
+            .send_or_process_locally(
+                node_name,
+                NetworkMessage::Select(plan_node, TraceContext::current()),
+            )
             .await?;
         match response {
             NetworkMessage::SelectResult(r) => {
@@ -553,6 +581,27 @@ impl Cluster for ClusterImpl {
         }
     }
 
+    async fn rewrite_partition(
+        &self,
+        node_name: &str,
+        partition_id: u64,
+        mutation: &RowsMutation,
+    ) -> Result<PartitionRewrite, CubeError> {
+        let response = self
+            .send_or_process_locally(
+                node_name,
+                NetworkMessage::RewritePartition {
+                    partition_id,
+                    mutation: mutation.to_string(),
+                },
+            )
+            .await?;
+        match response {
+            NetworkMessage::RewritePartitionResult(r) => r,
+            x => panic!("Unexpected result for rewrite partition: {:?}", x),
+        }
+    }
+
     fn job_result_listener(&self) -> JobResultListener {
         JobResultListener {
             receiver: self.meta_store_sender.subscribe(),
@@ -575,7 +624,10 @@ impl Cluster for ClusterImpl {
                     .await?,
             ))
         } else {
-            Ok(pick_worker_by_ids(self.config_obj.as_ref(), [chunk.get_id()]).to_string())
+            Ok(pick_worker_by_ids(
+                self.config_obj.as_ref(),
+                [chunk.get_id()],
+            ))
         }
     }
 
@@ -584,14 +636,13 @@ impl Cluster for ClusterImpl {
         table_id: u64,
         location: &str,
     ) -> Result<String, CubeError> {
-        let workers = self.config_obj.select_workers();
-        if workers.is_empty() {
-            return Ok(self.server_name.to_string());
-        }
         let mut hasher = DefaultHasher::new();
         table_id.hash(&mut hasher);
         location.hash(&mut hasher);
-        Ok(workers[(hasher.finish() % workers.len() as u64) as usize].to_string())
+        Ok(pick_worker_by_hash(
+            self.config_obj.as_ref(),
+            hasher.finish(),
+        ))
     }
 
     async fn warmup_partition(
@@ -600,42 +651,22 @@ impl Cluster for ClusterImpl {
         chunks: Vec<IdRow<Chunk>>,
     ) -> Result<(), CubeError> {
         let node_name = self.node_name_by_partition(&partition);
-        let mut futures = Vec::new();
-        if let Some(name) = partition.get_row().get_full_name(partition.get_id()) {
-            futures.push(self.warmup_download_with_corruption_check(
-                &node_name,
-                name,
-                partition.get_row().file_size(),
-                &partition,
-                None,
-            ));
-        }
-        for chunk in chunks.iter() {
-            let name = chunk.get_row().get_full_name(chunk.get_id());
-            futures.push(self.warmup_download_with_corruption_check(
-                &node_name,
-                name,
-                chunk.get_row().file_size(),
-                &partition,
-                Some(chunk.get_id()),
-            ));
-        }
-        let res = join_all(futures)
+        self.warmup_partition_on_node(&node_name, partition, chunks)
             .await
-            .into_iter()
-            .collect::<Result<Vec<_>, _>>();
-
-        res?;
-        Ok(())
     }
 
     #[instrument(level = "trace", skip(self, m))]
     async fn process_message_on_worker(&self, m: NetworkMessage) -> NetworkMessage {
         match m {
-            NetworkMessage::RouterSelect(plan) => {
+            NetworkMessage::RouterSelect(plan, trace_context) => {
+                let span = TraceContext::attach_to(
+                    &trace_context,
+                    tracing::info_span!("router_select", node = %self.server_name),
+                );
                 let res = self
                     .query_executor
                     .execute_router_plan(plan, self.this.upgrade().unwrap())
+                    .instrument(span)
                     .await
                     .and_then(|(schema, records)| {
                         let records = SerializedRecordBatchStream::write(&schema, records)?;
@@ -643,8 +674,12 @@ impl Cluster for ClusterImpl {
                     });
                 NetworkMessage::SelectResult(res)
             }
-            NetworkMessage::Select(plan) => {
-                let res = self.run_local_select_worker(plan).await;
+            NetworkMessage::Select(plan, trace_context) => {
+                let span = TraceContext::attach_to(
+                    &trace_context,
+                    tracing::info_span!("worker_select", node = %self.server_name),
+                );
+                let res = self.run_local_select_worker(plan).instrument(span).await;
                 NetworkMessage::SelectResult(res)
             }
             NetworkMessage::ExplainAnalyze(plan) => {
@@ -719,6 +754,29 @@ impl Cluster for ClusterImpl {
             NetworkMessage::NotifyJobListenersSuccess => {
                 panic!("NotifyJobListenersSuccess sent to worker")
             }
+            NetworkMessage::RewritePartition {
+                partition_id,
+                mutation,
+            } => {
+                let res = match RowsMutation::parse(&mutation) {
+                    Ok(mutation) => {
+                        let compaction_service = self
+                            .injector
+                            .upgrade()
+                            .unwrap()
+                            .get_service_typed::<dyn CompactionService>()
+                            .await;
+                        compaction_service
+                            .rewrite_partition(partition_id, mutation)
+                            .await
+                    }
+                    Err(e) => Err(e),
+                };
+                NetworkMessage::RewritePartitionResult(res)
+            }
+            NetworkMessage::RewritePartitionResult(_) => {
+                panic!("RewritePartitionResult sent to worker")
+            }
             NetworkMessage::SelectStart(..)
             | NetworkMessage::SelectResultSchema(..)
             | NetworkMessage::SelectResultBatch(..) => {
@@ -994,6 +1052,19 @@ impl ClusterImpl {
             }
         }));
 
+        if self.config_obj.dynamic_workers() {
+            let cluster = self.this.upgrade().unwrap();
+            if self.is_select_worker() {
+                futures.push(cube_ext::spawn(async move {
+                    cluster.worker_heartbeat_loop().await
+                }));
+            } else {
+                futures.push(cube_ext::spawn(async move {
+                    cluster.worker_membership_loop().await
+                }));
+            }
+        }
+
         join_all(futures)
             .await
             .into_iter()
@@ -1001,6 +1072,226 @@ impl ClusterImpl {
         Ok(())
     }
 
+    /// Registers the current worker on the router and keeps the list of active workers up to date.
+    async fn worker_heartbeat_loop(&self) {
+        let interval = Duration::from_secs(self.config_obj.worker_heartbeat_interval());
+        loop {
+            match self
+                .meta_store
+                .worker_heartbeat(self.server_name.clone())
+                .await
+            {
+                Ok(active) => self
+                    .config_obj
+                    .worker_membership()
+                    .set_active_workers(active),
+                Err(e) => error!("Error sending worker heartbeat: {}", e),
+            }
+            tokio::select! {
+                _ = self.stop_token.cancelled() => {
+                    return;
+                }
+                _ = Delay::new(interval) => {}
+            };
+        }
+    }
+
+    /// Removes workers that stopped sending heartbeats and warms up joining workers before
+    /// they start taking traffic.
+    async fn worker_membership_loop(&self) {
+        let timeout = self.config_obj.worker_heartbeat_timeout();
+        // Workers stay warming up if their warmup failed or the router restarted during warmup,
+        // so warmup is retried for them on the next iteration.
+        let warming_up = Arc::new(Mutex::new(HashSet::new()));
+        loop {
+            tokio::select! {
+                _ = self.stop_token.cancelled() => {
+                    return;
+                }
+                _ = Delay::new(Duration::from_secs(self.config_obj.worker_heartbeat_interval())) => {}
+            };
+            match self.meta_store.expire_workers(timeout).await {
+                Ok(expired) => {
+                    for worker in expired {
+                        warn!(
+                            "Worker {} hasn't sent heartbeats for {} seconds and was removed from the cluster",
+                            worker.get_row().name(),
+                            timeout
+                        );
+                    }
+                }
+                Err(e) => {
+                    error!("Error removing expired workers: {}", e);
+                    continue;
+                }
+            }
+            let workers = match self.meta_store.start_workers_warmup().await {
+                Ok(workers) => workers,
+                Err(e) => {
+                    error!("Error starting warmup of workers: {}", e);
+                    continue;
+                }
+            };
+            for worker in workers {
+                let status = worker.get_row().status();
+                let worker = worker.get_row().name().clone();
+                if !warming_up.lock().unwrap().insert(worker.clone()) {
+                    continue;
+                }
+                let cluster = self.this.upgrade().unwrap();
+                let warming_up = warming_up.clone();
+                cube_ext::spawn(async move {
+                    if let Err(e) = cluster.join_worker(&worker, status).await {
+                        error!("Error adding worker {} to the cluster: {}", worker, e);
+                    }
+                    warming_up.lock().unwrap().remove(&worker);
+                });
+            }
+        }
+    }
+
+    /// Warms up `worker`, makes it take traffic and then persists in-memory chunks of its
+    /// partitions that the previous owners received until every node learned about `worker`.
+    async fn join_worker(&self, worker: &str, status: WorkerStatus) -> Result<(), CubeError> {
+        if status == WorkerStatus::WarmingUp {
+            info!("Warming up worker {}", worker);
+            self.warmup_joining_worker(worker).await?;
+            if !self.meta_store.activate_worker(worker.to_string()).await? {
+                return Ok(());
+            }
+            info!("Worker {} takes traffic", worker);
+        }
+        // Other nodes receive the new list of workers with their next heartbeat. Until then they
+        // keep sending in-memory chunks of moved partitions to the previous owners.
+        let propagation = Duration::from_secs(2 * self.config_obj.worker_heartbeat_interval());
+        tokio::select! {
+            _ = self.stop_token.cancelled() => {
+                return Ok(());
+            }
+            _ = Delay::new(propagation) => {}
+        };
+        let partitions = self.partitions_moving_to(worker).await?;
+        self.persist_in_memory_chunks(&partitions).await?;
+        if self
+            .meta_store
+            .finish_worker_handover(worker.to_string())
+            .await?
+        {
+            info!("Worker {} is active", worker);
+        }
+        Ok(())
+    }
+
+    /// Prepares partitions that will be owned by `worker` once it becomes active: persists
+    /// their in-memory chunks on the current owners and downloads their files to `worker`.
+    async fn warmup_joining_worker(&self, worker: &str) -> Result<(), CubeError> {
+        let partitions = self.partitions_moving_to(worker).await?;
+        self.persist_in_memory_chunks(&partitions).await?;
+        if !self.config_obj.enable_startup_warmup() {
+            return Ok(());
+        }
+        // Persisted in-memory chunks are new files, so chunks are fetched again.
+        for (p, chunks, _) in self.partitions_moving_to(worker).await? {
+            if self.stop_token.is_cancelled() {
+                return Ok(());
+            }
+            let chunks = chunks
+                .into_iter()
+                .filter(|c| !c.get_row().in_memory())
+                .collect();
+            // Compaction might remove files in the meantime, so errors are not fatal.
+            ack_error!(self.warmup_partition_on_node(worker, p, chunks).await);
+        }
+        Ok(())
+    }
+
+    /// Returns partitions owned by `worker` once it's active along with their previous owners.
+    async fn partitions_moving_to(
+        &self,
+        worker: &str,
+    ) -> Result<Vec<(IdRow<Partition>, Vec<IdRow<Chunk>>, String)>, CubeError> {
+        let mut workers = self.config_obj.select_workers().clone();
+        workers.extend(
+            self.config_obj
+                .worker_membership()
+                .active_workers()
+                .iter()
+                .filter(|w| w.as_str() != worker)
+                .cloned(),
+        );
+        let previous_workers = workers.clone();
+        workers.push(worker.to_string());
+
+        Ok(self
+            .meta_store
+            .get_warmup_partitions()
+            .await?
+            .into_iter()
+            .filter(|(p, _)| {
+                rendezvous_pick(workers.iter(), partition_hash(p)).map(|w| w.as_str())
+                    == Some(worker)
+            })
+            .map(|(p, chunks)| {
+                let previous_owner = rendezvous_pick(previous_workers.iter(), partition_hash(&p))
+                    .unwrap_or(&self.server_name)
+                    .clone();
+                (p, chunks, previous_owner)
+            })
+            .collect())
+    }
+
+    /// In-memory chunks are kept only on the node that received them, so they are written to
+    /// persistent storage on the previous owners when the partitions move to another worker.
+    async fn persist_in_memory_chunks(
+        &self,
+        partitions: &[(IdRow<Partition>, Vec<IdRow<Chunk>>, String)],
+    ) -> Result<(), CubeError> {
+        let listener = self.job_result_listener();
+        let mut jobs = Vec::new();
+        let mut nodes = HashSet::new();
+        for (p, chunks, node) in partitions {
+            if !chunks
+                .iter()
+                .any(|c| c.get_row().in_memory() && c.get_row().get_partition_id() == p.get_id())
+            {
+                continue;
+            }
+            let node = node.clone();
+            let row_key = RowKey::Table(TableId::Partitions, p.get_id());
+            self.meta_store
+                .add_job(Job::new(
+                    row_key.clone(),
+                    JobType::InMemoryChunksFlush,
+                    node.clone(),
+                ))
+                .await?;
+            jobs.push((row_key, JobType::InMemoryChunksFlush));
+            nodes.insert(node);
+        }
+        if jobs.is_empty() {
+            return Ok(());
+        }
+        for node in nodes {
+            self.notify_job_runner(node).await?;
+        }
+        // Jobs of removed nodes are deleted without any result.
+        let results = timeout(
+            Duration::from_secs(self.config_obj.import_job_timeout()),
+            listener.wait_for_job_results(jobs),
+        )
+        .await
+        .map_err(|_| CubeError::internal("Timeout persisting in-memory chunks".to_string()))??;
+        for result in results {
+            if let JobEvent::Error(_, _, e) = result {
+                return Err(CubeError::internal(format!(
+                    "Error persisting in-memory chunks: {}",
+                    e
+                )));
+            }
+        }
+        Ok(())
+    }
+
     pub async fn stop_processing_loops(&self) -> Result<(), CubeError> {
         self.stop_token.cancel();
 
@@ -1115,7 +1406,7 @@ impl ClusterImpl {
 
         loop {
             let mut stop_receiver = cluster.close_worker_socket_rx.write().await;
-            let (socket, _) = tokio::select! {
+            let (mut socket, peer) = tokio::select! {
                 res = stop_receiver.changed() => {
                     if res.is_err() || *stop_receiver.borrow() {
                         return Ok(());
@@ -1135,8 +1426,20 @@ impl ClusterImpl {
             };
             let cluster_to_move = cluster.clone();
             let process_fn_to_move = process_fn.clone();
+            let name = name.to_string();
 
             cube_ext::spawn(async move {
+                let config = cluster_to_move.config_obj.clone();
+                if let Err(e) = auth::authenticate_server(
+                    &mut socket,
+                    config.cluster_secret(),
+                    Duration::from_secs(config.connection_timeout()),
+                )
+                .await
+                {
+                    error!("{} connection from {} rejected: {}", name, peer, e);
+                    return;
+                }
                 process_fn_to_move(cluster_to_move, socket).await;
             });
         }
@@ -1299,6 +1602,7 @@ impl ClusterImpl {
                         remote_to_local_names.clone(),
                         chunk_id_to_record_batches,
                         self.tracing_helper.trace_and_span_id(),
+                        TraceContext::current(),
                     ))
                     .instrument(tracing::span!(
                         tracing::Level::TRACE,
@@ -1390,6 +1694,41 @@ impl ClusterImpl {
         Ok(remote_to_local_names)
     }
 
+    async fn warmup_partition_on_node(
+        &self,
+        node_name: &str,
+        partition: IdRow<Partition>,
+        chunks: Vec<IdRow<Chunk>>,
+    ) -> Result<(), CubeError> {
+        let mut futures = Vec::new();
+        if let Some(name) = partition.get_row().get_full_name(partition.get_id()) {
+            futures.push(self.warmup_download_with_corruption_check(
+                node_name,
+                name,
+                partition.get_row().file_size(),
+                &partition,
+                None,
+            ));
+        }
+        for chunk in chunks.iter() {
+            let name = chunk.get_row().get_full_name(chunk.get_id());
+            futures.push(self.warmup_download_with_corruption_check(
+                node_name,
+                name,
+                chunk.get_row().file_size(),
+                &partition,
+                Some(chunk.get_id()),
+            ));
+        }
+        let res = join_all(futures)
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>();
+
+        res?;
+        Ok(())
+    }
+
     async fn warmup_download_with_corruption_check(
         &self,
         node_name: &str,
@@ -1464,8 +1803,13 @@ impl ClusterImpl {
 
     async fn start_stream_on_worker(self: Arc<Self>, m: NetworkMessage) -> Box<dyn MessageStream> {
         match m {
-            NetworkMessage::SelectStart(p) => {
-                let (schema, results) = match self.run_local_select_worker(p).await {
+            NetworkMessage::SelectStart(p, trace_context) => {
+                let span = TraceContext::attach_to(
+                    &trace_context,
+                    tracing::info_span!("worker_select_stream", node = %self.server_name),
+                );
+                let res = self.run_local_select_worker(p).instrument(span).await;
+                let (schema, results) = match res {
                     Err(e) => return Box::new(QueryStream::new_error(e)),
                     Ok(x) => x,
                 };
@@ -1480,7 +1824,7 @@ impl ClusterImpl {
         node_name: &str,
         plan: SerializedPlan,
     ) -> Result<SendableRecordBatchStream, CubeError> {
-        let init_message = NetworkMessage::SelectStart(plan);
+        let init_message = NetworkMessage::SelectStart(plan, TraceContext::current());
         let mut c = self.call_streaming(node_name, init_message).await?;
         let schema = match c.receive().await? {
             NetworkMessage::SelectResultSchema(s) => s,
@@ -1585,6 +1929,12 @@ impl ClusterImpl {
     /// Can take awhile, use the passed cancellation token to stop the worker before it finishes.
     /// Designed to run in the background.
     pub async fn warmup_select_worker(&self) {
+        if self.config_obj.dynamic_workers()
+            && !self.config_obj.select_workers().contains(&self.server_name)
+        {
+            log::info!("Dynamic worker is warmed up by the router after joining the cluster");
+            return;
+        }
         if self.config_obj.select_workers().len() == 0 {
             log::error!("No select workers specified");
             return;
@@ -1732,48 +2082,82 @@ fn is_self_reference(name: &str) -> bool {
     name.starts_with("@loop:")
 }
 
-pub fn node_name_by_partition<'a>(config: &'a dyn ConfigObj, p: &IdRow<Partition>) -> String {
+pub fn node_name_by_partition(config: &dyn ConfigObj, p: &IdRow<Partition>) -> String {
+    pick_worker_by_hash(config, partition_hash(p))
+}
+
+/// Hash used to assign the partition to a worker.
+pub fn partition_hash(p: &IdRow<Partition>) -> u64 {
     if let Some(id) = p.get_row().multi_partition_id() {
-        pick_worker_by_ids(config, [id]).to_string()
+        hash_ids([id])
     } else {
-        pick_worker_by_partitions(config, [p]).to_string()
+        hash_partitions([p])
     }
 }
+
 /// Picks a worker by opaque id for any distributing work in a cluster.
 /// Ids usually come from multi-partitions of the metastore.
-pub fn pick_worker_by_ids<'a>(
-    config: &'a dyn ConfigObj,
-    ids: impl IntoIterator<Item = u64>,
-) -> &'a str {
-    let workers = config.select_workers();
-    if workers.is_empty() {
-        return config.server_name().as_str();
-    }
-
-    let mut hasher = DefaultHasher::new();
-    for p in ids {
-        p.hash(&mut hasher);
-    }
-    workers[(hasher.finish() % workers.len() as u64) as usize].as_str()
+pub fn pick_worker_by_ids(config: &dyn ConfigObj, ids: impl IntoIterator<Item = u64>) -> String {
+    pick_worker_by_hash(config, hash_ids(ids))
 }
 
 /// Same as [pick_worker_by_ids], but uses ranges of partitions. This is a hack
 /// to keep the same node for partitions produced by compaction that merged
 /// chunks into the main table of a single partition.
 pub fn pick_worker_by_partitions<'a>(
-    config: &'a dyn ConfigObj,
+    config: &dyn ConfigObj,
     partitions: impl IntoIterator<Item = &'a IdRow<Partition>>,
-) -> &'a str {
-    let workers = config.select_workers();
-    if workers.is_empty() {
-        return config.server_name().as_str();
+) -> String {
+    pick_worker_by_hash(config, hash_partitions(partitions))
+}
+
+fn hash_ids(ids: impl IntoIterator<Item = u64>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for p in ids {
+        p.hash(&mut hasher);
     }
+    hasher.finish()
+}
 
+fn hash_partitions<'a>(partitions: impl IntoIterator<Item = &'a IdRow<Partition>>) -> u64 {
     let mut hasher = DefaultHasher::new();
     for partition in partitions {
         partition.get_row().get_min_val().hash(&mut hasher);
         partition.get_row().get_max_val().hash(&mut hasher);
         partition.get_row().get_index_id().hash(&mut hasher);
     }
-    workers[(hasher.finish() % workers.len() as u64) as usize].as_str()
+    hasher.finish()
+}
+
+/// Static worker lists keep the modulo assignment. With dynamic workers enabled, rendezvous
+/// hashing is used so that workers joining or leaving move a minimal set of partitions.
+fn pick_worker_by_hash(config: &dyn ConfigObj, hash: u64) -> String {
+    let workers = config.select_workers();
+    if config.dynamic_workers() {
+        let dynamic = config.worker_membership().active_workers();
+        return rendezvous_pick(workers.iter().chain(dynamic.iter()), hash)
+            .unwrap_or(config.server_name())
+            .to_string();
+    }
+    if workers.is_empty() {
+        return config.server_name().to_string();
+    }
+    workers[(hash % workers.len() as u64) as usize].to_string()
+}
+
+/// Workers that currently take traffic: [ConfigObj::select_workers] and active dynamic workers.
+/// Returns the current node if there are no workers.
+pub fn worker_nodes(config: &dyn ConfigObj) -> Vec<String> {
+    let mut workers = config.select_workers().clone();
+    if config.dynamic_workers() {
+        for w in config.worker_membership().active_workers().iter() {
+            if !workers.contains(w) {
+                workers.push(w.clone());
+            }
+        }
+    }
+    if workers.is_empty() {
+        workers.push(config.server_name().clone());
+    }
+    workers
 }
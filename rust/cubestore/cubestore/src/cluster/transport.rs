@@ -1,3 +1,4 @@
+use crate::cluster::auth::authenticate_client;
 use crate::cluster::message::NetworkMessage;
 use crate::config::injection::DIService;
 use crate::config::ConfigObj;
@@ -90,13 +91,19 @@ impl ClusterTransport for ClusterTransportImpl {
         &self,
         worker_node: String,
     ) -> Result<Box<dyn WorkerConnection>, CubeError> {
-        let stream = tokio::time::timeout(
+        let mut stream = tokio::time::timeout(
             Duration::from_secs(self.config.connection_timeout()),
             TcpStream::connect(worker_node.to_string()),
         )
         .await
         .map_err(|_| CubeError::internal(format!("Connection timeout to {}. Please check your worker connection env variables (CUBESTORE_WORKERS, CUBESTORE_WORKER_PORT, etc.).", worker_node)))?
         .map_err(|e| CubeError::internal(format!("Can't connect to {}: {}", worker_node, e)))?;
+        authenticate_client(
+            &mut stream,
+            self.config.cluster_secret(),
+            Duration::from_secs(self.config.connection_timeout()),
+        )
+        .await?;
         Ok(Box::new(Connection { stream }))
     }
 }
@@ -141,6 +148,12 @@ impl MetaStoreTransport for MetaStoreTransportImpl {
         .map_err(|e| {
             CubeError::internal(format!("Can't connect to {}: {}", meta_remote_addr, e))
         })?;
+        authenticate_client(
+            &mut stream,
+            self.config.cluster_secret(),
+            Duration::from_secs(self.config.connection_timeout()),
+        )
+        .await?;
         m.send(&mut stream).await?;
         let message = NetworkMessage::receive(&mut stream).await?;
         Ok(message)
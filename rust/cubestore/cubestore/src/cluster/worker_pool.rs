@@ -24,6 +24,7 @@ use crate::cluster::worker_services::{
     Callable, Configurator, ServicesServer, ServicesTransport, WorkerProcessing,
 };
 use crate::config::env_parse;
+use crate::telemetry::tracing::init_tracing_telemetry;
 use crate::util::respawn::respawn;
 use crate::CubeError;
 use datafusion::cube_ext;
@@ -400,6 +401,13 @@ where
     let runtime = tokio_builder.build().unwrap();
     C::setup(&runtime);
     runtime.block_on(async move {
+        if let Err(e) = init_tracing_telemetry() {
+            error!(
+                "Error during {} worker tracing init: {}",
+                P::process_titile(),
+                e
+            );
+        }
         let services_client = S::connect(services_sender, services_reciever, timeout);
         let config = match C::configure(services_client).await {
             Err(e) => {
@@ -13,17 +13,18 @@ pub const ENUM_MIN_HTTP_COMMAND: u8 = 0;
     since = "2.0.0",
     note = "Use associated constants instead. This will no longer be generated in 2021."
 )]
-pub const ENUM_MAX_HTTP_COMMAND: u8 = 3;
+pub const ENUM_MAX_HTTP_COMMAND: u8 = 4;
 #[deprecated(
     since = "2.0.0",
     note = "Use associated constants instead. This will no longer be generated in 2021."
 )]
 #[allow(non_camel_case_types)]
-pub const ENUM_VALUES_HTTP_COMMAND: [HttpCommand; 4] = [
+pub const ENUM_VALUES_HTTP_COMMAND: [HttpCommand; 5] = [
     HttpCommand::NONE,
     HttpCommand::HttpQuery,
     HttpCommand::HttpResultSet,
     HttpCommand::HttpError,
+    HttpCommand::HttpCacheEvent,
 ];
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
@@ -35,14 +36,16 @@ impl HttpCommand {
     pub const HttpQuery: Self = Self(1);
     pub const HttpResultSet: Self = Self(2);
     pub const HttpError: Self = Self(3);
+    pub const HttpCacheEvent: Self = Self(4);
 
     pub const ENUM_MIN: u8 = 0;
-    pub const ENUM_MAX: u8 = 3;
+    pub const ENUM_MAX: u8 = 4;
     pub const ENUM_VALUES: &'static [Self] = &[
         Self::NONE,
         Self::HttpQuery,
         Self::HttpResultSet,
         Self::HttpError,
+        Self::HttpCacheEvent,
     ];
     /// Returns the variant's name or "" if unknown.
     pub fn variant_name(self) -> Option<&'static str> {
@@ -51,6 +54,7 @@ impl HttpCommand {
             Self::HttpQuery => Some("HttpQuery"),
             Self::HttpResultSet => Some("HttpResultSet"),
             Self::HttpError => Some("HttpError"),
+            Self::HttpCacheEvent => Some("HttpCacheEvent"),
             _ => None,
         }
     }
@@ -241,6 +245,21 @@ impl<'a> HttpMessage<'a> {
             None
         }
     }
+
+    #[inline]
+    #[allow(non_snake_case)]
+    pub fn command_as_http_cache_event(&self) -> Option<HttpCacheEvent<'a>> {
+        if self.command_type() == HttpCommand::HttpCacheEvent {
+            self.command().map(|t| {
+                // Safety:
+                // Created from a valid Table for this object
+                // Which contains a valid union in this slot
+                unsafe { HttpCacheEvent::init_from_table(t) }
+            })
+        } else {
+            None
+        }
+    }
 }
 
 impl flatbuffers::Verifiable for HttpMessage<'_> {
@@ -273,6 +292,11 @@ impl flatbuffers::Verifiable for HttpMessage<'_> {
                             "HttpCommand::HttpError",
                             pos,
                         ),
+                    HttpCommand::HttpCacheEvent => v
+                        .verify_union_variant::<flatbuffers::ForwardsUOffset<HttpCacheEvent>>(
+                            "HttpCommand::HttpCacheEvent",
+                            pos,
+                        ),
                     _ => Ok(()),
                 },
             )?
@@ -384,6 +408,16 @@ impl core::fmt::Debug for HttpMessage<'_> {
                     )
                 }
             }
+            HttpCommand::HttpCacheEvent => {
+                if let Some(x) = self.command_as_http_cache_event() {
+                    ds.field("command", &x)
+                } else {
+                    ds.field(
+                        "command",
+                        &"InvalidFlatbuffer: Union discriminant does not match value.",
+                    )
+                }
+            }
             _ => {
                 let x: Option<()> = None;
                 ds.field("command", &x)
@@ -864,6 +898,133 @@ impl core::fmt::Debug for HttpError<'_> {
         ds.finish()
     }
 }
+pub enum HttpCacheEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct HttpCacheEvent<'a> {
+    pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for HttpCacheEvent<'a> {
+    type Inner = HttpCacheEvent<'a>;
+    #[inline]
+    unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table::new(buf, loc),
+        }
+    }
+}
+
+impl<'a> HttpCacheEvent<'a> {
+    pub const VT_EVENT: flatbuffers::VOffsetT = 4;
+    pub const VT_PATH: flatbuffers::VOffsetT = 6;
+
+    #[inline]
+    pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        HttpCacheEvent { _tab: table }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args HttpCacheEventArgs<'args>,
+    ) -> flatbuffers::WIPOffset<HttpCacheEvent<'bldr>> {
+        let mut builder = HttpCacheEventBuilder::new(_fbb);
+        if let Some(x) = args.path {
+            builder.add_path(x);
+        }
+        if let Some(x) = args.event {
+            builder.add_event(x);
+        }
+        builder.finish()
+    }
+
+    #[inline]
+    pub fn event(&self) -> Option<&'a str> {
+        // Safety:
+        // Created from valid Table for this object
+        // which contains a valid value in this slot
+        unsafe {
+            self._tab
+                .get::<flatbuffers::ForwardsUOffset<&str>>(HttpCacheEvent::VT_EVENT, None)
+        }
+    }
+    #[inline]
+    pub fn path(&self) -> Option<&'a str> {
+        // Safety:
+        // Created from valid Table for this object
+        // which contains a valid value in this slot
+        unsafe {
+            self._tab
+                .get::<flatbuffers::ForwardsUOffset<&str>>(HttpCacheEvent::VT_PATH, None)
+        }
+    }
+}
+
+impl flatbuffers::Verifiable for HttpCacheEvent<'_> {
+    #[inline]
+    fn run_verifier(
+        v: &mut flatbuffers::Verifier,
+        pos: usize,
+    ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+        v.visit_table(pos)?
+            .visit_field::<flatbuffers::ForwardsUOffset<&str>>("event", Self::VT_EVENT, false)?
+            .visit_field::<flatbuffers::ForwardsUOffset<&str>>("path", Self::VT_PATH, false)?
+            .finish();
+        Ok(())
+    }
+}
+pub struct HttpCacheEventArgs<'a> {
+    pub event: Option<flatbuffers::WIPOffset<&'a str>>,
+    pub path: Option<flatbuffers::WIPOffset<&'a str>>,
+}
+impl<'a> Default for HttpCacheEventArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        HttpCacheEventArgs {
+            event: None,
+            path: None,
+        }
+    }
+}
+
+pub struct HttpCacheEventBuilder<'a: 'b, 'b> {
+    fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+    start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> HttpCacheEventBuilder<'a, 'b> {
+    #[inline]
+    pub fn add_event(&mut self, event: flatbuffers::WIPOffset<&'b str>) {
+        self.fbb_
+            .push_slot_always::<flatbuffers::WIPOffset<_>>(HttpCacheEvent::VT_EVENT, event);
+    }
+    #[inline]
+    pub fn add_path(&mut self, path: flatbuffers::WIPOffset<&'b str>) {
+        self.fbb_
+            .push_slot_always::<flatbuffers::WIPOffset<_>>(HttpCacheEvent::VT_PATH, path);
+    }
+    #[inline]
+    pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> HttpCacheEventBuilder<'a, 'b> {
+        let start = _fbb.start_table();
+        HttpCacheEventBuilder {
+            fbb_: _fbb,
+            start_: start,
+        }
+    }
+    #[inline]
+    pub fn finish(self) -> flatbuffers::WIPOffset<HttpCacheEvent<'a>> {
+        let o = self.fbb_.end_table(self.start_);
+        flatbuffers::WIPOffset::new(o.value())
+    }
+}
+
+impl core::fmt::Debug for HttpCacheEvent<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut ds = f.debug_struct("HttpCacheEvent");
+        ds.field("event", &self.event());
+        ds.field("path", &self.path());
+        ds.finish()
+    }
+}
 pub enum HttpResultSetOffset {}
 #[derive(Copy, Clone, PartialEq)]
 
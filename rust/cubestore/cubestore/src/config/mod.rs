@@ -7,6 +7,7 @@ use crate::cachestore::{
     LazyRocksCacheStore,
 };
 use crate::cluster::ingestion::job_processor::{JobProcessor, JobProcessorImpl};
+use crate::cluster::membership::WorkerMembership;
 use crate::cluster::rate_limiter::{BasicProcessRateLimiter, ProcessRateLimiter};
 use crate::cluster::transport::{
     ClusterTransport, ClusterTransportImpl, MetaStoreTransport, MetaStoreTransportImpl,
@@ -23,7 +24,9 @@ use crate::metastore::{
 use crate::mysql::{MySqlServer, SqlAuthDefaultImpl, SqlAuthService};
 use crate::queryplanner::query_executor::{QueryExecutor, QueryExecutorImpl};
 use crate::queryplanner::{QueryPlanner, QueryPlannerImpl};
+use crate::remotefs::azure::AzureBlobRemoteFs;
 use crate::remotefs::cleanup::RemoteFsCleanup;
+use crate::remotefs::encrypted::{EncryptedRemoteFs, EncryptionKeys};
 use crate::remotefs::gcs::GCSRemoteFs;
 use crate::remotefs::minio::MINIORemoteFs;
 use crate::remotefs::queue::QueueRemoteFs;
@@ -290,7 +293,7 @@ pub fn validate_config(c: &dyn ConfigObj) -> ValidationMessages {
             "Router node cannot use remote metastore. Try removing CUBESTORE_META_ADDR".to_string(),
         );
     }
-    if !is_router(c) && !c.select_workers().contains(c.server_name()) {
+    if !is_router(c) && !c.dynamic_workers() && !c.select_workers().contains(c.server_name()) {
         warnings.push(format!("Current worker '{}' is missing in CUBESTORE_WORKERS. Please check CUBESTORE_SERVER_NAME and CUBESTORE_WORKERS variables", c.server_name()));
     }
 
@@ -315,6 +318,7 @@ pub fn validate_config(c: &dyn ConfigObj) -> ValidationMessages {
         "CUBESTORE_MINIO_BUCKET",
         "CUBESTORE_S3_BUCKET",
         "CUBESTORE_GCS_BUCKET",
+        "CUBESTORE_AZURE_CONTAINER",
         "CUBESTORE_REMOTE_DIR",
     ];
     remote_vars.retain(|v| env::var(v).is_ok());
@@ -348,6 +352,11 @@ pub enum FileStoreProvider {
         bucket_name: String,
         sub_path: Option<String>,
     },
+    Azure {
+        account: String,
+        container: String,
+        sub_path: Option<String>,
+    },
 }
 
 #[derive(Clone)]
@@ -400,6 +409,8 @@ pub trait ConfigObj: DIService {
 
     fn status_bind_address(&self) -> &Option<String>;
 
+    fn worker_status_bind_address(&self) -> &Option<String>;
+
     fn http_bind_address(&self) -> &Option<String>;
 
     fn query_timeout(&self) -> u64;
@@ -430,6 +441,18 @@ pub trait ConfigObj: DIService {
 
     fn metastore_remote_address(&self) -> &Option<String>;
 
+    /// Shared secret used to authenticate router, worker and meta store connections.
+    fn cluster_secret(&self) -> &Option<String>;
+
+    /// Allows workers missing in [ConfigObj::select_workers] to join the cluster at runtime.
+    fn dynamic_workers(&self) -> bool;
+
+    fn worker_heartbeat_interval(&self) -> u64;
+
+    fn worker_heartbeat_timeout(&self) -> u64;
+
+    fn worker_membership(&self) -> &Arc<WorkerMembership>;
+
     fn cachestore_rocksdb_config(&self) -> &RocksStoreConfig;
 
     fn cachestore_gc_loop_interval(&self) -> u64;
@@ -464,6 +487,8 @@ pub trait ConfigObj: DIService {
 
     fn cachestore_queue_results_expire(&self) -> u64;
 
+    fn cachestore_queue_requeue_loop_interval(&self) -> u64;
+
     fn cachestore_metrics_interval(&self) -> u64;
 
     fn download_concurrency(&self) -> u64;
@@ -559,12 +584,15 @@ pub struct ConfigObjImpl {
     pub data_dir: PathBuf,
     pub dump_dir: Option<PathBuf>,
     pub store_provider: FileStoreProvider,
+    pub encryption_key_file: Option<PathBuf>,
+    pub encryption_required: bool,
     pub select_worker_pool_size: usize,
     pub select_worker_idle_timeout: u64,
     pub job_runners_count: usize,
     pub long_term_job_runners_count: usize,
     pub bind_address: Option<String>,
     pub status_bind_address: Option<String>,
+    pub worker_status_bind_address: Option<String>,
     pub http_bind_address: Option<String>,
     pub query_timeout: u64,
     /// Must be set to 2*query_timeout in prod, only for overrides in tests.
@@ -580,6 +608,11 @@ pub struct ConfigObjImpl {
     pub worker_bind_address: Option<String>,
     pub metastore_bind_address: Option<String>,
     pub metastore_remote_address: Option<String>,
+    pub cluster_secret: Option<String>,
+    pub dynamic_workers: bool,
+    pub worker_heartbeat_interval: u64,
+    pub worker_heartbeat_timeout: u64,
+    pub worker_membership: Arc<WorkerMembership>,
     pub metastore_rocks_store_config: RocksStoreConfig,
     pub cachestore_rocks_store_config: RocksStoreConfig,
     pub cachestore_gc_loop_interval: u64,
@@ -590,6 +623,7 @@ pub struct ConfigObjImpl {
     pub cachestore_cache_compaction_trigger_size: u64,
     pub cachestore_cache_threshold_to_force_eviction: u8,
     pub cachestore_queue_results_expire: u64,
+    pub cachestore_queue_requeue_loop_interval: u64,
     pub cachestore_metrics_interval: u64,
     pub cachestore_cache_max_keys: u32,
     pub cachestore_cache_policy: CacheEvictionPolicy,
@@ -724,6 +758,10 @@ impl ConfigObj for ConfigObjImpl {
         &self.status_bind_address
     }
 
+    fn worker_status_bind_address(&self) -> &Option<String> {
+        &self.worker_status_bind_address
+    }
+
     fn http_bind_address(&self) -> &Option<String> {
         &self.http_bind_address
     }
@@ -784,6 +822,26 @@ impl ConfigObj for ConfigObjImpl {
         &self.metastore_remote_address
     }
 
+    fn cluster_secret(&self) -> &Option<String> {
+        &self.cluster_secret
+    }
+
+    fn dynamic_workers(&self) -> bool {
+        self.dynamic_workers
+    }
+
+    fn worker_heartbeat_interval(&self) -> u64 {
+        self.worker_heartbeat_interval
+    }
+
+    fn worker_heartbeat_timeout(&self) -> u64 {
+        self.worker_heartbeat_timeout
+    }
+
+    fn worker_membership(&self) -> &Arc<WorkerMembership> {
+        &self.worker_membership
+    }
+
     fn cachestore_rocksdb_config(&self) -> &RocksStoreConfig {
         &self.cachestore_rocks_store_config
     }
@@ -848,6 +906,10 @@ impl ConfigObj for ConfigObjImpl {
         self.cachestore_queue_results_expire
     }
 
+    fn cachestore_queue_requeue_loop_interval(&self) -> u64 {
+        self.cachestore_queue_requeue_loop_interval
+    }
+
     fn cachestore_metrics_interval(&self) -> u64 {
         self.cachestore_metrics_interval
     }
@@ -1273,6 +1335,14 @@ impl Config {
                             bucket_name,
                             sub_path: env::var("CUBESTORE_GCS_SUB_PATH").ok(),
                         }
+                    } else if let Ok(container) = env::var("CUBESTORE_AZURE_CONTAINER") {
+                        FileStoreProvider::Azure {
+                            account: env::var("CUBESTORE_AZURE_ACCOUNT").expect(
+                                "CUBESTORE_AZURE_ACCOUNT required when CUBESTORE_AZURE_CONTAINER is set",
+                            ),
+                            container,
+                            sub_path: env::var("CUBESTORE_AZURE_SUB_PATH").ok(),
+                        }
                     } else if let Ok(remote_dir) = env::var("CUBESTORE_REMOTE_DIR") {
                         FileStoreProvider::Filesystem {
                             remote_dir: Some(PathBuf::from(remote_dir)),
@@ -1281,6 +1351,10 @@ impl Config {
                         FileStoreProvider::Filesystem { remote_dir: None }
                     }
                 },
+                encryption_key_file: env::var("CUBESTORE_ENCRYPTION_KEY_FILE")
+                    .ok()
+                    .map(PathBuf::from),
+                encryption_required: env_bool("CUBESTORE_ENCRYPTION_REQUIRED", true),
                 select_worker_pool_size: env_parse("CUBESTORE_SELECT_WORKERS", 4),
                 select_worker_idle_timeout: env_parse_duration(
                     "CUBESTORE_SELECT_WORKERS_IDLE_TIMEOUT",
@@ -1296,6 +1370,7 @@ impl Config {
                 status_bind_address: Some(env::var("CUBESTORE_STATUS_BIND_ADDR").ok().unwrap_or(
                     format!("0.0.0.0:{}", env_parse("CUBESTORE_STATUS_PORT", 3031)),
                 )),
+                worker_status_bind_address: env::var("CUBESTORE_WORKER_STATUS_BIND_ADDR").ok(),
                 http_bind_address: Some(env::var("CUBESTORE_HTTP_BIND_ADDR").ok().unwrap_or(
                     format!("0.0.0.0:{}", env_parse("CUBESTORE_HTTP_PORT", 3030)),
                 )),
@@ -1324,6 +1399,23 @@ impl Config {
                     env_optparse::<u16>("CUBESTORE_META_PORT").map(|v| format!("0.0.0.0:{}", v))
                 }),
                 metastore_remote_address: env::var("CUBESTORE_META_ADDR").ok(),
+                cluster_secret: env::var("CUBESTORE_CLUSTER_SECRET")
+                    .ok()
+                    .filter(|s| !s.is_empty()),
+                dynamic_workers: env_bool("CUBESTORE_DYNAMIC_WORKERS", false),
+                worker_heartbeat_interval: env_parse_duration(
+                    "CUBESTORE_WORKER_HEARTBEAT_INTERVAL",
+                    5,
+                    Some(60),
+                    Some(1),
+                ),
+                worker_heartbeat_timeout: env_parse_duration(
+                    "CUBESTORE_WORKER_HEARTBEAT_TIMEOUT",
+                    30,
+                    None,
+                    Some(1),
+                ),
+                worker_membership: Arc::new(WorkerMembership::default()),
                 metastore_rocks_store_config: RocksStoreConfig::metastore_default(),
                 cachestore_rocks_store_config: RocksStoreConfig::cachestore_default(),
                 cachestore_gc_loop_interval: env_parse_duration(
@@ -1360,6 +1452,14 @@ impl Config {
                     Some(60 * 5),
                     Some(1),
                 ),
+                cachestore_queue_requeue_loop_interval: env_parse_duration(
+                    "CUBESTORE_QUEUE_REQUEUE_LOOP",
+                    5,
+                    // 5m
+                    Some(5 * 60),
+                    // 0 to disable
+                    Some(0),
+                ),
                 cachestore_metrics_interval: env_parse_duration(
                     "CUBESTORE_CACHESTORE_METRICS_LOOP",
                     15,
@@ -1538,12 +1638,15 @@ impl Config {
                             .join(format!("{}-upstream", name)),
                     ),
                 },
+                encryption_key_file: None,
+                encryption_required: true,
                 select_worker_pool_size: 0,
                 select_worker_idle_timeout: 600,
                 job_runners_count: 4,
                 long_term_job_runners_count: 8,
                 bind_address: None,
                 status_bind_address: None,
+                worker_status_bind_address: None,
                 http_bind_address: None,
                 query_timeout,
                 not_used_timeout: 2 * query_timeout,
@@ -1554,6 +1657,11 @@ impl Config {
                 worker_bind_address: None,
                 metastore_bind_address: None,
                 metastore_remote_address: None,
+                cluster_secret: None,
+                dynamic_workers: false,
+                worker_heartbeat_interval: 1,
+                worker_heartbeat_timeout: 10,
+                worker_membership: Arc::new(WorkerMembership::default()),
                 metastore_rocks_store_config: RocksStoreConfig::metastore_default(),
                 cachestore_rocks_store_config: RocksStoreConfig::cachestore_default(),
                 cachestore_gc_loop_interval: 30,
@@ -1564,6 +1672,7 @@ impl Config {
                 cachestore_cache_compaction_trigger_size: 4096 * 2 << 20,
                 cachestore_cache_threshold_to_force_eviction: 25,
                 cachestore_queue_results_expire: 90,
+                cachestore_queue_requeue_loop_interval: 5,
                 cachestore_metrics_interval: 15,
                 cachestore_cache_max_keys: 100_000,
                 cachestore_cache_policy: CacheEvictionPolicy::SampledLru,
@@ -1753,6 +1862,27 @@ impl Config {
         self.local_dir().join("metastore")
     }
 
+    /// Restores the meta store from a remote snapshot replaying its logs up to `until` (millis).
+    /// `location` is the prefix used in `SYS METASTORE BACKUP TO`, if the snapshot was backed up.
+    pub async fn restore_metastore(
+        &self,
+        snapshot: u128,
+        until: Option<u128>,
+        location: Option<&str>,
+    ) -> Result<u128, CubeError> {
+        let metastore_fs =
+            BaseRocksStoreFs::new_for_metastore(self.remote_fs().await?, self.config_obj());
+        RocksMetaStore::restore_from_remote(
+            &self.meta_store_path(),
+            metastore_fs,
+            self.config_obj(),
+            snapshot,
+            until,
+            location,
+        )
+        .await
+    }
+
     pub fn cache_store_path(&self) -> PathBuf {
         self.local_dir().join("cachestore")
     }
@@ -1763,12 +1893,25 @@ impl Config {
             .register_typed::<dyn ConfigObj, _, _, _>(async move |_| config_obj_to_register)
             .await;
 
+        // With encryption the backend is wrapped and keeps its local files separately.
+        let encryption_keys =
+            self.config_obj.encryption_key_file.as_ref().map(|f| {
+                Arc::new(EncryptionKeys::from_file(f).expect("Can't load encryption keys"))
+            });
+        let (backend_name, backend_dir) = match encryption_keys {
+            Some(_) => (
+                "unencrypted_remote_fs",
+                EncryptedRemoteFs::inner_dir(&self.config_obj.data_dir),
+            ),
+            None => ("original_remote_fs", self.config_obj.data_dir.clone()),
+        };
+
         match &self.config_obj.store_provider {
             FileStoreProvider::Filesystem { remote_dir } => {
                 let remote_dir = remote_dir.clone();
-                let data_dir = self.config_obj.data_dir.clone();
+                let data_dir = backend_dir.clone();
                 self.injector
-                    .register("original_remote_fs", async move |_| {
+                    .register(backend_name, async move |_| {
                         let arc: Arc<dyn DIService> = LocalDirRemoteFs::new(remote_dir, data_dir);
                         arc
                     })
@@ -1779,12 +1922,12 @@ impl Config {
                 bucket_name,
                 sub_path,
             } => {
-                let data_dir = self.config_obj.data_dir.clone();
+                let data_dir = backend_dir.clone();
                 let region = region.to_string();
                 let bucket_name = bucket_name.to_string();
                 let sub_path = sub_path.clone();
                 self.injector
-                    .register("original_remote_fs", async move |_| {
+                    .register(backend_name, async move |_| {
                         let arc: Arc<dyn DIService> =
                             S3RemoteFs::new(data_dir, region, bucket_name, sub_path).unwrap();
                         arc
@@ -1795,11 +1938,11 @@ impl Config {
                 bucket_name,
                 sub_path,
             } => {
-                let data_dir = self.config_obj.data_dir.clone();
+                let data_dir = backend_dir.clone();
                 let bucket_name = bucket_name.to_string();
                 let sub_path = sub_path.clone();
                 self.injector
-                    .register("original_remote_fs", async move |_| {
+                    .register(backend_name, async move |_| {
                         let arc: Arc<dyn DIService> =
                             GCSRemoteFs::new(data_dir, bucket_name, sub_path).unwrap();
                         arc
@@ -1810,19 +1953,52 @@ impl Config {
                 bucket_name,
                 sub_path,
             } => {
-                let data_dir = self.config_obj.data_dir.clone();
+                let data_dir = backend_dir.clone();
                 let bucket_name = bucket_name.to_string();
                 let sub_path = sub_path.clone();
                 self.injector
-                    .register("original_remote_fs", async move |_| {
+                    .register(backend_name, async move |_| {
                         let arc: Arc<dyn DIService> =
                             MINIORemoteFs::new(data_dir, bucket_name, sub_path).unwrap();
                         arc
                     })
                     .await;
             }
+            FileStoreProvider::Azure {
+                account,
+                container,
+                sub_path,
+            } => {
+                let data_dir = backend_dir.clone();
+                let account = account.to_string();
+                let container = container.to_string();
+                let sub_path = sub_path.clone();
+                self.injector
+                    .register(backend_name, async move |_| {
+                        let arc: Arc<dyn DIService> =
+                            AzureBlobRemoteFs::new(data_dir, account, container, sub_path).unwrap();
+                        arc
+                    })
+                    .await;
+            }
             FileStoreProvider::Local => unimplemented!(), // TODO
         };
+
+        if let Some(keys) = encryption_keys {
+            let data_dir = self.config_obj.data_dir.clone();
+            let encryption_required = self.config_obj.encryption_required;
+            self.injector
+                .register("original_remote_fs", async move |i| {
+                    let arc: Arc<dyn DIService> = EncryptedRemoteFs::new(
+                        i.get_service("unencrypted_remote_fs").await,
+                        data_dir,
+                        keys,
+                        encryption_required,
+                    );
+                    arc
+                })
+                .await;
+        }
     }
 
     pub async fn configure_cache_store(&self) {
@@ -2216,10 +2392,16 @@ impl Config {
             self.injector
                 .register_typed::<HttpServer, _, _, _>(async move |i| {
                     let config = i.get_service_typed::<dyn ConfigObj>().await;
+                    let cache_store = if i.has_service_typed::<LazyRocksCacheStore>().await {
+                        Some(i.get_service_typed().await)
+                    } else {
+                        None
+                    };
                     HttpServer::new(
                         config.http_bind_address().as_ref().unwrap().to_string(),
                         i.get_service_typed().await,
                         i.get_service_typed().await,
+                        cache_store,
                         Duration::from_secs(config.check_ws_orphaned_messages_interval_secs()),
                         Duration::from_secs(config.drop_ws_processing_messages_after_secs()),
                         Duration::from_secs(config.drop_ws_complete_messages_after_secs()),
@@ -4,13 +4,15 @@ use std::sync::Arc;
 
 use warp::{Filter, Rejection, Reply};
 
+use crate::cachestore::LazyRocksCacheStore;
 use crate::codegen::{
-    root_as_http_message, HttpColumnValue, HttpColumnValueArgs, HttpError, HttpErrorArgs,
-    HttpMessageArgs, HttpQuery, HttpQueryArgs, HttpResultSet, HttpResultSetArgs, HttpRow,
-    HttpRowArgs,
+    root_as_http_message, HttpCacheEvent, HttpCacheEventArgs, HttpColumnValue, HttpColumnValueArgs,
+    HttpError, HttpErrorArgs, HttpMessageArgs, HttpQuery, HttpQueryArgs, HttpResultSet,
+    HttpResultSetArgs, HttpRow, HttpRowArgs,
 };
 use crate::metastore::{Column, ColumnType, ImportFormat};
 use crate::mysql::SqlAuthService;
+use crate::sql::parser::{CacheCommand, CubeStoreParser, Statement};
 use crate::sql::{InlineTable, InlineTables, SqlQueryContext, SqlService};
 use crate::store::DataFrame;
 use crate::table::{Row, TableValue};
@@ -44,6 +46,7 @@ pub struct HttpServer {
     bind_address: String,
     sql_service: Arc<dyn SqlService>,
     auth: Arc<dyn SqlAuthService>,
+    cache_store: Option<Arc<LazyRocksCacheStore>>,
     check_orphaned_messages_interval: Duration,
     drop_processing_messages_after: Duration,
     drop_complete_messages_after: Duration,
@@ -80,6 +83,7 @@ impl HttpServer {
         bind_address: String,
         auth: Arc<dyn SqlAuthService>,
         sql_service: Arc<dyn SqlService>,
+        cache_store: Option<Arc<LazyRocksCacheStore>>,
         check_orphaned_messages_interval: Duration,
         drop_processing_messages_after: Duration,
         drop_complete_messages_after: Duration,
@@ -90,6 +94,7 @@ impl HttpServer {
             bind_address,
             auth,
             sql_service,
+            cache_store,
             check_orphaned_messages_interval,
             drop_processing_messages_after,
             drop_complete_messages_after,
@@ -232,7 +237,12 @@ impl HttpServer {
             HashMap::<(Option<String>, u32), ProcessingState>::new(),
         ));
         let process_loop = self.worker_loop.process_channel(
-            Arc::new((sql_service, messages_state.clone())),
+            Arc::new((
+                sql_service,
+                messages_state.clone(),
+                self.cache_store.clone(),
+                self.cancel_token.clone(),
+            )),
             &mut rx,
             async move |service,
                         (
@@ -244,10 +254,24 @@ impl HttpServer {
                     command,
                 },
             )| {
-                let (sql_service, messages_state) = service.as_ref();
+                let (sql_service, messages_state, cache_store, cancel_token) = service.as_ref();
                 let sql_service = sql_service.clone();
                 let messages_state = messages_state.clone();
-                if connection_id.is_some() {
+                if let Some(prefix) = command.cache_subscribe_prefix() {
+                    let cache_store = cache_store.clone();
+                    let cancel_token = cancel_token.child_token();
+                    cube_ext::spawn(async move {
+                        HttpServer::process_cache_subscription(
+                            cache_store,
+                            cancel_token,
+                            sender,
+                            message_id,
+                            connection_id,
+                            prefix,
+                        )
+                        .await
+                    });
+                } else if connection_id.is_some() {
                     cube_ext::spawn(async move {
                         let key = (connection_id.clone(), message_id);
                         {
@@ -541,6 +565,93 @@ impl HttpServer {
         }
     }
 
+    /// Subscription is answered by an empty result set as soon as it's registered. After that
+    /// every matching change is pushed as a separate message with the same `message_id` until
+    /// the socket is closed.
+    async fn process_cache_subscription(
+        cache_store: Option<Arc<LazyRocksCacheStore>>,
+        cancel_token: CancellationToken,
+        sender: Sender<Arc<HttpMessage>>,
+        message_id: u32,
+        connection_id: Option<String>,
+        prefix: String,
+    ) {
+        let res = HttpServer::stream_cache_changes(
+            cache_store,
+            cancel_token,
+            &sender,
+            message_id,
+            connection_id.clone(),
+            prefix,
+        )
+        .await;
+        if let Err(e) = res {
+            log::error!(
+                "Error processing cache subscription: {}\n",
+                e.display_with_backtrace()
+            );
+            let message = Arc::new(HttpMessage {
+                message_id,
+                connection_id,
+                command: HttpCommand::Error {
+                    error: e.to_string(),
+                },
+            });
+            if let Err(e) = sender.send(message).await {
+                trace!("Websocket is closed. Dropping subscription error: {}", e);
+            }
+        }
+    }
+
+    async fn stream_cache_changes(
+        cache_store: Option<Arc<LazyRocksCacheStore>>,
+        cancel_token: CancellationToken,
+        sender: &Sender<Arc<HttpMessage>>,
+        message_id: u32,
+        connection_id: Option<String>,
+        prefix: String,
+    ) -> Result<(), CubeError> {
+        let cache_store = cache_store.ok_or_else(|| {
+            CubeError::user("CACHE SUBSCRIBE is not supported on this node".to_string())
+        })?;
+        // Listener should be opened before the acknowledgement to not miss any event
+        let mut listener = cache_store.get_change_listener().await?;
+
+        let ack = Arc::new(HttpMessage {
+            message_id,
+            connection_id: connection_id.clone(),
+            command: HttpCommand::ResultSet {
+                data_frame: Arc::new(DataFrame::new(vec![], vec![])),
+            },
+        });
+        if sender.send(ack).await.is_err() {
+            return Ok(());
+        }
+
+        loop {
+            let event = tokio::select! {
+                _ = cancel_token.cancelled() => return Ok(()),
+                _ = sender.closed() => {
+                    trace!("Websocket is closed. Dropping cache subscription for '{}' prefix", prefix);
+                    return Ok(());
+                }
+                event = listener.wait_for_cache_change(&prefix) => event?,
+            };
+
+            let message = Arc::new(HttpMessage {
+                message_id,
+                connection_id: connection_id.clone(),
+                command: HttpCommand::CacheEvent {
+                    event: event.kind.to_string(),
+                    path: event.path,
+                },
+            });
+            if sender.send(message).await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+
     pub async fn authorize(
         auth: Arc<dyn SqlAuthService>,
         auth_header: Option<String>,
@@ -595,6 +706,34 @@ pub enum HttpCommand {
     Error {
         error: String,
     },
+    CacheEvent {
+        event: String,
+        path: String,
+    },
+}
+
+impl HttpCommand {
+    /// Returns the prefix if the command is a `CACHE SUBSCRIBE` query.
+    pub fn cache_subscribe_prefix(&self) -> Option<String> {
+        let query = match self {
+            HttpCommand::Query { query, .. } => query,
+            _ => return None,
+        };
+        // Don't parse every query twice
+        if !query
+            .trim_start()
+            .get(..5)
+            .map(|s| s.eq_ignore_ascii_case("cache"))
+            .unwrap_or(false)
+        {
+            return None;
+        }
+
+        match CubeStoreParser::new(query).and_then(|mut p| p.parse_statement()) {
+            Ok(Statement::Cache(CacheCommand::Subscribe { prefix })) => Some(prefix.value),
+            _ => None,
+        }
+    }
 }
 
 impl HttpMessage {
@@ -608,6 +747,7 @@ impl HttpMessage {
                 HttpCommand::CloseConnection { .. } | HttpCommand::Error { .. } => {
                     crate::codegen::HttpCommand::HttpError
                 }
+                HttpCommand::CacheEvent { .. } => crate::codegen::HttpCommand::HttpCacheEvent,
             },
             command: match &self.command {
                 HttpCommand::Query {
@@ -644,6 +784,20 @@ impl HttpMessage {
                         .as_union_value(),
                     )
                 }
+                HttpCommand::CacheEvent { event, path } => {
+                    let event_offset = builder.create_string(&event);
+                    let path_offset = builder.create_string(&path);
+                    Some(
+                        HttpCacheEvent::create(
+                            &mut builder,
+                            &HttpCacheEventArgs {
+                                event: Some(event_offset),
+                                path: Some(path_offset),
+                            },
+                        )
+                        .as_union_value(),
+                    )
+                }
                 HttpCommand::ResultSet { data_frame } => {
                     let columns_vec =
                         HttpMessage::build_columns(&mut builder, data_frame.get_columns());
@@ -844,6 +998,13 @@ impl HttpMessage {
                         data_frame: Arc::new(DataFrame::new(result_columns, result_rows)),
                     }
                 }
+                crate::codegen::HttpCommand::HttpCacheEvent => {
+                    let cache_event = http_message.command_as_http_cache_event().unwrap();
+                    HttpCommand::CacheEvent {
+                        event: cache_event.event().unwrap_or_default().to_string(),
+                        path: cache_event.path().unwrap_or_default().to_string(),
+                    }
+                }
                 command => {
                     return Err(CubeError::internal(format!(
                         "Unexpected command: {:?}",
@@ -908,6 +1069,41 @@ mod tests {
         assert_eq!(message, output_message);
     }
 
+    #[tokio::test]
+    async fn cache_event_test() {
+        let message = HttpMessage {
+            message_id: 1234,
+            command: HttpCommand::CacheEvent {
+                event: "set".to_string(),
+                path: "invalidation:key".to_string(),
+            },
+            connection_id: Some("foo".to_string()),
+        };
+        let bytes = message.bytes();
+        let output_message = HttpMessage::read(bytes).await.unwrap();
+        assert_eq!(message, output_message);
+    }
+
+    #[test]
+    fn cache_subscribe_prefix_test() {
+        let query = |query: &str| HttpCommand::Query {
+            query: query.to_string(),
+            inline_tables: vec![],
+            trace_obj: None,
+        };
+
+        assert_eq!(
+            query("CACHE SUBSCRIBE 'invalidation:'").cache_subscribe_prefix(),
+            Some("invalidation:".to_string())
+        );
+        assert_eq!(
+            query("  cache subscribe 'invalidation:'").cache_subscribe_prefix(),
+            Some("invalidation:".to_string())
+        );
+        assert_eq!(query("CACHE GET 'key'").cache_subscribe_prefix(), None);
+        assert_eq!(query("SELECT 1").cache_subscribe_prefix(), None);
+    }
+
     #[tokio::test]
     async fn inline_tables_query_test() {
         let columns = vec![
@@ -1071,6 +1267,7 @@ mod tests {
             "127.0.0.1:53031".to_string(),
             Arc::new(auth),
             Arc::new(sql_service),
+            None,
             Duration::from_millis(100),
             Duration::from_millis(10000),
             Duration::from_millis(1000),
@@ -2,36 +2,59 @@ use crate::config::injection::Injector;
 use crate::config::{is_router, uses_remote_metastore, Config};
 use crate::metastore::MetaStore;
 use crate::sql::SqlService;
-use crate::CubeError;
+use crate::util::prometheus;
+use crate::{app_metrics, CubeError};
 use std::convert::Infallible;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use warp::http::StatusCode;
-use warp::Filter;
+use warp::{Filter, Reply};
 
 pub fn serve_status_probes(c: &Config) {
-    let addr = match c.config_obj().status_bind_address() {
-        Some(a) => a.clone(),
-        None => return,
+    // Workers often share a host, so they only serve metrics on an explicitly configured address.
+    let config = c.config_obj();
+    let addr = if is_router(config.as_ref()) {
+        config.status_bind_address()
+    } else {
+        config.worker_status_bind_address()
     };
-
-    let p = match RouterProbes::try_new(c) {
-        Some(p) => p,
+    let addr = match addr {
+        Some(a) => a.clone(),
         None => return,
     };
 
-    let pc = p.clone();
-    let l = warp::path!("livez").and_then(move || {
-        let pc = pc.clone();
-        async move { status_probe_reply("liveness", pc.is_live().await) }
-    });
-    let r = warp::path!("readyz").and_then(move || {
-        let p = p.clone();
-        async move { status_probe_reply("readiness", p.is_ready().await) }
+    app_metrics::register_all();
+    // Metrics are served by every node, probes only by the router.
+    let metrics = warp::path!("metrics").map(|| -> Box<dyn Reply> {
+        Box::new(warp::reply::with_header(
+            prometheus::render(),
+            "content-type",
+            "text/plain; version=0.0.4",
+        ))
     });
+    let routes = match RouterProbes::try_new(c) {
+        Some(p) => {
+            let pc = p.clone();
+            let l = warp::path!("livez").and_then(move || {
+                let pc = pc.clone();
+                async move { status_probe_reply("liveness", pc.is_live().await) }
+            });
+            let r = warp::path!("readyz").and_then(move || {
+                let p = p.clone();
+                async move { status_probe_reply("readiness", p.is_ready().await) }
+            });
+            l.or(r)
+                .unify()
+                .map(|s: StatusCode| -> Box<dyn Reply> { Box::new(s) })
+                .or(metrics)
+                .unify()
+                .boxed()
+        }
+        None => metrics.boxed(),
+    };
 
     let addr: SocketAddr = addr.parse().expect("cannot parse status probe address");
-    match warp::serve(l.or(r)).try_bind_ephemeral(addr) {
+    match warp::serve(routes).try_bind_ephemeral(addr) {
         Ok((addr, f)) => {
             log::info!("Serving status probes at {}", addr);
             tokio::spawn(f);
@@ -0,0 +1,126 @@
+use std::pin::Pin;
+
+use async_compression::tokio::bufread::{
+    BzDecoder, DeflateDecoder, GzipDecoder, XzDecoder, ZstdDecoder,
+};
+use tokio::io::{AsyncBufRead, BufReader};
+
+/// Compression of an import location, detected by its file extension.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ImportCompression {
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
+    Deflate,
+}
+
+impl ImportCompression {
+    pub fn from_location(location: &str) -> Option<ImportCompression> {
+        // Signed URLs carry parameters after the file name.
+        let path = if location.starts_with("http") {
+            location.split('?').next().unwrap_or(location)
+        } else {
+            location
+        };
+        let extension = path.rsplit('.').next()?.to_lowercase();
+        match extension.as_str() {
+            "gz" | "gzip" => Some(ImportCompression::Gzip),
+            "zst" | "zstd" => Some(ImportCompression::Zstd),
+            "bz2" => Some(ImportCompression::Bzip2),
+            "xz" => Some(ImportCompression::Xz),
+            "deflate" => Some(ImportCompression::Deflate),
+            _ => None,
+        }
+    }
+
+    pub fn decoder<'a>(
+        &self,
+        reader: impl AsyncBufRead + Send + 'a,
+    ) -> Pin<Box<dyn AsyncBufRead + Send + 'a>> {
+        match self {
+            ImportCompression::Gzip => Box::pin(BufReader::new(GzipDecoder::new(reader))),
+            ImportCompression::Zstd => Box::pin(BufReader::new(ZstdDecoder::new(reader))),
+            ImportCompression::Bzip2 => Box::pin(BufReader::new(BzDecoder::new(reader))),
+            ImportCompression::Xz => Box::pin(BufReader::new(XzDecoder::new(reader))),
+            ImportCompression::Deflate => Box::pin(BufReader::new(DeflateDecoder::new(reader))),
+        }
+    }
+
+    /// Typical ratio of uncompressed to compressed size for exported CSV data.
+    pub fn size_ratio(&self) -> u64 {
+        match self {
+            ImportCompression::Gzip | ImportCompression::Deflate => 5,
+            ImportCompression::Zstd => 6,
+            ImportCompression::Bzip2 => 7,
+            ImportCompression::Xz => 8,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_compression::tokio::bufread::{
+        BzEncoder, DeflateEncoder, GzipEncoder, XzEncoder, ZstdEncoder,
+    };
+    use tokio::io::AsyncReadExt;
+
+    #[test]
+    fn detect_compression() {
+        let detect = ImportCompression::from_location;
+        assert_eq!(detect("/data/orders.csv"), None);
+        assert_eq!(detect("/data/orders.csv.gz"), Some(ImportCompression::Gzip));
+        assert_eq!(
+            detect("temp://orders.csv.ZST"),
+            Some(ImportCompression::Zstd)
+        );
+        assert_eq!(
+            detect("/data/orders.csv.bz2"),
+            Some(ImportCompression::Bzip2)
+        );
+        assert_eq!(detect("/data/orders.csv.xz"), Some(ImportCompression::Xz));
+        assert_eq!(
+            detect("/data/orders.csv.deflate"),
+            Some(ImportCompression::Deflate)
+        );
+        assert_eq!(
+            detect("https://bucket.s3.amazonaws.com/orders.csv.zst?X-Amz-Signature=a.b"),
+            Some(ImportCompression::Zstd)
+        );
+        assert_eq!(
+            detect("https://example.com/export?file=orders.csv.gz"),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn decode() {
+        let data = "apple,2\nbanana,3\n".repeat(100);
+        let compressed = |c: ImportCompression| -> Pin<Box<dyn AsyncBufRead + Send>> {
+            let input = data.as_bytes().to_vec();
+            let input = std::io::Cursor::new(input);
+            match c {
+                ImportCompression::Gzip => Box::pin(BufReader::new(GzipEncoder::new(input))),
+                ImportCompression::Zstd => Box::pin(BufReader::new(ZstdEncoder::new(input))),
+                ImportCompression::Bzip2 => Box::pin(BufReader::new(BzEncoder::new(input))),
+                ImportCompression::Xz => Box::pin(BufReader::new(XzEncoder::new(input))),
+                ImportCompression::Deflate => Box::pin(BufReader::new(DeflateEncoder::new(input))),
+            }
+        };
+        for c in [
+            ImportCompression::Gzip,
+            ImportCompression::Zstd,
+            ImportCompression::Bzip2,
+            ImportCompression::Xz,
+            ImportCompression::Deflate,
+        ] {
+            let mut decoded = String::new();
+            c.decoder(compressed(c))
+                .read_to_string(&mut decoded)
+                .await
+                .unwrap();
+            assert_eq!(decoded, data, "{:?}", c);
+        }
+    }
+}
@@ -0,0 +1,532 @@
+//! Typed import formats. Unlike CSV, values of these formats carry their own types, so file
+//! columns are checked against table columns before conversion.
+use std::io::{Read, Seek, SeekFrom};
+use std::pin::Pin;
+use std::sync::Arc;
+
+use arrow::array::{
+    Array, ArrayRef, BinaryArray, Float64Array, Int32Array, Int64Array, StringArray, UInt64Array,
+};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, TimeUnit};
+use arrow::ipc::reader::{FileReader, StreamReader};
+use arrow::record_batch::RecordBatch;
+use cubehll::HllSketch;
+use datafusion::cube_ext;
+use datafusion::cube_ext::ordfloat::OrdF64;
+use futures::{Stream, StreamExt};
+use itertools::Itertools;
+use parquet::arrow::{ArrowReader, ParquetFileArrowReader};
+use parquet::file::reader::SerializedFileReader;
+use serde_json::Value;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+use tokio_stream::wrappers::{LinesStream, ReceiverStream};
+
+use crate::metastore::{is_valid_plain_binary_hll, Column, ColumnType, HllFlavour, ImportFormat};
+use crate::table::{Row, TableValue, TimestampValue};
+use crate::util::int96::Int96;
+use crate::CubeError;
+
+/// Rows are read from Parquet and Arrow files in batches of this size.
+const IMPORT_BATCH_SIZE: usize = 4096;
+
+/// Parquet and Arrow readers are blocking and need a seekable file. Batches are sent to the
+/// import one by one, so memory usage doesn't depend on the file size.
+pub fn columnar_row_stream(
+    format: ImportFormat,
+    file: std::fs::File,
+    columns: Vec<Column>,
+) -> Pin<Box<dyn Stream<Item = Result<Option<Row>, CubeError>> + Send>> {
+    let (tx, rx) = tokio::sync::mpsc::channel(1);
+    cube_ext::spawn_blocking(move || {
+        let read = || -> Result<(), CubeError> {
+            for batch in record_batch_reader(format, file)? {
+                if tx.blocking_send(batch.map_err(|e| e.into())).is_err() {
+                    // Import was cancelled.
+                    return Ok(());
+                }
+            }
+            Ok(())
+        };
+        if let Err(e) = read() {
+            let _ = tx.blocking_send(Err(e));
+        }
+    });
+    ReceiverStream::new(rx)
+        .flat_map(move |batch| {
+            let rows = match batch.and_then(|b| batch_rows(&b, &columns)) {
+                Ok(rows) => rows.into_iter().map(|r| Ok(Some(r))).collect_vec(),
+                Err(e) => vec![Err(e)],
+            };
+            futures::stream::iter(rows)
+        })
+        .boxed()
+}
+
+fn record_batch_reader(
+    format: ImportFormat,
+    mut file: std::fs::File,
+) -> Result<Box<dyn Iterator<Item = arrow::error::Result<RecordBatch>>>, CubeError> {
+    Ok(match format {
+        ImportFormat::Parquet => {
+            let mut reader =
+                ParquetFileArrowReader::new(Arc::new(SerializedFileReader::new(file)?));
+            Box::new(reader.get_record_reader(IMPORT_BATCH_SIZE)?)
+        }
+        ImportFormat::Arrow => {
+            // Files start with a magic string, streams don't.
+            let mut magic = [0u8; 6];
+            file.seek(SeekFrom::Start(0))?;
+            let is_file = file.read(&mut magic)? == magic.len() && &magic == b"ARROW1";
+            file.seek(SeekFrom::Start(0))?;
+            if is_file {
+                Box::new(FileReader::try_new(file)?)
+            } else {
+                Box::new(StreamReader::try_new(file)?)
+            }
+        }
+        f => {
+            return Err(CubeError::internal(format!(
+                "{:?} is not a columnar import format",
+                f
+            )))
+        }
+    })
+}
+
+fn batch_rows(batch: &RecordBatch, columns: &[Column]) -> Result<Vec<Row>, CubeError> {
+    let mut rows = vec![vec![TableValue::Null; columns.len()]; batch.num_rows()];
+    for (field, array) in batch.schema().fields().iter().zip(batch.columns()) {
+        let (insert_pos, column) = find_column(columns, field.name())?;
+        for (row, value) in rows.iter_mut().zip(array_values(column, array)?) {
+            row[insert_pos] = value;
+        }
+    }
+    Ok(rows.into_iter().map(|r| Row::new(r)).collect())
+}
+
+fn find_column<'a>(columns: &'a [Column], name: &str) -> Result<(usize, &'a Column), CubeError> {
+    columns
+        .iter()
+        .find_position(|c| c.get_name() == name)
+        .ok_or_else(|| {
+            CubeError::user(format!(
+                "Column '{}' is not found during import in {:?}",
+                name, columns
+            ))
+        })
+}
+
+fn array_values(column: &Column, array: &ArrayRef) -> Result<Vec<TableValue>, CubeError> {
+    let is_integer = |t: &DataType| {
+        matches!(
+            t,
+            DataType::Int8
+                | DataType::Int16
+                | DataType::Int32
+                | DataType::Int64
+                | DataType::UInt8
+                | DataType::UInt16
+                | DataType::UInt32
+                | DataType::UInt64
+        )
+    };
+    let is_float = |t: &DataType| matches!(t, DataType::Float32 | DataType::Float64);
+    let values = |array: &dyn Array, f: &dyn Fn(usize) -> Result<TableValue, CubeError>| {
+        (0..array.len())
+            .map(|i| {
+                if array.is_null(i) {
+                    Ok(TableValue::Null)
+                } else {
+                    f(i)
+                }
+            })
+            .collect::<Result<Vec<_>, CubeError>>()
+    };
+
+    let data_type = array.data_type();
+    match (column.get_column_type(), data_type) {
+        (_, DataType::Utf8 | DataType::LargeUtf8) => {
+            let a = cast(array, &DataType::Utf8)?;
+            let a = a.as_any().downcast_ref::<StringArray>().unwrap();
+            values(a, &|i| {
+                ImportFormat::parse_column_value_str(column, a.value(i)).map_err(|e| {
+                    CubeError::user(format!(
+                        "Can't parse '{}' column value for '{}' column: {}",
+                        a.value(i),
+                        column.get_name(),
+                        e
+                    ))
+                })
+            })
+        }
+        (ColumnType::Int | ColumnType::Int96, DataType::UInt64) => {
+            let a = array.as_any().downcast_ref::<UInt64Array>().unwrap();
+            values(a, &|i| {
+                let value = a.value(i);
+                match column.get_column_type() {
+                    ColumnType::Int96 => Ok(TableValue::Int96(Int96::new(value as i128))),
+                    // Casting to Int64 would turn values above i64::MAX into NULL.
+                    _ => i64::try_from(value).map(TableValue::Int).map_err(|_| {
+                        CubeError::user(format!(
+                            "Value {} of '{}' column is out of the int range",
+                            value,
+                            column.get_name()
+                        ))
+                    }),
+                }
+            })
+        }
+        (ColumnType::Int | ColumnType::Int96, t) if is_integer(t) => {
+            let a = cast(array, &DataType::Int64)?;
+            let a = a.as_any().downcast_ref::<Int64Array>().unwrap();
+            values(a, &|i| {
+                Ok(match column.get_column_type() {
+                    ColumnType::Int96 => TableValue::Int96(Int96::new(a.value(i) as i128)),
+                    _ => TableValue::Int(a.value(i)),
+                })
+            })
+        }
+        (ColumnType::Float, t) if is_integer(t) || is_float(t) => {
+            let a = cast(array, &DataType::Float64)?;
+            let a = a.as_any().downcast_ref::<Float64Array>().unwrap();
+            values(a, &|i| Ok(TableValue::Float(OrdF64(a.value(i)))))
+        }
+        (ColumnType::Decimal { .. } | ColumnType::Decimal96 { .. }, t)
+            if is_integer(t) || is_float(t) =>
+        {
+            let a = cast(array, &DataType::Utf8)?;
+            let a = a.as_any().downcast_ref::<StringArray>().unwrap();
+            values(a, &|i| {
+                ImportFormat::parse_column_value_str(column, a.value(i))
+            })
+        }
+        (ColumnType::Boolean, DataType::Boolean) => values(array.as_ref(), &|i| {
+            Ok(TableValue::from_array(array.as_ref(), i))
+        }),
+        (ColumnType::Timestamp, DataType::Timestamp(unit, _)) => {
+            let nanos_multiplier = match unit {
+                TimeUnit::Second => 1_000_000_000,
+                TimeUnit::Millisecond => 1_000_000,
+                TimeUnit::Microsecond => 1_000,
+                TimeUnit::Nanosecond => 1,
+            };
+            let a = cast(array, &DataType::Int64)?;
+            let a = a.as_any().downcast_ref::<Int64Array>().unwrap();
+            values(a, &|i| {
+                timestamp_value(column, a.value(i), nanos_multiplier)
+            })
+        }
+        (ColumnType::Timestamp, DataType::Date32) => {
+            let a = cast(array, &DataType::Int32)?;
+            let a = a.as_any().downcast_ref::<Int32Array>().unwrap();
+            values(a, &|i| {
+                timestamp_value(column, a.value(i) as i64, 86_400_000_000_000)
+            })
+        }
+        (ColumnType::Timestamp, DataType::Date64) => {
+            let a = cast(array, &DataType::Int64)?;
+            let a = a.as_any().downcast_ref::<Int64Array>().unwrap();
+            values(a, &|i| timestamp_value(column, a.value(i), 1_000_000))
+        }
+        (
+            ColumnType::Bytes
+            | ColumnType::HyperLogLog(
+                HllFlavour::Airlift | HllFlavour::ZetaSketch | HllFlavour::Postgres,
+            ),
+            DataType::Binary,
+        ) => {
+            let a = array.as_any().downcast_ref::<BinaryArray>().unwrap();
+            values(a, &|i| binary_value(column, a.value(i)))
+        }
+        (column_type, data_type) => Err(CubeError::user(format!(
+            "Column '{}' of type {} can't be imported from {:?}",
+            column.get_name(),
+            column_type,
+            data_type
+        ))),
+    }
+}
+
+/// Values outside of the nanosecond timestamp range are valid in Arrow and Parquet, but can't be
+/// stored.
+fn timestamp_value(
+    column: &Column,
+    value: i64,
+    nanos_multiplier: i64,
+) -> Result<TableValue, CubeError> {
+    let nanos = value.checked_mul(nanos_multiplier).ok_or_else(|| {
+        CubeError::user(format!(
+            "Value {} of '{}' column is out of the timestamp range",
+            value,
+            column.get_name()
+        ))
+    })?;
+    Ok(TableValue::Timestamp(TimestampValue::new(nanos)))
+}
+
+fn binary_value(column: &Column, data: &[u8]) -> Result<TableValue, CubeError> {
+    Ok(match column.get_column_type() {
+        ColumnType::HyperLogLog(HllFlavour::Postgres) => {
+            TableValue::Bytes(HllSketch::read_hll_storage_spec(data)?.write())
+        }
+        ColumnType::HyperLogLog(f) => {
+            is_valid_plain_binary_hll(data, *f)?;
+            TableValue::Bytes(data.to_vec())
+        }
+        _ => TableValue::Bytes(data.to_vec()),
+    })
+}
+
+pub fn ndjson_row_stream<'a>(
+    reader: Pin<Box<dyn AsyncBufRead + Send + 'a>>,
+    columns: Vec<Column>,
+) -> Pin<Box<dyn Stream<Item = Result<Option<Row>, CubeError>> + Send + 'a>> {
+    LinesStream::new(reader.lines())
+        .map(move |line| -> Result<Option<Row>, CubeError> {
+            let line = line?;
+            if line.trim().is_empty() {
+                return Ok(None);
+            }
+            let object = match serde_json::from_str::<Value>(&line)? {
+                Value::Object(object) => object,
+                _ => {
+                    return Err(CubeError::user(format!(
+                        "JSON object is expected in NDJSON import: {}",
+                        line
+                    )))
+                }
+            };
+            let mut row = vec![TableValue::Null; columns.len()];
+            for (name, value) in object.iter() {
+                let (insert_pos, column) = find_column(&columns, name)?;
+                row[insert_pos] = json_value(column, value)?;
+            }
+            Ok(Some(Row::new(row)))
+        })
+        .boxed()
+}
+
+fn json_value(column: &Column, value: &Value) -> Result<TableValue, CubeError> {
+    let column_type = column.get_column_type();
+    let parsed = match (column_type, value) {
+        (_, Value::Null) => Some(TableValue::Null),
+        (_, Value::String(s)) => Some(ImportFormat::parse_column_value_str(column, s)?),
+        (ColumnType::Boolean, Value::Bool(b)) => Some(TableValue::Boolean(*b)),
+        (ColumnType::Int, Value::Number(n)) => n.as_i64().map(|v| TableValue::Int(v)),
+        (ColumnType::Float, Value::Number(n)) => n.as_f64().map(|v| TableValue::Float(OrdF64(v))),
+        (
+            ColumnType::Int96 | ColumnType::Decimal { .. } | ColumnType::Decimal96 { .. },
+            Value::Number(n),
+        ) => Some(ImportFormat::parse_column_value_str(
+            column,
+            &n.to_string(),
+        )?),
+        _ => None,
+    };
+    parsed.ok_or_else(|| {
+        CubeError::user(format!(
+            "Column '{}' of type {} can't be imported from JSON value {}",
+            column.get_name(),
+            column_type,
+            value
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{
+        BooleanArray, Date32Array, Float32Array, Int16Array, TimestampMillisecondArray,
+        TimestampSecondArray,
+    };
+    use arrow::datatypes::{Field, Schema};
+    use arrow::ipc::writer::FileWriter;
+    use parquet::arrow::ArrowWriter;
+    use tokio::io::BufReader;
+
+    fn columns() -> Vec<Column> {
+        vec![
+            Column::new("id".to_string(), ColumnType::Int, 0),
+            Column::new("name".to_string(), ColumnType::String, 1),
+            Column::new("price".to_string(), ColumnType::Float, 2),
+            Column::new("ts".to_string(), ColumnType::Timestamp, 3),
+            Column::new("flag".to_string(), ColumnType::Boolean, 4),
+        ]
+    }
+
+    fn batch() -> RecordBatch {
+        // Columns are stored in a different order and types than in the table.
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("name", DataType::Utf8, true),
+            Field::new("id", DataType::Int16, false),
+            Field::new("price", DataType::Float32, true),
+            Field::new("ts", DataType::Timestamp(TimeUnit::Millisecond, None), true),
+        ]));
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(StringArray::from(vec![Some("a"), None])),
+                Arc::new(Int16Array::from(vec![1, 2])),
+                Arc::new(Float32Array::from(vec![Some(1.5), None])),
+                Arc::new(TimestampMillisecondArray::from(vec![Some(1000), None])),
+            ],
+        )
+        .unwrap()
+    }
+
+    fn expected_rows() -> Vec<Row> {
+        vec![
+            Row::new(vec![
+                TableValue::Int(1),
+                TableValue::String("a".to_string()),
+                TableValue::Float(OrdF64(1.5)),
+                TableValue::Timestamp(TimestampValue::new(1_000_000_000)),
+                TableValue::Null,
+            ]),
+            Row::new(vec![
+                TableValue::Int(2),
+                TableValue::Null,
+                TableValue::Null,
+                TableValue::Null,
+                TableValue::Null,
+            ]),
+        ]
+    }
+
+    async fn collect_rows(
+        mut stream: Pin<Box<dyn Stream<Item = Result<Option<Row>, CubeError>> + Send + '_>>,
+    ) -> Result<Vec<Row>, CubeError> {
+        let mut rows = Vec::new();
+        while let Some(row) = stream.next().await {
+            if let Some(row) = row? {
+                rows.push(row);
+            }
+        }
+        Ok(rows)
+    }
+
+    #[tokio::test]
+    async fn import_parquet() {
+        let file = tempfile::tempfile().unwrap();
+        let mut w =
+            ArrowWriter::try_new(file.try_clone().unwrap(), batch().schema(), None).unwrap();
+        w.write(&batch()).unwrap();
+        w.close().unwrap();
+
+        let rows = collect_rows(columnar_row_stream(ImportFormat::Parquet, file, columns()))
+            .await
+            .unwrap();
+        assert_eq!(rows, expected_rows());
+    }
+
+    #[tokio::test]
+    async fn import_arrow() {
+        let mut file = tempfile::tempfile().unwrap();
+        let mut w = FileWriter::try_new(file.try_clone().unwrap(), &batch().schema()).unwrap();
+        w.write(&batch()).unwrap();
+        w.finish().unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+
+        let rows = collect_rows(columnar_row_stream(ImportFormat::Arrow, file, columns()))
+            .await
+            .unwrap();
+        assert_eq!(rows, expected_rows());
+    }
+
+    #[test]
+    fn import_type_mismatch() {
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "id",
+            DataType::Boolean,
+            false,
+        )]));
+        let batch =
+            RecordBatch::try_new(schema, vec![Arc::new(BooleanArray::from(vec![true]))]).unwrap();
+        let r = batch_rows(&batch, &columns());
+        assert!(r.is_err(), "boolean can't be imported as int");
+
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "unknown",
+            DataType::Int64,
+            false,
+        )]));
+        let batch =
+            RecordBatch::try_new(schema, vec![Arc::new(Int64Array::from(vec![1]))]).unwrap();
+        assert!(batch_rows(&batch, &columns()).is_err());
+    }
+
+    #[test]
+    fn import_timestamp_out_of_range() {
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "ts",
+            DataType::Timestamp(TimeUnit::Second, None),
+            false,
+        )]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![Arc::new(TimestampSecondArray::from(vec![i64::MAX / 1000]))],
+        )
+        .unwrap();
+        let err = batch_rows(&batch, &columns()).unwrap_err();
+        assert!(err.message.contains("'ts' column"), "{}", err);
+
+        let schema = Arc::new(Schema::new(vec![Field::new("ts", DataType::Date32, false)]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Date32Array::from(vec![i32::MAX]))],
+        )
+        .unwrap();
+        let err = batch_rows(&batch, &columns()).unwrap_err();
+        assert!(err.message.contains("'ts' column"), "{}", err);
+
+        let batch =
+            RecordBatch::try_new(schema, vec![Arc::new(Date32Array::from(vec![1]))]).unwrap();
+        assert_eq!(
+            batch_rows(&batch, &columns()).unwrap()[0].values()[3],
+            TableValue::Timestamp(TimestampValue::new(86_400_000_000_000))
+        );
+    }
+
+    #[test]
+    fn import_uint64_out_of_range() {
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::UInt64, false)]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(UInt64Array::from(vec![i64::MAX as u64 + 1]))],
+        )
+        .unwrap();
+        let err = batch_rows(&batch, &columns()).unwrap_err();
+        assert!(err.message.contains("'id' column"), "{}", err);
+
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![Arc::new(UInt64Array::from(vec![i64::MAX as u64]))],
+        )
+        .unwrap();
+        assert_eq!(
+            batch_rows(&batch, &columns()).unwrap()[0].values()[0],
+            TableValue::Int(i64::MAX)
+        );
+    }
+
+    #[tokio::test]
+    async fn import_ndjson() {
+        let data =
+            "{\"id\": 1, \"name\": \"a\", \"price\": 1.5, \"ts\": \"1970-01-01T00:00:01Z\"}\n\
+                    \n\
+                    {\"id\": 2, \"name\": null}\n";
+        let reader = Box::pin(BufReader::new(data.as_bytes()));
+        let rows = collect_rows(ndjson_row_stream(reader, columns()))
+            .await
+            .unwrap();
+        assert_eq!(rows, expected_rows());
+
+        for line in ["{\"id\": 1.5}", "{\"flag\": 1}", "{\"other\": 1}", "[1]"].iter() {
+            let reader = Box::pin(BufReader::new(line.as_bytes()));
+            let r = collect_rows(ndjson_row_stream(reader, columns())).await;
+            assert!(r.is_err(), "{} must not be imported", line);
+        }
+    }
+}
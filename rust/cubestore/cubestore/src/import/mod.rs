@@ -6,7 +6,6 @@ use std::pin::Pin;
 use std::sync::Arc;
 
 use arrow::array::{ArrayBuilder, ArrayRef};
-use async_compression::tokio::bufread::GzipDecoder;
 use async_std::io::SeekFrom;
 use async_std::task::{Context, Poll};
 use async_trait::async_trait;
@@ -27,6 +26,7 @@ use cubehll::HllSketch;
 
 use crate::config::injection::DIService;
 use crate::config::ConfigObj;
+use crate::import::compression::ImportCompression;
 use crate::import::limits::ConcurrencyLimits;
 use crate::metastore::table::Table;
 use crate::metastore::{is_valid_plain_binary_hll, HllFlavour, IdRow};
@@ -46,6 +46,8 @@ use crate::CubeError;
 use datafusion::cube_ext::ordfloat::OrdF64;
 use tokio::time::{sleep, Duration};
 
+pub mod compression;
+mod formats;
 pub mod limits;
 
 impl ImportFormat {
@@ -55,10 +57,23 @@ impl ImportFormat {
         location: String,
         columns: Vec<Column>,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<Option<Row>, CubeError>> + Send>>, CubeError> {
-        let reader: Pin<Box<dyn AsyncBufRead + Send>> = if location.contains(".gz") {
-            Box::pin(BufReader::new(GzipDecoder::new(BufReader::new(file))))
-        } else {
-            Box::pin(BufReader::new(file))
+        let compression = ImportCompression::from_location(&location);
+        if let ImportFormat::Parquet | ImportFormat::Arrow = self {
+            if compression.is_some() {
+                return Err(CubeError::user(format!(
+                    "Compressed {:?} import is not supported: {}",
+                    self, location
+                )));
+            }
+            return Ok(formats::columnar_row_stream(
+                *self,
+                file.into_std().await,
+                columns,
+            ));
+        }
+        let reader: Pin<Box<dyn AsyncBufRead + Send>> = match compression {
+            Some(compression) => compression.decoder(BufReader::new(file)),
+            None => Box::pin(BufReader::new(file)),
         };
         self.row_stream_from_reader(reader, columns)
     }
@@ -91,6 +106,12 @@ impl ImportFormat {
                 let delimiter = match self {
                     ImportFormat::CSV | ImportFormat::CSVNoHeader => ',',
                     ImportFormat::CSVOptions { delimiter, .. } => delimiter.unwrap_or(','),
+                    f => {
+                        return Err(CubeError::user(format!(
+                            "{:?} is not a CSV import format",
+                            f
+                        )))
+                    }
                 };
 
                 if delimiter as u16 > 255 {
@@ -165,6 +186,11 @@ impl ImportFormat {
                 });
                 Ok(rows.boxed())
             }
+            ImportFormat::NDJSON => Ok(formats::ndjson_row_stream(reader, columns)),
+            ImportFormat::Parquet | ImportFormat::Arrow => Err(CubeError::user(format!(
+                "{:?} import requires a file location",
+                self
+            ))),
         }
     }
 
@@ -734,10 +760,9 @@ impl ImportServiceImpl {
 
     fn estimate_rows(location: &str, size: Option<u64>) -> u64 {
         if let Some(size) = size {
-            let uncompressed_size = if location.contains(".gz") {
-                size * 5
-            } else {
-                size
+            let uncompressed_size = match ImportCompression::from_location(location) {
+                Some(compression) => size * compression.size_ratio(),
+                None => size,
             };
             let average_row_length = 256;
             uncompressed_size / average_row_length
@@ -1,4 +1,5 @@
 use super::{Column, Index, IndexId, IndexType, RocksSecondaryIndex, TableId};
+use crate::metastore::table::ParquetOptions;
 
 use crate::{rocks_table_impl, CubeError};
 use byteorder::{BigEndian, WriteBytesExt};
@@ -15,6 +16,7 @@ impl Index {
         partition_split_key_size: Option<u64>,
         multi_index_id: Option<u64>,
         index_type: IndexType,
+        parquet_options: Option<ParquetOptions>,
     ) -> Result<Index, CubeError> {
         if sort_key_size == 0 {
             return Err(CubeError::user(format!(
@@ -30,6 +32,8 @@ impl Index {
             partition_split_key_size,
             multi_index_id,
             index_type,
+            parquet_options,
+            first_added_column: None,
         })
     }
 
@@ -49,6 +53,10 @@ impl Index {
         &self.columns
     }
 
+    pub fn parquet_options(&self) -> &Option<ParquetOptions> {
+        &self.parquet_options
+    }
+
     // TODO remove
     pub fn get_columns(&self) -> &Vec<Column> {
         &self.columns
@@ -66,6 +74,34 @@ impl Index {
         self.multi_index_id
     }
 
+    /// Position of the first column added by `ALTER TABLE`. Files written before can have
+    /// fewer columns starting from this position.
+    pub fn first_added_column(&self) -> Option<u64> {
+        self.first_added_column
+    }
+
+    pub fn add_column(&self, column: &Column) -> Index {
+        let mut i = self.clone();
+        let position = self.columns.len();
+        i.columns.push(column.replace_index(position));
+        i.first_added_column.get_or_insert(position as u64);
+        i
+    }
+
+    /// Dropped columns are kept in place as existing files and sort order depend on them.
+    pub fn drop_column(&self, name: &str) -> Index {
+        let mut i = self.clone();
+        for c in i.columns.iter_mut() {
+            if c.get_name() == name {
+                *c = c.to_dropped();
+            }
+        }
+        if let Some(options) = &mut i.parquet_options {
+            options.remove_column(name);
+        }
+        i
+    }
+
     pub fn index_type_default() -> IndexType {
         IndexType::Regular
     }
@@ -21,6 +21,7 @@ pub enum JobType {
     RepartitionChunk,
     InMemoryChunksCompaction,
     NodeInMemoryChunksCompaction(/*node*/ String),
+    InMemoryChunksFlush,
 }
 
 fn get_job_type_index(j: &JobType) -> u32 {
@@ -35,6 +36,7 @@ fn get_job_type_index(j: &JobType) -> u32 {
         JobType::RepartitionChunk => 8,
         JobType::InMemoryChunksCompaction => 9,
         JobType::NodeInMemoryChunksCompaction(_) => 10,
+        JobType::InMemoryChunksFlush => 11,
     }
 }
 
@@ -51,6 +53,7 @@ fn get_job_type_priority(j: &JobType) -> u32 {
         JobType::RepartitionChunk => 1000,
         JobType::InMemoryChunksCompaction => 10000,
         JobType::NodeInMemoryChunksCompaction(_) => 10000,
+        JobType::InMemoryChunksFlush => 10000,
     }
 }
 
@@ -14,12 +14,13 @@ pub mod source;
 pub mod table;
 pub mod trace_object;
 pub mod wal;
+pub mod worker;
 
 pub use rocks_fs::*;
 pub use rocks_store::*;
 pub use rocks_table::*;
 
-use crate::cluster::node_name_by_partition;
+use crate::cluster::{node_name_by_partition, worker_nodes};
 use crate::metastore::partition::partition_file_name;
 use async_trait::async_trait;
 use log::info;
@@ -44,11 +45,15 @@ use crate::metastore::replay_handle::{
 use crate::metastore::source::{
     Source, SourceCredentials, SourceIndexKey, SourceRocksIndex, SourceRocksTable,
 };
-use crate::metastore::table::{AggregateColumnIndex, StreamOffset, TableIndexKey, TablePath};
+use crate::metastore::table::{
+    AggregateColumnIndex, ParquetOptions, StreamOffset, StreamValueFormat, TableAlteration,
+    TableIndexKey, TablePath, TableRetention,
+};
 use crate::metastore::trace_object::{
     TraceObject, TraceObjectIndexKey, TraceObjectRocksIndex, TraceObjectRocksTable,
 };
 use crate::metastore::wal::{WALIndexKey, WALRocksIndex};
+use crate::metastore::worker::{Worker, WorkerRocksTable, WorkerStatus};
 
 use crate::table::{Row, TableValue};
 
@@ -84,7 +89,9 @@ use std::mem::take;
 use std::path::Path;
 use std::str::FromStr;
 
-use crate::cachestore::{CacheItem, QueueItem, QueueItemStatus, QueueResult, QueueResultAckEvent};
+use crate::cachestore::{
+    CacheItem, CacheItemChangeEvent, QueueItem, QueueItemStatus, QueueResult, QueueResultAckEvent,
+};
 use crate::remotefs::LocalDirRemoteFs;
 use deepsize::DeepSizeOf;
 use snapshot_info::SnapshotInfo;
@@ -611,6 +618,11 @@ pub enum ImportFormat {
         quote: Option<char>,
         has_header: bool,
     },
+    Parquet,
+    /// Newline delimited JSON objects, one row per line.
+    NDJSON,
+    /// Arrow IPC file or stream.
+    Arrow,
 }
 
 data_frame_from! {
@@ -640,7 +652,11 @@ pub struct Index {
     #[serde(default)]
     multi_index_id: Option<u64>,
     #[serde(default = "Index::index_type_default")]
-    index_type: IndexType
+    index_type: IndexType,
+    #[serde(default)]
+    parquet_options: Option<ParquetOptions>,
+    #[serde(default)]
+    first_added_column: Option<u64>
 }
 }
 
@@ -798,6 +814,17 @@ pub struct PartitionData {
     pub chunks: Vec<IdRow<Chunk>>,
 }
 
+/// Inactive partition with rows of `partition` and `chunks` rewritten by DELETE or UPDATE.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PartitionRewrite {
+    pub partition: IdRow<Partition>,
+    pub chunks: Vec<IdRow<Chunk>>,
+    pub new_partition: IdRow<Partition>,
+    pub new_file_size: u64,
+    pub new_row_count: u64,
+    pub new_min_max: (Option<Row>, Option<Row>),
+}
+
 #[cuberpc::service]
 pub trait MetaStore: DIService + Send + Sync {
     async fn wait_for_current_seq_to_sync(&self) -> Result<(), CubeError>;
@@ -844,6 +871,9 @@ pub trait MetaStore: DIService + Send + Sync {
         unique_key_column_names: Option<Vec<String>>,
         aggregates: Option<Vec<(String, String)>>,
         partition_split_threshold: Option<u64>,
+        parquet_options: Option<ParquetOptions>,
+        retention: Option<(String, u64)>,
+        value_format: Option<StreamValueFormat>,
         trace_obj: Option<String>,
         drop_if_exists: bool,
     ) -> Result<IdRow<Table>, CubeError>;
@@ -872,6 +902,13 @@ pub trait MetaStore: DIService + Send + Sync {
         created_seconds_ago: i64,
     ) -> Result<Vec<IdRow<Table>>, CubeError>;
     async fn drop_table(&self, table_id: u64) -> Result<IdRow<Table>, CubeError>;
+    /// Changes table definition along with its indexes. Existing data isn't rewritten.
+    async fn alter_table(
+        &self,
+        schema_name: String,
+        table_name: String,
+        alteration: TableAlteration,
+    ) -> Result<IdRow<Table>, CubeError>;
 
     fn partition_table(&self) -> PartitionMetaStoreTable;
     async fn create_partition(&self, partition: Partition) -> Result<IdRow<Partition>, CubeError>;
@@ -909,6 +946,28 @@ pub trait MetaStore: DIService + Send + Sync {
         new_active: Vec<(IdRow<Partition>, u64)>,
         new_active_min_max: Vec<(u64, (Option<Row>, Option<Row>), (Option<Row>, Option<Row>))>,
     ) -> Result<(), CubeError>;
+    /// Replaces partition and its chunks with the single partition of the same key range.
+    /// Row count isn't checked as rows are removed by DELETE.
+    async fn swap_rewritten_partition(
+        &self,
+        current_active: (IdRow<Partition>, Vec<IdRow<Chunk>>),
+        new_active: (IdRow<Partition>, u64),
+        new_active_row_count: u64,
+        new_active_min_max: (Option<Row>, Option<Row>),
+    ) -> Result<(), CubeError>;
+    /// Activates all rewritten partitions at once. Nothing is changed if any of the current
+    /// partitions or chunks was deactivated concurrently.
+    async fn swap_rewritten_partitions(
+        &self,
+        rewrites: Vec<PartitionRewrite>,
+    ) -> Result<(), CubeError>;
+    /// Replaces partition, which main table is fully expired by the table retention, with an empty
+    /// partition of the same key range. Expired chunks are deactivated, other chunks are moved to
+    /// the new partition. Does nothing if the partition or chunks were changed concurrently.
+    async fn replace_expired_partition(
+        &self,
+        current_active: (IdRow<Partition>, Vec<IdRow<Chunk>>),
+    ) -> Result<(), CubeError>;
     async fn delete_partition(&self, partition_id: u64) -> Result<IdRow<Partition>, CubeError>;
     async fn mark_partition_warmed_up(&self, partition_id: u64) -> Result<(), CubeError>;
     async fn delete_middle_man_partition(
@@ -964,6 +1023,10 @@ pub trait MetaStore: DIService + Send + Sync {
         &self,
         index_id: u64,
     ) -> Result<Vec<IdRow<Partition>>, CubeError>;
+    async fn get_active_partitions_and_chunks_by_index_ids(
+        &self,
+        index_ids: Vec<u64>,
+    ) -> Result<Vec<(IdRow<Partition>, Vec<IdRow<Chunk>>)>, CubeError>;
     async fn get_index(&self, index_id: u64) -> Result<IdRow<Index>, CubeError>;
 
     async fn get_index_with_active_partitions_out_of_queue(
@@ -1182,6 +1245,24 @@ pub trait MetaStore: DIService + Send + Sync {
 
     async fn get_snapshots_list(&self) -> Result<Vec<SnapshotInfo>, CubeError>;
     async fn set_current_snapshot(&self, snapshot_id: u128) -> Result<(), CubeError>;
+    /// Copies a fresh snapshot under `location` on the remote fs. Returns the snapshot id.
+    async fn backup_snapshot(&self, location: String) -> Result<u128, CubeError>;
+
+    /// Registers a dynamic worker or prolongs its registration. Returns active dynamic workers.
+    async fn worker_heartbeat(&self, worker: String) -> Result<Vec<String>, CubeError>;
+    /// Returns workers that joined the cluster at runtime sorted by name.
+    async fn get_workers(&self) -> Result<Vec<IdRow<Worker>>, CubeError>;
+    /// Removes workers that haven't sent heartbeats for longer than `timeout_secs`.
+    async fn expire_workers(&self, timeout_secs: u64) -> Result<Vec<IdRow<Worker>>, CubeError>;
+    /// Moves joining workers to [WorkerStatus::WarmingUp]. Returns all workers that are warming
+    /// up or handing over partitions.
+    async fn start_workers_warmup(&self) -> Result<Vec<IdRow<Worker>>, CubeError>;
+    /// Makes a warmed up worker take traffic and moves it to [WorkerStatus::HandingOver].
+    /// Returns false if the worker has been removed in the meantime.
+    async fn activate_worker(&self, worker: String) -> Result<bool, CubeError>;
+    /// Moves a worker that took over its partitions to [WorkerStatus::Active]. Returns false if
+    /// the worker has been removed in the meantime.
+    async fn finish_worker_handover(&self, worker: String) -> Result<bool, CubeError>;
 }
 
 crate::di_service!(RocksMetaStore, [MetaStore]);
@@ -1203,6 +1284,7 @@ pub enum MetaStoreEvent {
     UpdateSource(IdRow<Source>, IdRow<Source>),
     UpdateReplayHandle(IdRow<ReplayHandle>, IdRow<ReplayHandle>),
     UpdateTraceObject(IdRow<TraceObject>, IdRow<TraceObject>),
+    UpdateWorker(IdRow<Worker>, IdRow<Worker>),
 
     DeleteChunk(IdRow<Chunk>),
     DeleteIndex(IdRow<Index>),
@@ -1214,6 +1296,7 @@ pub enum MetaStoreEvent {
     DeleteSource(IdRow<Source>),
     DeleteReplayHandle(IdRow<ReplayHandle>),
     DeleteTraceObject(IdRow<TraceObject>),
+    DeleteWorker(IdRow<Worker>),
 
     UpdateMultiIndex(IdRow<MultiIndex>, IdRow<MultiIndex>),
     DeleteMultiIndex(IdRow<MultiIndex>),
@@ -1228,6 +1311,7 @@ pub enum MetaStoreEvent {
     UpdateQueueItem(IdRow<QueueItem>, IdRow<QueueItem>),
     DeleteQueueItem(IdRow<QueueItem>),
     AckQueueItem(QueueResultAckEvent),
+    CacheItemChange(CacheItemChangeEvent),
 
     UpdateQueueResult(IdRow<QueueResult>, IdRow<QueueResult>),
     DeleteQueueResult(IdRow<QueueResult>),
@@ -1396,6 +1480,28 @@ impl RocksMetaStore {
         Ok(Self::new_from_store(store))
     }
 
+    /// Makes a snapshot restored up to `until` current, see [BaseRocksStoreFs::restore_snapshot].
+    /// Must be called while the meta store is not running.
+    pub async fn restore_from_remote(
+        path: &Path,
+        metastore_fs: Arc<BaseRocksStoreFs>,
+        config: Arc<dyn ConfigObj>,
+        snapshot: u128,
+        until: Option<u128>,
+        location: Option<&str>,
+    ) -> Result<u128, CubeError> {
+        metastore_fs
+            .restore_snapshot(
+                snapshot,
+                until,
+                location,
+                path,
+                config,
+                Arc::new(RocksMetaStoreDetails {}),
+            )
+            .await
+    }
+
     pub async fn wait_upload_loop(self: Arc<Self>) {
         if !self.store.config.upload_to_remote() {
             log::info!("Not running metastore upload loop");
@@ -1681,6 +1787,7 @@ impl RocksMetaStore {
             table_id.get_row().seq_column().map(|_| sorted_key_size - 1),
             multi_index.map(|i| i.get_id()),
             IndexType::Regular,
+            table_id.get_row().parquet_options().clone(),
         )?;
         let index_id = rocks_index.insert(index, batch_pipe)?;
         if multi_partitions.is_empty() {
@@ -1779,6 +1886,7 @@ impl RocksMetaStore {
             None,
             None,
             IndexType::Aggregate,
+            table_id.get_row().parquet_options().clone(),
         )?;
 
         let index_id = rocks_index.insert(index, batch_pipe)?;
@@ -2064,6 +2172,9 @@ impl MetaStore for RocksMetaStore {
         unique_key_column_names: Option<Vec<String>>,
         aggregates: Option<Vec<(String, String)>>,
         partition_split_threshold: Option<u64>,
+        parquet_options: Option<ParquetOptions>,
+        retention: Option<(String, u64)>,
+        value_format: Option<StreamValueFormat>,
         trace_obj: Option<String>,
         drop_if_exists: bool,
     ) -> Result<IdRow<Table>, CubeError> {
@@ -2152,6 +2263,39 @@ impl MetaStore for RocksMetaStore {
             } else {
                 vec![]
             };
+            if let Some(parquet_options) = &parquet_options {
+                parquet_options.validate(&columns)?;
+            }
+            let retention = if let Some((column_name, period_secs)) = retention {
+                let column = columns
+                    .iter()
+                    .find(|c| &c.name == &column_name)
+                    .ok_or_else(|| {
+                        CubeError::user(format!(
+                            "Retention column {} not found among column definitions {:?}",
+                            column_name, columns
+                        ))
+                    })?;
+                if column.column_type != ColumnType::Timestamp {
+                    return Err(CubeError::user(format!(
+                        "Retention column {} must be of timestamp type but {} found",
+                        column_name, column.column_type
+                    )));
+                }
+                let index = column.column_index as u64;
+                if aggregate_column_indices.iter().any(|a| a.index() == index) {
+                    return Err(CubeError::user(format!(
+                        "Aggregate column {} can't be used as a retention column",
+                        column_name
+                    )));
+                }
+                Some(TableRetention {
+                    column_index: index,
+                    period_secs,
+                })
+            } else {
+                None
+            };
             let table = Table::new(
                 table_name,
                 schema_id.get_id(),
@@ -2168,6 +2312,9 @@ impl MetaStore for RocksMetaStore {
                 aggregate_column_indices,
                 seq_column_index,
                 partition_split_threshold,
+                parquet_options,
+                retention,
+                value_format,
             );
             let table_id = rocks_table.insert(table, batch_pipe)?;
 
@@ -2422,6 +2569,100 @@ impl MetaStore for RocksMetaStore {
         .await
     }
 
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn alter_table(
+        &self,
+        schema_name: String,
+        table_name: String,
+        alteration: TableAlteration,
+    ) -> Result<IdRow<Table>, CubeError> {
+        self.write_operation(move |db_ref, batch_pipe| {
+            batch_pipe.invalidate_tables_cache();
+            let rocks_table = TableRocksTable::new(db_ref.clone());
+            let rocks_index = IndexRocksTable::new(db_ref.clone());
+            let table = RocksMetaStore::get_table_by_name(
+                schema_name,
+                table_name,
+                TableRocksTable::new(db_ref.clone()),
+                SchemaRocksTable::new(db_ref.clone()),
+            )?;
+            if table.get_row().in_memory_ingest() || table.get_row().select_statement().is_some() {
+                return Err(CubeError::user(format!(
+                    "ALTER TABLE is not supported for stream table {}",
+                    table.get_row().get_table_name()
+                )));
+            }
+            let indexes = rocks_index.get_rows_by_index(
+                &IndexIndexKey::TableId(table.get_id()),
+                &IndexRocksIndex::TableID,
+            )?;
+            if let TableAlteration::AddColumn { .. } | TableAlteration::DropColumn { .. } =
+                &alteration
+            {
+                if let Some(index) = indexes
+                    .iter()
+                    .find(|i| i.get_row().multi_index_id().is_some())
+                {
+                    return Err(CubeError::user(format!(
+                        "Columns can't be changed for tables with partitioned indexes: {}",
+                        index.get_row().get_name()
+                    )));
+                }
+            }
+
+            let new_table = match alteration {
+                TableAlteration::Rename { table_name } => {
+                    let key =
+                        TableIndexKey::ByName(table.get_row().get_schema_id(), table_name.clone());
+                    if !rocks_table
+                        .get_row_ids_by_index(&key, &TableRocksIndex::Name)?
+                        .is_empty()
+                    {
+                        return Err(CubeError::user(format!(
+                            "Table {} already exists",
+                            table_name
+                        )));
+                    }
+                    table.get_row().rename(table_name)
+                }
+                TableAlteration::AddColumn { name, column_type } => {
+                    let new_table = table.get_row().add_column(name, column_type)?;
+                    let column = new_table.get_columns().last().unwrap();
+                    // Aggregate indexes contain only dimensions and measures.
+                    for index in indexes
+                        .iter()
+                        .filter(|i| i.get_row().get_type() == IndexType::Regular)
+                    {
+                        rocks_index.update(
+                            index.get_id(),
+                            index.get_row().add_column(column),
+                            index.get_row(),
+                            batch_pipe,
+                        )?;
+                    }
+                    new_table
+                }
+                TableAlteration::DropColumn { name } => {
+                    let new_table = table.get_row().drop_column(&name)?;
+                    for index in indexes
+                        .iter()
+                        .filter(|i| i.get_row().columns().iter().any(|c| c.get_name() == &name))
+                    {
+                        rocks_index.update(
+                            index.get_id(),
+                            index.get_row().drop_column(&name),
+                            index.get_row(),
+                            batch_pipe,
+                        )?;
+                    }
+                    new_table
+                }
+            };
+            rocks_table.update(table.get_id(), new_table, table.get_row(), batch_pipe)
+        })
+        .await
+    }
+
     fn partition_table(&self) -> PartitionMetaStoreTable {
         PartitionMetaStoreTable {
             rocks_meta_store: self.store.clone(),
@@ -2536,11 +2777,7 @@ impl MetaStore for RocksMetaStore {
                 }
             }
 
-            let workers = if self.store.config.select_workers().is_empty() {
-                vec![self.store.config.server_name().clone()]
-            } else {
-                self.store.config.select_workers().clone()
-            };
+            let workers = worker_nodes(self.store.config.as_ref());
 
             let mut map = workers
                 .into_iter()
@@ -2649,11 +2886,103 @@ impl MetaStore for RocksMetaStore {
                     )))
                 },
                 |_| panic!("error from current partition must propagate before this call"),
+                false,
             )
         })
         .await
     }
 
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn swap_rewritten_partition(
+        &self,
+        current_active: (IdRow<Partition>, Vec<IdRow<Chunk>>),
+        new_active: (IdRow<Partition>, u64),
+        new_active_row_count: u64,
+        new_active_min_max: (Option<Row>, Option<Row>),
+    ) -> Result<(), CubeError> {
+        let (partition, chunks) = current_active;
+        let (new_partition, new_file_size) = new_active;
+        let rewrite = PartitionRewrite {
+            partition,
+            chunks,
+            new_partition,
+            new_file_size,
+            new_row_count: new_active_row_count,
+            new_min_max: new_active_min_max,
+        };
+        self.write_operation(move |db, pipe| swap_rewritten_partition_impl(db, pipe, rewrite))
+            .await
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn swap_rewritten_partitions(
+        &self,
+        rewrites: Vec<PartitionRewrite>,
+    ) -> Result<(), CubeError> {
+        self.write_operation(move |db, pipe| {
+            for rewrite in rewrites {
+                swap_rewritten_partition_impl(db.clone(), pipe, rewrite)?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn replace_expired_partition(
+        &self,
+        current_active: (IdRow<Partition>, Vec<IdRow<Chunk>>),
+    ) -> Result<(), CubeError> {
+        self.write_operation(move |db_ref, batch_pipe| {
+            let partitions_table = PartitionRocksTable::new(db_ref.clone());
+            let chunks_table = ChunkRocksTable::new(db_ref.clone());
+            let (partition, expired_chunks) = current_active;
+
+            let current = match partitions_table.get_row(partition.get_id())? {
+                Some(p) if p.get_row().is_active() => p,
+                _ => return Ok(()),
+            };
+            let mut expired_ids = HashSet::new();
+            for chunk in expired_chunks {
+                match chunks_table.get_row(chunk.get_id())? {
+                    Some(c) if c.get_row().active() => expired_ids.insert(c.get_id()),
+                    _ => return Ok(()),
+                };
+            }
+
+            let new_partition = Partition::new_child(&current, None)
+                .update_min_max_and_row_count(
+                    current.get_row().get_min_val().clone(),
+                    current.get_row().get_max_val().clone(),
+                    0,
+                    None,
+                    None,
+                )
+                .to_active(true);
+            let new_partition = partitions_table.insert(new_partition, batch_pipe)?;
+            partitions_table.update(
+                current.get_id(),
+                current.get_row().to_active(false),
+                current.get_row(),
+                batch_pipe,
+            )?;
+
+            for chunk in Self::chunks_by_partition(current.get_id(), &chunks_table, true)? {
+                if expired_ids.contains(&chunk.get_id()) {
+                    chunks_table.update_with_fn(chunk.get_id(), |c| c.deactivate(), batch_pipe)?;
+                } else {
+                    chunks_table.update_with_fn(
+                        chunk.get_id(),
+                        |c| c.set_partition_id(new_partition.get_id()),
+                        batch_pipe,
+                    )?;
+                }
+            }
+            Ok(())
+        })
+        .await
+    }
+
     #[tracing::instrument(level = "trace", skip(self))]
     async fn delete_partition(&self, partition_id: u64) -> Result<IdRow<Partition>, CubeError> {
         self.write_operation(move |db_ref, batch_pipe| {
@@ -3233,6 +3562,32 @@ impl MetaStore for RocksMetaStore {
         .await
     }
 
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn get_active_partitions_and_chunks_by_index_ids(
+        &self,
+        index_ids: Vec<u64>,
+    ) -> Result<Vec<(IdRow<Partition>, Vec<IdRow<Chunk>>)>, CubeError> {
+        self.read_operation_out_of_queue(move |db_ref| {
+            let rocks_chunk = ChunkRocksTable::new(db_ref.clone());
+            let rocks_partition = PartitionRocksTable::new(db_ref);
+            let mut result = Vec::new();
+            for index_id in index_ids {
+                // TODO iterate over range
+                for p in rocks_partition.get_rows_by_index(
+                    &PartitionIndexKey::ByIndexId(index_id),
+                    &PartitionRocksIndex::IndexId,
+                )? {
+                    if p.get_row().active {
+                        let chunks = Self::chunks_by_partition(p.get_id(), &rocks_chunk, false)?;
+                        result.push((p, chunks));
+                    }
+                }
+            }
+            Ok(result)
+        })
+        .await
+    }
+
     #[tracing::instrument(level = "trace", skip(self))]
     async fn get_index(&self, index_id: u64) -> Result<IdRow<Index>, CubeError> {
         self.read_operation(move |db_ref| {
@@ -3920,11 +4275,7 @@ impl MetaStore for RocksMetaStore {
     }
 
     async fn get_jobs_on_non_exists_nodes(&self) -> Result<Vec<IdRow<Job>>, CubeError> {
-        let workers = if self.store.config.select_workers().is_empty() {
-            vec![self.store.config.server_name().clone()]
-        } else {
-            self.store.config.select_workers().clone()
-        };
+        let workers = worker_nodes(self.store.config.as_ref());
         let nodes = workers
             .iter()
             .map(|s| s.to_string())
@@ -4461,7 +4812,8 @@ impl MetaStore for RocksMetaStore {
                             "{} <= {}", new_partition_rows[new_i], new_multi_partition_rows[mi]);
                     new_multi_partition_rows[mi] -= new_partition_rows[new_i];
                     Ok(())
-                }
+                },
+                false,
             )?;
 
             let total_new_rows = new_multi_partition_rows.iter().sum();
@@ -4604,6 +4956,139 @@ impl MetaStore for RocksMetaStore {
     async fn set_current_snapshot(&self, snapshot_id: u128) -> Result<(), CubeError> {
         self.store.set_current_snapshot(snapshot_id).await
     }
+    async fn backup_snapshot(&self, location: String) -> Result<u128, CubeError> {
+        self.store.backup(&location).await
+    }
+    async fn worker_heartbeat(&self, worker: String) -> Result<Vec<String>, CubeError> {
+        let config = self.store.config.clone();
+        if !config.dynamic_workers() {
+            return Err(CubeError::user(format!(
+                "Worker '{}' can't join the cluster: dynamic workers are disabled on the router. Please set CUBESTORE_DYNAMIC_WORKERS=1",
+                worker
+            )));
+        }
+        if config.select_workers().contains(&worker) {
+            return Ok(config.worker_membership().active_workers().as_ref().clone());
+        }
+        let active = self
+            .write_operation(move |db_ref, batch_pipe| {
+                let table = WorkerRocksTable::new(db_ref);
+                let now = Utc::now();
+                let workers = table.all_rows()?;
+                match workers.iter().find(|w| w.get_row().name() == &worker) {
+                    Some(w) => {
+                        let new_row = w.get_row().update_last_heartbeat(now);
+                        table.update(w.get_id(), new_row, w.get_row(), batch_pipe)?;
+                    }
+                    None => {
+                        info!("Worker {} joined the cluster", worker);
+                        table.insert(Worker::new(worker, now), batch_pipe)?;
+                    }
+                }
+                Ok(active_worker_names(&workers))
+            })
+            .await?;
+        config
+            .worker_membership()
+            .set_active_workers(active.clone());
+        Ok(active)
+    }
+    async fn get_workers(&self) -> Result<Vec<IdRow<Worker>>, CubeError> {
+        self.read_operation(move |db_ref| {
+            let mut workers = WorkerRocksTable::new(db_ref).all_rows()?;
+            workers.sort_by(|a, b| a.get_row().name().cmp(b.get_row().name()));
+            Ok(workers)
+        })
+        .await
+    }
+    async fn expire_workers(&self, timeout_secs: u64) -> Result<Vec<IdRow<Worker>>, CubeError> {
+        let config = self.store.config.clone();
+        let (expired, active) = self
+            .write_operation(move |db_ref, batch_pipe| {
+                let table = WorkerRocksTable::new(db_ref);
+                let timeout = chrono::Duration::seconds(timeout_secs as i64);
+                let now = Utc::now();
+                let (expired, alive): (Vec<_>, Vec<_>) = table
+                    .all_rows()?
+                    .into_iter()
+                    .partition(|w| now - *w.get_row().last_heartbeat() > timeout);
+                for w in expired.iter() {
+                    table.delete(w.get_id(), batch_pipe)?;
+                }
+                Ok((expired, active_worker_names(&alive)))
+            })
+            .await?;
+        // Also restores the list of active workers after a restart of the router.
+        config.worker_membership().set_active_workers(active);
+        Ok(expired)
+    }
+    async fn start_workers_warmup(&self) -> Result<Vec<IdRow<Worker>>, CubeError> {
+        self.write_operation(move |db_ref, batch_pipe| {
+            let table = WorkerRocksTable::new(db_ref);
+            let mut res = Vec::new();
+            for w in table.all_rows()? {
+                match w.get_row().status() {
+                    WorkerStatus::Joining => {
+                        let new_row = w.get_row().update_status(WorkerStatus::WarmingUp);
+                        res.push(table.update(w.get_id(), new_row, w.get_row(), batch_pipe)?);
+                    }
+                    WorkerStatus::WarmingUp | WorkerStatus::HandingOver => res.push(w),
+                    WorkerStatus::Active => {}
+                }
+            }
+            Ok(res)
+        })
+        .await
+    }
+    async fn activate_worker(&self, worker: String) -> Result<bool, CubeError> {
+        let config = self.store.config.clone();
+        let active = self
+            .write_operation(move |db_ref, batch_pipe| {
+                let table = WorkerRocksTable::new(db_ref);
+                let mut workers = table.all_rows()?;
+                let w = match workers.iter_mut().find(|w| {
+                    w.get_row().name() == &worker && w.get_row().status() == WorkerStatus::WarmingUp
+                }) {
+                    Some(w) => w,
+                    None => return Ok(None),
+                };
+                let new_row = w.get_row().update_status(WorkerStatus::HandingOver);
+                *w = table.update(w.get_id(), new_row, w.get_row(), batch_pipe)?;
+                Ok(Some(active_worker_names(&workers)))
+            })
+            .await?;
+        match active {
+            Some(active) => {
+                config.worker_membership().set_active_workers(active);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+    async fn finish_worker_handover(&self, worker: String) -> Result<bool, CubeError> {
+        self.write_operation(move |db_ref, batch_pipe| {
+            let table = WorkerRocksTable::new(db_ref);
+            for w in table.all_rows()? {
+                if w.get_row().name() == &worker
+                    && w.get_row().status() == WorkerStatus::HandingOver
+                {
+                    let new_row = w.get_row().update_status(WorkerStatus::Active);
+                    table.update(w.get_id(), new_row, w.get_row(), batch_pipe)?;
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        })
+        .await
+    }
+}
+
+fn active_worker_names(workers: &[IdRow<Worker>]) -> Vec<String> {
+    workers
+        .iter()
+        .filter(|w| w.get_row().status().is_active())
+        .map(|w| w.get_row().name().clone())
+        .collect()
 }
 
 pub async fn deactivate_table_on_corrupt_data<'a, T: 'static>(
@@ -4721,6 +5206,7 @@ fn swap_active_partitions_impl(
     mut update_new_partition_stats: impl FnMut(/*index*/ usize, &Partition) -> Partition,
     mut on_dropped_current_partition: impl FnMut(/*index*/ usize) -> Result<(), CubeError>,
     mut on_dropped_new_partition: impl FnMut(/*index*/ usize) -> Result<(), CubeError>,
+    rows_removed: bool,
 ) -> Result<(), CubeError> {
     let index_table = IndexRocksTable::new(db_ref.clone());
     let table_table = TableRocksTable::new(db_ref.clone());
@@ -4737,8 +5223,10 @@ fn swap_active_partitions_impl(
             )))?;
         let index = index_table.get_row_or_not_found(current_partition.get_row().get_index_id())?;
         let table = table_table.get_row_or_not_found(index.get_row().table_id())?;
-        index.get_row().get_type() == IndexType::Aggregate
+        rows_removed
+            || index.get_row().get_type() == IndexType::Aggregate
             || table.get_row().unique_key_columns().is_some()
+            || table.get_row().retention().is_some()
     } else {
         false
     };
@@ -4866,6 +5354,41 @@ fn swap_active_partitions_impl(
     Ok(())
 }
 
+fn swap_rewritten_partition_impl(
+    db_ref: DbTableRef,
+    batch_pipe: &mut BatchPipe,
+    rewrite: PartitionRewrite,
+) -> Result<(), CubeError> {
+    let min_val = rewrite.partition.get_row().get_min_val().clone();
+    let max_val = rewrite.partition.get_row().get_max_val().clone();
+    let current_id = rewrite.partition.get_id();
+    let row_count = rewrite.new_row_count;
+    let (min, max) = rewrite.new_min_max;
+    swap_active_partitions_impl(
+        db_ref,
+        batch_pipe,
+        &[(rewrite.partition, rewrite.chunks)],
+        &[(rewrite.new_partition, rewrite.new_file_size)],
+        move |_, p| {
+            p.update_min_max_and_row_count(
+                min_val.clone(),
+                max_val.clone(),
+                row_count,
+                min.clone(),
+                max.clone(),
+            )
+        },
+        |_| {
+            Err(CubeError::internal(format!(
+                "Current partition is not found during rewrite: {}",
+                current_id
+            )))
+        },
+        |_| panic!("error from current partition must propagate before this call"),
+        true,
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::table::AggregateColumn;
@@ -5121,6 +5644,9 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
                 false,
             )
             .await
@@ -5144,6 +5670,9 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
                 false,
             )
             .await
@@ -5266,6 +5795,9 @@ mod tests {
                     None,
                     None,
                     None,
+                    None,
+                    None,
+                    None,
                     false,
                 )
                 .await
@@ -5291,7 +5823,10 @@ mod tests {
                     None,
                     None,
                     None,
-                    false,
+                    None,
+                    None,
+                    None,
+                    false
                 )
                 .await
                 .is_err());
@@ -5312,6 +5847,7 @@ mod tests {
                 None,
                 None,
                 Index::index_type_default(),
+                None,
             )
             .unwrap();
             let expected_res = vec![IdRow::new(1, expected_index)];
@@ -5380,6 +5916,9 @@ mod tests {
                     None,
                     None,
                     None,
+                    None,
+                    None,
+                    None,
                     false,
                 )
                 .await
@@ -5402,6 +5941,7 @@ mod tests {
                 None,
                 None,
                 Index::index_type_default(),
+                None,
             )
             .unwrap();
             let expected_res = vec![IdRow::new(1, expected_index)];
@@ -5470,6 +6010,9 @@ mod tests {
                     ]),
                     None,
                     None,
+                    None,
+                    None,
+                    None,
                     false,
                 )
                 .await
@@ -5543,7 +6086,10 @@ mod tests {
                     ]),
                     None,
                     None,
-                    false,
+                    None,
+                    None,
+                    None,
+                    false
                 )
                 .await
                 .is_err());
@@ -5566,7 +6112,10 @@ mod tests {
                     None,
                     None,
                     None,
-                    false,
+                    None,
+                    None,
+                    None,
+                    false
                 )
                 .await
                 .is_err());
@@ -5592,7 +6141,10 @@ mod tests {
                     ]),
                     None,
                     None,
-                    false,
+                    None,
+                    None,
+                    None,
+                    false
                 )
                 .await
                 .is_err());
@@ -5939,6 +6491,245 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn worker_heartbeat() {
+        {
+            let config = Config::test("worker_heartbeat_disabled");
+            let _ = fs::remove_dir_all(config.local_dir());
+            let _ = fs::remove_dir_all(config.remote_dir());
+
+            let services = config.configure().await;
+            let e = services
+                .meta_store
+                .worker_heartbeat("worker-1:3123".to_string())
+                .await
+                .unwrap_err();
+            assert!(e.message.contains("CUBESTORE_DYNAMIC_WORKERS"), "{}", e);
+
+            let _ = fs::remove_dir_all(config.local_dir());
+            let _ = fs::remove_dir_all(config.remote_dir());
+        }
+        {
+            let config = Config::test("worker_heartbeat").update_config(|mut c| {
+                c.dynamic_workers = true;
+                c.select_workers = vec!["worker-0:3123".to_string()];
+                c
+            });
+            let _ = fs::remove_dir_all(config.local_dir());
+            let _ = fs::remove_dir_all(config.remote_dir());
+
+            let services = config.configure().await;
+            let meta_store = services.meta_store.clone();
+            assert!(meta_store
+                .worker_heartbeat("worker-0:3123".to_string())
+                .await
+                .unwrap()
+                .is_empty());
+            assert!(meta_store
+                .worker_heartbeat("worker-1:3123".to_string())
+                .await
+                .unwrap()
+                .is_empty());
+
+            meta_store
+                .worker_heartbeat("worker-2:3123".to_string())
+                .await
+                .unwrap();
+
+            let workers = meta_store.get_workers().await.unwrap();
+            assert_eq!(workers.len(), 2);
+            assert_eq!(workers[0].get_row().name(), "worker-1:3123");
+            assert_eq!(workers[0].get_row().status(), WorkerStatus::Joining);
+
+            let warming_up = |workers: Vec<IdRow<Worker>>| {
+                workers
+                    .into_iter()
+                    .map(|w| w.get_row().name().clone())
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(
+                warming_up(meta_store.start_workers_warmup().await.unwrap()),
+                vec!["worker-1:3123".to_string(), "worker-2:3123".to_string()]
+            );
+            // Warmup is restarted for workers that haven't been activated.
+            assert_eq!(
+                warming_up(meta_store.start_workers_warmup().await.unwrap()).len(),
+                2
+            );
+            assert!(config
+                .config_obj()
+                .worker_membership()
+                .active_workers()
+                .is_empty());
+
+            assert!(meta_store
+                .activate_worker("worker-1:3123".to_string())
+                .await
+                .unwrap());
+            assert!(!meta_store
+                .activate_worker("worker-1:3123".to_string())
+                .await
+                .unwrap());
+            assert!(!meta_store
+                .activate_worker("worker-3:3123".to_string())
+                .await
+                .unwrap());
+            // Handing over workers take traffic and stay in warmup until the handover finishes.
+            assert_eq!(
+                warming_up(meta_store.start_workers_warmup().await.unwrap()).len(),
+                2
+            );
+            assert!(meta_store
+                .finish_worker_handover("worker-1:3123".to_string())
+                .await
+                .unwrap());
+            assert!(!meta_store
+                .finish_worker_handover("worker-1:3123".to_string())
+                .await
+                .unwrap());
+            assert!(!meta_store
+                .finish_worker_handover("worker-2:3123".to_string())
+                .await
+                .unwrap());
+            assert_eq!(
+                warming_up(meta_store.start_workers_warmup().await.unwrap()),
+                vec!["worker-2:3123".to_string()]
+            );
+            assert_eq!(
+                meta_store
+                    .worker_heartbeat("worker-1:3123".to_string())
+                    .await
+                    .unwrap(),
+                vec!["worker-1:3123".to_string()]
+            );
+            assert_eq!(
+                worker_nodes(config.config_obj().as_ref()),
+                vec!["worker-0:3123".to_string(), "worker-1:3123".to_string()]
+            );
+
+            assert!(meta_store.expire_workers(3600).await.unwrap().is_empty());
+            Delay::new(Duration::from_millis(10)).await;
+            assert_eq!(meta_store.expire_workers(0).await.unwrap().len(), 2);
+            assert!(meta_store.get_workers().await.unwrap().is_empty());
+            assert_eq!(
+                worker_nodes(config.config_obj().as_ref()),
+                vec!["worker-0:3123".to_string()]
+            );
+
+            let _ = fs::remove_dir_all(config.local_dir());
+            let _ = fs::remove_dir_all(config.remote_dir());
+        }
+    }
+
+    #[tokio::test]
+    async fn backup_and_restore_snapshot() {
+        let schema_names = |schemas: Vec<IdRow<Schema>>| {
+            schemas
+                .into_iter()
+                .map(|s| s.get_row().get_name().clone())
+                .sorted()
+                .collect::<Vec<_>>()
+        };
+        let (snapshot, until, backup) = {
+            let config = Config::test("backup_and_restore_snapshot");
+
+            let _ = fs::remove_dir_all(config.local_dir());
+            let _ = fs::remove_dir_all(config.remote_dir());
+
+            let services = config.configure().await;
+            services.start_processing_loops().await.unwrap();
+            let rocks_meta_store = services.rocks_meta_store.as_ref().unwrap();
+            services
+                .meta_store
+                .create_schema("foo1".to_string(), false)
+                .await
+                .unwrap();
+            rocks_meta_store.upload_check_point().await.unwrap();
+            let snapshot = services.meta_store.get_snapshots_list().await.unwrap()[0].id;
+
+            services
+                .meta_store
+                .create_schema("foo2".to_string(), false)
+                .await
+                .unwrap();
+            rocks_meta_store.run_upload().await.unwrap();
+            Delay::new(Duration::from_millis(10)).await;
+            let until = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_millis();
+            Delay::new(Duration::from_millis(10)).await;
+            services
+                .meta_store
+                .create_schema("foo3".to_string(), false)
+                .await
+                .unwrap();
+            rocks_meta_store.run_upload().await.unwrap();
+
+            let snapshots = services.meta_store.get_snapshots_list().await.unwrap();
+            assert_eq!(snapshots.len(), 1);
+            assert!(snapshots[0].restorable_until > until);
+
+            let res = services
+                .meta_store
+                .backup_snapshot("metastore-backup".to_string())
+                .await;
+            assert_eq!(
+                res.unwrap_err().to_string(),
+                "Invalid metastore backup location: 'metastore-backup'"
+            );
+            let backup = services
+                .meta_store
+                .backup_snapshot("backups/first".to_string())
+                .await
+                .unwrap();
+            let snapshots = services.meta_store.get_snapshots_list().await.unwrap();
+            assert_eq!(snapshots.len(), 2);
+            assert_eq!(snapshots[1].id, backup);
+            assert!(snapshots[1].current);
+
+            services.stop_processing_loops().await.unwrap();
+            Delay::new(Duration::from_millis(1000)).await; // TODO logger init conflict
+            (snapshot, until, backup)
+        };
+
+        {
+            let res = Config::test("backup_and_restore_snapshot")
+                .restore_metastore(snapshot, Some(snapshot - 1), None)
+                .await;
+            assert!(res.is_err());
+
+            let restored = Config::test("backup_and_restore_snapshot")
+                .restore_metastore(snapshot, Some(until), None)
+                .await
+                .unwrap();
+            assert!(restored > backup);
+
+            let services = Config::test("backup_and_restore_snapshot")
+                .configure()
+                .await;
+            let schemas = services.meta_store.get_schemas().await.unwrap();
+            assert_eq!(schema_names(schemas), vec!["foo1", "foo2"]);
+            let snapshots = services.meta_store.get_snapshots_list().await.unwrap();
+            assert!(snapshots.iter().find(|s| s.id == restored).unwrap().current);
+        }
+
+        {
+            Config::test("backup_and_restore_snapshot")
+                .restore_metastore(backup, None, Some("backups/first"))
+                .await
+                .unwrap();
+
+            let config = Config::test("backup_and_restore_snapshot");
+            let services = config.configure().await;
+            let schemas = services.meta_store.get_schemas().await.unwrap();
+            assert_eq!(schema_names(schemas), vec!["foo1", "foo2", "foo3"]);
+
+            fs::remove_dir_all(config.local_dir()).unwrap();
+            fs::remove_dir_all(config.remote_dir()).unwrap();
+        }
+    }
+
     #[tokio::test]
     async fn upload_logs_without_snapshots() {
         let config = Config::test("upload_logs_without_snapshots");
@@ -6069,6 +6860,9 @@ mod tests {
                         None,
                         None,
                         None,
+                        None,
+                        None,
+                        None,
                         false,
                     )
                     .await
@@ -6292,6 +7086,9 @@ mod tests {
                     None,
                     None,
                     None,
+                    None,
+                    None,
+                    None,
                     false,
                 )
                 .await
@@ -6433,6 +7230,9 @@ mod tests {
                     None,
                     None,
                     None,
+                    None,
+                    None,
+                    None,
                     false,
                 )
                 .await
@@ -6623,6 +7423,122 @@ mod tests {
         let _ = fs::remove_dir_all(remote_store_path.clone());
     }
 
+    #[tokio::test]
+    async fn replace_expired_partition() {
+        let config = Config::test("replace_expired_partition");
+        let store_path = env::current_dir()
+            .unwrap()
+            .join("replace_expired_partition_test-local");
+        let remote_store_path = env::current_dir()
+            .unwrap()
+            .join("replace_expired_partition_test-remote");
+        let _ = fs::remove_dir_all(store_path.clone());
+        let _ = fs::remove_dir_all(remote_store_path.clone());
+        let remote_fs = LocalDirRemoteFs::new(Some(remote_store_path.clone()), store_path.clone());
+        {
+            let meta_store = RocksMetaStore::new(
+                store_path.join("metastore").as_path(),
+                BaseRocksStoreFs::new_for_metastore(remote_fs.clone(), config.config_obj()),
+                config.config_obj(),
+            )
+            .unwrap();
+            meta_store
+                .create_schema("foo".to_string(), false)
+                .await
+                .unwrap();
+            let cols = vec![Column::new("name".to_string(), ColumnType::String, 0)];
+            meta_store
+                .create_table(
+                    "foo".to_string(),
+                    "bar".to_string(),
+                    cols.clone(),
+                    None,
+                    None,
+                    vec![],
+                    true,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    false,
+                )
+                .await
+                .unwrap();
+            let partition = meta_store.get_partition(1).await.unwrap();
+
+            let mut chunks = Vec::new();
+            for rows in [10, 16] {
+                let ch = meta_store
+                    .create_chunk(partition.get_id(), rows, None, None, false)
+                    .await
+                    .unwrap();
+                chunks.push(meta_store.chunk_uploaded(ch.get_id()).await.unwrap());
+            }
+            let (expired, fresh) = (chunks[0].clone(), chunks[1].clone());
+
+            meta_store
+                .replace_expired_partition((partition.clone(), vec![expired.clone()]))
+                .await
+                .unwrap();
+
+            let partitions = meta_store
+                .get_active_partitions_and_chunks_by_index_ids(vec![1])
+                .await
+                .unwrap();
+            assert_eq!(partitions.len(), 1);
+            let (new_partition, new_chunks) = &partitions[0];
+            assert_ne!(new_partition.get_id(), partition.get_id());
+            assert_eq!(
+                new_partition.get_row().get_min_val(),
+                partition.get_row().get_min_val()
+            );
+            assert_eq!(
+                new_partition.get_row().get_max_val(),
+                partition.get_row().get_max_val()
+            );
+            assert_eq!(new_partition.get_row().main_table_row_count(), 0);
+            assert_eq!(
+                new_chunks.iter().map(|c| c.get_id()).collect::<Vec<_>>(),
+                vec![fresh.get_id()]
+            );
+            assert!(!meta_store
+                .get_chunk(expired.get_id())
+                .await
+                .unwrap()
+                .get_row()
+                .active());
+            assert!(!meta_store
+                .get_partition(partition.get_id())
+                .await
+                .unwrap()
+                .get_row()
+                .is_active());
+
+            // Stale snapshot of the partition is ignored.
+            meta_store
+                .replace_expired_partition((partition.clone(), vec![]))
+                .await
+                .unwrap();
+            let partitions = meta_store
+                .get_active_partitions_by_index_id(1)
+                .await
+                .unwrap();
+            assert_eq!(partitions.len(), 1);
+            assert_eq!(partitions[0].get_id(), new_partition.get_id());
+        }
+
+        let _ = fs::remove_dir_all(store_path.clone());
+        let _ = fs::remove_dir_all(remote_store_path.clone());
+    }
+
     #[tokio::test]
     async fn job_priority_test() {
         let config = Config::test("job_priority_test");
@@ -1,7 +1,7 @@
 use crate::config::ConfigObj;
 use crate::metastore::snapshot_info::SnapshotInfo;
 use crate::metastore::{RocksStore, RocksStoreDetails, WriteBatchContainer};
-use crate::remotefs::RemoteFs;
+use crate::remotefs::{RemoteFile, RemoteFs};
 use crate::CubeError;
 use async_trait::async_trait;
 use datafusion::cube_ext;
@@ -9,8 +9,8 @@ use futures::future::join_all;
 use itertools::Itertools;
 use log::{error, info};
 use regex::Regex;
-use std::collections::BTreeSet;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::num::ParseIntError;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
@@ -49,6 +49,50 @@ pub trait MetaStoreFs: Send + Sync {
     ) -> Result<(), CubeError>;
     async fn get_snapshots_list(&self) -> Result<Vec<SnapshotInfo>, CubeError>;
     async fn write_metastore_current(&self, remote_path: &str) -> Result<(), CubeError>;
+    /// Copies the snapshot with its logs under the `location` prefix so it can be restored even
+    /// after it was removed by the snapshots cleanup.
+    async fn backup_snapshot(&self, snapshot: u128, location: &str) -> Result<(), CubeError>;
+}
+
+/// Uploaded WAL log of a snapshot.
+struct LogFile {
+    remote_path: String,
+    seq: usize,
+    /// Upload time in millis.
+    time: u128,
+}
+
+/// Logs are named `<seq>.flex`, the remote modification time is used as their upload time.
+/// Backup copies get a new modification time so they're named `<seq>-<upload time in millis>.flex`
+/// to keep the upload time of the original log.
+fn parse_log_file(file: &RemoteFile) -> Result<LogFile, CubeError> {
+    let name = file
+        .remote_path
+        .split("/")
+        .last()
+        .ok_or(CubeError::internal(format!(
+            "Can't split path: {}",
+            file.remote_path
+        )))?;
+    let parse_error = |e: ParseIntError| {
+        CubeError::internal(format!("Can't parse flex path {}: {}", file.remote_path, e))
+    };
+    let stem = name.replace(".flex", "");
+    let (seq, time) = match stem.split_once("-") {
+        Some((seq, time)) => (
+            seq.parse::<usize>().map_err(parse_error)?,
+            time.parse::<u128>().map_err(parse_error)?,
+        ),
+        None => (
+            stem.parse::<usize>().map_err(parse_error)?,
+            file.updated.timestamp_millis() as u128,
+        ),
+    };
+    Ok(LogFile {
+        remote_path: file.remote_path.clone(),
+        seq,
+        time,
+    })
 }
 
 #[derive(Clone)]
@@ -278,6 +322,170 @@ impl BaseRocksStoreFs {
             .collect::<Vec<_>>();
         Ok(res)
     }
+
+    async fn list_logs(&self, logs_dir: String) -> Result<Vec<LogFile>, CubeError> {
+        let mut logs = self
+            .remote_fs
+            .list_with_metadata(logs_dir)
+            .await?
+            .iter()
+            .map(parse_log_file)
+            .collect::<Result<Vec<_>, _>>()?;
+        logs.sort_unstable_by_key(|l| l.seq);
+        Ok(logs)
+    }
+
+    /// Applies already downloaded logs in order.
+    async fn replay_logs(
+        &self,
+        logs: &[LogFile],
+        rocks_store: &Arc<RocksStore>,
+    ) -> Result<(), CubeError> {
+        for log in logs.iter() {
+            let path_to_log = self.remote_fs.local_file(log.remote_path.clone()).await?;
+            let batch = WriteBatchContainer::read_from_file(&path_to_log).await;
+            if let Ok(batch) = batch {
+                let db = rocks_store.db.clone();
+                db.write(batch.write_batch())?;
+            } else if let Err(e) = batch {
+                error!(
+                    "Corrupted {} WAL file. Discarding: {:?} {}",
+                    self.name, log.remote_path, e
+                );
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn backup_location(&self, location: &str) -> Result<String, CubeError> {
+        let location = location.trim_matches('/');
+        // Snapshots cleanup and listing work with the `metastore-` and `cachestore-` prefixes.
+        if location.is_empty()
+            || location.starts_with("metastore")
+            || location.starts_with("cachestore")
+            || location.split("/").any(|p| p == "..")
+        {
+            return Err(CubeError::user(format!(
+                "Invalid {} backup location: '{}'",
+                self.name, location
+            )));
+        }
+        Ok(location.to_string())
+    }
+
+    async fn write_current_file(
+        &self,
+        remote_name: String,
+        content: &str,
+    ) -> Result<(), CubeError> {
+        let uploads_dir = self.remote_fs.uploads_dir().await?;
+        let prefix = format!("{}-current", self.name);
+        let (file, file_path) = cube_ext::spawn_blocking(move || {
+            tempfile::Builder::new()
+                .prefix(&prefix)
+                .tempfile_in(uploads_dir)
+        })
+        .await??
+        .into_parts();
+
+        tokio::io::AsyncWriteExt::write_all(&mut fs::File::from_std(file), content.as_bytes())
+            .await?;
+
+        self.remote_fs
+            .upload_file(file_path.keep()?.to_str().unwrap().to_string(), remote_name)
+            .await?;
+        Ok(())
+    }
+
+    /// Rebuilds the store from `snapshot` replaying its logs uploaded up to `until` (millis) and
+    /// uploads the result as a new current snapshot. `location` is the prefix the snapshot was
+    /// copied to by [MetaStoreFs::backup_snapshot], if any. The local copy of the store at `path`
+    /// is moved aside so the next start loads the restored snapshot. Returns the new snapshot id.
+    pub async fn restore_snapshot(
+        self: Arc<Self>,
+        snapshot: u128,
+        until: Option<u128>,
+        location: Option<&str>,
+        path: &Path,
+        config: Arc<dyn ConfigObj>,
+        rocks_details: Arc<dyn RocksStoreDetails>,
+    ) -> Result<u128, CubeError> {
+        if until.map_or(false, |until| until < snapshot) {
+            return Err(CubeError::user(format!(
+                "Can't restore {} snapshot {} to a point in time before it was created",
+                self.name, snapshot
+            )));
+        }
+        let location = location.map(|l| self.backup_location(l)).transpose()?;
+        let prefix = location
+            .as_ref()
+            .map_or("".to_string(), |l| format!("{}/", l));
+        let snapshot_dir = format!("{}{}-{}/", prefix, self.name, snapshot);
+        let logs_dir = format!("{}{}-{}-logs/", prefix, self.name, snapshot);
+
+        let checkpoint_files = self.remote_fs.list(snapshot_dir.clone()).await?;
+        if checkpoint_files.is_empty() {
+            return Err(CubeError::user(format!(
+                "{} snapshot with id {} don't exists{}",
+                self.name,
+                snapshot,
+                location.map_or("".to_string(), |l| format!(" in '{}'", l))
+            )));
+        }
+        let logs = self
+            .list_logs(logs_dir)
+            .await?
+            .into_iter()
+            .take_while(|l| until.map_or(true, |until| l.time <= until))
+            .collect::<Vec<_>>();
+
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        // Work in a separate directory to keep the local copy untouched if anything goes wrong.
+        let restore_path = PathBuf::from(format!("{}-restore-{}", path.to_string_lossy(), now));
+        fs::create_dir_all(&restore_path).await?;
+        for file in checkpoint_files.iter() {
+            let local = self.remote_fs.download_file(file.clone(), None).await?;
+            let local = Path::new(&local);
+            fs::copy(local, restore_path.join(local.file_name().unwrap())).await?;
+        }
+        for log in logs.iter() {
+            self.remote_fs
+                .download_file(log.remote_path.clone(), None)
+                .await?;
+        }
+
+        info!(
+            "Restoring {} snapshot {} with {} logs",
+            self.name,
+            snapshot,
+            logs.len()
+        );
+        let rocks_store = RocksStore::new(&restore_path, self.clone(), config, rocks_details)?;
+        self.replay_logs(&logs, &rocks_store).await?;
+        RocksStore::check_all_indexes(&rocks_store).await?;
+        rocks_store.upload_check_point().await?;
+        drop(rocks_store);
+
+        let restored = self.load_current_snapshot_id().await?.ok_or_else(|| {
+            CubeError::internal(format!("Can't read {}-current after restore", self.name))
+        })?;
+        info!(
+            "Restored {} snapshot {} as {}",
+            self.name, snapshot, restored
+        );
+
+        if fs::metadata(path).await.is_ok() {
+            let moved = format!("{}-before-restore-{}", path.to_string_lossy(), now);
+            info!("Moving local {} {:?} to {}", self.name, path, moved);
+            fs::rename(path, &moved).await?;
+        }
+        fs::remove_dir_all(&restore_path).await?;
+        Ok(restored)
+    }
 }
 
 #[async_trait]
@@ -376,55 +584,38 @@ impl MetaStoreFs for BaseRocksStoreFs {
         snapshot: u128,
         rocks_store: &Arc<RocksStore>,
     ) -> Result<(), CubeError> {
-        let logs_to_batch = self
-            .remote_fs
-            .list(format!("{}-{}-logs", self.name, snapshot))
+        let logs = self
+            .list_logs(format!("{}-{}-logs", self.name, snapshot))
             .await?;
-        let mut logs_to_batch_to_seq = logs_to_batch
-            .into_iter()
-            .map(|f| -> Result<_, CubeError> {
-                let last = f
-                    .split("/")
-                    .last()
-                    .ok_or(CubeError::internal(format!("Can't split path: {}", f)))?;
-                let result = last.replace(".flex", "").parse::<usize>().map_err(|e| {
-                    CubeError::internal(format!("Can't parse flex path {}: {}", f, e))
-                })?;
-                Ok((f, result))
-            })
-            .collect::<Result<Vec<_>, _>>()?;
-        logs_to_batch_to_seq.sort_unstable_by_key(|(_, seq)| *seq);
-
-        for (log_file, _) in logs_to_batch_to_seq.iter() {
-            let path_to_log = self.remote_fs.local_file(log_file.clone()).await?;
-            let batch = WriteBatchContainer::read_from_file(&path_to_log).await;
-            if let Ok(batch) = batch {
-                let db = rocks_store.db.clone();
-                db.write(batch.write_batch())?;
-            } else if let Err(e) = batch {
-                error!(
-                    "Corrupted {} WAL file. Discarding: {:?} {}",
-                    self.name, log_file, e
-                );
-                break;
-            }
-        }
-        Ok(())
+        self.replay_logs(&logs, rocks_store).await
     }
 
     async fn get_snapshots_list(&self) -> Result<Vec<SnapshotInfo>, CubeError> {
         let remote_fs = self.remote_fs();
 
         let re = Regex::new(&*format!(r"^{}-(\d+)/", self.get_name())).unwrap();
-        let stores = remote_fs.list(format!("{}-", self.get_name())).await?;
+        let logs_re = Regex::new(&*format!(r"^{}-(\d+)-logs/", self.get_name())).unwrap();
+        let stores = remote_fs
+            .list_with_metadata(format!("{}-", self.get_name()))
+            .await?;
         let mut snapshots = BTreeSet::new();
+        let mut last_log_times = HashMap::new();
         for store in stores.iter() {
             let parse_result = re
-                .captures(store)
+                .captures(&store.remote_path)
                 .map(|c| c.get(1).unwrap().as_str())
                 .map(|p| u128::from_str(p));
             if let Some(Ok(millis)) = parse_result {
                 snapshots.insert(millis);
+                continue;
+            }
+            let parse_result = logs_re
+                .captures(&store.remote_path)
+                .map(|c| c.get(1).unwrap().as_str())
+                .map(|p| u128::from_str(p));
+            if let (Some(Ok(millis)), Ok(log)) = (parse_result, parse_log_file(store)) {
+                let last_time = last_log_times.entry(millis).or_insert(log.time);
+                *last_time = log.time.max(*last_time);
             }
         }
         let current_id = self.parse_local_current_snapshot_id().await.unwrap_or(None);
@@ -433,30 +624,55 @@ impl MetaStoreFs for BaseRocksStoreFs {
             .map(|v| SnapshotInfo {
                 id: v,
                 current: current_id.map_or(false, |cid| cid == v),
+                restorable_until: last_log_times.get(&v).map_or(v, |t| v.max(*t)),
             })
             .collect::<Vec<_>>();
         Ok(res)
     }
     async fn write_metastore_current(&self, remote_path: &str) -> Result<(), CubeError> {
-        let uploads_dir = self.remote_fs.uploads_dir().await?;
-        let prefix = format!("{}-current", self.name);
-        let (file, file_path) = cube_ext::spawn_blocking(move || {
-            tempfile::Builder::new()
-                .prefix(&prefix)
-                .tempfile_in(uploads_dir)
-        })
-        .await??
-        .into_parts();
-
-        tokio::io::AsyncWriteExt::write_all(&mut fs::File::from_std(file), remote_path.as_bytes())
-            .await?;
+        self.write_current_file(format!("{}-current", self.name), remote_path)
+            .await
+    }
 
-        self.remote_fs
-            .upload_file(
-                file_path.keep()?.to_str().unwrap().to_string(),
-                format!("{}-current", self.name),
-            )
+    async fn backup_snapshot(&self, snapshot: u128, location: &str) -> Result<(), CubeError> {
+        let location = self.backup_location(location)?;
+        let files = self
+            .remote_fs
+            .list_with_metadata(format!("{}-{}", self.name, snapshot))
             .await?;
+        if files.is_empty() {
+            return Err(CubeError::user(format!(
+                "{} snapshot with id {} don't exists",
+                self.name, snapshot
+            )));
+        }
+        for file in files.iter() {
+            let local = self
+                .remote_fs
+                .download_file(file.remote_path.clone(), None)
+                .await?;
+            let backup_path = match file.remote_path.strip_suffix(".flex") {
+                Some(log) => format!(
+                    "{}/{}-{}.flex",
+                    location,
+                    log,
+                    file.updated.timestamp_millis()
+                ),
+                None => format!("{}/{}", location, file.remote_path),
+            };
+            let temp_path = self.remote_fs.temp_upload_path(backup_path.clone()).await?;
+            fs::copy(&local, &temp_path).await?;
+            self.remote_fs.upload_file(temp_path, backup_path).await?;
+        }
+        self.write_current_file(
+            format!("{}/{}-current", location, self.name),
+            &format!("{}-{}", self.name, snapshot),
+        )
+        .await?;
+        info!(
+            "Backed up {} snapshot {} to {}",
+            self.name, snapshot, location
+        );
         Ok(())
     }
 }
@@ -1,3 +1,4 @@
+use crate::cachestore::CacheItemChangeEvent;
 use crate::config::ConfigObj;
 use crate::metastore::table::TablePath;
 use crate::metastore::{MetaStoreEvent, MetaStoreFs};
@@ -27,7 +28,7 @@ use std::time::{Duration, SystemTime};
 use std::{env, mem, time};
 use tokio::fs;
 use tokio::fs::File;
-use tokio::sync::broadcast::Sender;
+use tokio::sync::broadcast::{self, Sender};
 use tokio::sync::{oneshot, Mutex as AsyncMutex, Notify, RwLock};
 
 macro_rules! enum_from_primitive_impl {
@@ -76,7 +77,9 @@ enum_from_primitive! {
         CacheItems = 0x0C00,
         QueueItems = 0x0D00,
         QueueResults = 0x0E00,
-        TraceObjects = 0x0F00
+        TraceObjects = 0x0F00,
+        QueuePrefixes = 0x1000,
+        Workers = 0x1100
 
     }
 }
@@ -99,6 +102,8 @@ impl TableId {
             TableId::QueueItems => true,
             TableId::QueueResults => true,
             TableId::TraceObjects => false,
+            TableId::QueuePrefixes => false,
+            TableId::Workers => false,
         }
     }
 }
@@ -788,11 +793,16 @@ pub trait RocksStoreDetails: Send + Sync {
 }
 
 #[derive(Clone)]
+const CACHE_CHANGE_CHANNEL_CAPACITY: usize = 2048;
+
 pub struct RocksStore {
     pub db: Arc<DB>,
     pub config: Arc<dyn ConfigObj>,
     seq_store: Arc<Mutex<HashMap<TableId, u64>>>,
     pub listeners: Arc<RwLock<Vec<Sender<MetaStoreEvent>>>>,
+    /// [MetaStoreEvent::CacheItemChange] events are sent here instead of `listeners`. They are
+    /// emitted on every cache write, so other listeners would lag behind them.
+    pub cache_change_sender: Sender<CacheItemChangeEvent>,
     metastore_fs: Arc<dyn MetaStoreFs>,
     last_checkpoint_time: Arc<RwLock<SystemTime>>,
     write_notify: Arc<Notify>,
@@ -871,6 +881,7 @@ impl RocksStore {
             db: db_arc.clone(),
             seq_store: Arc::new(Mutex::new(HashMap::new())),
             listeners: Arc::new(RwLock::new(listeners)),
+            cache_change_sender: broadcast::channel(CACHE_CHANGE_CHANNEL_CAPACITY).0,
             metastore_fs,
             last_checkpoint_time: Arc::new(RwLock::new(SystemTime::now())),
             snapshot_uploaded: Arc::new(RwLock::new(false)),
@@ -1030,9 +1041,18 @@ impl RocksStore {
         self.write_notify.notify_waiters();
 
         if events.len() > 0 {
-            for listener in self.listeners.read().await.clone().iter_mut() {
-                for event in events.iter() {
-                    listener.send(event.clone())?;
+            let listeners = self.listeners.read().await.clone();
+            for event in events {
+                match event {
+                    MetaStoreEvent::CacheItemChange(change_event) => {
+                        // Error means that nobody is subscribed to cache changes
+                        let _ = self.cache_change_sender.send(change_event);
+                    }
+                    event => {
+                        for listener in listeners.iter() {
+                            listener.send(event.clone())?;
+                        }
+                    }
                 }
             }
         }
@@ -1410,6 +1430,28 @@ impl RocksStore {
         *upload_stopped = true;
         Ok(())
     }
+
+    /// Uploads a fresh checkpoint and copies it under `location` on the remote fs. Returns the id
+    /// of the backed up snapshot.
+    pub async fn backup(&self, location: &str) -> Result<u128, CubeError> {
+        if *self.snapshots_upload_stopped.lock().await {
+            return Err(CubeError::user(format!(
+                "Can't backup {}: snapshots upload is stopped after the current snapshot was changed",
+                self.details.get_name()
+            )));
+        }
+        self.upload_check_point().await?;
+        let snapshot = self
+            .last_checkpoint_time
+            .read()
+            .await
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_millis();
+        self.metastore_fs
+            .backup_snapshot(snapshot, location)
+            .await?;
+        Ok(snapshot)
+    }
 }
 
 #[cfg(test)]
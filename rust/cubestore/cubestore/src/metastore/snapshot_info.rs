@@ -4,4 +4,7 @@ use serde::{Deserialize, Serialize};
 pub struct SnapshotInfo {
     pub id: u128,
     pub current: bool,
+    /// Time in millis of the latest uploaded WAL log of this snapshot, i.e. the latest point in
+    /// time the snapshot can be restored to. Equals to [id] if there are no logs.
+    pub restorable_until: u128,
 }
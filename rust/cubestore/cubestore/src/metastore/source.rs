@@ -1,11 +1,15 @@
 use super::{IndexId, RocksSecondaryIndex, TableId};
 use crate::base_rocks_secondary_index;
+use crate::metastore::table::StreamValueFormat;
 use crate::metastore::{DataFrameValue, RocksEntity};
 use crate::rocks_table_impl;
+use crate::CubeError;
 use byteorder::{BigEndian, WriteBytesExt};
 
 use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
 use std::io::{Cursor, Write};
+use std::str::FromStr;
 
 #[derive(Clone, Serialize, Deserialize, Debug, Hash)]
 pub enum SourceCredentials {
@@ -19,9 +23,95 @@ pub enum SourceCredentials {
         password: Option<String>,
         host: String,
         use_ssl: bool,
+        #[serde(default)]
+        value_format: Option<StreamValueFormat>,
+        #[serde(default)]
+        schema_registry_url: Option<String>,
+        #[serde(default)]
+        security: KafkaSecurity,
     },
 }
 
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Eq, PartialEq, Hash)]
+pub enum KafkaSecurityProtocol {
+    Plaintext = 1,
+    Ssl = 2,
+    SaslPlaintext = 3,
+    SaslSsl = 4,
+}
+
+impl KafkaSecurityProtocol {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KafkaSecurityProtocol::Plaintext => "PLAINTEXT",
+            KafkaSecurityProtocol::Ssl => "SSL",
+            KafkaSecurityProtocol::SaslPlaintext => "SASL_PLAINTEXT",
+            KafkaSecurityProtocol::SaslSsl => "SASL_SSL",
+        }
+    }
+}
+
+impl FromStr for KafkaSecurityProtocol {
+    type Err = CubeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_ref() {
+            "PLAINTEXT" => Ok(KafkaSecurityProtocol::Plaintext),
+            "SSL" => Ok(KafkaSecurityProtocol::Ssl),
+            "SASL_PLAINTEXT" => Ok(KafkaSecurityProtocol::SaslPlaintext),
+            "SASL_SSL" => Ok(KafkaSecurityProtocol::SaslSsl),
+            _ => Err(CubeError::user(format!("Unknown security protocol: {}", s))),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Eq, PartialEq, Hash)]
+pub enum KafkaSaslMechanism {
+    Plain = 1,
+    ScramSha256 = 2,
+    ScramSha512 = 3,
+}
+
+impl KafkaSaslMechanism {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KafkaSaslMechanism::Plain => "PLAIN",
+            KafkaSaslMechanism::ScramSha256 => "SCRAM-SHA-256",
+            KafkaSaslMechanism::ScramSha512 => "SCRAM-SHA-512",
+        }
+    }
+}
+
+impl FromStr for KafkaSaslMechanism {
+    type Err = CubeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().replace('_', "-").as_ref() {
+            "PLAIN" => Ok(KafkaSaslMechanism::Plain),
+            "SCRAM-SHA-256" => Ok(KafkaSaslMechanism::ScramSha256),
+            "SCRAM-SHA-512" => Ok(KafkaSaslMechanism::ScramSha512),
+            _ => Err(CubeError::user(format!("Unknown SASL mechanism: {}", s))),
+        }
+    }
+}
+
+/// Kafka connection settings besides `use_ssl` and SASL credentials. Certificates and keys are
+/// stored in PEM so that every worker can connect without extra files.
+#[derive(Clone, Serialize, Deserialize, Debug, Default, Eq, PartialEq, Hash)]
+pub struct KafkaSecurity {
+    /// `SASL_SSL` if `use_ssl` is set and nothing by default otherwise.
+    pub security_protocol: Option<KafkaSecurityProtocol>,
+    /// `PLAIN` by default.
+    pub sasl_mechanism: Option<KafkaSaslMechanism>,
+    pub ssl_ca_pem: Option<String>,
+    /// Client certificate for mutual TLS.
+    pub ssl_certificate_pem: Option<String>,
+    pub ssl_key_pem: Option<String>,
+    pub ssl_key_password: Option<String>,
+    /// librdkafka consumer properties set as is. They override all other settings.
+    pub properties: BTreeMap<String, String>,
+}
+
 impl DataFrameValue<String> for SourceCredentials {
     fn value(v: &Self) -> String {
         format!("{:?}", v)
@@ -17,6 +17,7 @@ use itertools::Itertools;
 
 use serde::{Deserialize, Deserializer, Serialize};
 use std::io::Write;
+use std::str::FromStr;
 use std::sync::Arc;
 
 #[derive(Clone, Serialize, Deserialize, Debug, Eq, PartialEq, Hash)]
@@ -117,6 +118,209 @@ impl DataFrameValue<String> for Option<StreamOffset> {
     }
 }
 
+/// Encoding of messages consumed by streaming tables.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Eq, PartialEq, Hash)]
+pub enum StreamValueFormat {
+    Json = 1,
+    /// Confluent Avro: messages reference their schema in a schema registry.
+    Avro = 2,
+    /// Confluent Protobuf: messages reference their schema in a schema registry.
+    Protobuf = 3,
+}
+
+impl FromStr for StreamValueFormat {
+    type Err = CubeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_ref() {
+            "json" => Ok(StreamValueFormat::Json),
+            "avro" => Ok(StreamValueFormat::Avro),
+            "protobuf" => Ok(StreamValueFormat::Protobuf),
+            _ => Err(CubeError::user(format!("Unknown value format: {}", s))),
+        }
+    }
+}
+
+impl DataFrameValue<String> for Option<StreamValueFormat> {
+    fn value(v: &Self) -> String {
+        v.as_ref()
+            .map(|s| format!("{:?}", s))
+            .unwrap_or("NULL".to_string())
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Eq, PartialEq, Hash)]
+pub enum ParquetCompression {
+    Uncompressed = 1,
+    Snappy = 2,
+    Gzip = 3,
+    Brotli = 4,
+    Lz4 = 5,
+    Zstd = 6,
+}
+
+impl FromStr for ParquetCompression {
+    type Err = CubeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_ref() {
+            "uncompressed" | "none" => Ok(ParquetCompression::Uncompressed),
+            "snappy" => Ok(ParquetCompression::Snappy),
+            "gzip" => Ok(ParquetCompression::Gzip),
+            "brotli" => Ok(ParquetCompression::Brotli),
+            "lz4" => Ok(ParquetCompression::Lz4),
+            "zstd" => Ok(ParquetCompression::Zstd),
+            _ => Err(CubeError::user(format!("Unknown compression: {}", s))),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Eq, PartialEq, Hash)]
+pub enum ParquetEncoding {
+    Plain = 1,
+    DeltaBinaryPacked = 2,
+    DeltaLengthByteArray = 3,
+    DeltaByteArray = 4,
+}
+
+impl FromStr for ParquetEncoding {
+    type Err = CubeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_ref() {
+            "plain" => Ok(ParquetEncoding::Plain),
+            "delta_binary_packed" => Ok(ParquetEncoding::DeltaBinaryPacked),
+            "delta_length_byte_array" => Ok(ParquetEncoding::DeltaLengthByteArray),
+            "delta_byte_array" => Ok(ParquetEncoding::DeltaByteArray),
+            _ => Err(CubeError::user(format!("Unknown encoding: {}", s))),
+        }
+    }
+}
+
+impl ParquetEncoding {
+    /// Delta encodings are supported by parquet only for specific physical types.
+    pub fn allowed_for_type(&self, col_type: &ColumnType) -> bool {
+        match self {
+            Self::Plain => true,
+            Self::DeltaBinaryPacked => match col_type {
+                ColumnType::Int | ColumnType::Timestamp | ColumnType::Decimal { .. } => true,
+                _ => false,
+            },
+            Self::DeltaLengthByteArray | Self::DeltaByteArray => match col_type {
+                ColumnType::String | ColumnType::Bytes | ColumnType::HyperLogLog(_) => true,
+                _ => false,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Eq, PartialEq, Hash, Default)]
+pub struct ParquetColumnOptions {
+    pub compression: Option<ParquetCompression>,
+    pub encoding: Option<ParquetEncoding>,
+    pub dictionary: Option<bool>,
+}
+
+/// Options for parquet files of partitions and chunks. Column options override table ones.
+#[derive(Clone, Serialize, Deserialize, Debug, Eq, PartialEq, Hash, Default)]
+pub struct ParquetOptions {
+    pub compression: Option<ParquetCompression>,
+    pub dictionary: Option<bool>,
+    pub columns: Vec<(String, ParquetColumnOptions)>,
+}
+
+impl ParquetOptions {
+    pub fn column_mut(&mut self, name: &str) -> &mut ParquetColumnOptions {
+        let position = match self.columns.iter().position(|(c, _)| c == name) {
+            Some(position) => position,
+            None => {
+                self.columns
+                    .push((name.to_string(), ParquetColumnOptions::default()));
+                self.columns.len() - 1
+            }
+        };
+
+        &mut self.columns[position].1
+    }
+
+    pub fn remove_column(&mut self, name: &str) {
+        self.columns.retain(|(c, _)| c != name);
+    }
+
+    pub fn validate(&self, columns: &Vec<Column>) -> Result<(), CubeError> {
+        for (name, options) in self.columns.iter() {
+            let column = columns
+                .iter()
+                .find(|c| c.get_name() == name)
+                .ok_or_else(|| {
+                    CubeError::user(format!(
+                        "Column {} from parquet options not found among column definitions {:?}",
+                        name, columns
+                    ))
+                })?;
+
+            if let Some(encoding) = &options.encoding {
+                if !encoding.allowed_for_type(column.get_column_type()) {
+                    return Err(CubeError::user(format!(
+                        "Encoding {:?} not allowed for column {} with type {}",
+                        encoding,
+                        name,
+                        column.get_column_type()
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Change of table definition requested by `ALTER TABLE`.
+#[derive(Clone, Serialize, Deserialize, Debug, Eq, PartialEq, Hash)]
+pub enum TableAlteration {
+    AddColumn {
+        name: String,
+        column_type: ColumnType,
+    },
+    DropColumn {
+        name: String,
+    },
+    Rename {
+        table_name: String,
+    },
+}
+
+impl DataFrameValue<String> for Option<ParquetOptions> {
+    fn value(v: &Self) -> String {
+        v.as_ref()
+            .map(|v| serde_json::to_string(v).unwrap())
+            .unwrap_or("NULL".to_string())
+    }
+}
+
+/// Rows with the timestamp column value older than the period are dropped by the scheduler and
+/// at compaction time.
+#[derive(Clone, Serialize, Deserialize, Debug, Eq, PartialEq, Hash)]
+pub struct TableRetention {
+    pub column_index: u64,
+    pub period_secs: u64,
+}
+
+impl TableRetention {
+    /// Rows with the column value before the cutoff are expired.
+    pub fn cutoff(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now - chrono::Duration::seconds(self.period_secs as i64)
+    }
+}
+
+impl DataFrameValue<String> for Option<TableRetention> {
+    fn value(v: &Self) -> String {
+        v.as_ref()
+            .map(|v| serde_json::to_string(v).unwrap())
+            .unwrap_or("NULL".to_string())
+    }
+}
+
 data_frame_from! {
 #[derive(Clone, Serialize, Deserialize, Debug, Eq, PartialEq, Hash)]
 pub struct Table {
@@ -153,7 +357,13 @@ pub struct Table {
     #[serde(default)]
     location_download_sizes: Option<Vec<u64>>,
     #[serde(default)]
-    partition_split_threshold: Option<u64>
+    partition_split_threshold: Option<u64>,
+    #[serde(default)]
+    parquet_options: Option<ParquetOptions>,
+    #[serde(default)]
+    retention: Option<TableRetention>,
+    #[serde(default)]
+    value_format: Option<StreamValueFormat>
 }
 }
 
@@ -190,6 +400,9 @@ impl Table {
         aggregate_column_indices: Vec<AggregateColumnIndex>,
         seq_column_index: Option<u64>,
         partition_split_threshold: Option<u64>,
+        parquet_options: Option<ParquetOptions>,
+        retention: Option<TableRetention>,
+        value_format: Option<StreamValueFormat>,
     ) -> Table {
         let location_download_sizes = locations.as_ref().map(|locations| vec![0; locations.len()]);
         Table {
@@ -212,6 +425,9 @@ impl Table {
             seq_column_index,
             location_download_sizes,
             partition_split_threshold,
+            parquet_options,
+            retention,
+            value_format,
         }
     }
     pub fn get_columns(&self) -> &Vec<Column> {
@@ -367,6 +583,25 @@ impl Table {
             .unwrap_or(config_partition_split_threshold)
     }
 
+    pub fn parquet_options(&self) -> &Option<ParquetOptions> {
+        &self.parquet_options
+    }
+
+    pub fn retention(&self) -> &Option<TableRetention> {
+        &self.retention
+    }
+
+    pub fn retention_column(&self) -> Option<&Column> {
+        self.retention
+            .as_ref()
+            .map(|r| &self.columns[r.column_index as usize])
+    }
+
+    /// Overrides the value format of the stream source.
+    pub fn value_format(&self) -> &Option<StreamValueFormat> {
+        &self.value_format
+    }
+
     pub fn location_index(&self, location: &str) -> Result<usize, CubeError> {
         let locations = self.locations().ok_or_else(|| {
             CubeError::internal(format!(
@@ -389,6 +624,111 @@ impl Table {
     pub fn stream_offset(&self) -> &Option<StreamOffset> {
         &self.stream_offset
     }
+
+    pub fn rename(&self, table_name: String) -> Self {
+        let mut t = self.clone();
+        t.table_name = table_name;
+        t
+    }
+
+    /// Appends a column. Rows written before get NULL values for it.
+    pub fn add_column(&self, name: String, column_type: ColumnType) -> Result<Self, CubeError> {
+        if self.columns.iter().any(|c| c.get_name() == &name) {
+            return Err(CubeError::user(format!(
+                "Column {} already exists in table {}",
+                name, self.table_name
+            )));
+        }
+        if name.starts_with(Column::DROPPED_PREFIX) {
+            return Err(CubeError::user(format!(
+                "Column name can't start with {}: {}",
+                Column::DROPPED_PREFIX,
+                name
+            )));
+        }
+        let mut t = self.clone();
+        t.columns
+            .push(Column::new(name, column_type, self.columns.len()));
+        Ok(t)
+    }
+
+    /// Removes a column and shifts positions of the following ones.
+    pub fn drop_column(&self, name: &str) -> Result<Self, CubeError> {
+        let dropped = self
+            .columns
+            .iter()
+            .find(|c| c.get_name() == name)
+            .ok_or_else(|| {
+                CubeError::user(format!(
+                    "Column {} is not present in table {}",
+                    name, self.table_name
+                ))
+            })?
+            .get_index() as u64;
+        if self.columns.len() == 1 {
+            return Err(CubeError::user(format!(
+                "Can't drop the only column {} of table {}",
+                name, self.table_name
+            )));
+        }
+        if self
+            .aggregate_column_indices
+            .iter()
+            .any(|a| a.index == dropped)
+        {
+            return Err(CubeError::user(format!(
+                "Aggregate column {} can't be dropped",
+                name
+            )));
+        }
+        let is_key_column = self
+            .unique_key_column_indices
+            .as_ref()
+            .map_or(false, |k| k.contains(&dropped))
+            || self.seq_column_index == Some(dropped);
+        if is_key_column {
+            return Err(CubeError::user(format!(
+                "Key column {} can't be dropped",
+                name
+            )));
+        }
+        if self
+            .retention
+            .as_ref()
+            .map_or(false, |r| r.column_index == dropped)
+        {
+            return Err(CubeError::user(format!(
+                "Retention column {} can't be dropped",
+                name
+            )));
+        }
+        let shift = |i: u64| if i > dropped { i - 1 } else { i };
+
+        let mut t = self.clone();
+        t.columns = self
+            .columns
+            .iter()
+            .filter(|c| c.get_index() as u64 != dropped)
+            .map(|c| c.replace_index(shift(c.get_index() as u64) as usize))
+            .collect();
+        t.aggregate_column_indices = self
+            .aggregate_column_indices
+            .iter()
+            .map(|a| AggregateColumnIndex::new(shift(a.index), a.function.clone()))
+            .collect();
+        t.unique_key_column_indices = self
+            .unique_key_column_indices
+            .as_ref()
+            .map(|k| k.iter().map(|i| shift(*i)).collect());
+        t.seq_column_index = self.seq_column_index.map(shift);
+        if let Some(retention) = &mut t.retention {
+            retention.column_index = shift(retention.column_index);
+        }
+        if let Some(options) = &mut t.parquet_options {
+            options.remove_column(name);
+        }
+        Ok(t)
+    }
 }
 
 impl Column {
@@ -418,6 +758,22 @@ impl Column {
             column_index,
         }
     }
+
+    /// Prefix of index columns which were dropped from the table but are still stored in files.
+    pub const DROPPED_PREFIX: &'static str = "__dropped_";
+
+    /// Renames the column so it's hidden from the table while kept at the same index position.
+    pub fn to_dropped(&self) -> Column {
+        Column {
+            name: format!("{}{}", Self::DROPPED_PREFIX, self.column_index),
+            column_type: self.column_type.clone(),
+            column_index: self.column_index,
+        }
+    }
+
+    pub fn is_dropped(&self) -> bool {
+        self.name.starts_with(Self::DROPPED_PREFIX)
+    }
 }
 
 rocks_table_impl!(Table, TableRocksTable, TableId::Tables, {
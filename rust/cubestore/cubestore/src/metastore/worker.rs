@@ -0,0 +1,132 @@
+use super::{IndexId, RocksSecondaryIndex, TableId};
+use crate::base_rocks_secondary_index;
+use crate::metastore::RocksEntity;
+use crate::rocks_table_impl;
+use chrono::{DateTime, Utc};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WorkerStatus {
+    Joining,
+    WarmingUp,
+    Active,
+    HandingOver,
+}
+
+impl WorkerStatus {
+    /// Whether the worker owns partitions and takes traffic.
+    pub fn is_active(&self) -> bool {
+        match self {
+            WorkerStatus::Joining | WorkerStatus::WarmingUp => false,
+            WorkerStatus::Active | WorkerStatus::HandingOver => true,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WorkerStatus::Joining => "joining",
+            WorkerStatus::WarmingUp => "warming_up",
+            WorkerStatus::Active => "active",
+            WorkerStatus::HandingOver => "handing_over",
+        }
+    }
+}
+
+/// Worker that joined the cluster at runtime. See [crate::cluster::membership].
+#[derive(Clone, Serialize, Deserialize, Debug, Hash, PartialEq, Eq)]
+pub struct Worker {
+    name: String,
+    status: WorkerStatus,
+    registered_at: DateTime<Utc>,
+    last_heartbeat: DateTime<Utc>,
+}
+
+impl RocksEntity for Worker {}
+
+impl Worker {
+    pub fn new(name: String, now: DateTime<Utc>) -> Worker {
+        Worker {
+            name,
+            status: WorkerStatus::Joining,
+            registered_at: now,
+            last_heartbeat: now,
+        }
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn status(&self) -> WorkerStatus {
+        self.status
+    }
+
+    pub fn registered_at(&self) -> &DateTime<Utc> {
+        &self.registered_at
+    }
+
+    pub fn last_heartbeat(&self) -> &DateTime<Utc> {
+        &self.last_heartbeat
+    }
+
+    pub fn update_status(&self, status: WorkerStatus) -> Worker {
+        Worker {
+            status,
+            ..self.clone()
+        }
+    }
+
+    pub fn update_last_heartbeat(&self, last_heartbeat: DateTime<Utc>) -> Worker {
+        Worker {
+            last_heartbeat,
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum WorkerRocksIndex {
+    ByName = 1,
+}
+
+base_rocks_secondary_index!(Worker, WorkerRocksIndex);
+
+rocks_table_impl!(Worker, WorkerRocksTable, TableId::Workers, {
+    vec![Box::new(WorkerRocksIndex::ByName)]
+});
+
+#[derive(Hash, Clone, Debug)]
+pub enum WorkerIndexKey {
+    ByName(String),
+}
+
+impl RocksSecondaryIndex<Worker, WorkerIndexKey> for WorkerRocksIndex {
+    fn typed_key_by(&self, row: &Worker) -> WorkerIndexKey {
+        match self {
+            WorkerRocksIndex::ByName => WorkerIndexKey::ByName(row.name.clone()),
+        }
+    }
+
+    fn key_to_bytes(&self, key: &WorkerIndexKey) -> Vec<u8> {
+        match key {
+            WorkerIndexKey::ByName(name) => name.as_bytes().to_vec(),
+        }
+    }
+
+    fn is_unique(&self) -> bool {
+        match self {
+            WorkerRocksIndex::ByName => true,
+        }
+    }
+
+    fn version(&self) -> u32 {
+        match self {
+            WorkerRocksIndex::ByName => 1,
+        }
+    }
+
+    fn get_id(&self) -> IndexId {
+        *self as IndexId
+    }
+}
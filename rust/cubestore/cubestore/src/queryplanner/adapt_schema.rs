@@ -0,0 +1,264 @@
+use crate::metastore::{ColumnType, Index};
+use crate::table::data::null_array;
+use crate::table::parquet::arrow_schema;
+use crate::CubeError;
+use arrow::datatypes::{Schema, SchemaRef};
+use arrow::error::Result as ArrowResult;
+use arrow::record_batch::RecordBatch;
+use async_trait::async_trait;
+use datafusion::error::DataFusionError;
+use datafusion::physical_plan::{
+    ExecutionPlan, OptimizerHints, Partitioning, RecordBatchStream, SendableRecordBatchStream,
+};
+use futures::stream::Stream;
+use futures::StreamExt;
+use std::any::Any;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// Files and in-memory chunks keep the index layout they were written with. Columns added by
+/// `ALTER TABLE` are missing at their end and dropped columns are stored under old names.
+pub fn adapt_batch_to_index(batch: RecordBatch, index: &Index) -> Result<RecordBatch, CubeError> {
+    let schema = Arc::new(arrow_schema(index));
+    if !needs_adaptation(batch.schema().as_ref(), schema.as_ref()) {
+        return Ok(batch);
+    }
+    let columns = index_columns_mapping(batch.num_columns(), index)?;
+    Ok(adapt_batch(&batch, schema, &columns)?)
+}
+
+/// Same as [adapt_batch_to_index] for plans which read data of the whole index.
+pub fn adapt_plan_to_index(
+    input: Arc<dyn ExecutionPlan>,
+    index: &Index,
+) -> Result<Arc<dyn ExecutionPlan>, CubeError> {
+    let schema = Arc::new(arrow_schema(index));
+    if !needs_adaptation(input.schema().as_ref(), schema.as_ref()) {
+        return Ok(input);
+    }
+    let columns = index_columns_mapping(input.schema().fields().len(), index)?;
+    Ok(Arc::new(AdaptSchemaExec::new(input, schema, columns)))
+}
+
+fn needs_adaptation(actual: &Schema, expected: &Schema) -> bool {
+    actual.fields().len() != expected.fields().len()
+        || actual
+            .fields()
+            .iter()
+            .zip(expected.fields().iter())
+            .any(|(a, e)| a.name() != e.name())
+}
+
+fn index_columns_mapping(
+    num_columns: usize,
+    index: &Index,
+) -> Result<Vec<(Option<usize>, ColumnType)>, CubeError> {
+    if index.columns().len() < num_columns {
+        return Err(CubeError::internal(format!(
+            "Data has {} columns which is more than {} columns of index {}",
+            num_columns,
+            index.columns().len(),
+            index.get_name()
+        )));
+    }
+    Ok(index
+        .columns()
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let source = if i < num_columns { Some(i) } else { None };
+            (source, c.get_column_type().clone())
+        })
+        .collect())
+}
+
+fn adapt_batch(
+    batch: &RecordBatch,
+    schema: SchemaRef,
+    columns: &[(Option<usize>, ColumnType)],
+) -> ArrowResult<RecordBatch> {
+    let columns = columns
+        .iter()
+        .map(|(source, column_type)| match source {
+            Some(i) => batch.column(*i).clone(),
+            None => null_array(column_type, batch.num_rows()),
+        })
+        .collect();
+    RecordBatch::try_new(schema, columns)
+}
+
+/// Converts input batches to `schema`. Every output column is either taken from the input or
+/// filled with NULLs.
+#[derive(Debug)]
+pub struct AdaptSchemaExec {
+    pub input: Arc<dyn ExecutionPlan>,
+    pub schema: SchemaRef,
+    pub columns: Vec<(Option<usize>, ColumnType)>,
+}
+
+impl AdaptSchemaExec {
+    pub fn new(
+        input: Arc<dyn ExecutionPlan>,
+        schema: SchemaRef,
+        columns: Vec<(Option<usize>, ColumnType)>,
+    ) -> Self {
+        assert_eq!(schema.fields().len(), columns.len());
+        Self {
+            input,
+            schema,
+            columns,
+        }
+    }
+}
+
+#[async_trait]
+impl ExecutionPlan for AdaptSchemaExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        self.input.output_partitioning()
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.input.clone()]
+    }
+
+    fn with_new_children(
+        &self,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>, DataFusionError> {
+        assert_eq!(children.len(), 1);
+        Ok(Arc::new(Self {
+            input: children.into_iter().next().unwrap(),
+            schema: self.schema.clone(),
+            columns: self.columns.clone(),
+        }))
+    }
+
+    fn output_hints(&self) -> OptimizerHints {
+        let hints = self.input.output_hints();
+        let output_index = |input_index: usize| {
+            self.columns
+                .iter()
+                .position(|(source, _)| *source == Some(input_index))
+        };
+        OptimizerHints {
+            sort_order: hints.sort_order.and_then(|order| {
+                order
+                    .into_iter()
+                    .map(output_index)
+                    .collect::<Option<Vec<_>>>()
+            }),
+            single_value_columns: hints
+                .single_value_columns
+                .into_iter()
+                .filter_map(output_index)
+                .collect(),
+        }
+    }
+
+    async fn execute(
+        &self,
+        partition: usize,
+    ) -> Result<SendableRecordBatchStream, DataFusionError> {
+        let input = self.input.execute(partition).await?;
+        Ok(Box::pin(AdaptSchemaStream {
+            schema: self.schema.clone(),
+            columns: self.columns.clone(),
+            input,
+        }))
+    }
+}
+
+struct AdaptSchemaStream {
+    schema: SchemaRef,
+    columns: Vec<(Option<usize>, ColumnType)>,
+    input: SendableRecordBatchStream,
+}
+
+impl Stream for AdaptSchemaStream {
+    type Item = ArrowResult<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.input.poll_next_unpin(cx).map(|x| match x {
+            Some(Ok(batch)) => Some(adapt_batch(&batch, self.schema.clone(), &self.columns)),
+            other => other,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl RecordBatchStream for AdaptSchemaStream {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metastore::{Column, IndexType};
+    use arrow::array::{ArrayRef, Int64Array, StringArray};
+    use datafusion::physical_plan::collect;
+    use datafusion::physical_plan::memory::MemoryExec;
+
+    #[tokio::test]
+    async fn pads_added_and_renames_dropped_columns() {
+        let old_columns = vec![
+            Column::new("id".to_string(), ColumnType::Int, 0),
+            Column::new("name".to_string(), ColumnType::String, 1),
+        ];
+        let old_index = Index::try_new(
+            "default".to_string(),
+            1,
+            old_columns,
+            2,
+            None,
+            None,
+            IndexType::Regular,
+            None,
+        )
+        .unwrap();
+        let data: Vec<ArrayRef> = vec![
+            Arc::new(Int64Array::from(vec![1, 2])),
+            Arc::new(StringArray::from(vec!["a", "b"])),
+        ];
+        let old_schema = Arc::new(arrow_schema(&old_index));
+        let batch = RecordBatch::try_new(old_schema.clone(), data).unwrap();
+
+        let index = old_index.drop_column("name").add_column(&Column::new(
+            "amount".to_string(),
+            ColumnType::Int,
+            1,
+        ));
+        let expected_schema = Arc::new(arrow_schema(&index));
+        assert_eq!(expected_schema.field(1).name(), "__dropped_1");
+
+        let adapted = adapt_batch_to_index(batch.clone(), &index).unwrap();
+        assert_eq!(adapted.schema(), expected_schema);
+        assert_eq!(adapted.column(2).null_count(), 2);
+
+        let input = Arc::new(MemoryExec::try_new(&[vec![batch]], old_schema, None).unwrap());
+        let plan = adapt_plan_to_index(input, &index).unwrap();
+        assert_eq!(plan.schema(), expected_schema);
+        let batches = collect(plan).await.unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].schema(), expected_schema);
+        assert_eq!(batches[0].column(0).data(), adapted.column(0).data());
+        assert_eq!(batches[0].column(2).null_count(), 2);
+
+        // Data with the current layout is returned as is.
+        let same = adapt_plan_to_index(plan.clone(), &index).unwrap();
+        assert!(Arc::ptr_eq(&same, &plan));
+    }
+}
@@ -8,10 +8,12 @@ mod system_indexes;
 mod system_jobs;
 mod system_partitions;
 mod system_queue;
+mod system_queue_prefixes;
 mod system_queue_results;
 mod system_replay_handles;
 mod system_snapshots;
 mod system_tables;
+mod system_workers;
 
 pub use info_schema_columns::*;
 pub use info_schema_schemata::*;
@@ -23,7 +25,9 @@ pub use system_indexes::*;
 pub use system_jobs::*;
 pub use system_partitions::*;
 pub use system_queue::*;
+pub use system_queue_prefixes::*;
 pub use system_queue_results::*;
 pub use system_replay_handles::*;
 pub use system_snapshots::*;
 pub use system_tables::*;
+pub use system_workers::*;
@@ -44,6 +44,7 @@ impl InfoSchemaTableDef for SystemQueueTableDef {
             ),
             Field::new("value", DataType::Utf8, false),
             Field::new("extra", DataType::Utf8, true),
+            Field::new("attempts", DataType::Int64, false),
         ]
     }
 
@@ -119,6 +120,14 @@ impl InfoSchemaTableDef for SystemQueueTableDef {
                     items.iter().map(|row| row.get_row().get_extra().clone()),
                 ))
             }),
+            Box::new(|items| {
+                Arc::new(Int64Array::from(
+                    items
+                        .iter()
+                        .map(|row| row.get_row().get_attempts() as i64)
+                        .collect::<Vec<_>>(),
+                ))
+            }),
         ]
     }
 }
@@ -0,0 +1,73 @@
+use crate::cachestore::QueuePrefix;
+use crate::metastore::IdRow;
+use crate::queryplanner::{InfoSchemaTableDef, InfoSchemaTableDefContext};
+use crate::CubeError;
+use arrow::array::{ArrayRef, StringArray, UInt32Array, UInt64Array};
+use arrow::datatypes::{DataType, Field};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+pub struct SystemQueuePrefixesTableDef;
+
+#[async_trait]
+impl InfoSchemaTableDef for SystemQueuePrefixesTableDef {
+    type T = IdRow<QueuePrefix>;
+
+    async fn rows(
+        &self,
+        ctx: InfoSchemaTableDefContext,
+        limit: Option<usize>,
+    ) -> Result<Arc<Vec<Self::T>>, CubeError> {
+        Ok(Arc::new(ctx.cache_store.queue_prefixes_all(limit).await?))
+    }
+
+    fn schema(&self) -> Vec<Field> {
+        vec![
+            Field::new("id", DataType::UInt64, false),
+            Field::new("prefix", DataType::Utf8, false),
+            Field::new("concurrency", DataType::UInt32, true),
+            Field::new("max_attempts", DataType::UInt32, true),
+            Field::new("heartbeat_timeout", DataType::UInt32, true),
+        ]
+    }
+
+    fn columns(&self) -> Vec<Box<dyn Fn(Arc<Vec<Self::T>>) -> ArrayRef>> {
+        vec![
+            Box::new(|items| {
+                Arc::new(UInt64Array::from_iter(
+                    items.iter().map(|row| Some(row.get_id())),
+                ))
+            }),
+            Box::new(|items| {
+                Arc::new(StringArray::from_iter(
+                    items
+                        .iter()
+                        .map(|row| Some(row.get_row().get_prefix().clone())),
+                ))
+            }),
+            Box::new(|items| {
+                Arc::new(UInt32Array::from_iter(
+                    items
+                        .iter()
+                        .map(|row| row.get_row().get_concurrency().clone()),
+                ))
+            }),
+            Box::new(|items| {
+                Arc::new(UInt32Array::from_iter(
+                    items
+                        .iter()
+                        .map(|row| row.get_row().get_max_attempts().clone()),
+                ))
+            }),
+            Box::new(|items| {
+                Arc::new(UInt32Array::from_iter(
+                    items
+                        .iter()
+                        .map(|row| row.get_row().get_heartbeat_timeout().clone()),
+                ))
+            }),
+        ]
+    }
+}
+
+crate::base_info_schema_table_def!(SystemQueuePrefixesTableDef);
@@ -29,6 +29,11 @@ impl InfoSchemaTableDef for SystemSnapshotsTableDef {
                 false,
             ),
             Field::new("current", DataType::Boolean, true),
+            Field::new(
+                "restorable until (Utc)",
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            ),
         ]
     }
 
@@ -55,6 +60,14 @@ impl InfoSchemaTableDef for SystemSnapshotsTableDef {
                     snapshots.iter().map(|row| row.current).collect::<Vec<_>>(),
                 ))
             }),
+            Box::new(|snapshots| {
+                Arc::new(TimestampNanosecondArray::from(
+                    snapshots
+                        .iter()
+                        .map(|row| (row.restorable_until * 1000000) as i64)
+                        .collect::<Vec<_>>(),
+                ))
+            }),
         ]
     }
 }
@@ -0,0 +1,79 @@
+use crate::metastore::worker::Worker;
+use crate::metastore::IdRow;
+use crate::queryplanner::{InfoSchemaTableDef, InfoSchemaTableDefContext};
+use crate::CubeError;
+use arrow::array::{ArrayRef, StringArray, TimestampNanosecondArray};
+use arrow::datatypes::{DataType, Field, TimeUnit};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+pub struct SystemWorkersTableDef;
+
+#[async_trait]
+impl InfoSchemaTableDef for SystemWorkersTableDef {
+    type T = IdRow<Worker>;
+
+    async fn rows(
+        &self,
+        ctx: InfoSchemaTableDefContext,
+        _limit: Option<usize>,
+    ) -> Result<Arc<Vec<Self::T>>, CubeError> {
+        Ok(Arc::new(ctx.meta_store.get_workers().await?))
+    }
+
+    fn schema(&self) -> Vec<Field> {
+        vec![
+            Field::new("name", DataType::Utf8, false),
+            Field::new("status", DataType::Utf8, false),
+            Field::new(
+                "registered_at",
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            ),
+            Field::new(
+                "last_heartbeat",
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            ),
+        ]
+    }
+
+    fn columns(&self) -> Vec<Box<dyn Fn(Arc<Vec<Self::T>>) -> ArrayRef>> {
+        vec![
+            Box::new(|workers| {
+                Arc::new(StringArray::from(
+                    workers
+                        .iter()
+                        .map(|row| row.get_row().name().as_str())
+                        .collect::<Vec<_>>(),
+                ))
+            }),
+            Box::new(|workers| {
+                Arc::new(StringArray::from(
+                    workers
+                        .iter()
+                        .map(|row| row.get_row().status().as_str())
+                        .collect::<Vec<_>>(),
+                ))
+            }),
+            Box::new(|workers| {
+                Arc::new(TimestampNanosecondArray::from(
+                    workers
+                        .iter()
+                        .map(|row| row.get_row().registered_at().timestamp_nanos())
+                        .collect::<Vec<_>>(),
+                ))
+            }),
+            Box::new(|workers| {
+                Arc::new(TimestampNanosecondArray::from(
+                    workers
+                        .iter()
+                        .map(|row| row.get_row().last_heartbeat().timestamp_nanos())
+                        .collect::<Vec<_>>(),
+                ))
+            }),
+        ]
+    }
+}
+
+crate::base_info_schema_table_def!(SystemWorkersTableDef);
@@ -1,7 +1,8 @@
+pub mod adapt_schema;
 pub mod hll;
 mod optimizations;
 pub mod panic;
-mod partition_filter;
+pub mod partition_filter;
 mod planning;
 pub use planning::PlanningMeta;
 mod check_memory;
@@ -33,9 +34,9 @@ use crate::queryplanner::flatten_union::FlattenUnion;
 use crate::queryplanner::info_schema::{
     ColumnsInfoSchemaTableDef, RocksDBPropertiesTableDef, SchemataInfoSchemaTableDef,
     SystemCacheTableDef, SystemChunksTableDef, SystemIndexesTableDef, SystemJobsTableDef,
-    SystemPartitionsTableDef, SystemQueueResultsTableDef, SystemQueueTableDef,
-    SystemReplayHandlesTableDef, SystemSnapshotsTableDef, SystemTablesTableDef,
-    TablesInfoSchemaTableDef,
+    SystemPartitionsTableDef, SystemQueuePrefixesTableDef, SystemQueueResultsTableDef,
+    SystemQueueTableDef, SystemReplayHandlesTableDef, SystemSnapshotsTableDef,
+    SystemTablesTableDef, SystemWorkersTableDef, TablesInfoSchemaTableDef,
 };
 use crate::queryplanner::now::MaterializeNow;
 use crate::queryplanner::planning::{choose_index_ext, ClusterSendNode};
@@ -109,6 +110,11 @@ pub enum QueryPlan {
 
 #[async_trait]
 impl QueryPlanner for QueryPlannerImpl {
+    #[tracing::instrument(
+        level = "info",
+        name = "plan_query",
+        skip(self, statement, inline_tables)
+    )]
     async fn logical_plan(
         &self,
         statement: Statement,
@@ -294,6 +300,9 @@ impl ContextProvider for MetaStoreSchemaProvider {
                     Vec::new(),
                     None,
                     None,
+                    None,
+                    None,
+                    None,
                 ),
             ),
             schema: Arc::new(IdRow::new(0, metastore::Schema::new(schema.to_string()))),
@@ -372,6 +381,11 @@ impl ContextProvider for MetaStoreSchemaProvider {
                 self.cache_store.clone(),
                 InfoSchemaTable::SystemQueueResults,
             ))),
+            ("system", "queue_prefixes") => Some(Arc::new(InfoSchemaTableProvider::new(
+                self.meta_store.clone(),
+                self.cache_store.clone(),
+                InfoSchemaTable::SystemQueuePrefixes,
+            ))),
             ("system", "replay_handles") => Some(Arc::new(InfoSchemaTableProvider::new(
                 self.meta_store.clone(),
                 self.cache_store.clone(),
@@ -387,6 +401,11 @@ impl ContextProvider for MetaStoreSchemaProvider {
                 self.cache_store.clone(),
                 InfoSchemaTable::SystemSnapshots,
             ))),
+            ("system", "workers") => Some(Arc::new(InfoSchemaTableProvider::new(
+                self.meta_store.clone(),
+                self.cache_store.clone(),
+                InfoSchemaTable::SystemWorkers,
+            ))),
             ("metastore", "rocksdb_properties") => Some(Arc::new(InfoSchemaTableProvider::new(
                 self.meta_store.clone(),
                 self.cache_store.clone(),
@@ -437,9 +456,11 @@ pub enum InfoSchemaTable {
     SystemChunks,
     SystemQueue,
     SystemQueueResults,
+    SystemQueuePrefixes,
     SystemReplayHandles,
     SystemCache,
     SystemSnapshots,
+    SystemWorkers,
     CachestoreRocksDBProperties,
     MetastoreRocksDBProperties,
 }
@@ -513,11 +534,13 @@ impl InfoSchemaTable {
             InfoSchemaTable::SystemChunks => Box::new(SystemChunksTableDef),
             InfoSchemaTable::SystemQueue => Box::new(SystemQueueTableDef),
             InfoSchemaTable::SystemQueueResults => Box::new(SystemQueueResultsTableDef),
+            InfoSchemaTable::SystemQueuePrefixes => Box::new(SystemQueuePrefixesTableDef),
             InfoSchemaTable::SystemReplayHandles => Box::new(SystemReplayHandlesTableDef),
             InfoSchemaTable::SystemPartitions => Box::new(SystemPartitionsTableDef),
             InfoSchemaTable::SystemJobs => Box::new(SystemJobsTableDef),
             InfoSchemaTable::SystemCache => Box::new(SystemCacheTableDef),
             InfoSchemaTable::SystemSnapshots => Box::new(SystemSnapshotsTableDef),
+            InfoSchemaTable::SystemWorkers => Box::new(SystemWorkersTableDef),
             InfoSchemaTable::CachestoreRocksDBProperties => {
                 Box::new(RocksDBPropertiesTableDef::new_cachestore())
             }
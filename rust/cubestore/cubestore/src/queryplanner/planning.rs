@@ -2142,6 +2142,9 @@ pub mod tests {
             Vec::new(),
             None,
             None,
+            None,
+            None,
+            None,
         ));
         i.indices.push(
             Index::try_new(
@@ -2152,6 +2155,7 @@ pub mod tests {
                 None,
                 None,
                 Index::index_type_default(),
+                None,
             )
             .unwrap(),
         );
@@ -2165,6 +2169,7 @@ pub mod tests {
                     None,
                     Some(PARTITIONED_INDEX),
                     Index::index_type_default(),
+                    None,
                 )
                 .unwrap(),
             );
@@ -2193,6 +2198,9 @@ pub mod tests {
             Vec::new(),
             None,
             None,
+            None,
+            None,
+            None,
         ));
 
         i.indices.push(
@@ -2204,6 +2212,7 @@ pub mod tests {
                 None,
                 None,
                 Index::index_type_default(),
+                None,
             )
             .unwrap(),
         );
@@ -2216,6 +2225,7 @@ pub mod tests {
                 None,
                 None,
                 Index::index_type_default(),
+                None,
             )
             .unwrap(),
         );
@@ -2229,6 +2239,7 @@ pub mod tests {
                     None,
                     Some(PARTITIONED_INDEX),
                     Index::index_type_default(),
+                    None,
                 )
                 .unwrap(),
             );
@@ -2250,6 +2261,9 @@ pub mod tests {
             Vec::new(),
             None,
             None,
+            None,
+            None,
+            None,
         ));
 
         i
@@ -2304,6 +2318,7 @@ pub mod tests {
                     None,
                     None,
                     Index::index_type_default(),
+                    None,
                 )
                 .unwrap(),
             );
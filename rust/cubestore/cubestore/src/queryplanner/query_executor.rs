@@ -4,6 +4,7 @@ use crate::config::ConfigObj;
 use crate::metastore::multi_index::MultiPartition;
 use crate::metastore::table::Table;
 use crate::metastore::{Column, ColumnType, IdRow, Index, Partition};
+use crate::queryplanner::adapt_schema::{adapt_batch_to_index, AdaptSchemaExec};
 use crate::queryplanner::filter_by_key_range::FilterByKeyRangeExec;
 use crate::queryplanner::optimizations::CubeQueryPlanner;
 use crate::queryplanner::physical_plan_flags::PhysicalPlanFlags;
@@ -113,7 +114,7 @@ crate::di_service!(QueryExecutorImpl, [QueryExecutor]);
 
 #[async_trait]
 impl QueryExecutor for QueryExecutorImpl {
-    #[instrument(level = "trace", skip(self, plan, cluster))]
+    #[instrument(level = "info", skip(self, plan, cluster))]
     async fn execute_router_plan(
         &self,
         plan: SerializedPlan,
@@ -169,7 +170,10 @@ impl QueryExecutor for QueryExecutorImpl {
         Ok((split_plan.schema(), results?))
     }
 
-    #[instrument(level = "trace", skip(self, plan, remote_to_local_names))]
+    #[instrument(
+        level = "info",
+        skip(self, plan, remote_to_local_names, chunk_id_to_record_batches)
+    )]
     async fn execute_worker_plan(
         &self,
         plan: SerializedPlan,
@@ -439,6 +443,92 @@ impl CubeTable {
         &self.index_snapshot
     }
 
+    /// Files written before `ALTER TABLE ... ADD COLUMN` do not contain the added columns, they
+    /// are read as NULLs.
+    fn parquet_scan(
+        &self,
+        path: &str,
+        index_projection: &[usize],
+        projection: Option<Vec<usize>>,
+        projection_schema: &SchemaRef,
+        predicate: Option<Expr>,
+        batch_size: usize,
+    ) -> Result<Arc<dyn ExecutionPlan>, CubeError> {
+        let index = self.index_snapshot.index().get_row();
+        let missing_from = match index.first_added_column() {
+            Some(first_added)
+                if index_projection
+                    .last()
+                    .map(|i| *i as u64 >= first_added)
+                    .unwrap_or(false) =>
+            {
+                let file_columns = ParquetExec::try_from_path_with_cache(
+                    path,
+                    None,
+                    None,
+                    batch_size,
+                    1,
+                    None,
+                    self.parquet_metadata_cache.clone(),
+                )?
+                .schema()
+                .fields()
+                .len();
+                if file_columns < index.get_columns().len() {
+                    Some(file_columns)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        let file_columns = match missing_from {
+            None => {
+                return Ok(Arc::new(ParquetExec::try_from_path_with_cache(
+                    path,
+                    projection,
+                    predicate,
+                    batch_size,
+                    1,
+                    None, // TODO: propagate limit
+                    self.parquet_metadata_cache.clone(),
+                )?));
+            }
+            Some(file_columns) => file_columns,
+        };
+        // Projection is sorted, so columns present in the file come first.
+        let present = index_projection
+            .iter()
+            .take_while(|i| **i < file_columns)
+            .cloned()
+            .collect_vec();
+        let columns = index_projection
+            .iter()
+            .enumerate()
+            .map(|(pos, i)| {
+                let source = if pos < present.len() { Some(pos) } else { None };
+                (source, index.get_columns()[*i].get_column_type().clone())
+            })
+            .collect_vec();
+        // Read at least one column to get the number of rows.
+        let file_projection = if present.is_empty() { vec![0] } else { present };
+        // Statistics of the file do not cover added columns, so it is not pruned by predicate.
+        let scan = Arc::new(ParquetExec::try_from_path_with_cache(
+            path,
+            Some(file_projection),
+            None,
+            batch_size,
+            1,
+            None,
+            self.parquet_metadata_cache.clone(),
+        )?);
+        Ok(Arc::new(AdaptSchemaExec::new(
+            scan,
+            projection_schema.clone(),
+            columns,
+        )))
+    }
+
     fn async_scan(
         &self,
         table_projection: &Option<Vec<usize>>,
@@ -449,7 +539,8 @@ impl CubeTable {
 
         let mut partition_execs = Vec::<Arc<dyn ExecutionPlan>>::new();
         let table_cols = self.index_snapshot.table().get_row().get_columns();
-        let index_cols = self.index_snapshot.index().get_row().get_columns();
+        let index_row = self.index_snapshot.index().get_row();
+        let index_cols = index_row.get_columns();
 
         // We always introduce projection because index and table columns do not match in general
         // case so we can use simpler code without branching to handle it.
@@ -507,15 +598,14 @@ impl CubeTable {
             index_cols
                 .iter()
                 .map(|i| {
-                    self.schema
-                        .field(
-                            table_cols
-                                .iter()
-                                .find_position(|c| c.get_name() == i.get_name())
-                                .unwrap()
-                                .0,
-                        )
-                        .clone()
+                    // Columns dropped from the table are still kept in the index.
+                    match table_cols
+                        .iter()
+                        .find_position(|c| c.get_name() == i.get_name())
+                    {
+                        Some((table_col_i, _)) => self.schema.field(table_col_i).clone(),
+                        None => i.into(),
+                    }
                 })
                 .collect(),
         ));
@@ -554,15 +644,14 @@ impl CubeTable {
                     .remote_to_local_names
                     .get(remote_path.as_str())
                     .expect(format!("Missing remote path {}", remote_path).as_str());
-                let arc: Arc<dyn ExecutionPlan> = Arc::new(ParquetExec::try_from_path_with_cache(
+                let arc = self.parquet_scan(
                     &local_path,
+                    &index_projection,
                     index_projection_or_none_on_schema_match.clone(),
+                    &index_projection_schema,
                     predicate.clone(),
                     batch_size,
-                    1,
-                    None, // TODO: propagate limit
-                    self.parquet_metadata_cache.clone(),
-                )?);
+                )?;
                 let arc = FilterByKeyRangeExec::issue_filters(arc, filter.clone(), key_len);
                 partition_execs.push(arc);
             }
@@ -577,6 +666,10 @@ impl CubeTable {
                             "Record batch for in memory chunk {:?} is not provided",
                             chunk
                         )))?;
+                    let record_batches = record_batches
+                        .iter()
+                        .map(|b| adapt_batch_to_index(b.clone(), index_row))
+                        .collect::<Result<Vec<_>, _>>()?;
                     if let Some(batch) = record_batches.iter().next() {
                         if batch.schema() != index_schema {
                             return Err(CubeError::internal(format!(
@@ -587,7 +680,7 @@ impl CubeTable {
                         }
                     }
                     Arc::new(MemoryExec::try_new(
-                        &[record_batches.clone()],
+                        &[record_batches],
                         index_projection_schema.clone(),
                         index_projection_or_none_on_schema_match.clone(),
                     )?)
@@ -597,15 +690,14 @@ impl CubeTable {
                         .remote_to_local_names
                         .get(&remote_path)
                         .expect(format!("Missing remote path {}", remote_path).as_str());
-                    Arc::new(ParquetExec::try_from_path_with_cache(
+                    self.parquet_scan(
                         local_path,
+                        &index_projection,
                         index_projection_or_none_on_schema_match.clone(),
+                        &index_projection_schema,
                         predicate.clone(),
                         batch_size,
-                        1,
-                        None, // TODO: propagate limit
-                        self.parquet_metadata_cache.clone(),
-                    )?)
+                    )?
                 };
 
                 let node = FilterByKeyRangeExec::issue_filters(node, filter.clone(), key_len);
@@ -1163,7 +1255,7 @@ impl ClusterSendExec {
                 Some(multi_id) => pick_worker_by_ids(c, [multi_id]),
                 None => pick_worker_by_partitions(c, partitions.iter()),
             };
-            let node_entry = &mut m.entry(node.to_string()).or_default();
+            let node_entry = &mut m.entry(node).or_default();
             node_entry
                 .0
                 .extend(Self::issue_filters(partitions.as_slice()));
@@ -1,17 +1,20 @@
 use crate::cachestore::{
-    CacheItem, CacheStore, QueueItem, QueueItemStatus, QueueKey, QueueResult, QueueResultResponse,
-    QueueRetrieveResponse,
+    CacheItem, CacheListSide, CacheStore, QueueItem, QueueItemStatus, QueueKey, QueuePrefix,
+    QueueResult, QueueResultResponse, QueueRetrieveResponse,
 };
 use crate::metastore::job::{Job, JobStatus, JobType};
 use crate::metastore::multi_index::{MultiIndex, MultiPartition};
 use crate::metastore::replay_handle::{ReplayHandle, SeqPointer};
 use crate::metastore::snapshot_info::SnapshotInfo;
 use crate::metastore::source::{Source, SourceCredentials};
-use crate::metastore::table::{StreamOffset, Table, TablePath};
+use crate::metastore::table::{
+    ParquetOptions, StreamOffset, StreamValueFormat, Table, TableAlteration, TablePath,
+};
+use crate::metastore::worker::Worker;
 use crate::metastore::{
     Chunk, ChunkMetaStoreTable, Column, IdRow, ImportFormat, Index, IndexDef, IndexMetaStoreTable,
-    MetaStore, Partition, PartitionData, PartitionMetaStoreTable, RocksPropertyRow, RowKey, Schema,
-    SchemaMetaStoreTable, TableMetaStoreTable, WAL,
+    MetaStore, Partition, PartitionData, PartitionMetaStoreTable, PartitionRewrite,
+    RocksPropertyRow, RowKey, Schema, SchemaMetaStoreTable, TableMetaStoreTable, WAL,
 };
 use crate::table::Row;
 use crate::CubeError;
@@ -102,6 +105,9 @@ impl MetaStore for MetaStoreMock {
         _unique_key_column_names: Option<Vec<String>>,
         _aggregates: Option<Vec<(String, String)>>,
         _partition_split_threshold: Option<u64>,
+        _parquet_options: Option<ParquetOptions>,
+        _retention: Option<(String, u64)>,
+        _value_format: Option<StreamValueFormat>,
         _trace_obj: Option<String>,
         _drop_if_exists: bool,
     ) -> Result<IdRow<Table>, CubeError> {
@@ -163,6 +169,15 @@ impl MetaStore for MetaStoreMock {
         panic!("MetaStore mock!")
     }
 
+    async fn alter_table(
+        &self,
+        _schema_name: String,
+        _table_name: String,
+        _alteration: TableAlteration,
+    ) -> Result<IdRow<Table>, CubeError> {
+        panic!("MetaStore mock!")
+    }
+
     fn partition_table(&self) -> PartitionMetaStoreTable {
         panic!("MetaStore mock!")
     }
@@ -220,6 +235,30 @@ impl MetaStore for MetaStoreMock {
         panic!("MetaStore mock!")
     }
 
+    async fn swap_rewritten_partition(
+        &self,
+        _current_active: (IdRow<Partition>, Vec<IdRow<Chunk>>),
+        _new_active: (IdRow<Partition>, u64),
+        _new_active_row_count: u64,
+        _new_active_min_max: (Option<Row>, Option<Row>),
+    ) -> Result<(), CubeError> {
+        panic!("MetaStore mock!")
+    }
+
+    async fn swap_rewritten_partitions(
+        &self,
+        _rewrites: Vec<PartitionRewrite>,
+    ) -> Result<(), CubeError> {
+        panic!("MetaStore mock!")
+    }
+
+    async fn replace_expired_partition(
+        &self,
+        _current_active: (IdRow<Partition>, Vec<IdRow<Chunk>>),
+    ) -> Result<(), CubeError> {
+        panic!("MetaStore mock!")
+    }
+
     async fn delete_partition(&self, _partition_id: u64) -> Result<IdRow<Partition>, CubeError> {
         panic!("MetaStore mock!")
     }
@@ -328,6 +367,13 @@ impl MetaStore for MetaStoreMock {
         panic!("MetaStore mock!")
     }
 
+    async fn get_active_partitions_and_chunks_by_index_ids(
+        &self,
+        _index_ids: Vec<u64>,
+    ) -> Result<Vec<(IdRow<Partition>, Vec<IdRow<Chunk>>)>, CubeError> {
+        panic!("MetaStore mock!")
+    }
+
     async fn get_index(&self, _index_id: u64) -> Result<IdRow<Index>, CubeError> {
         panic!("MetaStore mock!")
     }
@@ -745,6 +791,34 @@ impl MetaStore for MetaStoreMock {
     async fn set_current_snapshot(&self, _snapshot_id: u128) -> Result<(), CubeError> {
         panic!("MetaStore mock!")
     }
+
+    async fn backup_snapshot(&self, _location: String) -> Result<u128, CubeError> {
+        panic!("MetaStore mock!")
+    }
+
+    async fn worker_heartbeat(&self, _worker: String) -> Result<Vec<String>, CubeError> {
+        panic!("MetaStore mock!")
+    }
+
+    async fn get_workers(&self) -> Result<Vec<IdRow<Worker>>, CubeError> {
+        panic!("MetaStore mock!")
+    }
+
+    async fn expire_workers(&self, _timeout_secs: u64) -> Result<Vec<IdRow<Worker>>, CubeError> {
+        panic!("MetaStore mock!")
+    }
+
+    async fn start_workers_warmup(&self) -> Result<Vec<IdRow<Worker>>, CubeError> {
+        panic!("MetaStore mock!")
+    }
+
+    async fn activate_worker(&self, _worker: String) -> Result<bool, CubeError> {
+        panic!("MetaStore mock!")
+    }
+
+    async fn finish_worker_handover(&self, _worker: String) -> Result<bool, CubeError> {
+        panic!("MetaStore mock!")
+    }
 }
 
 crate::di_service!(MetaStoreMock, [MetaStore]);
@@ -785,6 +859,51 @@ impl CacheStore for CacheStoreMock {
         panic!("CacheStore mock!")
     }
 
+    async fn cache_compare_and_set(
+        &self,
+        _item: CacheItem,
+        _expected: Option<String>,
+    ) -> Result<bool, CubeError> {
+        panic!("CacheStore mock!")
+    }
+
+    async fn cache_hset(
+        &self,
+        _path: String,
+        _fields: Vec<(String, String)>,
+    ) -> Result<u64, CubeError> {
+        panic!("CacheStore mock!")
+    }
+
+    async fn cache_hget(&self, _path: String, _field: String) -> Result<Option<String>, CubeError> {
+        panic!("CacheStore mock!")
+    }
+
+    async fn cache_hdel(&self, _path: String, _fields: Vec<String>) -> Result<u64, CubeError> {
+        panic!("CacheStore mock!")
+    }
+
+    async fn cache_hgetall(&self, _path: String) -> Result<Vec<(String, String)>, CubeError> {
+        panic!("CacheStore mock!")
+    }
+
+    async fn cache_list_push(
+        &self,
+        _path: String,
+        _values: Vec<String>,
+        _side: CacheListSide,
+    ) -> Result<u64, CubeError> {
+        panic!("CacheStore mock!")
+    }
+
+    async fn cache_list_pop(
+        &self,
+        _path: String,
+        _side: CacheListSide,
+    ) -> Result<Option<String>, CubeError> {
+        panic!("CacheStore mock!")
+    }
+
     async fn queue_all(&self, _limit: Option<usize>) -> Result<Vec<IdRow<QueueItem>>, CubeError> {
         panic!("CacheStore mock!")
     }
@@ -872,6 +991,23 @@ impl CacheStore for CacheStoreMock {
         panic!("CacheStore mock!")
     }
 
+    async fn queue_prefix_set(
+        &self,
+        _prefix: String,
+        _concurrency: Option<u32>,
+        _max_attempts: Option<u32>,
+        _heartbeat_timeout: Option<u32>,
+    ) -> Result<IdRow<QueuePrefix>, CubeError> {
+        panic!("CacheStore mock!")
+    }
+
+    async fn queue_prefixes_all(
+        &self,
+        _limit: Option<usize>,
+    ) -> Result<Vec<IdRow<QueuePrefix>>, CubeError> {
+        panic!("CacheStore mock!")
+    }
+
     async fn compaction(&self) -> Result<(), CubeError> {
         panic!("CacheStore mock!")
     }
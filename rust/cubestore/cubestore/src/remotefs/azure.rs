@@ -0,0 +1,777 @@
+use crate::app_metrics;
+use crate::di_service;
+use crate::remotefs::{CommonRemoteFsUtils, LocalDirRemoteFs, RemoteFile, RemoteFs};
+use crate::util::lock::acquire_lock;
+use crate::CubeError;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use datafusion::cube_ext;
+use hmac::{Hmac, Mac, NewMac};
+use log::{debug, info};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::{Method, Response};
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fmt::Formatter;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tempfile::{NamedTempFile, PathPersistError};
+use tokio::fs;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, BufWriter};
+use tokio::sync::Mutex;
+use url::Url;
+use xml::reader::{EventReader, XmlEvent};
+
+const API_VERSION: &str = "2020-04-08";
+
+/// Files up to this size are uploaded with a single request, larger ones in blocks of this size.
+const BLOCK_SIZE: usize = 64 * 1024 * 1024;
+
+#[derive(Clone)]
+pub enum AzureCredentials {
+    /// Decoded storage account key.
+    SharedKey(Vec<u8>),
+    /// Query string of a SAS token without the leading '?'.
+    Sas(String),
+}
+
+pub struct AzureBlobRemoteFs {
+    dir: PathBuf,
+    client: reqwest::Client,
+    endpoint: Url,
+    account: String,
+    container: String,
+    sub_path: Option<String>,
+    credentials: AzureCredentials,
+    delete_mut: Mutex<()>,
+}
+
+impl fmt::Debug for AzureBlobRemoteFs {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        // Do not expose Azure credentials.
+        f.debug_struct("AzureBlobRemoteFs")
+            .field("dir", &self.dir)
+            .field("endpoint", &self.endpoint.as_str())
+            .field("account", &self.account)
+            .field("container", &self.container)
+            .field("sub_path", &self.sub_path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AzureBlobRemoteFs {
+    pub fn new(
+        dir: PathBuf,
+        account: String,
+        container: String,
+        sub_path: Option<String>,
+    ) -> Result<Arc<Self>, CubeError> {
+        let endpoint = env::var("CUBESTORE_AZURE_ENDPOINT")
+            .unwrap_or_else(|_| format!("https://{}.blob.core.windows.net", account));
+        let credentials = if let Ok(key) = env::var("CUBESTORE_AZURE_ACCOUNT_KEY") {
+            AzureCredentials::SharedKey(base64::decode(key.trim())?)
+        } else if let Ok(token) = env::var("CUBESTORE_AZURE_SAS_TOKEN") {
+            AzureCredentials::Sas(token.trim_start_matches('?').to_string())
+        } else {
+            return Err(CubeError::user(
+                "CUBESTORE_AZURE_ACCOUNT_KEY or CUBESTORE_AZURE_SAS_TOKEN is required when CUBESTORE_AZURE_CONTAINER is set".to_string(),
+            ));
+        };
+        Self::with_credentials(dir, endpoint, account, container, sub_path, credentials)
+    }
+
+    pub fn with_credentials(
+        dir: PathBuf,
+        endpoint: String,
+        account: String,
+        container: String,
+        sub_path: Option<String>,
+        credentials: AzureCredentials,
+    ) -> Result<Arc<Self>, CubeError> {
+        let endpoint = Url::parse(&endpoint)?;
+        if endpoint.cannot_be_a_base() {
+            return Err(CubeError::user(format!(
+                "Invalid Azure Blob Storage endpoint: {}",
+                endpoint
+            )));
+        }
+        let client = reqwest::ClientBuilder::new()
+            .use_rustls_tls()
+            .user_agent("cubestore")
+            .build()?;
+        Ok(Arc::new(Self {
+            dir,
+            client,
+            endpoint,
+            account,
+            container,
+            sub_path,
+            credentials,
+            delete_mut: Mutex::new(()),
+        }))
+    }
+}
+
+di_service!(AzureBlobRemoteFs, [RemoteFs]);
+
+#[async_trait]
+impl RemoteFs for AzureBlobRemoteFs {
+    async fn temp_upload_path(&self, remote_path: String) -> Result<String, CubeError> {
+        CommonRemoteFsUtils::temp_upload_path(self, remote_path).await
+    }
+
+    async fn uploads_dir(&self) -> Result<String, CubeError> {
+        CommonRemoteFsUtils::uploads_dir(self).await
+    }
+
+    async fn check_upload_file(
+        &self,
+        remote_path: String,
+        expected_size: u64,
+    ) -> Result<(), CubeError> {
+        CommonRemoteFsUtils::check_upload_file(self, remote_path, expected_size).await
+    }
+
+    async fn upload_file(
+        &self,
+        temp_upload_path: String,
+        remote_path: String,
+    ) -> Result<u64, CubeError> {
+        app_metrics::REMOTE_FS_OPERATION_CORE.add_with_tags(
+            1,
+            Some(&vec![
+                "operation:upload_file".to_string(),
+                "driver:azure".to_string(),
+            ]),
+        );
+        let time = SystemTime::now();
+        debug!("Uploading {}", remote_path);
+        let blob = self.blob_path(&remote_path);
+        let size = fs::metadata(&temp_upload_path).await?.len();
+        if size <= BLOCK_SIZE as u64 {
+            let mut headers = HeaderMap::new();
+            headers.insert("x-ms-blob-type", HeaderValue::from_static("BlockBlob"));
+            let data = fs::read(&temp_upload_path).await?;
+            self.request(Method::PUT, Some(&blob), &[], headers, Some(data))
+                .await?;
+        } else {
+            let mut file = File::open(&temp_upload_path).await?;
+            let mut block_ids = Vec::new();
+            loop {
+                let mut data = Vec::with_capacity(BLOCK_SIZE);
+                (&mut file)
+                    .take(BLOCK_SIZE as u64)
+                    .read_to_end(&mut data)
+                    .await?;
+                if data.is_empty() {
+                    break;
+                }
+                let block_id = base64::encode(format!("{:08}", block_ids.len()));
+                let query = [("comp", "block"), ("blockid", block_id.as_str())];
+                self.request(
+                    Method::PUT,
+                    Some(&blob),
+                    &query,
+                    HeaderMap::new(),
+                    Some(data),
+                )
+                .await?;
+                block_ids.push(block_id);
+            }
+            let block_list = format!(
+                "<?xml version=\"1.0\" encoding=\"utf-8\"?><BlockList>{}</BlockList>",
+                block_ids
+                    .iter()
+                    .map(|id| format!("<Latest>{}</Latest>", id))
+                    .collect::<String>()
+            );
+            self.request(
+                Method::PUT,
+                Some(&blob),
+                &[("comp", "blocklist")],
+                HeaderMap::new(),
+                Some(block_list.into_bytes()),
+            )
+            .await?;
+        }
+        self.check_upload_file(remote_path.clone(), size).await?;
+
+        let local_path = self.dir.as_path().join(&remote_path);
+        if Path::new(&temp_upload_path) != local_path {
+            fs::create_dir_all(local_path.parent().unwrap())
+                .await
+                .map_err(|e| {
+                    CubeError::internal(format!(
+                        "Create dir {}: {}",
+                        local_path.parent().as_ref().unwrap().to_string_lossy(),
+                        e
+                    ))
+                })?;
+            fs::rename(&temp_upload_path, local_path.clone()).await?;
+        }
+        info!("Uploaded {} ({:?})", remote_path, time.elapsed()?);
+        Ok(fs::metadata(local_path).await?.len())
+    }
+
+    async fn download_file(
+        &self,
+        remote_path: String,
+        _expected_file_size: Option<u64>,
+    ) -> Result<String, CubeError> {
+        let mut local_file = self.dir.as_path().join(&remote_path);
+        let local_dir = local_file.parent().unwrap();
+        let downloads_dirs = local_dir.join("downloads");
+
+        fs::create_dir_all(&downloads_dirs).await?;
+        if !local_file.exists() {
+            app_metrics::REMOTE_FS_OPERATION_CORE.add_with_tags(
+                1,
+                Some(&vec![
+                    "operation:download_file".to_string(),
+                    "driver:azure".to_string(),
+                ]),
+            );
+            let time = SystemTime::now();
+            debug!("Downloading {}", remote_path);
+            let (temp_file, temp_path) =
+                cube_ext::spawn_blocking(move || NamedTempFile::new_in(downloads_dirs))
+                    .await??
+                    .into_parts();
+            let mut writer = BufWriter::new(tokio::fs::File::from_std(temp_file));
+            let mut response = self
+                .request(
+                    Method::GET,
+                    Some(&self.blob_path(&remote_path)),
+                    &[],
+                    HeaderMap::new(),
+                    None,
+                )
+                .await?;
+            let mut size = 0;
+            while let Some(chunk) = response.chunk().await? {
+                writer.write_all(&chunk).await?;
+                size += chunk.len();
+            }
+            writer.flush().await?;
+
+            local_file = cube_ext::spawn_blocking(move || -> Result<PathBuf, PathPersistError> {
+                temp_path.persist(&local_file)?;
+                Ok(local_file)
+            })
+            .await??;
+
+            info!(
+                "Downloaded {} ({:?}) ({} bytes)",
+                remote_path,
+                time.elapsed()?,
+                size
+            );
+        }
+        Ok(local_file.into_os_string().into_string().unwrap())
+    }
+
+    async fn delete_file(&self, remote_path: String) -> Result<(), CubeError> {
+        app_metrics::REMOTE_FS_OPERATION_CORE.add_with_tags(
+            1,
+            Some(&vec![
+                "operation:delete_file".to_string(),
+                "driver:azure".to_string(),
+            ]),
+        );
+        let time = SystemTime::now();
+        debug!("Deleting {}", remote_path);
+        self.request(
+            Method::DELETE,
+            Some(&self.blob_path(&remote_path)),
+            &[],
+            HeaderMap::new(),
+            None,
+        )
+        .await?;
+        info!("Deleting {} ({:?})", remote_path, time.elapsed()?);
+
+        let _guard = acquire_lock("delete file", self.delete_mut.lock()).await?;
+        let local = self.dir.as_path().join(remote_path);
+        if fs::metadata(local.clone()).await.is_ok() {
+            fs::remove_file(local.clone()).await?;
+            LocalDirRemoteFs::remove_empty_paths(self.dir.as_path().to_path_buf(), local.clone())
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn list(&self, remote_prefix: String) -> Result<Vec<String>, CubeError> {
+        Ok(self
+            .list_with_metadata(remote_prefix)
+            .await?
+            .into_iter()
+            .map(|f| f.remote_path)
+            .collect::<Vec<_>>())
+    }
+
+    async fn list_with_metadata(
+        &self,
+        remote_prefix: String,
+    ) -> Result<Vec<RemoteFile>, CubeError> {
+        let prefix = self.blob_path(&remote_prefix);
+        let leading_path = self.blob_path("");
+        let mut result = Vec::new();
+        let mut marker: Option<String> = None;
+        let mut pages_count = 0;
+        loop {
+            let mut query = vec![
+                ("restype", "container"),
+                ("comp", "list"),
+                ("prefix", prefix.as_str()),
+            ];
+            if let Some(marker) = marker.as_ref() {
+                query.push(("marker", marker.as_str()));
+            }
+            let response = self
+                .request(Method::GET, None, &query, HeaderMap::new(), None)
+                .await?;
+            let page = parse_blob_list(&response.text().await?)?;
+            pages_count += 1;
+            result.extend(page.blobs.into_iter().map(|mut file| {
+                if let Some(path) = file.remote_path.strip_prefix(&leading_path) {
+                    file.remote_path = path.to_string();
+                }
+                file
+            }));
+            match page.next_marker {
+                Some(m) if !m.is_empty() => marker = Some(m),
+                _ => break,
+            }
+        }
+        if pages_count > 100 {
+            log::warn!("Azure list returned more than 100 pages: {}", pages_count);
+        }
+        app_metrics::REMOTE_FS_OPERATION_CORE.add_with_tags(
+            pages_count as i64,
+            Some(&vec![
+                "operation:list".to_string(),
+                "driver:azure".to_string(),
+            ]),
+        );
+        Ok(result)
+    }
+
+    async fn local_path(&self) -> Result<String, CubeError> {
+        Ok(self.dir.to_str().unwrap().to_owned())
+    }
+
+    async fn local_file(&self, remote_path: String) -> Result<String, CubeError> {
+        let buf = self.dir.join(remote_path);
+        fs::create_dir_all(buf.parent().unwrap()).await?;
+        Ok(buf.to_str().unwrap().to_string())
+    }
+}
+
+impl AzureBlobRemoteFs {
+    fn blob_path(&self, remote_path: &str) -> String {
+        match self.sub_path.as_ref() {
+            Some(sub_path) => format!("{}/{}", sub_path.trim_end_matches('/'), remote_path),
+            None => remote_path.to_string(),
+        }
+    }
+
+    /// Url of the container or of a blob in it.
+    fn url(&self, blob: Option<&str>, query: &[(&str, &str)]) -> Url {
+        let mut url = self.endpoint.clone();
+        {
+            // Checked by the constructor.
+            let mut segments = url.path_segments_mut().unwrap();
+            segments.pop_if_empty().push(&self.container);
+            if let Some(blob) = blob {
+                segments.extend(blob.split('/'));
+            }
+        }
+        if !query.is_empty() {
+            url.query_pairs_mut().extend_pairs(query);
+        }
+        if let AzureCredentials::Sas(token) = &self.credentials {
+            let query = match url.query() {
+                Some(q) => format!("{}&{}", q, token),
+                None => token.clone(),
+            };
+            url.set_query(Some(&query));
+        }
+        url
+    }
+
+    async fn request(
+        &self,
+        method: Method,
+        blob: Option<&str>,
+        query: &[(&str, &str)],
+        mut headers: HeaderMap,
+        body: Option<Vec<u8>>,
+    ) -> Result<Response, CubeError> {
+        let url = self.url(blob, query);
+        let date = Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        headers.insert("x-ms-date", header_value(&date)?);
+        headers.insert("x-ms-version", HeaderValue::from_static(API_VERSION));
+        if let AzureCredentials::SharedKey(key) = &self.credentials {
+            let content_length = body.as_ref().map(|b| b.len()).unwrap_or(0);
+            let signature =
+                shared_key_signature(key, &self.account, &method, &url, &headers, content_length)?;
+            headers.insert(
+                AUTHORIZATION,
+                header_value(&format!("SharedKey {}:{}", self.account, signature))?,
+            );
+        }
+        let mut request = self.client.request(method.clone(), url).headers(headers);
+        if let Some(body) = body {
+            request = request.body(body);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let error = response.text().await.unwrap_or_default();
+            return Err(CubeError::internal(format!(
+                "Azure Blob Storage {} '{}' failed with {}: {}",
+                method,
+                blob.unwrap_or(&self.container),
+                status,
+                error
+            )));
+        }
+        Ok(response)
+    }
+
+    #[cfg(test)]
+    pub async fn create_container(&self) -> Result<(), CubeError> {
+        self.request(
+            Method::PUT,
+            None,
+            &[("restype", "container")],
+            HeaderMap::new(),
+            None,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+fn header_value(value: &str) -> Result<HeaderValue, CubeError> {
+    HeaderValue::from_str(value)
+        .map_err(|e| CubeError::internal(format!("Invalid header value '{}': {}", value, e)))
+}
+
+/// Signature of the request for Shared Key authorization, see
+/// https://docs.microsoft.com/en-us/rest/api/storageservices/authorize-with-shared-key
+fn shared_key_signature(
+    key: &[u8],
+    account: &str,
+    method: &Method,
+    url: &Url,
+    headers: &HeaderMap,
+    content_length: usize,
+) -> Result<String, CubeError> {
+    let header = |name: &str| -> Result<String, CubeError> {
+        Ok(match headers.get(name) {
+            Some(v) => v.to_str()?.to_string(),
+            None => String::new(),
+        })
+    };
+    let mut ms_headers = headers
+        .iter()
+        .filter(|(name, _)| name.as_str().starts_with("x-ms-"))
+        .map(|(name, value)| Ok((name.as_str(), value.to_str()?.trim())))
+        .collect::<Result<Vec<_>, CubeError>>()?;
+    ms_headers.sort();
+
+    let mut params = BTreeMap::<String, Vec<String>>::new();
+    for (name, value) in url.query_pairs() {
+        params
+            .entry(name.to_lowercase())
+            .or_default()
+            .push(value.to_string());
+    }
+    let mut resource = format!("/{}{}", account, url.path());
+    for (name, mut values) in params {
+        values.sort();
+        resource += &format!("\n{}:{}", name, values.join(","));
+    }
+
+    let mut string_to_sign = vec![
+        method.as_str().to_string(),
+        header("content-encoding")?,
+        header("content-language")?,
+        if content_length == 0 {
+            String::new()
+        } else {
+            content_length.to_string()
+        },
+        header("content-md5")?,
+        header("content-type")?,
+        // Date is sent in x-ms-date.
+        String::new(),
+        header("if-modified-since")?,
+        header("if-match")?,
+        header("if-none-match")?,
+        header("if-unmodified-since")?,
+        header("range")?,
+    ]
+    .join("\n");
+    string_to_sign.push('\n');
+    for (name, value) in ms_headers {
+        string_to_sign += &format!("{}:{}\n", name, value);
+    }
+    string_to_sign += &resource;
+
+    let mut mac = Hmac::<Sha256>::new_varkey(key)
+        .map_err(|e| CubeError::user(format!("Invalid Azure account key: {}", e)))?;
+    mac.update(string_to_sign.as_bytes());
+    Ok(base64::encode(mac.finalize().into_bytes()))
+}
+
+struct BlobListPage {
+    blobs: Vec<RemoteFile>,
+    next_marker: Option<String>,
+}
+
+/// Parses the response of the List Blobs operation.
+fn parse_blob_list(xml: &str) -> Result<BlobListPage, CubeError> {
+    let error = |e: String| CubeError::internal(format!("Can't parse Azure blob list: {}", e));
+    let mut page = BlobListPage {
+        blobs: Vec::new(),
+        next_marker: None,
+    };
+    let mut path = Vec::<String>::new();
+    let mut name = None;
+    let mut updated = None;
+    let mut size = None;
+    for event in EventReader::from_str(xml) {
+        match event.map_err(|e| error(e.to_string()))? {
+            XmlEvent::StartElement { name: element, .. } => path.push(element.local_name),
+            XmlEvent::EndElement { .. } => {
+                if path.last().map(|e| e.as_str()) == Some("Blob") {
+                    match (name.take(), updated.take(), size.take()) {
+                        (Some(remote_path), Some(updated), Some(file_size)) => {
+                            page.blobs.push(RemoteFile {
+                                remote_path,
+                                updated,
+                                file_size,
+                            })
+                        }
+                        _ => return Err(error("incomplete blob properties".to_string())),
+                    }
+                }
+                path.pop();
+            }
+            XmlEvent::Characters(text) => {
+                let path = path.iter().map(|e| e.as_str()).collect::<Vec<_>>();
+                match path.as_slice() {
+                    [.., "Blob", "Name"] => name = Some(text),
+                    [.., "Blob", "Properties", "Last-Modified"] => {
+                        updated = Some(DateTime::parse_from_rfc2822(&text)?.with_timezone(&Utc))
+                    }
+                    [.., "Blob", "Properties", "Content-Length"] => {
+                        size = Some(text.parse::<u64>()?)
+                    }
+                    [.., "NextMarker"] => page.next_marker = Some(text),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(page)
+}
+
+/// In-memory Blob service which understands requests made by [AzureBlobRemoteFs].
+#[cfg(test)]
+pub mod mock {
+    use std::collections::{BTreeMap, HashMap};
+    use std::sync::{Arc, Mutex};
+    use warp::http::{Method, Response, StatusCode};
+    use warp::hyper::body::Bytes;
+    use warp::path::FullPath;
+    use warp::Filter;
+
+    #[derive(Default)]
+    struct State {
+        blobs: BTreeMap<String, Vec<u8>>,
+        blocks: HashMap<(String, String), Vec<u8>>,
+    }
+
+    /// Starts the service on a random port and returns its endpoint. Requests must carry
+    /// `sig=<signature>` in the query string. Listings return at most 2 blobs per page.
+    pub fn start_blob_service(container: &'static str, signature: &'static str) -> String {
+        let state = Arc::new(Mutex::new(State::default()));
+        let route = warp::method()
+            .and(warp::path::full())
+            .and(warp::query::<HashMap<String, String>>())
+            .and(warp::body::bytes())
+            .map(
+                move |method: Method,
+                      path: FullPath,
+                      query: HashMap<String, String>,
+                      body: Bytes| {
+                    let response = |status: StatusCode, body: Vec<u8>| {
+                        Response::builder().status(status).body(body).unwrap()
+                    };
+                    if query.get("sig").map(|s| s.as_str()) != Some(signature) {
+                        return response(StatusCode::FORBIDDEN, Vec::new());
+                    }
+                    let blob = match path.as_str().strip_prefix(&format!("/{}", container)) {
+                        Some(blob) => blob.trim_start_matches('/').to_string(),
+                        None => return response(StatusCode::NOT_FOUND, Vec::new()),
+                    };
+                    let comp = query.get("comp").map(|s| s.as_str());
+                    let mut state = state.lock().unwrap();
+                    match (method, comp) {
+                        (Method::PUT, Some("block")) => {
+                            let id = query["blockid"].clone();
+                            state.blocks.insert((blob, id), body.to_vec());
+                            response(StatusCode::CREATED, Vec::new())
+                        }
+                        (Method::PUT, Some("blocklist")) => {
+                            let block_list = String::from_utf8(body.to_vec()).unwrap();
+                            let mut data = Vec::new();
+                            for id in block_list.split("<Latest>").skip(1) {
+                                let id = id.split("</Latest>").next().unwrap().to_string();
+                                data.extend(state.blocks.remove(&(blob.clone(), id)).unwrap());
+                            }
+                            state.blobs.insert(blob, data);
+                            response(StatusCode::CREATED, Vec::new())
+                        }
+                        (Method::PUT, None) => {
+                            state.blobs.insert(blob, body.to_vec());
+                            response(StatusCode::CREATED, Vec::new())
+                        }
+                        (Method::GET, Some("list")) => {
+                            let prefix = query.get("prefix").cloned().unwrap_or_default();
+                            let marker = query.get("marker").cloned().unwrap_or_default();
+                            let mut names = state
+                                .blobs
+                                .iter()
+                                .filter(|(n, _)| n.starts_with(&prefix) && **n >= marker);
+                            let mut xml = "<?xml version=\"1.0\" encoding=\"utf-8\"?><EnumerationResults><Blobs>".to_string();
+                            for (name, data) in names.by_ref().take(2) {
+                                xml += &format!(
+                                    "<Blob><Name>{}</Name><Properties><Last-Modified>Wed, 09 Sep 2009 09:20:02 GMT</Last-Modified><Content-Length>{}</Content-Length></Properties></Blob>",
+                                    name,
+                                    data.len()
+                                );
+                            }
+                            xml += "</Blobs>";
+                            match names.next() {
+                                Some((next, _)) => xml += &format!("<NextMarker>{}</NextMarker>", next),
+                                None => xml += "<NextMarker />",
+                            }
+                            xml += "</EnumerationResults>";
+                            response(StatusCode::OK, xml.into_bytes())
+                        }
+                        (Method::GET, None) => match state.blobs.get(&blob) {
+                            Some(data) => response(StatusCode::OK, data.clone()),
+                            None => response(StatusCode::NOT_FOUND, Vec::new()),
+                        },
+                        (Method::DELETE, None) => match state.blobs.remove(&blob) {
+                            Some(_) => response(StatusCode::ACCEPTED, Vec::new()),
+                            None => response(StatusCode::NOT_FOUND, Vec::new()),
+                        },
+                        _ => response(StatusCode::BAD_REQUEST, Vec::new()),
+                    }
+                },
+            );
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        format!("http://{}", addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_list() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+            <EnumerationResults ServiceEndpoint="https://account.blob.core.windows.net/" ContainerName="data">
+              <Prefix>sub/</Prefix>
+              <Blobs>
+                <Blob>
+                  <Name>sub/1.parquet</Name>
+                  <Properties>
+                    <Last-Modified>Wed, 09 Sep 2009 09:20:02 GMT</Last-Modified>
+                    <Content-Length>1024</Content-Length>
+                    <BlobType>BlockBlob</BlobType>
+                  </Properties>
+                </Blob>
+              </Blobs>
+              <NextMarker>2!80!marker</NextMarker>
+            </EnumerationResults>"#;
+        let page = parse_blob_list(xml).unwrap();
+        assert_eq!(page.blobs.len(), 1);
+        assert_eq!(page.blobs[0].remote_path, "sub/1.parquet");
+        assert_eq!(page.blobs[0].file_size, 1024);
+        assert_eq!(
+            page.blobs[0].updated.to_rfc3339(),
+            "2009-09-09T09:20:02+00:00"
+        );
+        assert_eq!(page.next_marker.as_deref(), Some("2!80!marker"));
+    }
+
+    #[test]
+    fn shared_key() {
+        // Well-known Azurite account key; requests are the examples from the "Authorize with
+        // Shared Key" page of the Azure Storage docs.
+        let key = base64::decode(
+            "Eby8vdM02xNOcqFlqUwJPLlmEtlCDXJ1OUzFT50uSRZ6IFsuFq2UVErCz4I6tq/K1SZFPTOtr/KBHBeksoGMGw==",
+        )
+        .unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-ms-date",
+            HeaderValue::from_static("Fri, 26 Jun 2015 23:39:12 GMT"),
+        );
+        headers.insert("x-ms-version", HeaderValue::from_static("2015-02-21"));
+
+        let url = Url::parse(
+            "https://myaccount.blob.core.windows.net/mycontainer?restype=container&comp=metadata&timeout=20",
+        )
+        .unwrap();
+        assert_eq!(
+            shared_key_signature(&key, "myaccount", &Method::GET, &url, &headers, 0).unwrap(),
+            "1u9lui2jDxj0+fpbHjQ5m5NnastJRSYM+PSmfi8TXx4="
+        );
+
+        headers.insert("x-ms-blob-type", HeaderValue::from_static("BlockBlob"));
+        headers.insert("content-type", HeaderValue::from_static("text/plain"));
+        let url =
+            Url::parse("https://myaccount.blob.core.windows.net/mycontainer/dir/a.csv").unwrap();
+        assert_eq!(
+            shared_key_signature(&key, "myaccount", &Method::PUT, &url, &headers, 11).unwrap(),
+            "w1n/YFizajDDzpktEWZBknnmqwiRLX/ZheAixkgtlB8="
+        );
+    }
+
+    #[test]
+    fn urls() {
+        let fs = AzureBlobRemoteFs::with_credentials(
+            PathBuf::from("/tmp"),
+            "http://127.0.0.1:10000/devstoreaccount1".to_string(),
+            "devstoreaccount1".to_string(),
+            "data".to_string(),
+            Some("sub".to_string()),
+            AzureCredentials::Sas("sv=2020-08-04&sig=a%2Bb".to_string()),
+        )
+        .unwrap();
+        assert_eq!(
+            fs.url(Some(&fs.blob_path("dir/a b.parquet")), &[]).as_str(),
+            "http://127.0.0.1:10000/devstoreaccount1/data/sub/dir/a%20b.parquet?sv=2020-08-04&sig=a%2Bb"
+        );
+        assert_eq!(
+            fs.url(None, &[("restype", "container"), ("prefix", "sub/")])
+                .as_str(),
+            "http://127.0.0.1:10000/devstoreaccount1/data?restype=container&prefix=sub%2F&sv=2020-08-04&sig=a%2Bb"
+        );
+    }
+}
@@ -0,0 +1,565 @@
+use crate::di_service;
+use crate::remotefs::{CommonRemoteFsUtils, LocalDirRemoteFs, RemoteFile, RemoteFs};
+use crate::util::lock::acquire_lock;
+use crate::CubeError;
+use async_trait::async_trait;
+use datafusion::cube_ext;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Formatter;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tempfile::NamedTempFile;
+use tokio::fs;
+use tokio::sync::Mutex;
+
+/// Encrypted files start with this marker. Unless encryption is required, files without it are
+/// read as is, this keeps files uploaded before encryption was enabled readable.
+const MAGIC: &[u8; 8] = b"CUBEENC1";
+/// Data is encrypted in segments of this size, each with its own authentication tag.
+const SEGMENT_SIZE: usize = 1024 * 1024;
+const TAG_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+const NONCE_PREFIX_LEN: usize = NONCE_LEN - 4;
+
+/// Master keys used to encrypt per-file data keys.
+///
+/// The key file is JSON with base64-encoded 256-bit keys:
+/// `{"current": "2024-01", "keys": {"2024-01": "...", "2023-06": "..."}}`.
+/// New files are encrypted with the `current` key. Every file records the id of its key, so
+/// rotation is done by adding a new key, making it current and keeping the old ones while files
+/// encrypted with them exist.
+pub struct EncryptionKeys {
+    current: String,
+    keys: HashMap<String, [u8; KEY_LEN]>,
+}
+
+#[derive(Deserialize)]
+struct KeyFile {
+    current: String,
+    keys: HashMap<String, String>,
+}
+
+impl fmt::Debug for EncryptionKeys {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        // Do not expose the keys.
+        let mut ids = self.keys.keys().collect::<Vec<_>>();
+        ids.sort();
+        f.debug_struct("EncryptionKeys")
+            .field("current", &self.current)
+            .field("ids", &ids)
+            .finish()
+    }
+}
+
+impl EncryptionKeys {
+    pub fn from_file(path: &Path) -> Result<Self, CubeError> {
+        let json = std::fs::read_to_string(path).map_err(|e| {
+            CubeError::user(format!(
+                "Can't read encryption key file {}: {}",
+                path.to_string_lossy(),
+                e
+            ))
+        })?;
+        Self::from_json(&json)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, CubeError> {
+        let file = serde_json::from_str::<KeyFile>(json)
+            .map_err(|e| CubeError::user(format!("Invalid encryption key file: {}", e)))?;
+        let mut keys = HashMap::new();
+        for (id, key) in file.keys {
+            if id.is_empty() || u8::MAX as usize <= id.len() {
+                return Err(CubeError::user(format!(
+                    "Encryption key id must be 1 to 254 bytes long: '{}'",
+                    id
+                )));
+            }
+            let key = base64::decode(key.trim())?;
+            let key: [u8; KEY_LEN] = key.as_slice().try_into().map_err(|_| {
+                CubeError::user(format!(
+                    "Encryption key '{}' must be {} bytes long, got {}",
+                    id,
+                    KEY_LEN,
+                    key.len()
+                ))
+            })?;
+            keys.insert(id, key);
+        }
+        if !keys.contains_key(&file.current) {
+            return Err(CubeError::user(format!(
+                "Current encryption key '{}' is missing in the key file",
+                file.current
+            )));
+        }
+        Ok(Self {
+            current: file.current,
+            keys,
+        })
+    }
+
+    fn key(&self, id: &str) -> Result<LessSafeKey, CubeError> {
+        match self.keys.get(id) {
+            Some(key) => aead_key(key),
+            None => Err(CubeError::internal(format!(
+                "Encryption key '{}' is missing in the key file",
+                id
+            ))),
+        }
+    }
+}
+
+/// Encrypts files before they are uploaded with `inner` and decrypts them after download.
+///
+/// Plaintext files are kept in `dir`, the usual local directory. `inner` must use a separate
+/// local directory, see [EncryptedRemoteFs::inner_dir], where encrypted copies are only kept
+/// while being uploaded or decrypted.
+///
+/// With `require_encryption` downloads of files without the encryption header fail, so
+/// plaintext files put into the bucket by mistake or by an attacker are never read.
+pub struct EncryptedRemoteFs {
+    inner: Arc<dyn RemoteFs>,
+    dir: PathBuf,
+    keys: Arc<EncryptionKeys>,
+    require_encryption: bool,
+    delete_mut: Mutex<()>,
+}
+
+impl fmt::Debug for EncryptedRemoteFs {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("EncryptedRemoteFs")
+            .field("inner", &self.inner)
+            .field("dir", &self.dir)
+            .field("keys", &self.keys)
+            .field("require_encryption", &self.require_encryption)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EncryptedRemoteFs {
+    pub fn new(
+        inner: Arc<dyn RemoteFs>,
+        dir: PathBuf,
+        keys: Arc<EncryptionKeys>,
+        require_encryption: bool,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            inner,
+            dir,
+            keys,
+            require_encryption,
+            delete_mut: Mutex::new(()),
+        })
+    }
+
+    /// Local directory for the wrapped file system. It is not a regular file, so the cleanup
+    /// of local files skips it.
+    pub fn inner_dir(dir: &Path) -> PathBuf {
+        dir.join(".encrypted")
+    }
+
+    /// Empty directories are kept, concurrent uploads and downloads might be using them.
+    async fn remove_inner_local_file(&self, remote_path: String) -> Result<(), CubeError> {
+        let inner_file = self.inner.local_file(remote_path).await?;
+        if fs::metadata(&inner_file).await.is_ok() {
+            fs::remove_file(&inner_file).await?;
+        }
+        Ok(())
+    }
+}
+
+di_service!(EncryptedRemoteFs, [RemoteFs]);
+
+#[async_trait]
+impl RemoteFs for EncryptedRemoteFs {
+    async fn temp_upload_path(&self, remote_path: String) -> Result<String, CubeError> {
+        CommonRemoteFsUtils::temp_upload_path(self, remote_path).await
+    }
+
+    async fn uploads_dir(&self) -> Result<String, CubeError> {
+        CommonRemoteFsUtils::uploads_dir(self).await
+    }
+
+    async fn check_upload_file(
+        &self,
+        remote_path: String,
+        expected_size: u64,
+    ) -> Result<(), CubeError> {
+        let encrypted_size = encrypted_size(&self.keys.current, expected_size);
+        self.inner
+            .check_upload_file(remote_path, encrypted_size)
+            .await
+    }
+
+    async fn upload_file(
+        &self,
+        temp_upload_path: String,
+        remote_path: String,
+    ) -> Result<u64, CubeError> {
+        let encrypted_path = self.inner.temp_upload_path(remote_path.clone()).await?;
+        let keys = self.keys.clone();
+        let source = PathBuf::from(&temp_upload_path);
+        let destination = PathBuf::from(&encrypted_path);
+        cube_ext::spawn_blocking(move || encrypt_file(&keys, &source, &destination)).await??;
+        self.inner
+            .upload_file(encrypted_path, remote_path.clone())
+            .await?;
+        self.remove_inner_local_file(remote_path.clone()).await?;
+
+        let local_path = self.dir.join(&remote_path);
+        if Path::new(&temp_upload_path) != local_path {
+            fs::create_dir_all(local_path.parent().unwrap()).await?;
+            fs::rename(&temp_upload_path, &local_path).await?;
+        }
+        Ok(fs::metadata(local_path).await?.len())
+    }
+
+    async fn download_file(
+        &self,
+        remote_path: String,
+        _expected_file_size: Option<u64>,
+    ) -> Result<String, CubeError> {
+        let local_file = self.dir.join(&remote_path);
+        if !local_file.exists() {
+            let encrypted_path = self.inner.download_file(remote_path.clone(), None).await?;
+            let downloads_dir = local_file.parent().unwrap().join("downloads");
+            fs::create_dir_all(&downloads_dir).await?;
+            let keys = self.keys.clone();
+            let require_encryption = self.require_encryption;
+            let local_file = local_file.clone();
+            let result = cube_ext::spawn_blocking(move || -> Result<(), CubeError> {
+                let temp_path = NamedTempFile::new_in(downloads_dir)?.into_temp_path();
+                decrypt_file(
+                    &keys,
+                    Path::new(&encrypted_path),
+                    &temp_path,
+                    require_encryption,
+                )?;
+                temp_path.persist(&local_file)?;
+                Ok(())
+            })
+            .await?;
+            self.remove_inner_local_file(remote_path).await?;
+            result?;
+        }
+        Ok(local_file.into_os_string().into_string().unwrap())
+    }
+
+    async fn delete_file(&self, remote_path: String) -> Result<(), CubeError> {
+        self.inner.delete_file(remote_path.clone()).await?;
+
+        let _guard = acquire_lock("delete file", self.delete_mut.lock()).await?;
+        let local = self.dir.join(remote_path);
+        if fs::metadata(&local).await.is_ok() {
+            fs::remove_file(&local).await?;
+            LocalDirRemoteFs::remove_empty_paths(self.dir.clone(), local).await?;
+        }
+        Ok(())
+    }
+
+    async fn list(&self, remote_prefix: String) -> Result<Vec<String>, CubeError> {
+        self.inner.list(remote_prefix).await
+    }
+
+    /// Sizes are the ones of encrypted files.
+    async fn list_with_metadata(
+        &self,
+        remote_prefix: String,
+    ) -> Result<Vec<RemoteFile>, CubeError> {
+        self.inner.list_with_metadata(remote_prefix).await
+    }
+
+    async fn local_path(&self) -> Result<String, CubeError> {
+        Ok(self.dir.to_str().unwrap().to_owned())
+    }
+
+    async fn local_file(&self, remote_path: String) -> Result<String, CubeError> {
+        let buf = self.dir.join(remote_path);
+        fs::create_dir_all(buf.parent().unwrap()).await?;
+        Ok(buf.to_str().unwrap().to_string())
+    }
+}
+
+fn aead_key(key: &[u8]) -> Result<LessSafeKey, CubeError> {
+    let key = UnboundKey::new(&AES_256_GCM, key)
+        .map_err(|_| CubeError::internal("Invalid encryption key".to_string()))?;
+    Ok(LessSafeKey::new(key))
+}
+
+fn segment_nonce(prefix: &[u8; NONCE_PREFIX_LEN], index: u32) -> Nonce {
+    let mut nonce = [0; NONCE_LEN];
+    nonce[..NONCE_PREFIX_LEN].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_LEN..].copy_from_slice(&index.to_be_bytes());
+    Nonce::assume_unique_for_key(nonce)
+}
+
+/// Header is the magic, the key id, the data key encrypted with that key and the nonce prefix
+/// for segments. The last segment is authenticated as such, which detects truncated files.
+fn header_size(key_id: &str) -> u64 {
+    (MAGIC.len() + 1 + key_id.len() + NONCE_LEN + KEY_LEN + TAG_LEN + NONCE_PREFIX_LEN) as u64
+}
+
+fn encrypted_size(key_id: &str, size: u64) -> u64 {
+    let segments = std::cmp::max(1, (size + SEGMENT_SIZE as u64 - 1) / SEGMENT_SIZE as u64);
+    header_size(key_id) + size + segments * TAG_LEN as u64
+}
+
+fn read_up_to(reader: &mut impl Read, size: usize) -> Result<Vec<u8>, CubeError> {
+    let mut buffer = Vec::with_capacity(size);
+    reader.take(size as u64).read_to_end(&mut buffer)?;
+    Ok(buffer)
+}
+
+fn encrypt_file(keys: &EncryptionKeys, source: &Path, destination: &Path) -> Result<(), CubeError> {
+    let crypto_error = |_| CubeError::internal(format!("Can't encrypt {:?}", source));
+    let rng = SystemRandom::new();
+    let mut data_key = [0; KEY_LEN];
+    let mut key_nonce = [0; NONCE_LEN];
+    let mut nonce_prefix = [0; NONCE_PREFIX_LEN];
+    rng.fill(&mut data_key).map_err(crypto_error)?;
+    rng.fill(&mut key_nonce).map_err(crypto_error)?;
+    rng.fill(&mut nonce_prefix).map_err(crypto_error)?;
+
+    let mut wrapped_key = data_key.to_vec();
+    keys.key(&keys.current)?
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(key_nonce),
+            Aad::from(keys.current.as_bytes()),
+            &mut wrapped_key,
+        )
+        .map_err(crypto_error)?;
+
+    let mut writer = BufWriter::new(File::create(destination)?);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[keys.current.len() as u8])?;
+    writer.write_all(keys.current.as_bytes())?;
+    writer.write_all(&key_nonce)?;
+    writer.write_all(&wrapped_key)?;
+    writer.write_all(&nonce_prefix)?;
+
+    let data_key = aead_key(&data_key)?;
+    let mut reader = BufReader::new(File::open(source)?);
+    let mut segment = read_up_to(&mut reader, SEGMENT_SIZE)?;
+    for index in 0..=u32::MAX {
+        let next = if segment.len() == SEGMENT_SIZE {
+            read_up_to(&mut reader, SEGMENT_SIZE)?
+        } else {
+            Vec::new()
+        };
+        let last = next.is_empty();
+        data_key
+            .seal_in_place_append_tag(
+                segment_nonce(&nonce_prefix, index),
+                Aad::from([last as u8]),
+                &mut segment,
+            )
+            .map_err(crypto_error)?;
+        writer.write_all(&segment)?;
+        if last {
+            writer.flush()?;
+            return Ok(());
+        }
+        segment = next;
+    }
+    Err(CubeError::internal(format!(
+        "File is too large to encrypt: {:?}",
+        source
+    )))
+}
+
+/// Copies files without the [MAGIC] header as is, unless `require_encryption` is set.
+fn decrypt_file(
+    keys: &EncryptionKeys,
+    source: &Path,
+    destination: &Path,
+    require_encryption: bool,
+) -> Result<(), CubeError> {
+    let crypto_error = |_| {
+        CubeError::internal(format!(
+            "Can't decrypt {:?}: data is corrupted or was encrypted with another key",
+            source
+        ))
+    };
+    let mut reader = BufReader::new(File::open(source)?);
+    let magic = read_up_to(&mut reader, MAGIC.len())?;
+    if magic != MAGIC && require_encryption {
+        return Err(CubeError::internal(format!(
+            "Can't decrypt {:?}: file is not encrypted and encryption is required",
+            source
+        )));
+    }
+    let mut writer = BufWriter::new(File::create(destination)?);
+    if magic != MAGIC {
+        writer.write_all(&magic)?;
+        std::io::copy(&mut reader, &mut writer)?;
+        writer.flush()?;
+        return Ok(());
+    }
+
+    let mut key_id_len = [0; 1];
+    reader.read_exact(&mut key_id_len)?;
+    let mut key_id = vec![0; key_id_len[0] as usize];
+    reader.read_exact(&mut key_id)?;
+    let key_id = String::from_utf8(key_id)?;
+    let mut key_nonce = [0; NONCE_LEN];
+    reader.read_exact(&mut key_nonce)?;
+    let mut data_key = [0; KEY_LEN + TAG_LEN];
+    reader.read_exact(&mut data_key)?;
+    let mut nonce_prefix = [0; NONCE_PREFIX_LEN];
+    reader.read_exact(&mut nonce_prefix)?;
+    let data_key = keys
+        .key(&key_id)?
+        .open_in_place(
+            Nonce::assume_unique_for_key(key_nonce),
+            Aad::from(key_id.as_bytes()),
+            &mut data_key,
+        )
+        .map_err(crypto_error)?;
+    let data_key = aead_key(data_key)?;
+
+    let mut segment = read_up_to(&mut reader, SEGMENT_SIZE + TAG_LEN)?;
+    for index in 0..=u32::MAX {
+        let next = if segment.len() == SEGMENT_SIZE + TAG_LEN {
+            read_up_to(&mut reader, SEGMENT_SIZE + TAG_LEN)?
+        } else {
+            Vec::new()
+        };
+        let last = next.is_empty();
+        let plaintext = data_key
+            .open_in_place(
+                segment_nonce(&nonce_prefix, index),
+                Aad::from([last as u8]),
+                &mut segment,
+            )
+            .map_err(crypto_error)?;
+        writer.write_all(plaintext)?;
+        if last {
+            writer.flush()?;
+            return Ok(());
+        }
+        segment = next;
+    }
+    Err(CubeError::internal(format!(
+        "File is too large to decrypt: {:?}",
+        source
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEYS: &str = r#"{
+        "current": "new",
+        "keys": {
+            "old": "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=",
+            "new": "ICEiIyQlJicoKSorLC0uLzAxMjM0NTY3ODk6Ozw9Pj8="
+        }
+    }"#;
+
+    fn roundtrip(keys: &EncryptionKeys, data: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let dir = tempfile::tempdir().unwrap();
+        let (plain, encrypted, decrypted) = (
+            dir.path().join("plain"),
+            dir.path().join("encrypted"),
+            dir.path().join("decrypted"),
+        );
+        std::fs::write(&plain, data).unwrap();
+        encrypt_file(keys, &plain, &encrypted).unwrap();
+        decrypt_file(keys, &encrypted, &decrypted, true).unwrap();
+        (
+            std::fs::read(&encrypted).unwrap(),
+            std::fs::read(&decrypted).unwrap(),
+        )
+    }
+
+    #[test]
+    fn encrypt_decrypt() {
+        let keys = EncryptionKeys::from_json(KEYS).unwrap();
+        for size in [0, 10, SEGMENT_SIZE, 2 * SEGMENT_SIZE + 7] {
+            let data = (0..size).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+            let (encrypted, decrypted) = roundtrip(&keys, &data);
+            assert_eq!(decrypted, data, "size {}", size);
+            assert!(encrypted.starts_with(MAGIC));
+            assert_eq!(
+                encrypted.len() as u64,
+                encrypted_size("new", size as u64),
+                "size {}",
+                size
+            );
+            if size != 0 {
+                assert!(!encrypted.windows(10).any(|w| w == &data[..10]));
+            }
+        }
+    }
+
+    #[test]
+    fn rotated_and_plaintext_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let (plain, encrypted, decrypted) = (
+            dir.path().join("plain"),
+            dir.path().join("encrypted"),
+            dir.path().join("decrypted"),
+        );
+        std::fs::write(&plain, b"PAR1 data").unwrap();
+
+        let old_keys = EncryptionKeys::from_json(&KEYS.replace("\"new\",", "\"old\",")).unwrap();
+        encrypt_file(&old_keys, &plain, &encrypted).unwrap();
+        let keys = EncryptionKeys::from_json(KEYS).unwrap();
+        decrypt_file(&keys, &encrypted, &decrypted, true).unwrap();
+        assert_eq!(std::fs::read(&decrypted).unwrap(), b"PAR1 data");
+
+        let err = decrypt_file(&keys, &plain, &decrypted, true).unwrap_err();
+        assert!(err.message.contains("not encrypted"), "{}", err);
+        decrypt_file(&keys, &plain, &decrypted, false).unwrap();
+        assert_eq!(std::fs::read(&decrypted).unwrap(), b"PAR1 data");
+
+        let without_old = EncryptionKeys::from_json(&format!(
+            r#"{{"current": "new", "keys": {{"new": "{}"}}}}"#,
+            base64::encode([7; KEY_LEN])
+        ))
+        .unwrap();
+        let err = decrypt_file(&without_old, &encrypted, &decrypted, true).unwrap_err();
+        assert!(err.message.contains("'old' is missing"), "{}", err);
+    }
+
+    #[test]
+    fn corrupted_files() {
+        let keys = EncryptionKeys::from_json(KEYS).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let (plain, encrypted, decrypted) = (
+            dir.path().join("plain"),
+            dir.path().join("encrypted"),
+            dir.path().join("decrypted"),
+        );
+        std::fs::write(&plain, vec![1; 2 * SEGMENT_SIZE]).unwrap();
+        encrypt_file(&keys, &plain, &encrypted).unwrap();
+        let data = std::fs::read(&encrypted).unwrap();
+
+        let mut flipped = data.clone();
+        flipped[data.len() / 2] ^= 1;
+        std::fs::write(&encrypted, &flipped).unwrap();
+        assert!(decrypt_file(&keys, &encrypted, &decrypted, true).is_err());
+
+        // Drop the last segment.
+        let truncated = &data[..data.len() - SEGMENT_SIZE - TAG_LEN];
+        std::fs::write(&encrypted, truncated).unwrap();
+        assert!(decrypt_file(&keys, &encrypted, &decrypted, true).is_err());
+    }
+
+    #[test]
+    fn invalid_key_files() {
+        let err = EncryptionKeys::from_json(r#"{"current": "a", "keys": {}}"#).unwrap_err();
+        assert!(err.message.contains("'a' is missing"), "{}", err);
+        let err =
+            EncryptionKeys::from_json(r#"{"current": "a", "keys": {"a": "AAEC"}}"#).unwrap_err();
+        assert!(err.message.contains("must be 32 bytes"), "{}", err);
+        let keys = EncryptionKeys::from_json(KEYS).unwrap();
+        assert!(!format!("{:?}", keys).contains("AAEC"));
+    }
+}
@@ -1,4 +1,6 @@
+pub mod azure;
 pub mod cleanup;
+pub mod encrypted;
 pub mod gcs;
 pub mod minio;
 pub mod queue;
@@ -448,6 +450,8 @@ impl LocalDirRemoteFs {
 
 #[cfg(test)]
 mod tests {
+    use super::azure::{AzureBlobRemoteFs, AzureCredentials};
+    use super::encrypted::{EncryptedRemoteFs, EncryptionKeys};
     use super::s3::S3RemoteFs;
     use super::*;
     use std::io::prelude::*;
@@ -628,6 +632,61 @@ mod tests {
         clear_test_dir("local-upstream");
     }
 
+    #[tokio::test]
+    async fn encrypted_local_dir() {
+        clear_test_dir("encrypted");
+        clear_test_dir("encrypted-upstream");
+        let local_path = get_test_local_dir("encrypted");
+        let local_upstream = get_test_local_dir("encrypted-upstream");
+        let keys = EncryptionKeys::from_json(&format!(
+            r#"{{"current": "k1", "keys": {{"k1": "{}"}}}}"#,
+            base64::encode([1; 32])
+        ))
+        .unwrap();
+        let inner = LocalDirRemoteFs::new(
+            Some(local_upstream.clone()),
+            EncryptedRemoteFs::inner_dir(&local_path),
+        );
+        let keys = Arc::new(keys);
+        let remote_fs =
+            EncryptedRemoteFs::new(inner.clone(), local_path.clone(), keys.clone(), true);
+
+        let name_maker = NameMaker::new("".to_string());
+        test_remote_filesystem(remote_fs.clone(), local_path.as_ref(), name_maker, true).await;
+
+        create_and_upload_file(&(remote_fs.clone() as Arc<dyn RemoteFs>), "enc.txt")
+            .await
+            .unwrap();
+        let uploaded = fs::read(local_upstream.join("enc.txt")).unwrap();
+        assert!(uploaded.starts_with(b"CUBEENC1"));
+        fs::remove_file(local_path.join("enc.txt")).unwrap();
+        let downloaded = remote_fs
+            .download_file("enc.txt".to_string(), None)
+            .await
+            .unwrap();
+        assert_eq!(fs::read(downloaded).unwrap(), b"test");
+
+        // Plaintext files are rejected unless encryption is optional.
+        fs::write(local_upstream.join("plain.txt"), b"plain").unwrap();
+        let err = remote_fs
+            .download_file("plain.txt".to_string(), None)
+            .await
+            .unwrap_err();
+        assert!(err.message.contains("not encrypted"), "{}", err);
+        assert!(!local_path.join("plain.txt").exists());
+
+        // Files uploaded before encryption was enabled stay readable when it is optional.
+        let optional_fs = EncryptedRemoteFs::new(inner, local_path.clone(), keys, false);
+        let downloaded = optional_fs
+            .download_file("plain.txt".to_string(), None)
+            .await
+            .unwrap();
+        assert_eq!(fs::read(downloaded).unwrap(), b"plain");
+
+        clear_test_dir("encrypted");
+        clear_test_dir("encrypted-upstream");
+    }
+
     #[tokio::test]
     async fn aws_s3() {
         if env::var("CUBESTORE_AWS_ACCESS_KEY_ID").is_err() {
@@ -665,4 +724,69 @@ mod tests {
 
         clear_test_dir("aws_s3");
     }
+
+    #[tokio::test]
+    async fn azure_blob_mock() {
+        clear_test_dir("azure_mock");
+        let local_path = get_test_local_dir("azure_mock");
+        let endpoint = super::azure::mock::start_blob_service("data", "test");
+
+        let name_maker = NameMaker::new("".to_string());
+        for sub_path in [None, Some("remotefs_test_subpathdir".to_string())] {
+            let remote_fs = AzureBlobRemoteFs::with_credentials(
+                local_path.clone(),
+                endpoint.clone(),
+                "account".to_string(),
+                "data".to_string(),
+                sub_path,
+                AzureCredentials::Sas("sv=2020-08-04&sig=test".to_string()),
+            )
+            .unwrap();
+            test_remote_filesystem(remote_fs, local_path.as_ref(), name_maker.clone(), true).await;
+            clear_test_dir("azure_mock");
+        }
+
+        let remote_fs = AzureBlobRemoteFs::with_credentials(
+            local_path.clone(),
+            endpoint,
+            "account".to_string(),
+            "data".to_string(),
+            None,
+            AzureCredentials::Sas("sv=2020-08-04&sig=wrong".to_string()),
+        )
+        .unwrap();
+        let err = remote_fs.list("".to_string()).await.unwrap_err();
+        assert!(err.message.contains("403"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn azure_blob_azurite() {
+        let endpoint = match env::var("CUBESTORE_TEST_AZURITE_ENDPOINT") {
+            Ok(e) => e,
+            Err(_) => return,
+        };
+        // Well-known development account of the Azurite emulator.
+        let key = base64::decode("Eby8vdM02xNOcqFlqUwJPLlmEtlCDXJ1OUzFT50uSRZ6IFsuFq2UVErCz4I6tq/K1SZFPTOtr/KBHBeksoGMGw==").unwrap();
+
+        clear_test_dir("azurite");
+        let local_path = get_test_local_dir("azurite");
+        let name_maker = NameMaker::new(Uuid::new_v4().to_string());
+        let container = format!("cubestore-{}", Uuid::new_v4());
+        for sub_path in [None, Some("remotefs_test_subpathdir".to_string())] {
+            let remote_fs = AzureBlobRemoteFs::with_credentials(
+                local_path.clone(),
+                endpoint.clone(),
+                "devstoreaccount1".to_string(),
+                container.clone(),
+                sub_path.clone(),
+                AzureCredentials::SharedKey(key.clone()),
+            )
+            .unwrap();
+            if sub_path.is_none() {
+                remote_fs.create_container().await.unwrap();
+            }
+            test_remote_filesystem(remote_fs, local_path.as_ref(), name_maker.clone(), true).await;
+            clear_test_dir("azurite");
+        }
+    }
 }
@@ -227,6 +227,7 @@ impl RemoteFs for QueueRemoteFs {
         }
     }
 
+    #[tracing::instrument(level = "info", skip(self))]
     async fn download_file(
         &self,
         remote_path: String,
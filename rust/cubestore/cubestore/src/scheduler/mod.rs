@@ -9,17 +9,19 @@ use crate::metastore::replay_handle::{
     union_seq_pointer_by_location, SeqPointerForLocation,
 };
 use crate::metastore::table::Table;
+use crate::metastore::worker::WorkerStatus;
 use crate::metastore::{
     deactivate_table_due_to_corrupt_data, deactivate_table_on_corrupt_data, Chunk, IdRow,
     MetaStore, MetaStoreEvent, Partition, RowKey, TableId,
 };
 use crate::remotefs::RemoteFs;
 use crate::shared::deadline_queue::DeadlineQueue;
+use crate::store::retention::RetentionCutoff;
 use crate::store::{ChunkStore, WALStore};
 use crate::util::time_span::warn_long_fut;
 use crate::util::WorkerLoop;
 use crate::CubeError;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use datafusion::cube_ext;
 use futures::future::join_all;
 use futures_timer::Delay;
@@ -47,6 +49,8 @@ pub struct SchedulerImpl {
     chunk_events_queue: Mutex<Vec<(SystemTime, u64)>>,
     in_memory_chunks_to_delete: Mutex<Vec<(String, String)>>, //(node, chunk_is)
     node_last_actions: Mutex<HashMap<String, LastNodeActionTimes>>,
+    /// Last time partitions of the index were rewritten to drop rows expired by the retention.
+    retention_last_rewrites: Mutex<HashMap<u64, DateTime<Utc>>>,
 }
 
 crate::di_service!(SchedulerImpl, []);
@@ -101,6 +105,7 @@ impl SchedulerImpl {
             chunk_events_queue: Mutex::new(Vec::with_capacity(1000)),
             in_memory_chunks_to_delete: Mutex::new(Vec::with_capacity(1000)),
             node_last_actions: Mutex::new(workers),
+            retention_last_rewrites: Mutex::new(HashMap::new()),
             chunk_processing_loop: WorkerLoop::new("ChunkProcessing"),
         }
     }
@@ -338,6 +343,16 @@ impl SchedulerImpl {
             );
         }
 
+        if let Err(e) = warn_long_fut(
+            "Drop expired data",
+            Duration::from_millis(5000),
+            self.drop_expired_data(),
+        )
+        .await
+        {
+            error!("Error dropping expired data: {}", e);
+        }
+
         if let Err(e) = warn_long_fut(
             "Scheduling compactions",
             Duration::from_millis(5000),
@@ -550,6 +565,99 @@ impl SchedulerImpl {
         }
         Ok(())
     }
+    /// Deactivates chunks and replaces partitions expired by the table retention. Partitions
+    /// with some expired rows are compacted to rewrite them without those rows, which is done
+    /// at most once per retention period for each index.
+    async fn drop_expired_data(&self) -> Result<(), CubeError> {
+        let tables = self.meta_store.get_tables_with_path(false).await?;
+        let now = Utc::now();
+        let mut retention_by_index = HashMap::new();
+        for table in tables
+            .iter()
+            .filter(|t| t.table.get_row().retention().is_some())
+        {
+            let indexes = self
+                .meta_store
+                .get_table_indexes(table.table.get_id())
+                .await?;
+            for index in indexes {
+                // Multi-partitions are split concurrently and never compact the main table.
+                if index.get_row().multi_index_id().is_some() {
+                    continue;
+                }
+                if let Some(r) = RetentionCutoff::new(table.table.get_row(), index.get_row(), now) {
+                    retention_by_index.insert(index.get_id(), r);
+                }
+            }
+        }
+        if retention_by_index.is_empty() {
+            return Ok(());
+        }
+
+        let rewrite_indexes = {
+            let mut last_rewrites = self.retention_last_rewrites.lock().await;
+            last_rewrites.retain(|index_id, _| retention_by_index.contains_key(index_id));
+            retention_by_index
+                .iter()
+                .filter(|(index_id, r)| {
+                    last_rewrites
+                        .get(index_id)
+                        .map_or(true, |last| *last + r.period() <= now)
+                })
+                .map(|(index_id, _)| *index_id)
+                .collect::<HashSet<_>>()
+        };
+
+        let partitions = self
+            .meta_store
+            .get_active_partitions_and_chunks_by_index_ids(
+                retention_by_index.keys().cloned().collect(),
+            )
+            .await?;
+        let mut rewritten_indexes = HashSet::new();
+        for (p, chunks) in partitions {
+            let index_id = p.get_row().get_index_id();
+            let retention = &retention_by_index[&index_id];
+            let expired_chunks = chunks
+                .into_iter()
+                .filter(|c| retention.is_expired(c.get_row().min(), c.get_row().max()))
+                .collect_vec();
+            let (partition_min, partition_max) = (p.get_row().get_min(), p.get_row().get_max());
+            if p.get_row().main_table_row_count() > 0
+                && retention.is_expired(partition_min, partition_max)
+            {
+                log::debug!("Replacing expired partition {}", p.get_id());
+                self.meta_store
+                    .replace_expired_partition((p, expired_chunks))
+                    .await?;
+                continue;
+            }
+            if !expired_chunks.is_empty() {
+                let expired_chunks = expired_chunks.iter().map(|c| c.get_id()).collect_vec();
+                log::debug!(
+                    "Deactivating expired chunks of partition {}: {:?}",
+                    p.get_id(),
+                    expired_chunks
+                );
+                self.meta_store
+                    .deactivate_chunks_without_check(expired_chunks)
+                    .await?;
+            }
+            if rewrite_indexes.contains(&index_id)
+                && retention.has_expired_rows(partition_min, partition_max)
+            {
+                self.schedule_partition_to_compact(&p).await?;
+                rewritten_indexes.insert(index_id);
+            }
+        }
+
+        let mut last_rewrites = self.retention_last_rewrites.lock().await;
+        for index_id in rewritten_indexes {
+            last_rewrites.insert(index_id, now);
+        }
+        Ok(())
+    }
+
     async fn deactivate_chunks_without_partitions(&self) -> Result<(), CubeError> {
         let chunks_without_partitions = self
             .meta_store
@@ -1119,6 +1227,9 @@ impl SchedulerImpl {
                 .unwrap_or(false)
         {
             let node = self.cluster.node_name_by_partition(partition);
+            if self.is_handing_over(&node).await? {
+                return Ok(());
+            }
             let job = self
                 .meta_store
                 .add_job(Job::new(
@@ -1199,7 +1310,7 @@ impl SchedulerImpl {
         &self,
         multi_partition_id: u64,
     ) -> Result<(), CubeError> {
-        let node = pick_worker_by_ids(self.config.as_ref(), [multi_partition_id]).to_string();
+        let node = pick_worker_by_ids(self.config.as_ref(), [multi_partition_id]);
         let job = self
             .meta_store
             .add_job(Job::new(
@@ -1227,7 +1338,7 @@ impl SchedulerImpl {
         {
             return Ok(());
         }
-        let node = pick_worker_by_ids(self.config.as_ref(), [multi_partition_id]).to_string();
+        let node = pick_worker_by_ids(self.config.as_ref(), [multi_partition_id]);
         let job = self
             .meta_store
             .add_job(Job::new(
@@ -1264,7 +1375,15 @@ impl SchedulerImpl {
     }
 
     pub async fn schedule_node_in_memory_compaction(&self, node: String) -> Result<(), CubeError> {
+        if self.is_handing_over(&node).await? {
+            return Ok(());
+        }
         let mut node_last_actions = self.node_last_actions.lock().await;
+        if self.config.dynamic_workers() {
+            node_last_actions
+                .entry(node.clone())
+                .or_insert_with(LastNodeActionTimes::new);
+        }
         if let Some(last_action) = node_last_actions.get_mut(&node) {
             if last_action
                 .in_memory_compaction()
@@ -1299,6 +1418,18 @@ impl SchedulerImpl {
         }
     }
 
+    /// Previous owners keep some in-memory chunks of partitions that moved to a joining worker
+    /// until the handover finishes. The worker would consider these chunks lost, so its
+    /// in-memory chunks aren't compacted until then.
+    async fn is_handing_over(&self, node: &str) -> Result<bool, CubeError> {
+        if !self.config.dynamic_workers() {
+            return Ok(false);
+        }
+        Ok(self.meta_store.get_workers().await?.iter().any(|w| {
+            w.get_row().name() == node && w.get_row().status() == WorkerStatus::HandingOver
+        }))
+    }
+
     async fn schedule_partition_warmup(
         &self,
         p: &IdRow<Partition>,
@@ -4,7 +4,7 @@ use crate::metastore::{Column, ColumnType};
 use crate::cluster::rate_limiter::{ProcessRateLimiter, TaskType, TraceIndex};
 use crate::queryplanner::{QueryPlan, QueryPlanner};
 use crate::sql::parser::{
-    CacheCommand, CacheStoreCommand, CubeStoreParser, QueueCommand,
+    CacheCommand, CacheStoreCommand, CubeStoreParser, QueueAddSchedule, QueueCommand,
     Statement as CubeStoreStatement, SystemCommand,
 };
 use crate::sql::{QueryPlans, SqlQueryContext, SqlService};
@@ -276,6 +276,122 @@ impl CacheStoreSqlService {
                     true,
                 )
             }
+            CacheCommand::CompareAndSet {
+                key,
+                expected,
+                value,
+                ttl,
+            } => {
+                let value_size =
+                    key.value.deep_size_of() + expected.deep_size_of() + value.deep_size_of();
+                let success = self
+                    .cachestore
+                    .cache_compare_and_set(CacheItem::new(key.value, ttl, value), expected)
+                    .await?;
+
+                (
+                    Arc::new(DataFrame::new(
+                        vec![Column::new("success".to_string(), ColumnType::Boolean, 0)],
+                        vec![Row::new(vec![TableValue::Boolean(success)])],
+                    )),
+                    Some(value_size),
+                    true,
+                )
+            }
+            CacheCommand::HSet { key, fields } => {
+                let value_size = key.value.deep_size_of() + fields.deep_size_of();
+                let added = self.cachestore.cache_hset(key.value, fields).await?;
+
+                (
+                    Arc::new(DataFrame::new(
+                        vec![Column::new("added".to_string(), ColumnType::Int, 0)],
+                        vec![Row::new(vec![TableValue::Int(added as i64)])],
+                    )),
+                    Some(value_size),
+                    true,
+                )
+            }
+            CacheCommand::HGet { key, field } => {
+                let value = self.cachestore.cache_hget(key.value, field).await?;
+
+                (
+                    Arc::new(DataFrame::new(
+                        vec![Column::new("value".to_string(), ColumnType::String, 0)],
+                        vec![Row::new(vec![value
+                            .map(TableValue::String)
+                            .unwrap_or(TableValue::Null)])],
+                    )),
+                    None,
+                    true,
+                )
+            }
+            CacheCommand::HDel { key, fields } => {
+                let removed = self.cachestore.cache_hdel(key.value, fields).await?;
+
+                (
+                    Arc::new(DataFrame::new(
+                        vec![Column::new("removed".to_string(), ColumnType::Int, 0)],
+                        vec![Row::new(vec![TableValue::Int(removed as i64)])],
+                    )),
+                    None,
+                    true,
+                )
+            }
+            CacheCommand::HGetAll { key } => {
+                let fields = self.cachestore.cache_hgetall(key.value).await?;
+
+                (
+                    Arc::new(DataFrame::new(
+                        vec![
+                            Column::new("field".to_string(), ColumnType::String, 0),
+                            Column::new("value".to_string(), ColumnType::String, 1),
+                        ],
+                        fields
+                            .into_iter()
+                            .map(|(field, value)| {
+                                Row::new(vec![TableValue::String(field), TableValue::String(value)])
+                            })
+                            .collect(),
+                    )),
+                    None,
+                    true,
+                )
+            }
+            CacheCommand::ListPush { key, values, side } => {
+                let value_size = key.value.deep_size_of() + values.deep_size_of();
+                let length = self
+                    .cachestore
+                    .cache_list_push(key.value, values, side)
+                    .await?;
+
+                (
+                    Arc::new(DataFrame::new(
+                        vec![Column::new("length".to_string(), ColumnType::Int, 0)],
+                        vec![Row::new(vec![TableValue::Int(length as i64)])],
+                    )),
+                    Some(value_size),
+                    true,
+                )
+            }
+            CacheCommand::ListPop { key, side } => {
+                let value = self.cachestore.cache_list_pop(key.value, side).await?;
+
+                (
+                    Arc::new(DataFrame::new(
+                        vec![Column::new("value".to_string(), ColumnType::String, 0)],
+                        vec![Row::new(vec![value
+                            .map(TableValue::String)
+                            .unwrap_or(TableValue::Null)])],
+                    )),
+                    None,
+                    true,
+                )
+            }
+            CacheCommand::Subscribe { .. } => {
+                return Err(CubeError::user(
+                    "CACHE SUBSCRIBE is supported only over the WebSocket protocol".to_string(),
+                ));
+            }
         };
 
         let trace_index = TraceIndex {
@@ -329,19 +445,27 @@ impl CacheStoreSqlService {
                 key,
                 priority,
                 orphaned,
+                schedule,
                 value,
             } => {
                 let value_size = key.value.deep_size_of() + value.deep_size_of();
-                let response = self
-                    .cachestore
-                    .queue_add(QueueItem::new(
-                        key.value,
-                        value,
-                        QueueItem::status_default(),
-                        priority,
-                        orphaned,
-                    ))
-                    .await?;
+                let item = QueueItem::new(
+                    key.value,
+                    value,
+                    QueueItem::status_default(),
+                    priority,
+                    orphaned,
+                );
+                let item = match schedule {
+                    Some(QueueAddSchedule::Delay(seconds)) => {
+                        let scheduled =
+                            *item.get_created() + chrono::Duration::seconds(seconds as i64);
+                        item.with_scheduled(scheduled)
+                    }
+                    Some(QueueAddSchedule::At(scheduled)) => item.with_scheduled(scheduled),
+                    None => item,
+                };
+                let response = self.cachestore.queue_add(item).await?;
 
                 (
                     Arc::new(DataFrame::new(
@@ -365,6 +489,18 @@ impl CacheStoreSqlService {
 
                 (Arc::new(DataFrame::new(vec![], vec![])), None, false)
             }
+            QueueCommand::SetPrefix {
+                prefix,
+                concurrency,
+                max_attempts,
+                heartbeat_timeout,
+            } => {
+                self.cachestore
+                    .queue_prefix_set(prefix.value, concurrency, max_attempts, heartbeat_timeout)
+                    .await?;
+
+                (Arc::new(DataFrame::new(vec![], vec![])), None, false)
+            }
             QueueCommand::Cancel { key } => {
                 let columns = vec![
                     Column::new("payload".to_string(), ColumnType::String, 0),
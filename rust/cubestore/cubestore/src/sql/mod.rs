@@ -42,10 +42,16 @@ use crate::config::ConfigObj;
 use crate::import::limits::ConcurrencyLimits;
 use crate::import::{parse_space_separated_binstring, ImportService, Ingestion};
 use crate::metastore::multi_index::MultiIndex;
-use crate::metastore::source::SourceCredentials;
+use crate::metastore::partition::partition_file_name;
+use crate::metastore::source::{
+    KafkaSaslMechanism, KafkaSecurity, KafkaSecurityProtocol, SourceCredentials,
+};
+use crate::metastore::table::{
+    ParquetCompression, ParquetOptions, StreamValueFormat, TableAlteration,
+};
 use crate::metastore::{
     is_valid_plain_binary_hll, HllFlavour, IdRow, ImportFormat, Index, IndexDef, IndexType,
-    MetaStoreTable, Schema,
+    MetaStoreTable, Partition, Schema,
 };
 use crate::queryplanner::panic::PanicWorkerNode;
 use crate::queryplanner::pretty_printers::{pp_phys_plan, pp_plan};
@@ -54,12 +60,16 @@ use crate::queryplanner::serialized_plan::{RowFilter, SerializedPlan};
 use crate::queryplanner::{PlanningMeta, QueryPlan, QueryPlanner};
 use crate::remotefs::RemoteFs;
 use crate::sql::cache::SqlResultCache;
-use crate::sql::parser::{CubeStoreParser, DropCommand, MetaStoreCommand, SystemCommand};
+use crate::sql::parser::{
+    AlterTableOperation, CubeStoreParser, DropCommand, MetaStoreCommand, SystemCommand,
+};
+use crate::store::mutation::RowsMutation;
 use crate::store::ChunkDataStore;
 use crate::table::{data, Row, TableValue, TimestampValue};
 use crate::util::decimal::{Decimal, Decimal96};
 use crate::util::strings::path_to_string;
 use crate::CubeError;
+use crate::CubeErrorCauseType;
 use crate::{
     app_metrics,
     metastore::{Column, ColumnType, MetaStore},
@@ -309,6 +319,132 @@ impl SqlServiceImpl {
         Ok(data.len() as u64)
     }
 
+    /// Applies `DELETE` or `UPDATE` by rewriting every partition which can contain matching rows.
+    /// Rewritten partitions are activated at once so either all rows are mutated or none of them.
+    /// The mutation is retried if partitions were replaced concurrently.
+    async fn mutate_rows(
+        &self,
+        schema_name: String,
+        table_name: String,
+        mutation: RowsMutation,
+    ) -> Result<(), CubeError> {
+        let table = self
+            .db
+            .get_table(schema_name.clone(), table_name.clone())
+            .await?;
+        let indexes = self.db.get_table_indexes(table.get_id()).await?;
+        mutation.validate(table.get_row(), &indexes)?;
+
+        let mut last_error = None;
+        for _ in 0..3 {
+            let mut partitions = Vec::new();
+            for index in indexes.iter() {
+                let filter = mutation.partition_filter(index.get_row())?;
+                for p in self
+                    .db
+                    .get_active_partitions_by_index_id(index.get_id())
+                    .await?
+                {
+                    let min_row = p
+                        .get_row()
+                        .get_min_val()
+                        .as_ref()
+                        .map(|r| r.values().as_slice());
+                    let max_row = p
+                        .get_row()
+                        .get_max_val()
+                        .as_ref()
+                        .map(|r| r.values().as_slice());
+                    if filter.can_match(min_row, max_row) {
+                        partitions.push(p);
+                    }
+                }
+            }
+            if partitions.is_empty() {
+                return Ok(());
+            }
+
+            // Partitions are rewritten by their owners, one at a time on every node.
+            let mut partitions_by_node = HashMap::<String, Vec<&IdRow<Partition>>>::new();
+            for p in partitions.iter() {
+                partitions_by_node
+                    .entry(self.cluster.node_name_by_partition(p))
+                    .or_default()
+                    .push(p);
+            }
+            let mutation = &mutation;
+            let node_futures = partitions_by_node
+                .into_iter()
+                .map(|(node_name, partitions)| async move {
+                    let mut results = Vec::with_capacity(partitions.len());
+                    for p in partitions {
+                        let result = self
+                            .cluster
+                            .rewrite_partition(&node_name, p.get_id(), mutation)
+                            .await;
+                        let failed = result.is_err();
+                        results.push(result);
+                        if failed {
+                            break;
+                        }
+                    }
+                    results
+                })
+                .collect::<Vec<_>>();
+
+            let mut rewrites = Vec::with_capacity(partitions.len());
+            let mut result = Ok(());
+            for r in join_all(node_futures).await.into_iter().flatten() {
+                match r {
+                    Ok(rewrite) => rewrites.push(rewrite),
+                    Err(e) => {
+                        if result.is_ok() {
+                            result = Err(e);
+                        }
+                    }
+                }
+            }
+            let new_partitions = rewrites
+                .iter()
+                .map(|r| r.new_partition.clone())
+                .collect::<Vec<_>>();
+            if result.is_ok() {
+                result = self.db.swap_rewritten_partitions(rewrites).await;
+            }
+            match result {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    self.drop_rewritten_partitions(new_partitions).await?;
+                    if e.cause == CubeErrorCauseType::User {
+                        return Err(e);
+                    }
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(CubeError::user(format!(
+            "Failed to apply {} to {}.{}: {}",
+            mutation.name(),
+            schema_name,
+            table_name,
+            last_error.unwrap()
+        )))
+    }
+
+    async fn drop_rewritten_partitions(
+        &self,
+        partitions: Vec<IdRow<Partition>>,
+    ) -> Result<(), CubeError> {
+        for p in partitions {
+            self.remote_fs
+                .delete_file(partition_file_name(p.get_id(), p.get_row().suffix()))
+                .await?;
+            self.db.delete_partition(p.get_id()).await?;
+        }
+        Ok(())
+    }
+
     async fn dump_select_inputs(
         &self,
         query: &str,
@@ -492,6 +628,166 @@ pub fn boolean_prop(credentials: &Vec<SqlOption>, prop_name: &str) -> Option<boo
         })
 }
 
+fn parse_dictionary_flag(v: &str) -> Result<bool, CubeError> {
+    match v.to_lowercase().as_str() {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(CubeError::user(format!(
+            "Bad dictionary value {}. Expected true or false.",
+            v
+        ))),
+    }
+}
+
+/// Per column options are passed as `'column:value,column2:value'`.
+fn parse_column_prop(v: &str, prop_name: &str) -> Result<Vec<(String, String)>, CubeError> {
+    v.split(',')
+        .map(|p| match p.rsplit_once(':') {
+            Some((column, value)) if !column.trim().is_empty() => {
+                Ok((column.trim().to_string(), value.trim().to_string()))
+            }
+            _ => Err(CubeError::user(format!(
+                "Bad {} {}. Expected 'column:value' pairs separated by comma.",
+                prop_name, v
+            ))),
+        })
+        .collect()
+}
+
+fn parquet_options_from_with_options(
+    with_options: &Vec<SqlOption>,
+) -> Result<Option<ParquetOptions>, CubeError> {
+    let mut options = ParquetOptions::default();
+    let mut is_set = false;
+    if let Some(compression) = string_prop(with_options, "compression") {
+        options.compression = Some(compression.parse::<ParquetCompression>()?);
+        is_set = true;
+    }
+    if let Some(dictionary) = string_prop(with_options, "dictionary") {
+        options.dictionary = Some(parse_dictionary_flag(&dictionary)?);
+        is_set = true;
+    }
+    if let Some(v) = string_prop(with_options, "column_compression") {
+        for (column, compression) in parse_column_prop(&v, "column_compression")? {
+            options.column_mut(&column).compression = Some(compression.parse()?);
+        }
+        is_set = true;
+    }
+    if let Some(v) = string_prop(with_options, "column_encoding") {
+        for (column, encoding) in parse_column_prop(&v, "column_encoding")? {
+            options.column_mut(&column).encoding = Some(encoding.parse()?);
+        }
+        is_set = true;
+    }
+    if let Some(v) = string_prop(with_options, "column_dictionary") {
+        for (column, dictionary) in parse_column_prop(&v, "column_dictionary")? {
+            options.column_mut(&column).dictionary = Some(parse_dictionary_flag(&dictionary)?);
+        }
+        is_set = true;
+    }
+
+    Ok(if is_set { Some(options) } else { None })
+}
+
+/// Parses retention period like `'90 days'` into seconds.
+fn parse_retention_period(v: &str) -> Result<u64, CubeError> {
+    let bad_period = || {
+        CubeError::user(format!(
+            "Bad retention {}. Expected period like '90 days' or '12 hours'.",
+            v
+        ))
+    };
+    let (amount, unit) = v.trim().split_once(' ').ok_or_else(bad_period)?;
+    let amount = amount.parse::<u64>().map_err(|_| bad_period())?;
+    let unit_secs = match unit.trim().to_lowercase().trim_end_matches('s') {
+        "second" => 1,
+        "minute" => 60,
+        "hour" => 60 * 60,
+        "day" => 24 * 60 * 60,
+        "week" => 7 * 24 * 60 * 60,
+        _ => return Err(bad_period()),
+    };
+    match amount.checked_mul(unit_secs) {
+        Some(secs) if secs > 0 => Ok(secs),
+        _ => Err(bad_period()),
+    }
+}
+
+fn retention_from_with_options(
+    with_options: &Vec<SqlOption>,
+) -> Result<Option<(String, u64)>, CubeError> {
+    match (
+        string_prop(with_options, "retention_column"),
+        string_prop(with_options, "retention"),
+    ) {
+        (Some(column), Some(period)) => Ok(Some((column, parse_retention_period(&period)?))),
+        (None, None) => Ok(None),
+        _ => Err(CubeError::user(
+            "Both retention_column and retention should be set".to_string(),
+        )),
+    }
+}
+
+/// Kafka source credentials besides `user`, `password` and `use_ssl`. Credentials with dots in
+/// their names, like `` `ssl.endpoint.identification.algorithm` = 'none' ``, are passed to
+/// librdkafka as is.
+fn kafka_security_from_credentials(
+    credentials: &Vec<SqlOption>,
+) -> Result<KafkaSecurity, CubeError> {
+    let security = KafkaSecurity {
+        security_protocol: string_prop(credentials, "security_protocol")
+            .map(|p| p.parse::<KafkaSecurityProtocol>())
+            .transpose()?,
+        sasl_mechanism: string_prop(credentials, "sasl_mechanism")
+            .map(|m| m.parse::<KafkaSaslMechanism>())
+            .transpose()?,
+        ssl_ca_pem: string_prop(credentials, "ssl_ca_pem"),
+        ssl_certificate_pem: string_prop(credentials, "ssl_certificate_pem"),
+        ssl_key_pem: string_prop(credentials, "ssl_key_pem"),
+        ssl_key_password: string_prop(credentials, "ssl_key_password"),
+        properties: credentials
+            .iter()
+            .filter(|o| o.name.value.contains('.'))
+            .map(|o| {
+                let value = match &o.value {
+                    Value::SingleQuotedString(v) | Value::Number(v, _) => v.to_string(),
+                    Value::Boolean(v) => v.to_string(),
+                    v => {
+                        return Err(CubeError::user(format!(
+                            "Bad value {} of kafka property {}",
+                            v, o.name.value
+                        )))
+                    }
+                };
+                Ok((o.name.value.to_string(), value))
+            })
+            .collect::<Result<_, CubeError>>()?,
+    };
+    if security.ssl_certificate_pem.is_some() != security.ssl_key_pem.is_some() {
+        return Err(CubeError::user(
+            "Both ssl_certificate_pem and ssl_key_pem should be set".to_string(),
+        ));
+    }
+    if security.sasl_mechanism.is_some()
+        && (string_prop(credentials, "user").is_none()
+            || string_prop(credentials, "password").is_none())
+    {
+        return Err(CubeError::user(
+            "user and password are required for sasl_mechanism".to_string(),
+        ));
+    }
+    // Consumers are assigned to their partitions by these.
+    for name in ["bootstrap.servers", "group.id"] {
+        if security.properties.contains_key(name) {
+            return Err(CubeError::user(format!(
+                "Kafka property {} can't be set",
+                name
+            )));
+        }
+    }
+    Ok(security)
+}
+
 #[derive(Debug)]
 pub struct MySqlDialectWithBackTicks {}
 
@@ -523,7 +819,13 @@ impl SqlService for SqlServiceImpl {
             .await
     }
 
-    #[instrument(level = "trace", skip(self))]
+    // Query text and inline tables are not recorded, they can be large or contain secrets.
+    #[instrument(
+        level = "info",
+        name = "query",
+        skip(self, context, query),
+        fields(user = ?context.user)
+    )]
     async fn exec_query_with_context(
         &self,
         context: SqlQueryContext,
@@ -536,10 +838,10 @@ impl SqlService for SqlServiceImpl {
         if let Some(data_frame) = SqlServiceImpl::handle_workbench_queries(query) {
             return Ok(Arc::new(data_frame));
         }
-        let ast = {
+        let ast = tracing::info_span!("parse_sql").in_scope(|| {
             let mut parser = CubeStoreParser::new(query)?;
-            parser.parse_statement()?
-        };
+            parser.parse_statement()
+        })?;
         // trace!("AST is: {:?}", ast);
         match ast {
             CubeStoreStatement::Statement(Statement::ShowVariable { variable }) => {
@@ -623,6 +925,20 @@ impl SqlService for SqlServiceImpl {
                         self.db.set_current_snapshot(id).await?;
                         Ok(Arc::new(DataFrame::new(vec![], vec![])))
                     }
+                    MetaStoreCommand::Backup { location } => {
+                        let snapshot = self.db.backup_snapshot(location.clone()).await?;
+                        let columns = vec![
+                            Column::new("snapshot_id".to_string(), ColumnType::String, 0),
+                            Column::new("location".to_string(), ColumnType::String, 1),
+                        ];
+                        Ok(Arc::new(DataFrame::new(
+                            columns,
+                            vec![Row::new(vec![
+                                TableValue::String(snapshot.to_string()),
+                                TableValue::String(location),
+                            ])],
+                        )))
+                    }
                     MetaStoreCommand::Compaction => {
                         self.db.compaction().await?;
                         Ok(Arc::new(DataFrame::new(vec![], vec![])))
@@ -693,6 +1009,9 @@ impl SqlService for SqlServiceImpl {
                                 match input_format.as_str() {
                                     "csv" => Result::Ok(ImportFormat::CSV),
                                     "csv_no_header" => Result::Ok(ImportFormat::CSVNoHeader),
+                                    "parquet" => Result::Ok(ImportFormat::Parquet),
+                                    "ndjson" => Result::Ok(ImportFormat::NDJSON),
+                                    "arrow" => Result::Ok(ImportFormat::Arrow),
                                     _ => Result::Err(CubeError::user(format!(
                                         "Bad input_format {}",
                                         option.value
@@ -746,6 +1065,13 @@ impl SqlService for SqlServiceImpl {
                             escape,
                             quote,
                         },
+                        f
+                        @ (ImportFormat::Parquet | ImportFormat::NDJSON | ImportFormat::Arrow) => {
+                            return Err(CubeError::user(format!(
+                                "Delimiter can't be used with {:?} input format",
+                                f
+                            )))
+                        }
                     }
                 }
                 let build_range_end = with_options
@@ -810,6 +1136,11 @@ impl SqlService for SqlServiceImpl {
                             option.value
                         ))),
                     })?;
+                let parquet_options = parquet_options_from_with_options(&with_options)?;
+                let retention = retention_from_with_options(&with_options)?;
+                let value_format = string_prop(&with_options, "value_format")
+                    .map(|f| f.parse::<StreamValueFormat>())
+                    .transpose()?;
 
                 let res = self
                     .table_creator
@@ -831,6 +1162,9 @@ impl SqlService for SqlServiceImpl {
                         unique_key,
                         aggregates,
                         partitioned_index,
+                        parquet_options,
+                        retention,
+                        value_format,
                         &context.trace_obj,
                     )
                     .await?;
@@ -877,6 +1211,57 @@ impl SqlService for SqlServiceImpl {
                     .await?;
                 Ok(Arc::new(DataFrame::from(vec![res])))
             }
+            CubeStoreStatement::AlterTable {
+                table_name,
+                operation,
+            } => {
+                app_metrics::DATA_QUERIES.add_with_tags(
+                    1,
+                    Some(&vec![metrics::format_tag("command", "alter_table")]),
+                );
+
+                if table_name.0.len() != 2 {
+                    return Err(CubeError::user(format!(
+                        "Schema's name should be present in table name but found: {}",
+                        table_name
+                    )));
+                }
+                let schema_name = table_name.0[0].value.clone();
+                let alteration = match operation {
+                    AlterTableOperation::AddColumn { column_def } => {
+                        let column = convert_columns_type(&vec![column_def])?.remove(0);
+                        TableAlteration::AddColumn {
+                            name: column.get_name().clone(),
+                            column_type: column.get_column_type().clone(),
+                        }
+                    }
+                    AlterTableOperation::DropColumn { name } => {
+                        TableAlteration::DropColumn { name: name.value }
+                    }
+                    AlterTableOperation::RenameTable {
+                        table_name: new_name,
+                    } => {
+                        let new_name = match new_name.0.as_slice() {
+                            [name] => name.value.clone(),
+                            [schema, name] if schema.value == schema_name => name.value.clone(),
+                            _ => {
+                                return Err(CubeError::user(format!(
+                                    "Table {} can't be moved to another schema: {}",
+                                    table_name, new_name
+                                )))
+                            }
+                        };
+                        TableAlteration::Rename {
+                            table_name: new_name,
+                        }
+                    }
+                };
+                let res = self
+                    .db
+                    .alter_table(schema_name, table_name.0[1].value.clone(), alteration)
+                    .await?;
+                Ok(Arc::new(DataFrame::from(vec![res])))
+            }
             CubeStoreStatement::CreateSource {
                 name,
                 source_type,
@@ -907,6 +1292,20 @@ impl SqlService for SqlServiceImpl {
                             let password = string_prop(&credentials, "password");
                             let host = string_prop(&credentials, "host");
                             let use_ssl = boolean_prop(&credentials, "use_ssl");
+                            let value_format = string_prop(&credentials, "value_format")
+                                .map(|f| f.parse::<StreamValueFormat>())
+                                .transpose()?;
+                            let schema_registry_url =
+                                string_prop(&credentials, "schema_registry_url");
+                            let security = kafka_security_from_credentials(&credentials)?;
+                            if value_format.map_or(false, |f| f != StreamValueFormat::Json)
+                                && schema_registry_url.is_none()
+                            {
+                                return Err(CubeError::user(
+                                    "schema_registry_url is required for avro and protobuf value formats"
+                                        .to_string(),
+                                ));
+                            }
                             Ok(SourceCredentials::Kafka {
                                 user,
                                 password,
@@ -914,6 +1313,9 @@ impl SqlService for SqlServiceImpl {
                                     "host is required as credential for kafka source".to_string(),
                                 ))?,
                                 use_ssl: use_ssl.unwrap_or(false),
+                                value_format,
+                                schema_registry_url,
+                                security,
                             })
                         }
                         x => Err(CubeError::user(format!("Not supported stream type: {}", x))),
@@ -1019,6 +1421,26 @@ impl SqlService for SqlServiceImpl {
                     .await?;
                 Ok(Arc::new(DataFrame::new(vec![], vec![])))
             }
+            CubeStoreStatement::Statement(
+                statement @ (Statement::Delete { .. } | Statement::Update { .. }),
+            ) => {
+                let (table_name, mutation) = RowsMutation::from_statement(statement)?;
+                app_metrics::DATA_QUERIES.add_with_tags(
+                    1,
+                    Some(&vec![metrics::format_tag("command", mutation.name())]),
+                );
+
+                let nv = &table_name.0;
+                if nv.len() != 2 {
+                    return Err(CubeError::user(format!("Schema's name should be present in query (boo.table1). Your query was '{}'", query)));
+                }
+                let schema_name = &nv[0].value;
+                let table_name = &nv[1].value;
+
+                self.mutate_rows(schema_name.clone(), table_name.clone(), mutation)
+                    .await?;
+                Ok(Arc::new(DataFrame::new(vec![], vec![])))
+            }
             CubeStoreStatement::Queue(command) => {
                 self.cachestore
                     .exec_queue_command_with_context(context, command)
@@ -1854,6 +2276,9 @@ mod tests {
                 TableValue::String("NULL".to_string()),
                 TableValue::String("NULL".to_string()),
                 TableValue::String("NULL".to_string()),
+                TableValue::String("NULL".to_string()),
+                TableValue::String("NULL".to_string()),
+                TableValue::String("NULL".to_string()),
             ]));
         }
 
@@ -1960,6 +2385,9 @@ mod tests {
                 TableValue::String("NULL".to_string()),
                 TableValue::String("NULL".to_string()),
                 TableValue::String("NULL".to_string()),
+                TableValue::String("NULL".to_string()),
+                TableValue::String("NULL".to_string()),
+                TableValue::String("NULL".to_string()),
             ]));
         }
 
@@ -3452,6 +3880,194 @@ mod tests {
         }).await;
     }
 
+    #[tokio::test]
+    async fn dynamic_worker_in_memory_chunks() {
+        let test_name = "dynamic_worker_in_memory_chunks";
+        let port_base = 24506;
+        let static_worker = format!("127.0.0.1:{}", port_base + 1);
+        let dynamic_worker = format!("127.0.0.1:{}", port_base + 2);
+        let remote_dir = env::current_dir()
+            .unwrap()
+            .join(format!("{}-upstream", test_name));
+        let static_worker_to_move = static_worker.clone();
+        let remote_dir_to_move = remote_dir.clone();
+        Config::test(test_name).update_config(|mut config| {
+            config.select_workers = vec![static_worker.clone()];
+            config.metastore_bind_address = Some(format!("127.0.0.1:{}", port_base));
+            config.dynamic_workers = true;
+            config
+        }).start_test(async move |services| {
+            let service = services.sql_service;
+            let meta_store = services.meta_store;
+            let cluster = services.cluster;
+            let static_worker = static_worker_to_move;
+            let remote_dir = remote_dir_to_move;
+
+            Config::test(&format!("{}_worker_1", test_name)).update_config(|mut config| {
+                config.worker_bind_address = Some(static_worker.clone());
+                config.server_name = static_worker.clone();
+                config.select_workers = vec![static_worker.clone()];
+                config.metastore_remote_address = Some(format!("127.0.0.1:{}", port_base));
+                config.store_provider = FileStoreProvider::Filesystem {
+                    remote_dir: Some(remote_dir.clone()),
+                };
+                config.dynamic_workers = true;
+                config
+            }).start_test_worker(async move |_| {
+                service.exec_query("CREATE SCHEMA foo").await.unwrap();
+                // Rows of streaming tables are kept in in-memory chunks of the partition owner.
+                for t in 0..5 {
+                    service
+                        .exec_query(&format!("CREATE TABLE foo.t{} (id int, num int) UNIQUE KEY (id)", t))
+                        .await
+                        .unwrap();
+                    service
+                        .exec_query(&format!("INSERT INTO foo.t{} (id, num, __seq) VALUES (1, 1, 1), (2, 2, 2), (3, 3, 3)", t))
+                        .await
+                        .unwrap();
+                }
+
+                Config::test(&format!("{}_worker_2", test_name)).update_config(|mut config| {
+                    config.worker_bind_address = Some(dynamic_worker.clone());
+                    config.server_name = dynamic_worker.clone();
+                    config.select_workers = vec![static_worker.clone()];
+                    config.metastore_remote_address = Some(format!("127.0.0.1:{}", port_base));
+                    config.store_provider = FileStoreProvider::Filesystem {
+                        remote_dir: Some(remote_dir.clone()),
+                    };
+                    config.dynamic_workers = true;
+                    config
+                }).start_test_worker(async move |_| {
+                    let mut active = false;
+                    for _ in 0..60 {
+                        let result = service
+                            .exec_query(&format!("SELECT status FROM system.workers WHERE name = '{}'", dynamic_worker))
+                            .await
+                            .unwrap();
+                        if result.get_rows() == &vec![Row::new(vec![TableValue::String("active".to_string())])] {
+                            active = true;
+                            break;
+                        }
+                        Delay::new(Duration::from_millis(500)).await;
+                    }
+                    assert!(active, "Worker {} hasn't become active", dynamic_worker);
+
+                    // Partitions that moved to the new worker don't have in-memory chunks left.
+                    for (p, chunks) in meta_store.get_warmup_partitions().await.unwrap() {
+                        if cluster.node_name_by_partition(&p) == dynamic_worker {
+                            assert!(chunks.iter().all(|c| !c.get_row().in_memory()), "{:?}", chunks);
+                        }
+                    }
+
+                    for t in 0..5 {
+                        let result = service
+                            .exec_query(&format!("SELECT count(*), sum(num) FROM foo.t{}", t))
+                            .await
+                            .unwrap();
+                        assert_eq!(
+                            result.get_rows(),
+                            &vec![Row::new(vec![TableValue::Int(3), TableValue::Int(6)])]
+                        );
+                    }
+                }).await;
+            }).await;
+        }).await;
+    }
+
+    #[tokio::test]
+    async fn dynamic_worker_ingestion_during_warmup() {
+        let test_name = "dynamic_worker_ingestion_during_warmup";
+        let port_base = 24516;
+        let static_worker = format!("127.0.0.1:{}", port_base + 1);
+        let dynamic_worker = format!("127.0.0.1:{}", port_base + 2);
+        let remote_dir = env::current_dir()
+            .unwrap()
+            .join(format!("{}-upstream", test_name));
+        let static_worker_to_move = static_worker.clone();
+        let remote_dir_to_move = remote_dir.clone();
+        Config::test(test_name).update_config(|mut config| {
+            config.select_workers = vec![static_worker.clone()];
+            config.metastore_bind_address = Some(format!("127.0.0.1:{}", port_base));
+            config.dynamic_workers = true;
+            config
+        }).start_test(async move |services| {
+            let service = services.sql_service;
+            let static_worker = static_worker_to_move;
+            let remote_dir = remote_dir_to_move;
+
+            Config::test(&format!("{}_worker_1", test_name)).update_config(|mut config| {
+                config.worker_bind_address = Some(static_worker.clone());
+                config.server_name = static_worker.clone();
+                config.select_workers = vec![static_worker.clone()];
+                config.metastore_remote_address = Some(format!("127.0.0.1:{}", port_base));
+                config.store_provider = FileStoreProvider::Filesystem {
+                    remote_dir: Some(remote_dir.clone()),
+                };
+                config.dynamic_workers = true;
+                config
+            }).start_test_worker(async move |_| {
+                service.exec_query("CREATE SCHEMA foo").await.unwrap();
+                for t in 0..5 {
+                    service
+                        .exec_query(&format!("CREATE TABLE foo.t{} (id int, num int) UNIQUE KEY (id)", t))
+                        .await
+                        .unwrap();
+                }
+
+                Config::test(&format!("{}_worker_2", test_name)).update_config(|mut config| {
+                    config.worker_bind_address = Some(dynamic_worker.clone());
+                    config.server_name = dynamic_worker.clone();
+                    config.select_workers = vec![static_worker.clone()];
+                    config.metastore_remote_address = Some(format!("127.0.0.1:{}", port_base));
+                    config.store_provider = FileStoreProvider::Filesystem {
+                        remote_dir: Some(remote_dir.clone()),
+                    };
+                    config.dynamic_workers = true;
+                    config
+                }).start_test_worker(async move |_| {
+                    // Rows are ingested into in-memory chunks while the worker warms up and
+                    // takes over its partitions.
+                    let mut inserted = 0;
+                    let mut handing_over = false;
+                    let mut active = false;
+                    for _ in 0..200 {
+                        let result = service
+                            .exec_query(&format!("SELECT status FROM system.workers WHERE name = '{}'", dynamic_worker))
+                            .await
+                            .unwrap();
+                        let status = |s: &str| vec![Row::new(vec![TableValue::String(s.to_string())])];
+                        handing_over |= result.get_rows() == &status("handing_over");
+                        if result.get_rows() == &status("active") {
+                            active = true;
+                            break;
+                        }
+                        inserted += 1;
+                        for t in 0..5 {
+                            service
+                                .exec_query(&format!("INSERT INTO foo.t{} (id, num, __seq) VALUES ({}, 1, {})", t, inserted, inserted))
+                                .await
+                                .unwrap();
+                        }
+                        Delay::new(Duration::from_millis(100)).await;
+                    }
+                    assert!(active, "Worker {} hasn't become active", dynamic_worker);
+                    assert!(handing_over, "Worker {} hasn't handed over partitions", dynamic_worker);
+
+                    for t in 0..5 {
+                        let result = service
+                            .exec_query(&format!("SELECT count(*), sum(num) FROM foo.t{}", t))
+                            .await
+                            .unwrap();
+                        assert_eq!(
+                            result.get_rows(),
+                            &vec![Row::new(vec![TableValue::Int(inserted), TableValue::Int(inserted)])]
+                        );
+                    }
+                }).await;
+            }).await;
+        }).await;
+    }
+
     #[tokio::test]
     async fn create_table_with_location_cluster() {
         if env::var("CUBESTORE_AWS_ACCESS_KEY_ID").is_err() {
@@ -3754,6 +4370,57 @@ mod tests {
         }).await;
     }
 
+    #[tokio::test]
+    async fn mutation_is_atomic() {
+        Config::test("mutation_is_atomic").update_config(|mut config| {
+            config.partition_split_threshold = 5;
+            config.compaction_chunks_count_threshold = 0;
+            config.select_worker_pool_size = 1;
+            config
+        }).start_test(async move |services| {
+            let service = services.sql_service;
+
+            service.exec_query("CREATE SCHEMA foo").await.unwrap();
+
+            service.exec_query("CREATE TABLE foo.table (t int)").await.unwrap();
+
+            let listener = services.cluster.job_result_listener();
+
+            service.exec_query(
+                "INSERT INTO foo.table (t) VALUES (NULL), (1), (3), (5), (10), (20), (25), (25), (25), (25), (25), (NULL), (NULL), (NULL), (2), (4), (5), (27), (28), (29)"
+            ).await.unwrap();
+
+            let wait = listener.wait_for_job_results(vec![
+                (RowKey::Table(TableId::Partitions, 1), JobType::PartitionCompaction),
+            ]);
+            timeout(Duration::from_secs(10), wait).await.unwrap().unwrap();
+
+            let partitions = services.meta_store.get_active_partitions_by_index_id(1).await.unwrap();
+            let before = partitions.iter().map(|p| p.get_id()).sorted().collect_vec();
+            assert_eq!(before.len(), 4);
+
+            // 20 stays within the [10, 27) partition while 28 leaves the [27, +inf) one.
+            let result = service.exec_query("UPDATE foo.table SET t = 25 WHERE t = 20 OR t = 28").await;
+            assert!(result.is_err(), "rows can't be updated out of the partition range");
+
+            let partitions = services.meta_store.get_active_partitions_by_index_id(1).await.unwrap();
+            let after = partitions.iter().map(|p| p.get_id()).sorted().collect_vec();
+            assert_eq!(after, before);
+            let result = service.exec_query("SELECT count(*) from foo.table WHERE t = 25").await.unwrap();
+            assert_eq!(result.get_rows()[0], Row::new(vec![TableValue::Int(5)]));
+            let result = service.exec_query("SELECT count(*) from foo.table WHERE t = 20 OR t = 28").await.unwrap();
+            assert_eq!(result.get_rows()[0], Row::new(vec![TableValue::Int(2)]));
+
+            service.exec_query("DELETE FROM foo.table WHERE t = 5 OR t = 28").await.unwrap();
+
+            let partitions = services.meta_store.get_active_partitions_by_index_id(1).await.unwrap();
+            let replaced = before.iter().filter(|id| partitions.iter().all(|p| p.get_id() != **id)).count();
+            assert_eq!(replaced, 2);
+            let result = service.exec_query("SELECT count(*) from foo.table").await.unwrap();
+            assert_eq!(result.get_rows()[0], Row::new(vec![TableValue::Int(17)]));
+        }).await;
+    }
+
     #[test]
     fn create_table_with_temp_file() {
         tokio::runtime::Builder::new_multi_thread()
@@ -4041,6 +4708,58 @@ mod tests {
             .await;
     }
 
+    #[tokio::test]
+    async fn create_kafka_source_with_security() {
+        Config::test("create_kafka_source_with_security").start_test(async move |services| {
+            let service = services.sql_service;
+            let meta_store = services.meta_store;
+
+            service
+                .exec_query("CREATE SOURCE OR UPDATE kafka AS 'kafka' VALUES (user = 'foo', password = 'bar', host = 'localhost:9093', \
+                    security_protocol = 'sasl_ssl', sasl_mechanism = 'SCRAM-SHA-256', ssl_ca_pem = 'ca', ssl_certificate_pem = 'cert', \
+                    ssl_key_pem = 'key', `ssl.endpoint.identification.algorithm` = 'none', `fetch.min.bytes` = 1024)")
+                .await
+                .unwrap();
+
+            let source = meta_store.get_source_by_name("kafka".to_string()).await.unwrap();
+            match source.get_row().source_type() {
+                SourceCredentials::Kafka { user, security, .. } => {
+                    assert_eq!(user, &Some("foo".to_string()));
+                    assert_eq!(
+                        security,
+                        &KafkaSecurity {
+                            security_protocol: Some(KafkaSecurityProtocol::SaslSsl),
+                            sasl_mechanism: Some(KafkaSaslMechanism::ScramSha256),
+                            ssl_ca_pem: Some("ca".to_string()),
+                            ssl_certificate_pem: Some("cert".to_string()),
+                            ssl_key_pem: Some("key".to_string()),
+                            ssl_key_password: None,
+                            properties: vec![
+                                ("fetch.min.bytes".to_string(), "1024".to_string()),
+                                ("ssl.endpoint.identification.algorithm".to_string(), "none".to_string()),
+                            ].into_iter().collect(),
+                        }
+                    );
+                }
+                x => panic!("Unexpected source: {:?}", x),
+            }
+
+            for credentials in [
+                "host = 'localhost:9093', security_protocol = 'tls'",
+                "host = 'localhost:9093', sasl_mechanism = 'SCRAM-SHA-1', user = 'foo', password = 'bar'",
+                "host = 'localhost:9093', sasl_mechanism = 'SCRAM-SHA-512'",
+                "host = 'localhost:9093', ssl_certificate_pem = 'cert'",
+                "host = 'localhost:9093', `group.id` = 'foo'",
+            ] {
+                service
+                    .exec_query(&format!("CREATE SOURCE OR UPDATE kafka AS 'kafka' VALUES ({})", credentials))
+                    .await
+                    .expect_err(credentials);
+            }
+        })
+            .await;
+    }
+
     #[tokio::test]
     async fn validate_ksql_location() {
         Config::test("validate_ksql_location").update_config(|mut c| {
@@ -1,4 +1,5 @@
-use crate::cachestore::{QueueItemStatus, QueueKey};
+use crate::cachestore::{CacheListSide, QueueItemStatus, QueueKey};
+use chrono::{DateTime, TimeZone, Utc};
 use sqlparser::ast::{
     ColumnDef, HiveDistributionStyle, Ident, ObjectName, Query, SqlOption,
     Statement as SQLStatement, Value,
@@ -56,12 +57,23 @@ pub enum Statement {
         credentials: Vec<SqlOption>,
         or_update: bool,
     },
+    AlterTable {
+        table_name: ObjectName,
+        operation: AlterTableOperation,
+    },
     Cache(CacheCommand),
     Queue(QueueCommand),
     System(SystemCommand),
     Dump(Box<Query>),
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlterTableOperation {
+    AddColumn { column_def: ColumnDef },
+    DropColumn { name: Ident },
+    RenameTable { table_name: ObjectName },
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum RocksStoreName {
     Meta,
@@ -89,6 +101,40 @@ pub enum CacheCommand {
     Incr {
         path: Ident,
     },
+    CompareAndSet {
+        key: Ident,
+        expected: Option<String>,
+        value: String,
+        ttl: Option<u32>,
+    },
+    HSet {
+        key: Ident,
+        fields: Vec<(String, String)>,
+    },
+    HGet {
+        key: Ident,
+        field: String,
+    },
+    HDel {
+        key: Ident,
+        fields: Vec<String>,
+    },
+    HGetAll {
+        key: Ident,
+    },
+    ListPush {
+        key: Ident,
+        values: Vec<String>,
+        side: CacheListSide,
+    },
+    ListPop {
+        key: Ident,
+        side: CacheListSide,
+    },
+    // Available only over the WebSocket protocol
+    Subscribe {
+        prefix: Ident,
+    },
 }
 
 impl CacheCommand {
@@ -100,15 +146,45 @@ impl CacheCommand {
             CacheCommand::Remove { .. } => "remove",
             CacheCommand::Truncate { .. } => "truncate",
             CacheCommand::Incr { .. } => "incr",
+            CacheCommand::CompareAndSet { .. } => "cas",
+            CacheCommand::HSet { .. } => "hset",
+            CacheCommand::HGet { .. } => "hget",
+            CacheCommand::HDel { .. } => "hdel",
+            CacheCommand::HGetAll { .. } => "hgetall",
+            CacheCommand::ListPush {
+                side: CacheListSide::Left,
+                ..
+            } => "lpush",
+            CacheCommand::ListPush {
+                side: CacheListSide::Right,
+                ..
+            } => "rpush",
+            CacheCommand::ListPop {
+                side: CacheListSide::Left,
+                ..
+            } => "lpop",
+            CacheCommand::ListPop {
+                side: CacheListSide::Right,
+                ..
+            } => "rpop",
+            CacheCommand::Subscribe { .. } => "subscribe",
         }
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueueAddSchedule {
+    // Seconds from now
+    Delay(u32),
+    At(DateTime<Utc>),
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum QueueCommand {
     Add {
         priority: i64,
         orphaned: Option<u32>,
+        schedule: Option<QueueAddSchedule>,
         key: Ident,
         value: String,
     },
@@ -153,6 +229,12 @@ pub enum QueueCommand {
         timeout: u64,
     },
     Truncate {},
+    SetPrefix {
+        prefix: Ident,
+        concurrency: Option<u32>,
+        max_attempts: Option<u32>,
+        heartbeat_timeout: Option<u32>,
+    },
 }
 
 impl QueueCommand {
@@ -164,6 +246,7 @@ impl QueueCommand {
             QueueCommand::List { status_filter, .. } => match status_filter {
                 Some(QueueItemStatus::Active) => "active",
                 Some(QueueItemStatus::Pending) => "pending",
+                Some(QueueItemStatus::DeadLetter) => "dead_letter",
                 _ => "list",
             },
             QueueCommand::Cancel { .. } => "cancel",
@@ -174,6 +257,7 @@ impl QueueCommand {
             QueueCommand::Result { .. } => "result",
             QueueCommand::ResultBlocking { .. } => "result_blocking",
             QueueCommand::Truncate { .. } => "truncate",
+            QueueCommand::SetPrefix { .. } => "set_prefix",
         }
     }
 }
@@ -197,6 +281,7 @@ pub enum DropCommand {
 #[derive(Debug, Clone, PartialEq)]
 pub enum MetaStoreCommand {
     SetCurrent { id: u128 },
+    Backup { location: String },
     Compaction,
     Healthcheck,
 }
@@ -243,6 +328,10 @@ impl<'a> CubeStoreParser<'a> {
                     self.parser.next_token();
                     self.parse_create()
                 }
+                Keyword::ALTER => {
+                    self.parser.next_token();
+                    self.parse_alter()
+                }
                 _ if w.value.eq_ignore_ascii_case("dump") => {
                     self.parser.next_token();
                     let s = self.parser.parse_statement()?;
@@ -292,6 +381,43 @@ impl<'a> CubeStoreParser<'a> {
         }
     }
 
+    pub fn parse_alter(&mut self) -> Result<Statement, ParserError> {
+        self.parser.expect_keyword(Keyword::TABLE)?;
+        let table_name = self.parser.parse_object_name()?;
+        let operation = if self.parser.parse_keyword(Keyword::ADD) {
+            self.parser.parse_keyword(Keyword::COLUMN);
+            let name = self.parser.parse_identifier()?;
+            let data_type = self.parser.parse_data_type()?;
+            AlterTableOperation::AddColumn {
+                column_def: ColumnDef {
+                    name,
+                    data_type,
+                    collation: None,
+                    options: vec![],
+                },
+            }
+        } else if self.parser.parse_keyword(Keyword::DROP) {
+            self.parser.parse_keyword(Keyword::COLUMN);
+            AlterTableOperation::DropColumn {
+                name: self.parser.parse_identifier()?,
+            }
+        } else if self.parse_custom_token("rename") {
+            self.parser.expect_keyword(Keyword::TO)?;
+            AlterTableOperation::RenameTable {
+                table_name: self.parser.parse_object_name()?,
+            }
+        } else {
+            return Err(ParserError::ParserError(format!(
+                "Expected ADD, DROP or RENAME TO, found: {}",
+                self.parser.peek_token()
+            )));
+        };
+        Ok(Statement::AlterTable {
+            table_name,
+            operation,
+        })
+    }
+
     pub fn parse_streaming_source_table(&mut self) -> Result<Vec<ColumnDef>, ParserError> {
         if self.parser.parse_keyword(Keyword::CREATE) && self.parser.parse_keyword(Keyword::TABLE) {
             let statement = self.parser.parse_create_table_ext(false, false, false)?;
@@ -349,9 +475,67 @@ impl<'a> CubeStoreParser<'a> {
                 key: self.parser.parse_identifier()?,
             },
             "truncate" => CacheCommand::Truncate {},
+            "cas" => {
+                let ttl = if self.parse_custom_token(&"ttl") {
+                    Some(self.parse_integer("ttl", false)?)
+                } else {
+                    None
+                };
+                let key = self.parser.parse_identifier()?;
+                let expected = if self.parser.parse_keyword(Keyword::NULL) {
+                    None
+                } else {
+                    Some(self.parser.parse_literal_string()?)
+                };
+
+                CacheCommand::CompareAndSet {
+                    key,
+                    expected,
+                    value: self.parser.parse_literal_string()?,
+                    ttl,
+                }
+            }
+            "hset" => {
+                let key = self.parser.parse_identifier()?;
+                let mut fields = vec![(
+                    self.parser.parse_literal_string()?,
+                    self.parser.parse_literal_string()?,
+                )];
+                while let Token::SingleQuotedString(_) = self.parser.peek_token() {
+                    fields.push((
+                        self.parser.parse_literal_string()?,
+                        self.parser.parse_literal_string()?,
+                    ));
+                }
+
+                CacheCommand::HSet { key, fields }
+            }
+            "hget" => CacheCommand::HGet {
+                key: self.parser.parse_identifier()?,
+                field: self.parser.parse_literal_string()?,
+            },
+            "hdel" => CacheCommand::HDel {
+                key: self.parser.parse_identifier()?,
+                fields: self.parse_literal_strings()?,
+            },
+            "hgetall" => CacheCommand::HGetAll {
+                key: self.parser.parse_identifier()?,
+            },
+            "lpush" | "rpush" => CacheCommand::ListPush {
+                key: self.parser.parse_identifier()?,
+                values: self.parse_literal_strings()?,
+                side: Self::parse_list_side(&method),
+            },
+            "lpop" | "rpop" => CacheCommand::ListPop {
+                key: self.parser.parse_identifier()?,
+                side: Self::parse_list_side(&method),
+            },
+            "subscribe" => CacheCommand::Subscribe {
+                prefix: self.parser.parse_identifier()?,
+            },
             other => {
                 return Err(ParserError::ParserError(format!(
-                    "Unknown cache command: {}, available: SET|GET|KEYS|INC|REMOVE|TRUNCATE",
+                    "Unknown cache command: {}, available: SET|GET|KEYS|INC|REMOVE|TRUNCATE|CAS|HSET|HGET|HDEL|HGETALL|LPUSH|RPUSH|LPOP|RPOP|SUBSCRIBE",
                     other
                 )))
             }
@@ -360,6 +544,52 @@ impl<'a> CubeStoreParser<'a> {
         Ok(Statement::Cache(command))
     }
 
+    fn parse_list_side(method: &str) -> CacheListSide {
+        if method.starts_with('l') {
+            CacheListSide::Left
+        } else {
+            CacheListSide::Right
+        }
+    }
+
+    /// One or more string literals
+    fn parse_literal_strings(&mut self) -> Result<Vec<String>, ParserError> {
+        let mut values = vec![self.parser.parse_literal_string()?];
+        while let Token::SingleQuotedString(_) = self.parser.peek_token() {
+            values.push(self.parser.parse_literal_string()?);
+        }
+
+        Ok(values)
+    }
+
+    /// Unix timestamp in seconds or RFC 3339 string
+    fn parse_timestamp(&mut self, var_name: &str) -> Result<DateTime<Utc>, ParserError> {
+        match self.parser.peek_token() {
+            Token::SingleQuotedString(v) => {
+                self.parser.next_token();
+
+                DateTime::parse_from_rfc3339(&v)
+                    .map(|t| t.with_timezone(&Utc))
+                    .map_err(|e| {
+                        ParserError::ParserError(format!(
+                            "{} must be a RFC 3339 timestamp, actual: {}, error: {}",
+                            var_name, v, e
+                        ))
+                    })
+            }
+            _ => {
+                let seconds: i64 = self.parse_integer(var_name, false)?;
+
+                Utc.timestamp_opt(seconds, 0).single().ok_or_else(|| {
+                    ParserError::ParserError(format!(
+                        "{} must be a valid unix timestamp, actual: {}",
+                        var_name, seconds
+                    ))
+                })
+            }
+        }
+    }
+
     fn parse_integer<R: num::Integer + std::str::FromStr>(
         &mut self,
         var_name: &str,
@@ -446,6 +676,11 @@ impl<'a> CubeStoreParser<'a> {
             MetaStoreCommand::SetCurrent {
                 id: self.parse_integer("metastore snapshot id", false)?,
             }
+        } else if self.parse_custom_token("backup") {
+            self.parser.expect_keyword(Keyword::TO)?;
+            MetaStoreCommand::Backup {
+                location: self.parser.parse_literal_string()?,
+            }
         } else if self.parse_custom_token("compaction") {
             MetaStoreCommand::Compaction
         } else if self.parse_custom_token("healthcheck") {
@@ -484,9 +719,18 @@ impl<'a> CubeStoreParser<'a> {
                     None
                 };
 
+                let schedule = if self.parse_custom_token(&"delay") {
+                    Some(QueueAddSchedule::Delay(self.parse_integer("delay", false)?))
+                } else if self.parse_custom_token(&"at") {
+                    Some(QueueAddSchedule::At(self.parse_timestamp("at")?))
+                } else {
+                    None
+                };
+
                 QueueCommand::Add {
                     priority,
                     orphaned,
+                    schedule,
                     key: self.parser.parse_identifier()?,
                     value: self.parser.parse_literal_string()?,
                 }
@@ -562,6 +806,16 @@ impl<'a> CubeStoreParser<'a> {
                     sort_by_priority: false,
                 }
             }
+            "dead_letter" => {
+                let with_payload = self.parse_custom_token(&"with_payload");
+
+                QueueCommand::List {
+                    prefix: self.parser.parse_identifier()?,
+                    with_payload,
+                    status_filter: Some(QueueItemStatus::DeadLetter),
+                    sort_by_priority: false,
+                }
+            }
             "list" => {
                 let with_payload = self.parse_custom_token(&"with_payload");
 
@@ -599,6 +853,32 @@ impl<'a> CubeStoreParser<'a> {
                 }
             }
             "truncate" => QueueCommand::Truncate {},
+            "set_prefix" => {
+                let concurrency = if self.parse_custom_token(&"concurrency") {
+                    Some(self.parse_integer("concurrency", false)?)
+                } else {
+                    None
+                };
+
+                let max_attempts = if self.parse_custom_token(&"max_attempts") {
+                    Some(self.parse_integer("max_attempts", false)?)
+                } else {
+                    None
+                };
+
+                let heartbeat_timeout = if self.parse_custom_token(&"heartbeat_timeout") {
+                    Some(self.parse_integer("heartbeat_timeout", false)?)
+                } else {
+                    None
+                };
+
+                QueueCommand::SetPrefix {
+                    prefix: self.parser.parse_identifier()?,
+                    concurrency,
+                    max_attempts,
+                    heartbeat_timeout,
+                }
+            }
             other => {
                 return Err(ParserError::ParserError(format!(
                     "Unknown queue command: {}",
@@ -813,7 +1093,7 @@ impl<'a> CubeStoreParser<'a> {
 mod tests {
 
     use super::*;
-    use sqlparser::ast::Statement as SQLStatement;
+    use sqlparser::ast::{DataType, Statement as SQLStatement};
 
     #[test]
     fn parse_aggregate_index() {
@@ -889,4 +1169,226 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn parse_metastore_backup() {
+        let query = "SYS METASTORE BACKUP TO 'backups/2023-01-01'";
+        let mut parser = CubeStoreParser::new(&query).unwrap();
+        let res = parser.parse_statement().unwrap();
+        match res {
+            Statement::System(SystemCommand::MetaStore(MetaStoreCommand::Backup { location })) => {
+                assert_eq!(location, "backups/2023-01-01");
+            }
+            _ => {
+                assert!(false)
+            }
+        }
+
+        let query = "SYS METASTORE BACKUP 'backups'";
+        let mut parser = CubeStoreParser::new(&query).unwrap();
+        assert!(parser.parse_statement().is_err());
+    }
+
+    #[test]
+    fn parse_alter_table() {
+        let parse = |query: &str| -> (ObjectName, AlterTableOperation) {
+            let mut parser = CubeStoreParser::new(query).unwrap();
+            match parser.parse_statement().unwrap() {
+                Statement::AlterTable {
+                    table_name,
+                    operation,
+                } => (table_name, operation),
+                other => panic!("Unexpected statement: {:?}", other),
+            }
+        };
+
+        match parse("ALTER TABLE foo.orders ADD COLUMN amount decimal(10, 2)") {
+            (table_name, AlterTableOperation::AddColumn { column_def }) => {
+                assert_eq!(table_name.to_string(), "foo.orders");
+                assert_eq!(column_def.name.value, "amount");
+                assert_eq!(column_def.data_type, DataType::Decimal(Some(10), Some(2)));
+            }
+            other => panic!("Unexpected operation: {:?}", other),
+        }
+        match parse("ALTER TABLE foo.orders ADD status text") {
+            (_, AlterTableOperation::AddColumn { column_def }) => {
+                assert_eq!(column_def.name.value, "status");
+                assert_eq!(column_def.data_type, DataType::Text);
+            }
+            other => panic!("Unexpected operation: {:?}", other),
+        }
+        match parse("alter table foo.orders drop column status") {
+            (_, AlterTableOperation::DropColumn { name }) => assert_eq!(name.value, "status"),
+            other => panic!("Unexpected operation: {:?}", other),
+        }
+        match parse("ALTER TABLE foo.orders DROP status") {
+            (_, AlterTableOperation::DropColumn { name }) => assert_eq!(name.value, "status"),
+            other => panic!("Unexpected operation: {:?}", other),
+        }
+        match parse("ALTER TABLE foo.orders RENAME TO foo.orders_v2") {
+            (_, AlterTableOperation::RenameTable { table_name }) => {
+                assert_eq!(table_name.to_string(), "foo.orders_v2")
+            }
+            other => panic!("Unexpected operation: {:?}", other),
+        }
+
+        let mut parser = CubeStoreParser::new("ALTER TABLE foo.orders MODIFY status").unwrap();
+        assert!(parser.parse_statement().is_err());
+    }
+
+    #[test]
+    fn parse_queue_add_schedule() {
+        let parse = |query: &str| -> QueueCommand {
+            let mut parser = CubeStoreParser::new(query).unwrap();
+            match parser.parse_statement().unwrap() {
+                Statement::Queue(command) => command,
+                other => panic!("Unexpected statement: {:?}", other),
+            }
+        };
+
+        match parse("QUEUE ADD PRIORITY 1 ORPHANED 60 DELAY 30 'prefix:1' 'payload'") {
+            QueueCommand::Add {
+                priority,
+                orphaned,
+                schedule,
+                key,
+                value,
+            } => {
+                assert_eq!(priority, 1);
+                assert_eq!(orphaned, Some(60));
+                assert_eq!(schedule, Some(QueueAddSchedule::Delay(30)));
+                assert_eq!(key.value, "prefix:1".to_string());
+                assert_eq!(value, "payload".to_string());
+            }
+            other => panic!("Unexpected command: {:?}", other),
+        }
+
+        let expected = Some(QueueAddSchedule::At(
+            Utc.timestamp_opt(1700000000, 0).unwrap(),
+        ));
+        match parse("QUEUE ADD AT 1700000000 'prefix:1' 'payload'") {
+            QueueCommand::Add { schedule, .. } => assert_eq!(schedule, expected),
+            other => panic!("Unexpected command: {:?}", other),
+        }
+        match parse("QUEUE ADD AT '2023-11-14T22:13:20Z' 'prefix:1' 'payload'") {
+            QueueCommand::Add { schedule, .. } => assert_eq!(schedule, expected),
+            other => panic!("Unexpected command: {:?}", other),
+        }
+        match parse("QUEUE ADD 'prefix:1' 'payload'") {
+            QueueCommand::Add { schedule, .. } => assert_eq!(schedule, None),
+            other => panic!("Unexpected command: {:?}", other),
+        }
+
+        let mut parser = CubeStoreParser::new("QUEUE ADD AT 'tomorrow' 'prefix:1' 'v'").unwrap();
+        assert!(parser.parse_statement().is_err());
+    }
+
+    #[test]
+    fn parse_queue_set_prefix() {
+        let parse = |query: &str| -> QueueCommand {
+            let mut parser = CubeStoreParser::new(query).unwrap();
+            match parser.parse_statement().unwrap() {
+                Statement::Queue(command) => command,
+                other => panic!("Unexpected statement: {:?}", other),
+            }
+        };
+
+        match parse("QUEUE SET_PREFIX CONCURRENCY 2 MAX_ATTEMPTS 3 HEARTBEAT_TIMEOUT 30000 prefix")
+        {
+            QueueCommand::SetPrefix {
+                prefix,
+                concurrency,
+                max_attempts,
+                heartbeat_timeout,
+            } => {
+                assert_eq!(prefix.value, "prefix".to_string());
+                assert_eq!(concurrency, Some(2));
+                assert_eq!(max_attempts, Some(3));
+                assert_eq!(heartbeat_timeout, Some(30000));
+            }
+            other => panic!("Unexpected command: {:?}", other),
+        }
+
+        match parse("QUEUE SET_PREFIX HEARTBEAT_TIMEOUT 1000 prefix") {
+            QueueCommand::SetPrefix {
+                concurrency,
+                max_attempts,
+                heartbeat_timeout,
+                ..
+            } => {
+                assert_eq!(concurrency, None);
+                assert_eq!(max_attempts, None);
+                assert_eq!(heartbeat_timeout, Some(1000));
+            }
+            other => panic!("Unexpected command: {:?}", other),
+        }
+
+        match parse("QUEUE DEAD_LETTER prefix") {
+            QueueCommand::List { status_filter, .. } => {
+                assert_eq!(status_filter, Some(QueueItemStatus::DeadLetter))
+            }
+            other => panic!("Unexpected command: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_cache_typed_commands() {
+        let parse = |query: &str| {
+            let mut parser = CubeStoreParser::new(query).unwrap();
+            match parser.parse_statement().unwrap() {
+                Statement::Cache(command) => command,
+                other => panic!("Unexpected statement: {:?}", other),
+            }
+        };
+
+        match parse("CACHE HSET 'hash:1' 'f1' 'v1' 'f2' 'v2'") {
+            CacheCommand::HSet { key, fields } => {
+                assert_eq!(key.value, "hash:1".to_string());
+                assert_eq!(
+                    fields,
+                    vec![
+                        ("f1".to_string(), "v1".to_string()),
+                        ("f2".to_string(), "v2".to_string())
+                    ]
+                );
+            }
+            other => panic!("Unexpected command: {:?}", other),
+        }
+
+        match parse("CACHE RPUSH 'list:1' 'a' 'b'") {
+            CacheCommand::ListPush { key, values, side } => {
+                assert_eq!(key.value, "list:1".to_string());
+                assert_eq!(values, vec!["a".to_string(), "b".to_string()]);
+                assert_eq!(side, CacheListSide::Right);
+            }
+            other => panic!("Unexpected command: {:?}", other),
+        }
+
+        assert_eq!(parse("CACHE LPOP 'list:1'").as_tag_command(), "lpop");
+
+        match parse("CACHE CAS TTL 60 'lock:1' NULL 'owner'") {
+            CacheCommand::CompareAndSet {
+                key,
+                expected,
+                value,
+                ttl,
+            } => {
+                assert_eq!(key.value, "lock:1".to_string());
+                assert_eq!(expected, None);
+                assert_eq!(value, "owner".to_string());
+                assert_eq!(ttl, Some(60));
+            }
+            other => panic!("Unexpected command: {:?}", other),
+        }
+
+        match parse("CACHE SUBSCRIBE 'invalidation:'") {
+            CacheCommand::Subscribe { prefix } => {
+                assert_eq!(prefix.value, "invalidation:".to_string());
+            }
+            other => panic!("Unexpected command: {:?}", other),
+        }
+
+        let mut parser = CubeStoreParser::new("CACHE HSET 'hash:1' 'f1'").unwrap();
+        assert!(parser.parse_statement().is_err());
+    }
 }
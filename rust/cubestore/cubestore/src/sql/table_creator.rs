@@ -1,11 +1,11 @@
 use std::sync::Arc;
 use std::time::Duration;
 
-use crate::cluster::{Cluster, JobEvent, JobResultListener};
+use crate::cluster::{worker_nodes, Cluster, JobEvent, JobResultListener};
 use crate::config::ConfigObj;
 use crate::import::ImportService;
 use crate::metastore::job::JobType;
-use crate::metastore::table::StreamOffset;
+use crate::metastore::table::{ParquetOptions, StreamOffset, StreamValueFormat};
 use crate::metastore::{
     table::Table, HllFlavour, IdRow, ImportFormat, IndexDef, IndexType, RowKey, TableId,
 };
@@ -68,6 +68,9 @@ impl TableCreator {
         unique_key: Option<Vec<Ident>>,
         aggregates: Option<Vec<(Ident, Ident)>>,
         partitioned_index: Option<PartitionedIndexRef>,
+        parquet_options: Option<ParquetOptions>,
+        retention: Option<(String, u64)>,
+        value_format: Option<StreamValueFormat>,
         trace_obj: &Option<String>,
     ) -> Result<IdRow<Table>, CubeError> {
         if !if_not_exists {
@@ -89,6 +92,9 @@ impl TableCreator {
                     unique_key,
                     aggregates,
                     partitioned_index,
+                    parquet_options,
+                    retention,
+                    value_format,
                     &trace_obj,
                 )
                 .await;
@@ -125,6 +131,9 @@ impl TableCreator {
                     unique_key,
                     aggregates,
                     partitioned_index,
+                    parquet_options,
+                    retention,
+                    value_format,
                     &trace_obj,
                 )
                 .await
@@ -150,6 +159,9 @@ impl TableCreator {
         unique_key: Option<Vec<Ident>>,
         aggregates: Option<Vec<(Ident, Ident)>>,
         partitioned_index: Option<PartitionedIndexRef>,
+        parquet_options: Option<ParquetOptions>,
+        retention: Option<(String, u64)>,
+        value_format: Option<StreamValueFormat>,
         trace_obj: &Option<String>,
     ) -> Result<IdRow<Table>, CubeError> {
         let mut retries = 0;
@@ -178,6 +190,9 @@ impl TableCreator {
                     unique_key.clone(),
                     aggregates.clone(),
                     partitioned_index.clone(),
+                    parquet_options.clone(),
+                    retention.clone(),
+                    value_format,
                     trace_obj,
                 )
                 .await?;
@@ -250,6 +265,9 @@ impl TableCreator {
         unique_key: Option<Vec<Ident>>,
         aggregates: Option<Vec<(Ident, Ident)>>,
         partitioned_index: Option<PartitionedIndexRef>,
+        parquet_options: Option<ParquetOptions>,
+        retention: Option<(String, u64)>,
+        value_format: Option<StreamValueFormat>,
         trace_obj: &Option<String>,
     ) -> Result<IdRow<Table>, CubeError> {
         let columns_to_set = convert_columns_type(columns)?;
@@ -367,6 +385,9 @@ impl TableCreator {
                             .collect()
                     }),
                     None,
+                    parquet_options,
+                    retention,
+                    value_format,
                     None,
                     false,
                 )
@@ -398,10 +419,7 @@ impl TableCreator {
             .into_iter()
             .sum::<u64>();
 
-            let mut sel_workers_count = self.config_obj.select_workers().len() as u64;
-            if sel_workers_count == 0 {
-                sel_workers_count = 1;
-            }
+            let sel_workers_count = worker_nodes(self.config_obj.as_ref()).len() as u64;
             let threshold = (size / sel_workers_count)
                 .min(self.config_obj.max_partition_split_threshold())
                 .max(self.config_obj.partition_split_threshold());
@@ -447,6 +465,9 @@ impl TableCreator {
                         .collect()
                 }),
                 partition_split_threshold,
+                parquet_options,
+                retention,
+                value_format,
                 trace_obj_to_save,
                 if_not_exists,
             )
@@ -1,3 +1,4 @@
+use crate::cluster::node_name_by_partition;
 use crate::config::injection::DIService;
 use crate::config::ConfigObj;
 use crate::metastore::chunks::chunk_file_name;
@@ -7,12 +8,15 @@ use crate::metastore::replay_handle::{union_seq_pointer_by_location, SeqPointerF
 use crate::metastore::table::AggregateColumn;
 use crate::metastore::{
     deactivate_table_on_corrupt_data, table::Table, Chunk, IdRow, Index, IndexType, MetaStore,
-    Partition, PartitionData,
+    Partition, PartitionData, PartitionRewrite,
 };
+use crate::queryplanner::adapt_schema::adapt_plan_to_index;
 use crate::queryplanner::trace_data_loaded::{DataLoadedSize, TraceDataLoadedExec};
 use crate::remotefs::{ensure_temp_file_is_dropped, RemoteFs};
+use crate::store::mutation::RowsMutation;
+use crate::store::retention::RetentionCutoff;
 use crate::store::{min_max_values_from_data, ChunkDataStore, ChunkStore, ROW_GROUP_SIZE};
-use crate::table::data::{cmp_min_rows, cmp_partition_key};
+use crate::table::data::{cmp_min_rows, cmp_partition_key, cmp_row_key_heap};
 use crate::table::parquet::{arrow_schema, ParquetTableStore};
 use crate::table::redistribute::redistribute;
 use crate::table::{Row, TableValue};
@@ -60,6 +64,9 @@ pub trait CompactionService: DIService + Send + Sync {
     ) -> Result<(), CubeError>;
     async fn compact_in_memory_chunks(&self, partition_id: u64) -> Result<(), CubeError>;
     async fn compact_node_in_memory_chunks(&self, node: String) -> Result<(), CubeError>;
+    /// Writes all in-memory chunks of the partition to persistent chunks ignoring compaction
+    /// thresholds. Should be called on the node that owns the partition.
+    async fn persist_in_memory_chunks(&self, partition_id: u64) -> Result<(), CubeError>;
     /// Split multi-partition that has too many rows. Figures out the keys based on stored data.
     async fn split_multi_partition(&self, multi_partition_id: u64) -> Result<(), CubeError>;
     /// Process partitions that were added concurrently with multi-split.
@@ -68,6 +75,13 @@ pub trait CompactionService: DIService + Send + Sync {
         multi_partition_id: u64,
         partition_id: u64,
     ) -> Result<(), CubeError>;
+    /// Writes rows of the partition with all of its chunks applying `DELETE` or `UPDATE` to them
+    /// into a new inactive partition. It's activated by [MetaStore::swap_rewritten_partitions].
+    async fn rewrite_partition(
+        &self,
+        partition_id: u64,
+        mutation: RowsMutation,
+    ) -> Result<PartitionRewrite, CubeError>;
 }
 
 pub struct CompactionServiceImpl {
@@ -115,12 +129,35 @@ impl CompactionServiceImpl {
                 .unwrap_or(false)
     }
 
+    async fn compact_partition_in_memory_chunks(
+        &self,
+        partition_id: u64,
+        persist_all: bool,
+    ) -> Result<(), CubeError> {
+        let (partition, index, table, _) = self
+            .meta_store
+            .get_partition_for_compaction(partition_id)
+            .await?;
+
+        let chunks = self
+            .meta_store
+            .get_chunks_by_partition(partition_id, false)
+            .await?
+            .into_iter()
+            .filter(|c| c.get_row().in_memory() && c.get_row().active())
+            .collect::<Vec<_>>();
+
+        self.compact_prepared_in_memory_chunks(partition, index, table, chunks, persist_all)
+            .await
+    }
+
     async fn compact_prepared_in_memory_chunks(
         &self,
         partition: IdRow<Partition>,
         index: IdRow<Index>,
         table: IdRow<Table>,
         chunks: Vec<IdRow<Chunk>>,
+        persist_all: bool,
     ) -> Result<(), CubeError> {
         // Test invariants
         if !partition.get_row().is_active() && partition.get_row().multi_partition_id().is_some() {
@@ -133,6 +170,11 @@ impl CompactionServiceImpl {
 
         let compaction_in_memory_chunks_size_limit =
             self.config.compaction_in_memory_chunks_size_limit();
+        // After the partition moved to another worker, the previous owner only persists
+        // in-memory chunks it has received. The rest of them are kept by the new owner.
+        let is_owner =
+            node_name_by_partition(self.config.as_ref(), &partition) == *self.config.server_name();
+        let persist_all = persist_all || !is_owner;
 
         let active_in_memory = chunks
             .into_iter()
@@ -142,11 +184,8 @@ impl CompactionServiceImpl {
             .into_iter()
             .map(|c| {
                 let chunk_store = self.chunk_store.clone();
-                let partition = partition.clone();
                 cube_ext::spawn(async move {
-                    let has_in_memory_chunk = chunk_store
-                        .has_in_memory_chunk(c.clone(), partition)
-                        .await?;
+                    let has_in_memory_chunk = chunk_store.has_in_memory_chunk(c.clone()).await?;
                     Result::<_, CubeError>::Ok((c, has_in_memory_chunk))
                 })
             })
@@ -159,10 +198,12 @@ impl CompactionServiceImpl {
             .collect::<Result<Vec<_>, _>>()?;
         let (in_memory, failed) = chunk_and_inmemory
             .into_iter()
+            .filter(|(_, has_in_memory_chunk)| *has_in_memory_chunk || is_owner)
             .partition::<Vec<_>, _>(|(_, has_in_memory_chunk)| *has_in_memory_chunk);
         let (mem_chunks, persistent_chunks) =
             in_memory.into_iter().map(|(c, _)| c).partition(|c| {
-                c.get_row().get_row_count() <= compaction_in_memory_chunks_size_limit
+                !persist_all
+                    && c.get_row().get_row_count() <= compaction_in_memory_chunks_size_limit
                     && c.get_row()
                         .oldest_insert_at()
                         .map(|m| {
@@ -181,12 +222,26 @@ impl CompactionServiceImpl {
         let in_memory_res = self
             .compact_chunks_to_memory(mem_chunks, &partition, &index, &table)
             .await;
+        // Memory of non owners isn't freed by the scheduler.
+        let handed_over = if is_owner {
+            Vec::new()
+        } else {
+            persistent_chunks
+                .iter()
+                .map(|c| chunk_file_name(c.get_id(), c.get_row().suffix()))
+                .collect()
+        };
         let persistent_res = self
             .compact_chunks_to_persistent(persistent_chunks, &partition, &index, &table)
             .await;
         deactivate_res?;
         in_memory_res?;
         persistent_res?;
+        if !handed_over.is_empty() {
+            self.chunk_store
+                .free_deleted_memory_chunks(handed_over)
+                .await?;
+        }
 
         Ok(())
     }
@@ -467,7 +522,19 @@ impl CompactionService for CompactionServiceImpl {
             .map(|c| c.clone())
             .collect::<Vec<_>>();
 
-        if chunks.is_empty() {
+        // Multi-partitions never change the main table so expired rows are left there.
+        let retention = match &multi_part {
+            None => RetentionCutoff::new(table.get_row(), index.get_row(), Utc::now()),
+            Some(_) => None,
+        };
+        let main_table_expired = retention.as_ref().map_or(false, |r| {
+            r.is_expired(partition.get_row().get_min(), partition.get_row().get_max())
+        });
+        let main_table_has_expired_rows = retention.as_ref().map_or(false, |r| {
+            r.has_expired_rows(partition.get_row().get_min(), partition.get_row().get_max())
+        });
+
+        if chunks.is_empty() && !main_table_has_expired_rows {
             return Ok(());
         }
 
@@ -506,6 +573,11 @@ impl CompactionService for CompactionServiceImpl {
         }
 
         data_loaded_size.add(chunks_total_size);
+        if data.is_empty() {
+            data.push(RecordBatch::new_empty(Arc::new(arrow_schema(
+                index.get_row(),
+            ))));
+        }
 
         let chunks = chunks_to_use;
 
@@ -574,6 +646,8 @@ impl CompactionService for CompactionServiceImpl {
         let store = ParquetTableStore::new(index.get_row().clone(), ROW_GROUP_SIZE);
         let old_partition_remote = match &new_chunk {
             Some(_) => None,
+            // Fully expired main table isn't even downloaded.
+            None if main_table_expired => None,
             None => partition.get_row().get_full_name(partition.get_id()),
         };
         let old_partition_local = if let Some(f) = old_partition_remote {
@@ -607,47 +681,23 @@ impl CompactionService for CompactionServiceImpl {
         });
 
         let key_size = index.get_row().sort_key_size() as usize;
-        let (store, new) = cube_ext::spawn_blocking(move || -> Result<_, CubeError> {
-            // Concat rows from all chunks.
-            let mut columns = Vec::with_capacity(num_columns);
-            for i in 0..num_columns {
-                let v = arrow::compute::concat(
-                    &data.iter().map(|a| a.column(i).as_ref()).collect_vec(),
-                )?;
-                columns.push(v);
-            }
-            // Sort rows from all chunks.
-            let mut sort_key = Vec::with_capacity(key_size);
-            for i in 0..key_size {
-                sort_key.push(SortColumn {
-                    values: columns[i].clone(),
-                    options: Some(SortOptions {
-                        descending: false,
-                        nulls_first: true,
-                    }),
-                });
-            }
-            let indices = lexsort_to_indices(&sort_key, None)?;
-            let mut new = Vec::with_capacity(num_columns);
-            for c in columns {
-                new.push(arrow::compute::take(c.as_ref(), &indices, None)?)
-            }
-            Ok((store, new))
-        })
-        .await??;
+        let new = concat_and_sort_chunks(data, num_columns, key_size).await?;
 
         // Merge and write rows.
         let schema = Arc::new(arrow_schema(index.get_row()));
         let main_table: Arc<dyn ExecutionPlan> = match old_partition_local {
             Some(file) => {
-                let parquet_exec = Arc::new(ParquetExec::try_from_path(
-                    file.as_str(),
-                    None,
-                    None,
-                    ROW_GROUP_SIZE,
-                    1,
-                    None,
-                )?);
+                let parquet_exec = adapt_plan_to_index(
+                    Arc::new(ParquetExec::try_from_path(
+                        file.as_str(),
+                        None,
+                        None,
+                        ROW_GROUP_SIZE,
+                        1,
+                        None,
+                    )?),
+                    index.get_row(),
+                )?;
 
                 Arc::new(TraceDataLoadedExec::new(
                     parquet_exec,
@@ -666,8 +716,16 @@ impl CompactionService for CompactionServiceImpl {
             IndexType::Regular => None,
             IndexType::Aggregate => Some(table.get_row().aggregate_columns()),
         };
-        let records =
-            merge_chunks(key_size, main_table, new, unique_key, aggregate_columns).await?;
+        let merged = merge_chunks_plan(key_size, main_table, new, unique_key, aggregate_columns)?;
+        let records = match &retention {
+            Some(r) => {
+                r.mutation()?
+                    .plan(index.get_row().columns(), key_size, merged)?
+                    .execute(0)
+                    .await?
+            }
+            None => merged.execute(0).await?,
+        };
         let count_and_min =
             write_to_files(records, total_rows as usize, store, new_local_files2).await?;
 
@@ -694,6 +752,30 @@ impl CompactionService for CompactionServiceImpl {
             return Ok(());
         }
 
+        if count_and_min.is_empty() {
+            // All rows are expired. The partition is replaced by an empty one of the same range.
+            let mut new_partitions = new_partitions.into_iter();
+            let new_partition = new_partitions.next().unwrap();
+            for p in new_partitions {
+                self.meta_store.delete_partition(p.get_id()).await?;
+            }
+            let new_remote_path =
+                partition_file_name(new_partition.get_id(), new_partition.get_row().suffix());
+            let file_size = self
+                .remote_fs
+                .upload_file(new_local_files[0].clone(), new_remote_path)
+                .await?;
+            self.meta_store
+                .swap_rewritten_partition(
+                    (partition, chunks),
+                    (new_partition, file_size),
+                    0,
+                    (None, None),
+                )
+                .await?;
+            return Ok(());
+        }
+
         let mut filtered_partitions = Vec::new();
         for (i, p) in new_partitions
             .into_iter()
@@ -803,8 +885,9 @@ impl CompactionService for CompactionServiceImpl {
 
         for (partition, index, table, chunks) in candidates.into_iter() {
             if self.is_compaction_needed(&chunks) {
-                futures
-                    .push(self.compact_prepared_in_memory_chunks(partition, index, table, chunks));
+                futures.push(
+                    self.compact_prepared_in_memory_chunks(partition, index, table, chunks, false),
+                );
             }
         }
 
@@ -816,20 +899,12 @@ impl CompactionService for CompactionServiceImpl {
     }
 
     async fn compact_in_memory_chunks(&self, partition_id: u64) -> Result<(), CubeError> {
-        let (partition, index, table, _) = self
-            .meta_store
-            .get_partition_for_compaction(partition_id)
-            .await?;
-
-        let chunks = self
-            .meta_store
-            .get_chunks_by_partition(partition_id, false)
-            .await?
-            .into_iter()
-            .filter(|c| c.get_row().in_memory() && c.get_row().active())
-            .collect::<Vec<_>>();
+        self.compact_partition_in_memory_chunks(partition_id, false)
+            .await
+    }
 
-        self.compact_prepared_in_memory_chunks(partition, index, table, chunks)
+    async fn persist_in_memory_chunks(&self, partition_id: u64) -> Result<(), CubeError> {
+        self.compact_partition_in_memory_chunks(partition_id, true)
             .await
     }
 
@@ -943,6 +1018,226 @@ impl CompactionService for CompactionServiceImpl {
         s.split_single_partition(data).await?;
         s.finish(false).await
     }
+
+    async fn rewrite_partition(
+        &self,
+        partition_id: u64,
+        mutation: RowsMutation,
+    ) -> Result<PartitionRewrite, CubeError> {
+        let (partition, index, table, multi_part) = self
+            .meta_store
+            .get_partition_for_compaction(partition_id)
+            .await?;
+        if multi_part.is_some() {
+            return Err(CubeError::internal(format!(
+                "Mutation of multi-partition {} is not supported",
+                partition_id
+            )));
+        }
+        if !partition.get_row().is_active() {
+            return Err(CubeError::internal(format!(
+                "Partition {} was deactivated before mutation",
+                partition_id
+            )));
+        }
+
+        // In-memory chunks are included as well so no rows are left behind.
+        let chunks = self
+            .meta_store
+            .get_chunks_by_partition(partition_id, false)
+            .await?;
+        let num_columns = index.get_row().columns().len();
+        let schema = Arc::new(arrow_schema(index.get_row()));
+        let mut data = Vec::new();
+        for chunk in chunks.iter() {
+            for b in self
+                .chunk_store
+                .get_chunk_columns_with_preloaded_meta(
+                    chunk.clone(),
+                    partition.clone(),
+                    index.clone(),
+                )
+                .await?
+            {
+                data.push(b);
+            }
+        }
+        if data.is_empty() {
+            data.push(RecordBatch::new_empty(schema.clone()));
+        }
+        let total_rows = chunks
+            .iter()
+            .map(|c| c.get_row().get_row_count())
+            .sum::<u64>()
+            + partition.get_row().main_table_row_count();
+
+        let new_partition = self
+            .meta_store
+            .create_partition(Partition::new_child(&partition, None))
+            .await?;
+        // The new partition is deleted if anything fails, it's not visible to queries until the
+        // swap anyway.
+        let written = async {
+            let new_remote_path =
+                partition_file_name(new_partition.get_id(), new_partition.get_row().suffix());
+            let new_local_file = scopeguard::guard(
+                self.remote_fs
+                    .temp_upload_path(new_remote_path.clone())
+                    .await?,
+                ensure_temp_file_is_dropped,
+            );
+
+            let old_partition_local = match partition.get_row().get_full_name(partition.get_id()) {
+                Some(f) => {
+                    let result = self
+                        .remote_fs
+                        .download_file(f, partition.get_row().file_size())
+                        .await;
+                    deactivate_table_on_corrupt_data(
+                        self.meta_store.clone(),
+                        &result,
+                        &partition,
+                        None,
+                    )
+                    .await;
+                    Some(result?)
+                }
+                None => None,
+            };
+            let main_table: Arc<dyn ExecutionPlan> = match old_partition_local {
+                Some(file) => adapt_plan_to_index(
+                    Arc::new(ParquetExec::try_from_path(
+                        file.as_str(),
+                        None,
+                        None,
+                        ROW_GROUP_SIZE,
+                        1,
+                        None,
+                    )?),
+                    index.get_row(),
+                )?,
+                None => Arc::new(EmptyExec::new(false, schema)),
+            };
+
+            let key_size = index.get_row().sort_key_size() as usize;
+            let new = concat_and_sort_chunks(data, num_columns, key_size).await?;
+            let merged = merge_chunks_plan(
+                key_size,
+                main_table,
+                new,
+                table.get_row().unique_key_columns(),
+                None,
+            )?;
+            let records = mutation
+                .plan(index.get_row().columns(), key_size, merged)?
+                .execute(0)
+                .await?;
+            let store = ParquetTableStore::new(index.get_row().clone(), ROW_GROUP_SIZE);
+            let count_and_min = write_to_files(
+                records,
+                total_rows as usize,
+                store,
+                vec![(*new_local_file).clone()],
+            )
+            .await?;
+
+            if mutation.updates_sort_key(index.get_row().columns(), key_size) {
+                // Updated rows are kept in the same partition so they must stay within its range.
+                let out_of_range = count_and_min.first().map_or(false, |(_, min, max)| {
+                    partition
+                        .get_row()
+                        .get_min_val()
+                        .as_ref()
+                        .map_or(false, |v| {
+                            cmp_row_key_heap(key_size, min, v.values()) == Ordering::Less
+                        })
+                        || partition
+                            .get_row()
+                            .get_max_val()
+                            .as_ref()
+                            .map_or(false, |v| {
+                                cmp_row_key_heap(key_size, max, v.values()) != Ordering::Less
+                            })
+                });
+                if out_of_range {
+                    return Err(CubeError::user(format!(
+                        "Updated rows are out of range of partition {}. Try to update them by \
+                         DELETE and INSERT instead.",
+                        partition_id
+                    )));
+                }
+            }
+
+            let file_size = self
+                .remote_fs
+                .upload_file((*new_local_file).clone(), new_remote_path)
+                .await?;
+            let (row_count, min, max) = match count_and_min.first() {
+                Some((c, min, max)) => (
+                    *c as u64,
+                    Some(Row::new(min.clone())),
+                    Some(Row::new(max.clone())),
+                ),
+                None => (0, None, None),
+            };
+
+            Ok::<_, CubeError>((file_size, row_count, min, max))
+        }
+        .await;
+        let (file_size, row_count, min, max) = match written {
+            Ok(written) => written,
+            Err(e) => {
+                self.meta_store
+                    .delete_partition(new_partition.get_id())
+                    .await?;
+                return Err(e);
+            }
+        };
+
+        Ok(PartitionRewrite {
+            partition,
+            chunks,
+            new_partition,
+            new_file_size: file_size,
+            new_row_count: row_count,
+            new_min_max: (min, max),
+        })
+    }
+}
+
+/// Concats rows from all chunks and sorts them by the first `key_size` columns.
+async fn concat_and_sort_chunks(
+    data: Vec<RecordBatch>,
+    num_columns: usize,
+    key_size: usize,
+) -> Result<Vec<ArrayRef>, CubeError> {
+    cube_ext::spawn_blocking(move || -> Result<_, CubeError> {
+        // Concat rows from all chunks.
+        let mut columns = Vec::with_capacity(num_columns);
+        for i in 0..num_columns {
+            let v =
+                arrow::compute::concat(&data.iter().map(|a| a.column(i).as_ref()).collect_vec())?;
+            columns.push(v);
+        }
+        // Sort rows from all chunks.
+        let mut sort_key = Vec::with_capacity(key_size);
+        for i in 0..key_size {
+            sort_key.push(SortColumn {
+                values: columns[i].clone(),
+                options: Some(SortOptions {
+                    descending: false,
+                    nulls_first: true,
+                }),
+            });
+        }
+        let indices = lexsort_to_indices(&sort_key, None)?;
+        let mut new = Vec::with_capacity(num_columns);
+        for c in columns {
+            new.push(arrow::compute::take(c.as_ref(), &indices, None)?)
+        }
+        Ok(new)
+    })
+    .await?
 }
 
 /// Compute keys that partitions must be split by.
@@ -1294,6 +1589,18 @@ pub async fn merge_chunks(
     unique_key_columns: Option<Vec<&crate::metastore::Column>>,
     aggregate_columns: Option<Vec<AggregateColumn>>,
 ) -> Result<SendableRecordBatchStream, CubeError> {
+    let res = merge_chunks_plan(key_size, l, r, unique_key_columns, aggregate_columns)?;
+    Ok(res.execute(0).await?)
+}
+
+/// Same as [merge_chunks], but returns the plan so it can be extended before execution.
+pub fn merge_chunks_plan(
+    key_size: usize,
+    l: Arc<dyn ExecutionPlan>,
+    r: Vec<ArrayRef>,
+    unique_key_columns: Option<Vec<&crate::metastore::Column>>,
+    aggregate_columns: Option<Vec<AggregateColumn>>,
+) -> Result<Arc<dyn ExecutionPlan>, CubeError> {
     let schema = l.schema();
     let r = RecordBatch::try_new(schema.clone(), r)?;
 
@@ -1348,7 +1655,7 @@ pub async fn merge_chunks(
         )?);
     }
 
-    Ok(res.execute(0).await?)
+    Ok(res)
 }
 
 pub async fn merge_replay_handles(
@@ -1431,6 +1738,9 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
                 false,
             )
             .await
@@ -1677,6 +1987,9 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
                 false,
             )
             .await
@@ -1862,6 +2175,9 @@ mod tests {
                 Some(vec![("sum".to_string(), "sum_int".to_string())]),
                 None,
                 None,
+                None,
+                None,
+                None,
                 false,
             )
             .await
@@ -2030,6 +2346,83 @@ mod tests {
             .await;
     }
 
+    #[tokio::test]
+    async fn partition_compaction_retention() {
+        Config::test("partition_compaction_retention")
+            .start_test(async move |services| {
+                let service = services.sql_service;
+                let _ = service.exec_query("CREATE SCHEMA test").await.unwrap();
+                let compaction_service = services
+                    .injector
+                    .get_service_typed::<dyn CompactionService>()
+                    .await;
+                let now = Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ");
+                for table in ["a", "b"] {
+                    service
+                        .exec_query(&format!(
+                            "create table test.{} (ts timestamp, v int) \
+                             with (retention_column = 'ts', retention = '1 day')",
+                            table
+                        ))
+                        .await
+                        .unwrap();
+                }
+                service
+                    .exec_query(&format!(
+                        "insert into test.a (ts, v) values \
+                         ('2020-01-01T00:00:00.000Z', 1), ('{}', 2), (NULL, 3)",
+                        now
+                    ))
+                    .await
+                    .unwrap();
+                service
+                    .exec_query(
+                        "insert into test.b (ts, v) values \
+                         ('2020-01-01T00:00:00.000Z', 1), ('2020-01-02T00:00:00.000Z', 2)",
+                    )
+                    .await
+                    .unwrap();
+
+                for index_id in [1, 2] {
+                    let partitions = services
+                        .meta_store
+                        .get_active_partitions_by_index_id(index_id)
+                        .await
+                        .unwrap();
+                    compaction_service
+                        .compact(partitions[0].get_id(), DataLoadedSize::new())
+                        .await
+                        .unwrap();
+                }
+
+                let r = service
+                    .exec_query("select v from test.a order by v")
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    r.get_rows(),
+                    &vec![
+                        Row::new(vec![TableValue::Int(2)]),
+                        Row::new(vec![TableValue::Int(3)])
+                    ]
+                );
+                let r = service
+                    .exec_query("select count(*) from test.b")
+                    .await
+                    .unwrap();
+                assert_eq!(r.get_rows(), &vec![Row::new(vec![TableValue::Int(0)])]);
+                // Key range of the index is still covered by the empty partition.
+                let partitions = services
+                    .meta_store
+                    .get_active_partitions_by_index_id(2)
+                    .await
+                    .unwrap();
+                assert_eq!(partitions.len(), 1);
+                assert_eq!(partitions[0].get_row().main_table_row_count(), 0);
+            })
+            .await;
+    }
+
     #[tokio::test]
     async fn partition_compaction_decimal96() {
         Config::test("partition_compaction_decimal96")
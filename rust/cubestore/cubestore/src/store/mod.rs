@@ -1,4 +1,6 @@
 pub mod compaction;
+pub mod mutation;
+pub mod retention;
 
 use arrow::compute::{lexsort_to_indices, SortColumn, SortOptions};
 use async_trait::async_trait;
@@ -36,8 +38,9 @@ use crate::cluster::{node_name_by_partition, Cluster};
 use crate::config::injection::DIService;
 use crate::config::ConfigObj;
 use crate::metastore::chunks::chunk_file_name;
+use crate::queryplanner::adapt_schema::adapt_batch_to_index;
 use crate::queryplanner::trace_data_loaded::DataLoadedSize;
-use crate::table::data::cmp_partition_key;
+use crate::table::data::{cmp_partition_key, null_array};
 use crate::table::parquet::{arrow_schema, ParquetTableStore};
 use arrow::array::{Array, ArrayRef, Int64Builder, StringBuilder, UInt64Array};
 use arrow::record_batch::RecordBatch;
@@ -230,11 +233,9 @@ pub trait ChunkDataStore: DIService + Send + Sync {
         data_loaded_size: Arc<DataLoadedSize>,
     ) -> Result<(), CubeError>;
     async fn get_chunk_columns(&self, chunk: IdRow<Chunk>) -> Result<Vec<RecordBatch>, CubeError>;
-    async fn has_in_memory_chunk(
-        &self,
-        chunk: IdRow<Chunk>,
-        partition: IdRow<Partition>,
-    ) -> Result<bool, CubeError>;
+    /// Checks whether the in-memory chunk is kept on this node. Previous owners of moved
+    /// partitions keep their in-memory chunks until these are persisted.
+    async fn has_in_memory_chunk(&self, chunk: IdRow<Chunk>) -> Result<bool, CubeError>;
     async fn get_chunk_columns_with_preloaded_meta(
         &self,
         chunk: IdRow<Chunk>,
@@ -573,24 +574,30 @@ impl ChunkDataStore for ChunkStore {
         index: IdRow<Index>,
     ) -> Result<Vec<RecordBatch>, CubeError> {
         if chunk.get_row().in_memory() {
+            let chunk_name = chunk_file_name(chunk.get_id(), chunk.get_row().suffix());
+            // The previous owner of a moved partition still serves chunks it has received.
+            let batch = self.memory_chunks.read().await.get(&chunk_name).cloned();
+            if let Some(batch) = batch {
+                return Ok(vec![adapt_batch_to_index(batch, index.get_row())?]);
+            }
             let node_name = self.cluster.node_name_by_partition(&partition);
             let server_name = self.cluster.server_name();
             if node_name != server_name {
                 return Err(CubeError::internal(format!("In memory chunk {:?} with owner node '{}' is trying to be repartitioned or compacted on non owner node '{}'", chunk, node_name, server_name)));
             }
-            let memory_chunks = self.memory_chunks.read().await;
-            let chunk_name = chunk_file_name(chunk.get_id(), chunk.get_row().suffix());
-            Ok(vec![memory_chunks
-                .get(&chunk_name)
-                .map(|b| b.clone())
-                .unwrap_or(RecordBatch::new_empty(Arc::new(
-                    arrow_schema(&index.get_row()),
-                )))])
+            Err(CubeError::internal(format!(
+                "In memory chunk {:?} is missing on its owner node '{}'",
+                chunk, server_name
+            )))
         } else {
             let (local_file, index) = self.download_chunk(chunk, partition, index).await?;
             Ok(cube_ext::spawn_blocking(move || -> Result<_, CubeError> {
-                let parquet = ParquetTableStore::new(index, ROW_GROUP_SIZE);
-                Ok(parquet.read_columns(&local_file)?)
+                let parquet = ParquetTableStore::new(index.clone(), ROW_GROUP_SIZE);
+                parquet
+                    .read_columns(&local_file)?
+                    .into_iter()
+                    .map(|b| adapt_batch_to_index(b, &index))
+                    .collect()
             })
             .await??)
         }
@@ -663,17 +670,8 @@ impl ChunkDataStore for ChunkStore {
         Ok((new, non_empty_chunk_ids))
     }
 
-    async fn has_in_memory_chunk(
-        &self,
-        chunk: IdRow<Chunk>,
-        partition: IdRow<Partition>,
-    ) -> Result<bool, CubeError> {
+    async fn has_in_memory_chunk(&self, chunk: IdRow<Chunk>) -> Result<bool, CubeError> {
         if chunk.get_row().in_memory() {
-            let node_name = self.cluster.node_name_by_partition(&partition);
-            let server_name = self.cluster.server_name();
-            if node_name != server_name {
-                return Err(CubeError::internal(format!("In memory chunk {:?} with owner node '{}' is trying to be repartitioned or compacted on non owner node '{}'", chunk, node_name, server_name)));
-            }
             let chunk_name = chunk_file_name(chunk.get_id(), chunk.get_row().suffix());
             let memory_chunks = self.memory_chunks.read().await;
             Ok(memory_chunks.contains_key(&chunk_name))
@@ -883,6 +881,9 @@ mod tests {
                     None,
                     None,
                     None,
+                    None,
+                    None,
+                    None,
                     false,
                 )
                 .await
@@ -979,6 +980,9 @@ mod tests {
                     None,
                     None,
                     None,
+                    None,
+                    None,
+                    None,
                     false,
                 )
                 .await
@@ -1089,6 +1093,9 @@ mod tests {
                     Some(vec![("sum".to_string(), "sum_int".to_string())]),
                     None,
                     None,
+                    None,
+                    None,
+                    None,
                     false,
                 )
                 .await
@@ -1443,6 +1450,11 @@ fn remap_columns(
     assert_eq!(old_columns.len(), old.len());
     let mut new = Vec::with_capacity(new_columns.len());
     for new_column in new_columns.iter() {
+        if new_column.is_dropped() {
+            let num_rows = old.first().map(|a| a.len()).unwrap_or(0);
+            new.push(null_array(new_column.get_column_type(), num_rows));
+            continue;
+        }
         let old_column = old_columns
             .iter()
             .find(|c| c.get_name() == new_column.get_name())
@@ -0,0 +1,516 @@
+use crate::metastore::table::Table;
+use crate::metastore::{Column, IdRow, Index, IndexType};
+use crate::queryplanner::partition_filter::PartitionFilter;
+use crate::sql::parser::{CubeStoreParser, Statement as CubeStoreStatement};
+use crate::CubeError;
+use arrow::datatypes::{Field, Schema, SchemaRef};
+use datafusion::catalog::TableReference;
+use datafusion::datasource::datasource::Statistics;
+use datafusion::datasource::TableProvider;
+use datafusion::error::DataFusionError;
+use datafusion::logical_plan::{Column as DFColumn, Expr, LogicalPlan};
+use datafusion::physical_plan::empty::EmptyExec;
+use datafusion::physical_plan::expressions::{Column as PhysicalColumn, PhysicalSortExpr};
+use datafusion::physical_plan::sort::{SortExec, SortOptions};
+use datafusion::physical_plan::udaf::AggregateUDF;
+use datafusion::physical_plan::udf::ScalarUDF;
+use datafusion::physical_plan::ExecutionPlan;
+use datafusion::prelude::ExecutionContext;
+use datafusion::sql::parser::Statement as DFStatement;
+use datafusion::sql::planner::{ContextProvider, SqlToRel};
+use sqlparser::ast::{Expr as SQExpr, ObjectName, Statement, UnaryOperator, Value};
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+/// Predicate based `DELETE` or `UPDATE`. It's applied by rewriting every partition of the table
+/// which can contain matching rows.
+#[derive(Debug, Clone)]
+pub enum RowsMutation {
+    Delete {
+        selection: Option<SQExpr>,
+    },
+    Update {
+        assignments: Vec<(String, SQExpr)>,
+        selection: Option<SQExpr>,
+    },
+}
+
+impl RowsMutation {
+    /// Parses `DELETE` or `UPDATE` statement, e.g. the one which is sent to the worker that
+    /// rewrites a partition.
+    pub fn parse(statement: &str) -> Result<Self, CubeError> {
+        let mut parser = CubeStoreParser::new(statement)?;
+        match parser.parse_statement()? {
+            CubeStoreStatement::Statement(s) => Ok(Self::from_statement(s)?.1),
+            _ => Err(CubeError::internal(format!(
+                "Unexpected mutation statement: {}",
+                statement
+            ))),
+        }
+    }
+
+    pub fn from_statement(statement: Statement) -> Result<(ObjectName, Self), CubeError> {
+        match statement {
+            Statement::Delete {
+                table_name,
+                selection,
+            } => Ok((table_name, RowsMutation::Delete { selection })),
+            Statement::Update {
+                table_name,
+                assignments,
+                selection,
+            } => Ok((
+                table_name,
+                RowsMutation::Update {
+                    assignments: assignments
+                        .into_iter()
+                        .map(|a| (a.id.value, a.value))
+                        .collect(),
+                    selection,
+                },
+            )),
+            s => Err(CubeError::internal(format!(
+                "Unexpected mutation statement: {}",
+                s
+            ))),
+        }
+    }
+
+    /// Checks the mutation can be applied to the table before any partition is rewritten.
+    pub fn validate(&self, table: &Table, indexes: &Vec<IdRow<Index>>) -> Result<(), CubeError> {
+        if table.in_memory_ingest() {
+            return Err(CubeError::user(format!(
+                "DELETE and UPDATE are not supported for stream table {}",
+                table.get_table_name()
+            )));
+        }
+        for index in indexes.iter() {
+            if index.get_row().get_type() == IndexType::Aggregate {
+                return Err(CubeError::user(format!(
+                    "DELETE and UPDATE are not supported for tables with aggregate indexes: {}",
+                    index.get_row().get_name()
+                )));
+            }
+            if index.get_row().multi_index_id().is_some() {
+                return Err(CubeError::user(format!(
+                    "DELETE and UPDATE are not supported for tables with partitioned indexes: {}",
+                    index.get_row().get_name()
+                )));
+            }
+        }
+
+        if let RowsMutation::Update { assignments, .. } = self {
+            for (name, value) in assignments.iter() {
+                if table.get_columns().iter().all(|c| c.get_name() != name) {
+                    return Err(CubeError::user(format!(
+                        "Column {} is not present in table {}",
+                        name,
+                        table.get_table_name()
+                    )));
+                }
+                // Sort key columns can be updated as long as rows stay within their partitions,
+                // but identity of rows must be kept for deduplication.
+                let is_key_column = table
+                    .unique_key_columns()
+                    .map_or(false, |k| k.iter().any(|c| c.get_name() == name))
+                    || table.seq_column().map_or(false, |c| c.get_name() == name);
+                if is_key_column {
+                    return Err(CubeError::user(format!(
+                        "Key column {} can't be updated",
+                        name
+                    )));
+                }
+                // Constant values keep the mutation idempotent so it can be safely retried.
+                if !is_constant(value) {
+                    return Err(CubeError::user(format!(
+                        "Only constant values can be assigned in UPDATE but found: {} = {}",
+                        name, value
+                    )));
+                }
+            }
+        }
+
+        for index in indexes.iter() {
+            let columns = index.get_row().columns();
+            self.plan(
+                columns,
+                index.get_row().sort_key_size() as usize,
+                Arc::new(EmptyExec::new(false, Arc::new(mutation_schema(columns)))),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Partitions which can't match the selection are left untouched.
+    pub fn partition_filter(&self, index: &Index) -> Result<PartitionFilter, CubeError> {
+        let columns = index.columns();
+        let provider = MutationTableProvider::new(
+            columns,
+            Arc::new(EmptyExec::new(false, Arc::new(mutation_schema(columns)))),
+        );
+        let (exprs, _) = provider.logical_exprs(vec![self.selection()])?;
+        let key_schema = Schema::new(
+            columns
+                .iter()
+                .take(index.sort_key_size() as usize)
+                .map(|c| c.clone().into())
+                .collect::<Vec<Field>>(),
+        );
+        Ok(PartitionFilter::extract(&key_schema, &exprs))
+    }
+
+    /// Whether updated rows have to be sorted again.
+    pub fn updates_sort_key(&self, columns: &Vec<Column>, sort_key_size: usize) -> bool {
+        match self {
+            RowsMutation::Delete { .. } => false,
+            RowsMutation::Update { assignments, .. } => columns[..sort_key_size]
+                .iter()
+                .any(|c| assignments.iter().any(|(name, _)| name == c.get_name())),
+        }
+    }
+
+    /// Builds a plan which returns rows of `input` with the mutation applied. Rows are
+    /// returned sorted by the first `sort_key_size` columns as long as `input` is.
+    pub fn plan(
+        &self,
+        columns: &Vec<Column>,
+        sort_key_size: usize,
+        input: Arc<dyn ExecutionPlan>,
+    ) -> Result<Arc<dyn ExecutionPlan>, CubeError> {
+        let provider = MutationTableProvider::new(columns, input);
+        let plan = match self {
+            RowsMutation::Delete { .. } => {
+                let (mut exprs, input) = provider.logical_exprs(vec![self.selection()])?;
+                let selection = exprs.remove(0);
+                // Rows for which the selection evaluates to NULL are kept.
+                LogicalPlan::Filter {
+                    predicate: Expr::Not(Box::new(selection.clone()))
+                        .or(Expr::IsNull(Box::new(selection))),
+                    input,
+                }
+            }
+            RowsMutation::Update { assignments, .. } => {
+                let mut sql_exprs = vec![self.selection()];
+                sql_exprs.extend(assignments.iter().map(|(_, v)| v.clone()));
+                let (mut exprs, input) = provider.logical_exprs(sql_exprs)?;
+                let selection = exprs.remove(0);
+                let schema = provider.schema();
+                let projection = columns
+                    .iter()
+                    .map(|c| {
+                        let column = Expr::Column(DFColumn::from_name(c.get_name()));
+                        match assignments
+                            .iter()
+                            .position(|(name, _)| name == c.get_name())
+                        {
+                            Some(i) => Expr::Case {
+                                expr: None,
+                                when_then_expr: vec![(
+                                    Box::new(selection.clone()),
+                                    Box::new(Expr::Cast {
+                                        expr: Box::new(exprs[i].clone()),
+                                        data_type: schema.field(c.get_index()).data_type().clone(),
+                                    }),
+                                )],
+                                else_expr: Some(Box::new(column)),
+                            }
+                            .alias(c.get_name()),
+                            None => column,
+                        }
+                    })
+                    .collect();
+                LogicalPlan::Projection {
+                    expr: projection,
+                    schema: input.schema().clone(),
+                    input,
+                }
+            }
+        };
+
+        let plan = ExecutionContext::new().create_physical_plan(&plan)?;
+        if !self.updates_sort_key(columns, sort_key_size) {
+            return Ok(plan);
+        }
+        let schema = plan.schema();
+        let sort_expr = (0..sort_key_size)
+            .map(|i| PhysicalSortExpr {
+                expr: Arc::new(PhysicalColumn::new(schema.field(i).name(), i)),
+                options: SortOptions {
+                    descending: false,
+                    nulls_first: true,
+                },
+            })
+            .collect();
+        Ok(Arc::new(SortExec::try_new(sort_expr, plan)?))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RowsMutation::Delete { .. } => "delete",
+            RowsMutation::Update { .. } => "update",
+        }
+    }
+
+    fn selection(&self) -> SQExpr {
+        let selection = match self {
+            RowsMutation::Delete { selection } => selection,
+            RowsMutation::Update { selection, .. } => selection,
+        };
+        selection
+            .clone()
+            .unwrap_or(SQExpr::Value(Value::Boolean(true)))
+    }
+}
+
+/// Statement which is read back by [RowsMutation::parse].
+impl fmt::Display for RowsMutation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let selection = match self {
+            RowsMutation::Delete { selection } => {
+                write!(f, "DELETE FROM data")?;
+                selection
+            }
+            RowsMutation::Update {
+                assignments,
+                selection,
+            } => {
+                write!(f, "UPDATE data SET ")?;
+                for (i, (name, value)) in assignments.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "`{}` = {}", name, value)?;
+                }
+                selection
+            }
+        };
+        if let Some(selection) = selection {
+            write!(f, " WHERE {}", selection)?;
+        }
+        Ok(())
+    }
+}
+
+fn is_constant(e: &SQExpr) -> bool {
+    match e {
+        SQExpr::Value(_) | SQExpr::TypedString { .. } => true,
+        SQExpr::UnaryOp {
+            op: UnaryOperator::Minus | UnaryOperator::Plus,
+            expr,
+        } => is_constant(expr),
+        SQExpr::Cast { expr, .. } | SQExpr::Nested(expr) => is_constant(expr),
+        _ => false,
+    }
+}
+
+fn mutation_schema(columns: &Vec<Column>) -> Schema {
+    Schema::new(
+        columns
+            .iter()
+            .map(|c| c.clone().into())
+            .collect::<Vec<Field>>(),
+    )
+}
+
+/// Exposes rows of a single partition as the `data` table to plan mutation expressions.
+#[derive(Debug, Clone)]
+struct MutationTableProvider {
+    schema: SchemaRef,
+    input: Arc<dyn ExecutionPlan>,
+}
+
+impl MutationTableProvider {
+    const TABLE_NAME: &'static str = "data";
+
+    fn new(columns: &Vec<Column>, input: Arc<dyn ExecutionPlan>) -> Self {
+        Self {
+            schema: Arc::new(mutation_schema(columns)),
+            input,
+        }
+    }
+
+    /// Returns logical expressions along with the table scan they are evaluated over.
+    fn logical_exprs(
+        &self,
+        exprs: Vec<SQExpr>,
+    ) -> Result<(Vec<Expr>, Arc<LogicalPlan>), CubeError> {
+        let select = format!(
+            "SELECT {} FROM {}",
+            exprs
+                .iter()
+                .map(|e| format!("({})", e))
+                .collect::<Vec<_>>()
+                .join(", "),
+            Self::TABLE_NAME
+        );
+        let statement = match CubeStoreParser::new(&select)?.parse_statement()? {
+            CubeStoreStatement::Statement(s) => s,
+            s => {
+                return Err(CubeError::internal(format!(
+                    "Unexpected mutation select: {:?}",
+                    s
+                )))
+            }
+        };
+        let plan = SqlToRel::new(self).statement_to_plan(&DFStatement::Statement(statement))?;
+        match plan {
+            LogicalPlan::Projection { expr, input, .. } => Ok((expr, input)),
+            p => Err(CubeError::internal(format!(
+                "Unexpected mutation plan: {:?}",
+                p
+            ))),
+        }
+    }
+}
+
+impl ContextProvider for MutationTableProvider {
+    fn get_table_provider(&self, name: TableReference) -> Option<Arc<dyn TableProvider>> {
+        match name {
+            TableReference::Bare { table } if table == Self::TABLE_NAME => {
+                Some(Arc::new(self.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    fn get_function_meta(&self, _name: &str) -> Option<Arc<ScalarUDF>> {
+        None
+    }
+
+    fn get_aggregate_meta(&self, _name: &str) -> Option<Arc<AggregateUDF>> {
+        None
+    }
+}
+
+impl TableProvider for MutationTableProvider {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn scan(
+        &self,
+        _projection: &Option<Vec<usize>>,
+        _batch_size: usize,
+        _filters: &[Expr],
+        _limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>, DataFusionError> {
+        Ok(self.input.clone())
+    }
+
+    fn statistics(&self) -> Statistics {
+        Statistics {
+            num_rows: None,
+            total_byte_size: None,
+            column_statistics: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metastore::ColumnType;
+    use crate::table::{Row, TableValue};
+    use arrow::array::{ArrayRef, Int64Array, StringArray};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::physical_plan::collect;
+    use datafusion::physical_plan::memory::MemoryExec;
+
+    fn columns() -> Vec<Column> {
+        vec![
+            Column::new("id".to_string(), ColumnType::Int, 0),
+            Column::new("name".to_string(), ColumnType::String, 1),
+        ]
+    }
+
+    async fn apply(mutation: &str) -> Vec<Row> {
+        let columns = columns();
+        let schema = Arc::new(mutation_schema(&columns));
+        let data: Vec<ArrayRef> = vec![
+            Arc::new(Int64Array::from(vec![Some(1), Some(2), None])),
+            Arc::new(StringArray::from(vec![Some("a"), Some("b"), Some("c")])),
+        ];
+        let batch = RecordBatch::try_new(schema.clone(), data).unwrap();
+        let input = Arc::new(MemoryExec::try_new(&[vec![batch]], schema, None).unwrap());
+        let plan = RowsMutation::parse(mutation)
+            .unwrap()
+            .plan(&columns, 1, input)
+            .unwrap();
+        let batches = collect(plan).await.unwrap();
+        batches
+            .iter()
+            .flat_map(|b| {
+                (0..b.num_rows())
+                    .map(|i| Row::new(TableValue::from_columns(b.columns(), i)))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn delete_and_update_plans() {
+        assert_eq!(
+            apply("DELETE FROM s.t WHERE id = 2").await,
+            vec![
+                Row::new(vec![
+                    TableValue::Int(1),
+                    TableValue::String("a".to_string())
+                ]),
+                Row::new(vec![TableValue::Null, TableValue::String("c".to_string())]),
+            ]
+        );
+        assert_eq!(apply("DELETE FROM s.t").await, vec![]);
+        assert_eq!(
+            apply("UPDATE s.t SET name = 'x' WHERE id > 1").await,
+            vec![
+                Row::new(vec![
+                    TableValue::Int(1),
+                    TableValue::String("a".to_string())
+                ]),
+                Row::new(vec![
+                    TableValue::Int(2),
+                    TableValue::String("x".to_string())
+                ]),
+                Row::new(vec![TableValue::Null, TableValue::String("c".to_string())]),
+            ]
+        );
+        // Rows are sorted again when the sort key is updated.
+        assert_eq!(
+            apply("UPDATE s.t SET id = 0 WHERE name = 'b'").await,
+            vec![
+                Row::new(vec![TableValue::Null, TableValue::String("c".to_string())]),
+                Row::new(vec![
+                    TableValue::Int(0),
+                    TableValue::String("b".to_string())
+                ]),
+                Row::new(vec![
+                    TableValue::Int(1),
+                    TableValue::String("a".to_string())
+                ]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn statement_round_trip() {
+        for statement in [
+            "DELETE FROM s.t WHERE id = 2",
+            "DELETE FROM s.t",
+            "UPDATE s.t SET name = 'x', id = 0 WHERE id > 1",
+        ] {
+            let restored = RowsMutation::parse(statement).unwrap().to_string();
+            assert_eq!(
+                apply(&restored).await,
+                apply(statement).await,
+                "{}",
+                restored
+            );
+        }
+    }
+}
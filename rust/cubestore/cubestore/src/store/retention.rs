@@ -0,0 +1,142 @@
+use crate::metastore::table::Table;
+use crate::metastore::Index;
+use crate::store::mutation::RowsMutation;
+use crate::table::{Row, TableValue};
+use crate::CubeError;
+use chrono::{DateTime, Duration, Utc};
+
+/// Expiration of index rows by the table retention policy.
+///
+/// Values of the retention column in a partition or chunk are known only when they're bound by
+/// its min and max rows: the column is a part of the sort key and all preceding key columns have
+/// the same value in both rows. Expired rows of other partitions are removed the next time they're
+/// compacted. Indexes without the retention column keep all rows.
+#[derive(Debug, Clone)]
+pub struct RetentionCutoff {
+    column_name: String,
+    /// Position of the column in the index.
+    position: usize,
+    sort_key_size: usize,
+    cutoff: DateTime<Utc>,
+    period: Duration,
+}
+
+impl RetentionCutoff {
+    /// [None] if the table has no retention or the index doesn't contain the retention column.
+    pub fn new(table: &Table, index: &Index, now: DateTime<Utc>) -> Option<Self> {
+        let retention = table.retention().as_ref()?;
+        let column_name = table.retention_column()?.get_name();
+        let position = index
+            .columns()
+            .iter()
+            .position(|c| c.get_name() == column_name)?;
+        Some(Self {
+            column_name: column_name.clone(),
+            position,
+            sort_key_size: index.sort_key_size() as usize,
+            cutoff: retention.cutoff(now),
+            period: Duration::seconds(retention.period_secs as i64),
+        })
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// All rows between `min` and `max` are expired.
+    pub fn is_expired(&self, min: &Option<Row>, max: &Option<Row>) -> bool {
+        self.bounds(min, max)
+            .map_or(false, |(_, max)| max < self.cutoff.timestamp_nanos())
+    }
+
+    /// Some of the rows between `min` and `max` are expired.
+    pub fn has_expired_rows(&self, min: &Option<Row>, max: &Option<Row>) -> bool {
+        self.bounds(min, max)
+            .map_or(false, |(min, _)| min < self.cutoff.timestamp_nanos())
+    }
+
+    /// Removes expired rows. Rows with NULL values in the retention column are kept.
+    pub fn mutation(&self) -> Result<RowsMutation, CubeError> {
+        RowsMutation::parse(&format!(
+            "DELETE FROM data WHERE `{}` < to_timestamp('{}')",
+            self.column_name,
+            self.cutoff.format("%Y-%m-%dT%H:%M:%S%.3fZ")
+        ))
+    }
+
+    /// Min and max values of the retention column in nanoseconds. [None] if they can't be derived
+    /// from the rows or NULL values are present.
+    fn bounds(&self, min: &Option<Row>, max: &Option<Row>) -> Option<(i64, i64)> {
+        if self.position >= self.sort_key_size {
+            return None;
+        }
+        let min = min.as_ref()?.values();
+        let max = max.as_ref()?.values();
+        if min.len() <= self.position
+            || max.len() <= self.position
+            || min[..self.position] != max[..self.position]
+        {
+            return None;
+        }
+        match (&min[self.position], &max[self.position]) {
+            (TableValue::Timestamp(min), TableValue::Timestamp(max)) => {
+                Some((min.get_time_stamp(), max.get_time_stamp()))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::TimestampValue;
+    use chrono::TimeZone;
+
+    fn cutoff(position: usize) -> RetentionCutoff {
+        RetentionCutoff {
+            column_name: "ts".to_string(),
+            position,
+            sort_key_size: 2,
+            cutoff: Utc.ymd(2022, 1, 10).and_hms(0, 0, 0),
+            period: Duration::days(1),
+        }
+    }
+
+    fn row(id: i64, day: u32) -> Option<Row> {
+        let ts = Utc.ymd(2022, 1, day).and_hms(0, 0, 0).timestamp_nanos();
+        Some(Row::new(vec![
+            TableValue::Int(id),
+            TableValue::Timestamp(TimestampValue::new(ts)),
+        ]))
+    }
+
+    #[test]
+    fn retention_bounds() {
+        let c = cutoff(1);
+        assert!(c.is_expired(&row(1, 1), &row(1, 9)));
+        assert!(c.has_expired_rows(&row(1, 1), &row(1, 9)));
+        assert!(!c.is_expired(&row(1, 1), &row(1, 10)));
+        assert!(c.has_expired_rows(&row(1, 1), &row(1, 10)));
+        assert!(!c.has_expired_rows(&row(1, 10), &row(1, 20)));
+
+        // Values of the second key column aren't bound when the first one differs.
+        assert!(!c.is_expired(&row(1, 1), &row(2, 9)));
+        assert!(!c.has_expired_rows(&row(1, 1), &row(2, 9)));
+        assert!(!c.is_expired(&None, &None));
+
+        // NULL values are never expired.
+        let null_min = Some(Row::new(vec![TableValue::Int(1), TableValue::Null]));
+        assert!(!c.is_expired(&null_min, &row(1, 9)));
+        assert!(!c.has_expired_rows(&null_min, &row(1, 9)));
+
+        // Not a key column.
+        assert!(!cutoff(2).is_expired(&row(1, 1), &row(1, 9)));
+    }
+
+    #[test]
+    fn retention_mutation() {
+        let mutation = cutoff(1).mutation().unwrap();
+        assert_eq!(mutation.name(), "delete");
+    }
+}
@@ -1,8 +1,10 @@
 use crate::config::injection::DIService;
 use crate::config::ConfigObj;
+use crate::metastore::source::{KafkaSaslMechanism, KafkaSecurity, KafkaSecurityProtocol};
 use crate::metastore::table::StreamOffset;
 use crate::metastore::Column;
 use crate::streaming::kafka_post_processing::{KafkaPostProcessPlan, KafkaPostProcessPlanner};
+use crate::streaming::schema_registry::MessageDecoder;
 use crate::streaming::traffic_sender::TrafficSender;
 use crate::streaming::{parse_json_payload_and_key, StreamingSource};
 use crate::table::{Row, TableValue};
@@ -30,14 +32,13 @@ pub struct KafkaStreamingSource {
     unique_key_columns: Vec<Column>,
     columns: Vec<Column>,
     seq_column_index: usize,
-    user: Option<String>,
-    password: Option<String>,
     topic: String,
     host: String,
     offset: Option<StreamOffset>,
     partition: usize,
     kafka_client: Arc<dyn KafkaClientService>,
-    use_ssl: bool,
+    connection_properties: Vec<(String, String)>,
+    decoder: MessageDecoder,
     post_processing_plan: Option<KafkaPostProcessPlan>,
     trace_obj: Option<String>,
 }
@@ -58,6 +59,8 @@ impl KafkaStreamingSource {
         partition: usize,
         kafka_client: Arc<dyn KafkaClientService>,
         use_ssl: bool,
+        security: &KafkaSecurity,
+        decoder: MessageDecoder,
         trace_obj: Option<String>,
     ) -> Result<Self, CubeError> {
         let (post_processing_plan, columns, unique_key_columns, seq_column_index) =
@@ -84,14 +87,13 @@ impl KafkaStreamingSource {
             unique_key_columns,
             columns,
             seq_column_index,
-            user,
-            password,
             topic,
             host,
             offset,
             partition,
             kafka_client,
-            use_ssl,
+            connection_properties: connection_properties(&user, &password, use_ssl, security),
+            decoder,
             post_processing_plan,
             trace_obj,
         })
@@ -107,9 +109,8 @@ pub trait KafkaClientService: DIService + Send + Sync {
         partition: i32,
         offset: Offset,
         hosts: Vec<String>,
-        user: &Option<String>,
-        password: &Option<String>,
-        use_ssl: bool,
+        connection_properties: &Vec<(String, String)>,
+        decoder: MessageDecoder,
         to_row: Arc<dyn Fn(KafkaMessage) -> Result<Option<Row>, CubeError> + Send + Sync>,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<Row, CubeError>> + Send>>, CubeError>;
 
@@ -131,8 +132,8 @@ pub struct KafkaClientServiceImpl {
 pub enum KafkaMessage<'a> {
     BorrowedMessage(BorrowedMessage<'a>),
     MockMessage {
-        key: Option<String>,
-        payload: Option<String>,
+        key: Option<Vec<u8>>,
+        payload: Option<Vec<u8>>,
         offset: i64,
     },
 }
@@ -141,14 +142,14 @@ impl<'a> KafkaMessage<'a> {
     pub fn key(&self) -> Option<&[u8]> {
         match self {
             KafkaMessage::BorrowedMessage(m) => m.key(),
-            KafkaMessage::MockMessage { key, .. } => key.as_ref().map(|k| k.as_bytes()),
+            KafkaMessage::MockMessage { key, .. } => key.as_deref(),
         }
     }
 
     pub fn payload(&self) -> Option<&[u8]> {
         match self {
             KafkaMessage::BorrowedMessage(m) => m.payload(),
-            KafkaMessage::MockMessage { payload, .. } => payload.as_ref().map(|k| k.as_bytes()),
+            KafkaMessage::MockMessage { payload, .. } => payload.as_deref(),
         }
     }
 
@@ -169,26 +170,18 @@ impl KafkaClientService for KafkaClientServiceImpl {
         partition: i32,
         offset: Offset,
         hosts: Vec<String>,
-        user: &Option<String>,
-        password: &Option<String>,
-        use_ssl: bool,
+        connection_properties: &Vec<(String, String)>,
+        decoder: MessageDecoder,
         to_row: Arc<dyn Fn(KafkaMessage) -> Result<Option<Row>, CubeError> + Send + Sync>,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<Row, CubeError>> + Send>>, CubeError> {
         let mut config = ClientConfig::new();
         config.set("bootstrap.servers", hosts.join(","));
-        if use_ssl {
-            config.set("security.protocol", "SASL_SSL");
-        }
-        if let Some(user) = user {
-            config.set("sasl.mechanisms", "PLAIN");
-            config.set("sasl.username", user);
-        }
-        if let Some(password) = password {
-            config.set("sasl.password", password);
-        }
         config.set("session.timeout.ms", "45000");
         config.set("max.poll.interval.ms", "45000");
         config.set("group.id", format!("{}-{}-{}", topic, partition, table_id));
+        for (name, value) in connection_properties {
+            config.set(name, value);
+        }
 
         let stream_consumer: StreamConsumer = config.create().map_err(|e| {
             CubeError::user(format!(
@@ -225,27 +218,34 @@ impl KafkaClientService for KafkaClientServiceImpl {
         Ok(Box::pin(stream::from_fn(move || {
             let stream_consumer = stream_consumer.clone();
             let to_row = to_row.clone();
+            let decoder = decoder.clone();
             let config_obj = config_obj.clone();
             async move {
                 loop {
-                    let message = stream_consumer.recv().await;
-                    let row = message
-                        .map_err(|e| {
-                            CubeError::user(format!("Error during fetching kafka message: {}", e))
-                        })
-                        .and_then(|m| {
-                            let res = to_row(KafkaMessage::BorrowedMessage(m));
-                            if config_obj.skip_kafka_parsing_errors() {
-                                if let Err(e) = res {
-                                    log::error!(
-                                        "Skipping parsing kafka message due to error: {}",
-                                        e
-                                    );
-                                    return Ok(None);
-                                }
+                    let row = match stream_consumer.recv().await {
+                        Ok(m) => {
+                            // Schemas are fetched here as `to_row` can't wait for them.
+                            // Registry errors aren't parsing errors: the message must not be
+                            // skipped but read again once the registry is available.
+                            match decoder.load_schemas(m.key(), m.payload()).await {
+                                Ok(()) => match to_row(KafkaMessage::BorrowedMessage(m)) {
+                                    Err(e) if config_obj.skip_kafka_parsing_errors() => {
+                                        log::error!(
+                                            "Skipping parsing kafka message due to error: {}",
+                                            e
+                                        );
+                                        Ok(None)
+                                    }
+                                    res => res,
+                                },
+                                Err(e) => Err(e),
                             }
-                            res
-                        });
+                        }
+                        Err(e) => Err(CubeError::user(format!(
+                            "Error during fetching kafka message: {}",
+                            e
+                        ))),
+                    };
                     match row {
                         Ok(None) => continue,
                         Ok(Some(row)) => break Some(Ok(row)),
@@ -292,6 +292,49 @@ impl KafkaClientServiceImpl {
 
 crate::di_service!(KafkaClientServiceImpl, [KafkaClientService]);
 
+/// librdkafka properties of consumers connecting to the source.
+pub fn connection_properties(
+    user: &Option<String>,
+    password: &Option<String>,
+    use_ssl: bool,
+    security: &KafkaSecurity,
+) -> Vec<(String, String)> {
+    let mut properties = Vec::new();
+    let mut set = |name: &str, value: &str| properties.push((name.to_string(), value.to_string()));
+    let security_protocol = security.security_protocol.or(if use_ssl {
+        Some(KafkaSecurityProtocol::SaslSsl)
+    } else {
+        None
+    });
+    if let Some(security_protocol) = security_protocol {
+        set("security.protocol", security_protocol.as_str());
+    }
+    if user.is_some() || security.sasl_mechanism.is_some() {
+        let mechanism = security.sasl_mechanism.unwrap_or(KafkaSaslMechanism::Plain);
+        set("sasl.mechanisms", mechanism.as_str());
+    }
+    if let Some(user) = user {
+        set("sasl.username", user);
+    }
+    if let Some(password) = password {
+        set("sasl.password", password);
+    }
+    for (name, value) in [
+        ("ssl.ca.pem", &security.ssl_ca_pem),
+        ("ssl.certificate.pem", &security.ssl_certificate_pem),
+        ("ssl.key.pem", &security.ssl_key_pem),
+        ("ssl.key.password", &security.ssl_key_password),
+    ] {
+        if let Some(value) = value {
+            set(name, value);
+        }
+    }
+    for (name, value) in security.properties.iter() {
+        set(name, value);
+    }
+    properties
+}
+
 #[async_trait]
 impl StreamingSource for KafkaStreamingSource {
     async fn row_stream(
@@ -304,6 +347,7 @@ impl StreamingSource for KafkaStreamingSource {
         let unique_key_columns = self.unique_key_columns.clone();
         let seq_column_index_to_move = self.seq_column_index;
         let traffic_sender = TrafficSender::new(self.trace_obj.clone());
+        let decoder = self.decoder.clone();
         let stream = self
             .kafka_client
             .create_message_stream(
@@ -320,31 +364,15 @@ impl StreamingSource for KafkaStreamingSource {
                         .unwrap_or(Offset::End),
                 ),
                 vec![self.host.clone()],
-                &self.user,
-                &self.password,
-                self.use_ssl,
+                &self.connection_properties,
+                self.decoder.clone(),
                 Arc::new(move |m| -> Result<_, _> {
-                    if let Some(payload_str) = m.payload().map(|p| String::from_utf8_lossy(p)) {
-                        traffic_sender.process_event(payload_str.len() as u64)?;
-                        let payload = json::parse(payload_str.as_ref()).map_err(|e| {
-                            CubeError::user(format!("Can't parse '{}' payload: {}", payload_str, e))
+                    if let Some(payload_bytes) = m.payload() {
+                        traffic_sender.process_event(payload_bytes.len() as u64)?;
+                        let payload = decoder.decode_payload(payload_bytes)?;
+                        let key = m.key().map_or(Ok(JsonValue::Object(Object::new())), |k| {
+                            decoder.decode_key(k)
                         })?;
-                        // Kafka can store additional metadata in suffix that contains information about window size for example
-                        // Another use case is streams would usually don't have any keys
-                        let mut key = JsonValue::Object(Object::new());
-                        if let Some(key_str) = m.key().map(|p| String::from_utf8_lossy(p)) {
-                            if key_str.starts_with("{") {
-                                if let Some(last_brace) = key_str.find("}") {
-                                    key = json::parse(&key_str.as_ref()[0..last_brace + 1])
-                                        .map_err(|e| {
-                                            CubeError::user(format!(
-                                                "Can't parse '{}' key: {}",
-                                                key_str, e
-                                            ))
-                                        })?;
-                                }
-                            }
-                        }
 
                         let mut values = parse_json_payload_and_key(
                             &column_to_move,
@@ -355,7 +383,9 @@ impl StreamingSource for KafkaStreamingSource {
                         .map_err(|e| {
                             CubeError::user(format!(
                                 "Can't parse kafka row with '{}' key and '{}' payload: {}",
-                                key, payload_str, e
+                                key,
+                                String::from_utf8_lossy(payload_bytes),
+                                e
                             ))
                         })?;
                         values[seq_column_index_to_move] = TableValue::Int(m.offset());
@@ -701,4 +731,72 @@ mod tests {
             &TableValue::String("2023-06-05T09:00:00.000".to_string())
         );
     }
+
+    #[test]
+    fn kafka_connection_properties() {
+        let props = |user: Option<&str>, use_ssl: bool, security: &KafkaSecurity| {
+            connection_properties(
+                &user.map(|u| u.to_string()),
+                &user.map(|_| "pass".to_string()),
+                use_ssl,
+                security,
+            )
+            .into_iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            props(Some("user"), true, &KafkaSecurity::default()),
+            vec![
+                "security.protocol=SASL_SSL",
+                "sasl.mechanisms=PLAIN",
+                "sasl.username=user",
+                "sasl.password=pass",
+            ]
+        );
+        assert!(props(None, false, &KafkaSecurity::default()).is_empty());
+
+        let security = KafkaSecurity {
+            security_protocol: Some(KafkaSecurityProtocol::SaslPlaintext),
+            sasl_mechanism: Some(KafkaSaslMechanism::ScramSha512),
+            ..KafkaSecurity::default()
+        };
+        assert_eq!(
+            props(Some("user"), true, &security),
+            vec![
+                "security.protocol=SASL_PLAINTEXT",
+                "sasl.mechanisms=SCRAM-SHA-512",
+                "sasl.username=user",
+                "sasl.password=pass",
+            ]
+        );
+
+        let mut security = KafkaSecurity {
+            security_protocol: Some(KafkaSecurityProtocol::Ssl),
+            ssl_ca_pem: Some("ca".to_string()),
+            ssl_certificate_pem: Some("cert".to_string()),
+            ssl_key_pem: Some("key".to_string()),
+            ..KafkaSecurity::default()
+        };
+        security.properties.insert(
+            "ssl.endpoint.identification.algorithm".to_string(),
+            "none".to_string(),
+        );
+        security
+            .properties
+            .insert("security.protocol".to_string(), "PLAINTEXT".to_string());
+        assert_eq!(
+            props(None, false, &security),
+            vec![
+                "security.protocol=SSL",
+                "ssl.ca.pem=ca",
+                "ssl.certificate.pem=cert",
+                "ssl.key.pem=key",
+                // Set after others, so take precedence.
+                "security.protocol=PLAINTEXT",
+                "ssl.endpoint.identification.algorithm=none",
+            ]
+        );
+    }
 }
@@ -1,5 +1,7 @@
 pub mod kafka;
 mod kafka_post_processing;
+mod protobuf;
+mod schema_registry;
 mod topic_table_provider;
 mod traffic_sender;
 
@@ -8,11 +10,12 @@ use crate::config::injection::DIService;
 use crate::config::ConfigObj;
 use crate::metastore::replay_handle::{ReplayHandle, SeqPointer, SeqPointerForLocation};
 use crate::metastore::source::SourceCredentials;
-use crate::metastore::table::{StreamOffset, Table};
+use crate::metastore::table::{StreamOffset, StreamValueFormat, Table};
 use crate::metastore::{Column, ColumnType, IdRow, MetaStore};
 use crate::sql::timestamp_from_string;
 use crate::store::ChunkDataStore;
 use crate::streaming::kafka::{KafkaClientService, KafkaStreamingSource};
+use crate::streaming::schema_registry::MessageDecoder;
 use crate::table::data::{append_row, create_array_builders};
 use crate::table::{Row, TableValue, TimestampValue};
 use crate::util::decimal::Decimal;
@@ -145,6 +148,9 @@ impl StreamingServiceImpl {
                 password,
                 host,
                 use_ssl,
+                value_format,
+                schema_registry_url,
+                security,
             } => Ok(Arc::new(KafkaStreamingSource::try_new(
                 table.get_id(),
                 table.get_row().unique_key_columns()
@@ -164,6 +170,11 @@ impl StreamingServiceImpl {
                 )?,
                 self.kafka_client.clone(),
                 *use_ssl,
+                security,
+                MessageDecoder::new(
+                    table.get_row().value_format().or(*value_format).unwrap_or(StreamValueFormat::Json),
+                    schema_registry_url.clone(),
+                )?,
                 trace_obj,
             )?)),
         }
@@ -940,6 +951,8 @@ mod tests {
     use crate::scheduler::SchedulerImpl;
     use crate::sql::MySqlDialectWithBackTicks;
     use crate::streaming::kafka::KafkaMessage;
+    use crate::streaming::schema_registry::mock::start_schema_registry;
+    use crate::streaming::schema_registry::tests::{avro_event, avro_message, AVRO_SCHEMA};
     use crate::streaming::{KSqlQuery, KSqlQuerySchema, KsqlClient, KsqlResponse};
     use crate::TableId;
     use chrono::{SecondsFormat, TimeZone, Utc};
@@ -1078,9 +1091,8 @@ mod tests {
             partition: i32,
             offset: Offset,
             _hosts: Vec<String>,
-            _user: &Option<String>,
-            _password: &Option<String>,
-            _use_ssl: bool,
+            _connection_properties: &Vec<(String, String)>,
+            _decoder: MessageDecoder,
             to_row: Arc<dyn Fn(KafkaMessage) -> Result<Option<Row>, CubeError> + Send + Sync>,
         ) -> Result<Pin<Box<dyn Stream<Item = Result<Row, CubeError>> + Send>>, CubeError> {
             let max_offset = 5000;
@@ -1110,10 +1122,10 @@ mod tests {
                         key: Some(format!(
                             "{}foo",
                             serde_json::json!({ "MESSAGEID": i.to_string() }).to_string()
-                        )),
+                        ).into_bytes()),
                         payload: Some(
                             serde_json::json!({ "ANONYMOUSID": j.to_string(), "FILTER_ID":i, "TIMESTAMP": ts_string })
-                                .to_string(),
+                                .to_string().into_bytes(),
                         ),
                         offset: i,
                     });
@@ -1133,6 +1145,60 @@ mod tests {
         }
     }
 
+    /// Serves Avro encoded events of schema 1 in partition 0.
+    pub struct MockAvroKafkaClient;
+
+    crate::di_service!(MockAvroKafkaClient, [KafkaClientService]);
+
+    #[async_trait::async_trait]
+    impl KafkaClientService for MockAvroKafkaClient {
+        async fn create_message_stream(
+            &self,
+            _table_id: u64,
+            _topic: String,
+            _partition: i32,
+            offset: Offset,
+            _hosts: Vec<String>,
+            _connection_properties: &Vec<(String, String)>,
+            decoder: MessageDecoder,
+            to_row: Arc<dyn Fn(KafkaMessage) -> Result<Option<Row>, CubeError> + Send + Sync>,
+        ) -> Result<Pin<Box<dyn Stream<Item = Result<Row, CubeError>> + Send>>, CubeError> {
+            let max_offset = 1000;
+            let offset = match offset {
+                Offset::Offset(offset) => offset,
+                Offset::End => max_offset,
+                _ => 0,
+            };
+            let messages = (offset..max_offset)
+                .map(|i| KafkaMessage::MockMessage {
+                    key: Some(
+                        serde_json::json!({ "MESSAGEID": i.to_string() })
+                            .to_string()
+                            .into_bytes(),
+                    ),
+                    payload: Some(avro_message(
+                        1,
+                        avro_event(&(i % 10).to_string(), i, Some(i as f64 / 10.0)),
+                    )),
+                    offset: i,
+                })
+                .collect::<Vec<_>>();
+            for m in messages.iter() {
+                decoder.load_schemas(m.key(), m.payload()).await?;
+            }
+            let rows = messages
+                .into_iter()
+                .map(|m| to_row(m))
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .flatten()
+                .map(|m| Ok(m))
+                .collect::<Vec<_>>();
+
+            Ok(Box::pin(stream::iter(rows)))
+        }
+    }
+
     #[tokio::test]
     async fn streaming_replay() {
         Config::test("streaming_replay").update_config(|mut c| {
@@ -1806,4 +1872,67 @@ mod tests {
         })
             .await;
     }
+
+    #[tokio::test]
+    async fn streaming_filter_kafka_avro() {
+        let registry_url = start_schema_registry(vec![(1, "AVRO", AVRO_SCHEMA.to_string())]);
+        Config::test("streaming_filter_kafka_avro").update_config(|mut c| {
+            c.stream_replay_check_interval_secs = 1;
+            c.compaction_in_memory_chunks_max_lifetime_threshold = 8;
+            c.partition_split_threshold = 1000000;
+            c.max_partition_split_threshold = 1000000;
+            c.compaction_chunks_count_threshold = 100;
+            c.compaction_chunks_total_size_threshold = 100000;
+            c.stale_stream_timeout = 1;
+            c.wal_split_threshold = 1638;
+            c
+        }).start_with_injector_override(async move |injector| {
+            injector.register_typed::<dyn KafkaClientService, _, _, _>(async move |_| {
+                Arc::new(MockAvroKafkaClient)
+            })
+                .await
+        }, async move |services| {
+            let service = services.sql_service;
+
+            let _ = service.exec_query("CREATE SCHEMA test").await.unwrap();
+
+            let err = service
+                .exec_query("CREATE SOURCE OR UPDATE kafka AS 'kafka' VALUES (host = 'localhost:9092', value_format = 'avro')")
+                .await;
+            assert!(err.is_err());
+
+            service
+                .exec_query(&format!("CREATE SOURCE OR UPDATE kafka AS 'kafka' VALUES (host = 'localhost:9092', schema_registry_url = '{}')", registry_url))
+                .await
+                .unwrap();
+
+            let listener = services.cluster.job_result_listener();
+
+            // The source reads JSON by default, the table overrides the format.
+            let _ = service
+                .exec_query("CREATE TABLE test.events_by_type_1 (`ANONYMOUSID` text, `MESSAGEID` text, `FILTER_ID` int) \
+                            WITH (stream_offset = 'earliest', value_format = 'avro', select_statement = 'SELECT * FROM EVENTS_BY_TYPE WHERE FILTER_ID >= 100 and FILTER_ID < 300') \
+                            unique key (`ANONYMOUSID`, `MESSAGEID`, `FILTER_ID`) INDEX by_anonymous(`ANONYMOUSID`, `FILTER_ID`) location 'stream://kafka/EVENTS_BY_TYPE/0'")
+                .await
+                .unwrap();
+
+            let wait = listener.wait_for_job_results(vec![
+                (RowKey::Table(TableId::Tables, 1), JobType::TableImportCSV("stream://kafka/EVENTS_BY_TYPE/0".to_string())),
+            ]);
+            let _ = timeout(Duration::from_secs(15), wait).await;
+
+            let result = service
+                .exec_query("SELECT COUNT(*), min(FILTER_ID), max(FILTER_ID) FROM test.events_by_type_1")
+                .await
+                .unwrap();
+            assert_eq!(result.get_rows(), &vec![Row::new(vec![TableValue::Int(200), TableValue::Int(100), TableValue::Int(299)])]);
+
+            let result = service
+                .exec_query("SELECT ANONYMOUSID, COUNT(*) FROM test.events_by_type_1 WHERE MESSAGEID = '150' GROUP BY 1")
+                .await
+                .unwrap();
+            assert_eq!(result.get_rows(), &vec![Row::new(vec![TableValue::String("0".to_string()), TableValue::Int(1)])]);
+        })
+            .await;
+    }
 }
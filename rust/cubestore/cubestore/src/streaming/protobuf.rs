@@ -0,0 +1,948 @@
+//! Decoding of Protobuf messages by schemas fetched from a schema registry.
+//!
+//! Schemas come as `.proto` sources, so only the subset needed to map messages to table columns
+//! is understood: messages, enums, oneofs and maps. Options, services and extensions are skipped.
+use crate::CubeError;
+use chrono::{SecondsFormat, TimeZone, Utc};
+use json::object::Object;
+use json::JsonValue;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq)]
+enum FieldType {
+    Double,
+    Float,
+    Int32,
+    Int64,
+    UInt32,
+    UInt64,
+    SInt32,
+    SInt64,
+    Fixed32,
+    Fixed64,
+    SFixed32,
+    SFixed64,
+    Bool,
+    String,
+    Bytes,
+    /// Message or enum. Resolved relative to the message declaring the field.
+    Named(String),
+    Map(Box<FieldType>, Box<FieldType>),
+}
+
+impl FieldType {
+    fn from_name(name: &str) -> Self {
+        match name {
+            "double" => FieldType::Double,
+            "float" => FieldType::Float,
+            "int32" => FieldType::Int32,
+            "int64" => FieldType::Int64,
+            "uint32" => FieldType::UInt32,
+            "uint64" => FieldType::UInt64,
+            "sint32" => FieldType::SInt32,
+            "sint64" => FieldType::SInt64,
+            "fixed32" => FieldType::Fixed32,
+            "fixed64" => FieldType::Fixed64,
+            "sfixed32" => FieldType::SFixed32,
+            "sfixed64" => FieldType::SFixed64,
+            "bool" => FieldType::Bool,
+            "string" => FieldType::String,
+            "bytes" => FieldType::Bytes,
+            x => FieldType::Named(x.to_string()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Field {
+    name: String,
+    number: u64,
+    field_type: FieldType,
+    repeated: bool,
+    /// Absent fields are decoded as NULL instead of the default value of their type.
+    has_presence: bool,
+}
+
+#[derive(Debug, Default)]
+struct Message {
+    fields: Vec<Field>,
+    /// Full names of nested messages in declaration order.
+    nested_messages: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ProtoSchema {
+    /// Full names of top level messages in declaration order.
+    messages_order: Vec<String>,
+    messages: HashMap<String, Message>,
+    enums: HashMap<String, HashMap<i64, String>>,
+}
+
+const TIMESTAMP_TYPE: &str = "google.protobuf.Timestamp";
+/// Messages can reference themselves, so nesting of decoded data is limited like in protoc.
+const MAX_NESTING_DEPTH: usize = 100;
+
+impl ProtoSchema {
+    pub fn parse(source: &str) -> Result<Self, CubeError> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            pos: 0,
+            proto3: false,
+            schema: ProtoSchema::default(),
+        };
+        parser.parse_file()?;
+        Ok(parser.schema)
+    }
+
+    /// Decodes message referenced by indexes of the Confluent wire format: index of the top level
+    /// message followed by indexes of nested messages.
+    pub fn decode(&self, message_indexes: &[u64], data: &[u8]) -> Result<JsonValue, CubeError> {
+        let (first, nested) = message_indexes.split_first().unwrap_or((&0, &[][..]));
+        let mut name = self.messages_order.get(*first as usize);
+        for i in nested {
+            name = name.and_then(|n| self.messages[n].nested_messages.get(*i as usize));
+        }
+        let name = name.ok_or_else(|| {
+            CubeError::user(format!(
+                "Message with indexes {:?} not found in protobuf schema",
+                message_indexes
+            ))
+        })?;
+        self.decode_message(name, data, 0)
+    }
+
+    fn decode_message(
+        &self,
+        name: &str,
+        data: &[u8],
+        depth: usize,
+    ) -> Result<JsonValue, CubeError> {
+        if depth > MAX_NESTING_DEPTH {
+            return Err(CubeError::user(format!(
+                "Protobuf message {} exceeds maximum nesting depth of {}",
+                name, MAX_NESTING_DEPTH
+            )));
+        }
+        let message = &self.messages[name];
+        let mut obj = Object::new();
+        let mut reader = WireReader::new(data);
+        while !reader.is_empty() {
+            let tag = reader.varint()?;
+            let value = reader.value(tag & 7)?;
+            let field = match message.fields.iter().find(|f| f.number == tag >> 3) {
+                Some(field) => field,
+                None => continue,
+            };
+            match (&field.field_type, value) {
+                (FieldType::Map(key_type, value_type), WireValue::Bytes(entry)) => {
+                    let (key, value) =
+                        self.decode_map_entry(name, key_type, value_type, entry, depth)?;
+                    match obj.get_mut(&field.name) {
+                        Some(JsonValue::Object(map)) => map.insert(&key, value),
+                        _ => {
+                            let mut map = Object::new();
+                            map.insert(&key, value);
+                            obj.insert(&field.name, JsonValue::Object(map));
+                        }
+                    }
+                }
+                (field_type, value) if field.repeated => {
+                    let values = match value {
+                        WireValue::Bytes(packed) if self.is_packable(name, field_type) => {
+                            self.decode_packed(name, field_type, packed, depth)?
+                        }
+                        value => vec![self.decode_value(name, field_type, value, depth)?],
+                    };
+                    match obj.get_mut(&field.name) {
+                        Some(JsonValue::Array(array)) => array.extend(values),
+                        _ => obj.insert(&field.name, JsonValue::Array(values)),
+                    }
+                }
+                (field_type, value) => {
+                    obj.insert(
+                        &field.name,
+                        self.decode_value(name, field_type, value, depth)?,
+                    );
+                }
+            }
+        }
+        for field in message.fields.iter() {
+            if !field.repeated && !field.has_presence && obj.get(&field.name).is_none() {
+                if let Some(default) = self.default_value(name, &field.field_type) {
+                    obj.insert(&field.name, default);
+                }
+            }
+        }
+        Ok(JsonValue::Object(obj))
+    }
+
+    fn decode_value(
+        &self,
+        scope: &str,
+        field_type: &FieldType,
+        value: WireValue,
+        depth: usize,
+    ) -> Result<JsonValue, CubeError> {
+        Ok(match (field_type, value) {
+            (FieldType::Double, WireValue::Fixed64(v)) => JsonValue::from(f64::from_bits(v)),
+            (FieldType::Float, WireValue::Fixed32(v)) => JsonValue::from(f32::from_bits(v) as f64),
+            (FieldType::Int32, WireValue::Varint(v)) => JsonValue::from(v as i32),
+            (FieldType::Int64, WireValue::Varint(v)) => JsonValue::from(v as i64),
+            (FieldType::UInt32, WireValue::Varint(v)) => JsonValue::from(v as u32),
+            (FieldType::UInt64, WireValue::Varint(v)) => JsonValue::from(v),
+            (FieldType::SInt32, WireValue::Varint(v)) => JsonValue::from(zigzag(v) as i32),
+            (FieldType::SInt64, WireValue::Varint(v)) => JsonValue::from(zigzag(v)),
+            (FieldType::Fixed32, WireValue::Fixed32(v)) => JsonValue::from(v),
+            (FieldType::Fixed64, WireValue::Fixed64(v)) => JsonValue::from(v),
+            (FieldType::SFixed32, WireValue::Fixed32(v)) => JsonValue::from(v as i32),
+            (FieldType::SFixed64, WireValue::Fixed64(v)) => JsonValue::from(v as i64),
+            (FieldType::Bool, WireValue::Varint(v)) => JsonValue::Boolean(v != 0),
+            (FieldType::String, WireValue::Bytes(v)) | (FieldType::Bytes, WireValue::Bytes(v)) => {
+                JsonValue::from(String::from_utf8_lossy(v).to_string())
+            }
+            (FieldType::Named(type_name), value) => match (self.resolve(scope, type_name), value) {
+                (Some(name), WireValue::Varint(v)) if self.enums.contains_key(&name) => {
+                    match self.enums[&name].get(&(v as i64)) {
+                        Some(value_name) => JsonValue::from(value_name.as_str()),
+                        None => JsonValue::from(v as i64),
+                    }
+                }
+                (Some(name), WireValue::Bytes(v)) if self.messages.contains_key(&name) => {
+                    self.decode_message(&name, v, depth + 1)?
+                }
+                (None, WireValue::Bytes(v))
+                    if type_name.trim_start_matches('.') == TIMESTAMP_TYPE =>
+                {
+                    decode_timestamp(v)?
+                }
+                (None, _) => {
+                    return Err(CubeError::user(format!(
+                        "Unknown protobuf type: {}",
+                        type_name
+                    )))
+                }
+                (Some(name), _) => {
+                    return Err(CubeError::user(format!(
+                        "Unexpected protobuf wire type for {} field",
+                        name
+                    )))
+                }
+            },
+            (field_type, _) => {
+                return Err(CubeError::user(format!(
+                    "Unexpected protobuf wire type for {:?} field",
+                    field_type
+                )))
+            }
+        })
+    }
+
+    fn decode_packed(
+        &self,
+        scope: &str,
+        field_type: &FieldType,
+        data: &[u8],
+        depth: usize,
+    ) -> Result<Vec<JsonValue>, CubeError> {
+        let wire_type = match field_type {
+            FieldType::Double | FieldType::Fixed64 | FieldType::SFixed64 => 1,
+            FieldType::Float | FieldType::Fixed32 | FieldType::SFixed32 => 5,
+            _ => 0,
+        };
+        let mut reader = WireReader::new(data);
+        let mut values = Vec::new();
+        while !reader.is_empty() {
+            let value = reader.value(wire_type)?;
+            values.push(self.decode_value(scope, field_type, value, depth)?);
+        }
+        Ok(values)
+    }
+
+    fn decode_map_entry(
+        &self,
+        scope: &str,
+        key_type: &FieldType,
+        value_type: &FieldType,
+        data: &[u8],
+        depth: usize,
+    ) -> Result<(String, JsonValue), CubeError> {
+        let mut key = None;
+        let mut value = None;
+        let mut reader = WireReader::new(data);
+        while !reader.is_empty() {
+            let tag = reader.varint()?;
+            let v = reader.value(tag & 7)?;
+            match tag >> 3 {
+                1 => key = Some(self.decode_value(scope, key_type, v, depth)?),
+                2 => value = Some(self.decode_value(scope, value_type, v, depth)?),
+                _ => {}
+            }
+        }
+        let key = key
+            .or_else(|| self.default_value(scope, key_type))
+            .unwrap_or(JsonValue::Null);
+        let key = key
+            .as_str()
+            .map(|k| k.to_string())
+            .unwrap_or_else(|| key.dump());
+        let value = value
+            .or_else(|| self.default_value(scope, value_type))
+            .unwrap_or(JsonValue::Null);
+        Ok((key, value))
+    }
+
+    /// Value of a proto3 field that isn't present in the message. [None] for messages.
+    fn default_value(&self, scope: &str, field_type: &FieldType) -> Option<JsonValue> {
+        match field_type {
+            FieldType::Double | FieldType::Float => Some(JsonValue::from(0.0)),
+            FieldType::Bool => Some(JsonValue::Boolean(false)),
+            FieldType::String | FieldType::Bytes => Some(JsonValue::from("")),
+            FieldType::Named(type_name) => self
+                .resolve(scope, type_name)
+                .and_then(|name| self.enums.get(&name))
+                .map(|values| match values.get(&0) {
+                    Some(name) => JsonValue::from(name.as_str()),
+                    None => JsonValue::from(0),
+                }),
+            FieldType::Map(..) => None,
+            _ => Some(JsonValue::from(0)),
+        }
+    }
+
+    fn is_packable(&self, scope: &str, field_type: &FieldType) -> bool {
+        match field_type {
+            FieldType::String | FieldType::Bytes | FieldType::Map(..) => false,
+            FieldType::Named(type_name) => self
+                .resolve(scope, type_name)
+                .map_or(false, |name| self.enums.contains_key(&name)),
+            _ => true,
+        }
+    }
+
+    /// Looks up the type name in the scope of the message and then in its enclosing scopes.
+    fn resolve(&self, scope: &str, type_name: &str) -> Option<String> {
+        let exists =
+            |name: &String| self.messages.contains_key(name) || self.enums.contains_key(name);
+        if let Some(name) = type_name.strip_prefix('.') {
+            return Some(name.to_string()).filter(exists);
+        }
+        let mut scope = scope;
+        loop {
+            let candidate = qualify(scope, type_name);
+            if exists(&candidate) {
+                return Some(candidate);
+            }
+            if scope.is_empty() {
+                return None;
+            }
+            scope = scope.rfind('.').map_or("", |i| &scope[..i]);
+        }
+    }
+}
+
+/// Splits message indexes of the Confluent wire format from the message data. Indexes are
+/// prefixed with their count, a single zero stands for the first top level message.
+pub fn split_message_indexes(data: &[u8]) -> Result<(Vec<u64>, &[u8]), CubeError> {
+    let mut reader = WireReader::new(data);
+    let count = zigzag(reader.varint()?);
+    let indexes = if count == 0 {
+        vec![0]
+    } else {
+        (0..count)
+            .map(|_| Ok(zigzag(reader.varint()?) as u64))
+            .collect::<Result<Vec<_>, CubeError>>()?
+    };
+    Ok((indexes, &data[reader.pos..]))
+}
+
+fn decode_timestamp(data: &[u8]) -> Result<JsonValue, CubeError> {
+    let mut seconds = 0;
+    let mut nanos = 0;
+    let mut reader = WireReader::new(data);
+    while !reader.is_empty() {
+        let tag = reader.varint()?;
+        match (tag >> 3, reader.value(tag & 7)?) {
+            (1, WireValue::Varint(v)) => seconds = v as i64,
+            (2, WireValue::Varint(v)) => nanos = v as i32 as u32,
+            _ => {}
+        }
+    }
+    let timestamp = Utc.timestamp_opt(seconds, nanos).single().ok_or_else(|| {
+        CubeError::user(format!(
+            "Invalid protobuf timestamp: {} seconds {} nanos",
+            seconds, nanos
+        ))
+    })?;
+    Ok(JsonValue::from(
+        timestamp.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+    ))
+}
+
+fn zigzag(v: u64) -> i64 {
+    ((v >> 1) as i64) ^ -((v & 1) as i64)
+}
+
+fn qualify(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", scope, name)
+    }
+}
+
+enum WireValue<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+    Fixed32(u32),
+}
+
+struct WireReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> WireReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn varint(&mut self) -> Result<u64, CubeError> {
+        let mut result = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            result |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+        Err(CubeError::user("Malformed protobuf varint".to_string()))
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], CubeError> {
+        if self.data.len() - self.pos < len {
+            return Err(CubeError::user("Truncated protobuf message".to_string()));
+        }
+        let res = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(res)
+    }
+
+    fn value(&mut self, wire_type: u64) -> Result<WireValue<'a>, CubeError> {
+        match wire_type {
+            0 => Ok(WireValue::Varint(self.varint()?)),
+            1 => Ok(WireValue::Fixed64(u64::from_le_bytes(
+                self.take(8)?.try_into().unwrap(),
+            ))),
+            2 => {
+                let len = self.varint()? as usize;
+                Ok(WireValue::Bytes(self.take(len)?))
+            }
+            5 => Ok(WireValue::Fixed32(u32::from_le_bytes(
+                self.take(4)?.try_into().unwrap(),
+            ))),
+            x => Err(CubeError::user(format!(
+                "Unsupported protobuf wire type: {}",
+                x
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Symbol(char),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, CubeError> {
+    let chars = source.chars().collect::<Vec<_>>();
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_' || c == '.';
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+        } else if c == '"' || c == '\'' {
+            let start = i + 1;
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                if chars[i] == '\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            if i >= chars.len() {
+                return Err(CubeError::user(
+                    "Unterminated string in protobuf schema".to_string(),
+                ));
+            }
+            tokens.push(Token::Str(chars[start..i].iter().collect()));
+            i += 1;
+        } else if is_word_char(c) {
+            let start = i;
+            while i < chars.len() && is_word_char(chars[i]) {
+                i += 1;
+            }
+            tokens.push(Token::Word(chars[start..i].iter().collect()));
+        } else {
+            tokens.push(Token::Symbol(c));
+            i += 1;
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    proto3: bool,
+    schema: ProtoSchema,
+}
+
+impl Parser {
+    fn parse_file(&mut self) -> Result<(), CubeError> {
+        let mut package = String::new();
+        while let Some(token) = self.peek().cloned() {
+            match token {
+                Token::Word(w) if w == "syntax" => {
+                    self.next()?;
+                    self.expect('=')?;
+                    self.proto3 = self.next()? == Token::Str("proto3".to_string());
+                    self.expect(';')?;
+                }
+                Token::Word(w) if w == "package" => {
+                    self.next()?;
+                    package = self.word()?;
+                    self.expect(';')?;
+                }
+                Token::Word(w) if w == "message" => {
+                    self.next()?;
+                    let name = self.parse_message(&package)?;
+                    self.schema.messages_order.push(name);
+                }
+                Token::Word(w) if w == "enum" => {
+                    self.next()?;
+                    self.parse_enum(&package)?;
+                }
+                Token::Symbol(';') => {
+                    self.next()?;
+                }
+                _ => self.skip_statement()?,
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_message(&mut self, scope: &str) -> Result<String, CubeError> {
+        let full_name = qualify(scope, &self.word()?);
+        self.expect('{')?;
+        let mut message = Message::default();
+        loop {
+            match self.peek().cloned() {
+                Some(Token::Symbol('}')) => {
+                    self.next()?;
+                    break;
+                }
+                Some(Token::Symbol(';')) => {
+                    self.next()?;
+                }
+                Some(Token::Word(w)) => match w.as_str() {
+                    "message" => {
+                        self.next()?;
+                        let nested = self.parse_message(&full_name)?;
+                        message.nested_messages.push(nested);
+                    }
+                    "enum" => {
+                        self.next()?;
+                        self.parse_enum(&full_name)?;
+                    }
+                    "oneof" => {
+                        self.next()?;
+                        self.word()?;
+                        self.expect('{')?;
+                        loop {
+                            match self.peek().cloned() {
+                                Some(Token::Symbol('}')) => {
+                                    self.next()?;
+                                    break;
+                                }
+                                Some(Token::Symbol(';')) => {
+                                    self.next()?;
+                                }
+                                Some(Token::Word(w)) if w == "option" => self.skip_statement()?,
+                                _ => message.fields.push(self.parse_field(false, true)?),
+                            }
+                        }
+                    }
+                    "option" | "reserved" | "extensions" | "extend" => self.skip_statement()?,
+                    "repeated" => {
+                        self.next()?;
+                        message.fields.push(self.parse_field(true, false)?);
+                    }
+                    "optional" | "required" => {
+                        self.next()?;
+                        message.fields.push(self.parse_field(false, true)?);
+                    }
+                    _ => {
+                        let has_presence = !self.proto3;
+                        message.fields.push(self.parse_field(false, has_presence)?);
+                    }
+                },
+                token => {
+                    return Err(CubeError::user(format!(
+                        "Unexpected {:?} in protobuf message {}",
+                        token, full_name
+                    )))
+                }
+            }
+        }
+        self.schema.messages.insert(full_name.clone(), message);
+        Ok(full_name)
+    }
+
+    fn parse_field(&mut self, repeated: bool, has_presence: bool) -> Result<Field, CubeError> {
+        let type_name = self.word()?;
+        let field_type = if type_name == "map" && self.peek() == Some(&Token::Symbol('<')) {
+            self.expect('<')?;
+            let key_type = FieldType::from_name(&self.word()?);
+            self.expect(',')?;
+            let value_type = FieldType::from_name(&self.word()?);
+            self.expect('>')?;
+            FieldType::Map(Box::new(key_type), Box::new(value_type))
+        } else {
+            FieldType::from_name(&type_name)
+        };
+        let name = self.word()?;
+        self.expect('=')?;
+        let number = self.int()? as u64;
+        self.skip_options()?;
+        self.expect(';')?;
+        Ok(Field {
+            name,
+            number,
+            field_type,
+            repeated,
+            has_presence,
+        })
+    }
+
+    fn parse_enum(&mut self, scope: &str) -> Result<(), CubeError> {
+        let full_name = qualify(scope, &self.word()?);
+        self.expect('{')?;
+        let mut values = HashMap::new();
+        loop {
+            match self.peek().cloned() {
+                Some(Token::Symbol('}')) => {
+                    self.next()?;
+                    break;
+                }
+                Some(Token::Symbol(';')) => {
+                    self.next()?;
+                }
+                Some(Token::Word(w)) if w == "option" || w == "reserved" => {
+                    self.skip_statement()?
+                }
+                _ => {
+                    let name = self.word()?;
+                    self.expect('=')?;
+                    let number = self.int()?;
+                    self.skip_options()?;
+                    self.expect(';')?;
+                    // The first name wins for aliased values.
+                    values.entry(number).or_insert(name);
+                }
+            }
+        }
+        self.schema.enums.insert(full_name, values);
+        Ok(())
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<Token, CubeError> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| CubeError::user("Unexpected end of protobuf schema".to_string()))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), CubeError> {
+        match self.next()? {
+            Token::Symbol(c) if c == symbol => Ok(()),
+            token => Err(CubeError::user(format!(
+                "Expected '{}' but {:?} found in protobuf schema",
+                symbol, token
+            ))),
+        }
+    }
+
+    fn word(&mut self) -> Result<String, CubeError> {
+        match self.next()? {
+            Token::Word(w) => Ok(w),
+            token => Err(CubeError::user(format!(
+                "Expected identifier but {:?} found in protobuf schema",
+                token
+            ))),
+        }
+    }
+
+    fn int(&mut self) -> Result<i64, CubeError> {
+        let negative = self.peek() == Some(&Token::Symbol('-'));
+        if negative {
+            self.next()?;
+        }
+        let word = self.word()?;
+        let value = match word.strip_prefix("0x").or(word.strip_prefix("0X")) {
+            Some(hex) => i64::from_str_radix(hex, 16),
+            None => word.parse::<i64>(),
+        }
+        .map_err(|_| CubeError::user(format!("Bad number {} in protobuf schema", word)))?;
+        Ok(if negative { -value } else { value })
+    }
+
+    fn skip_options(&mut self) -> Result<(), CubeError> {
+        if self.peek() == Some(&Token::Symbol('[')) {
+            while self.next()? != Token::Symbol(']') {}
+        }
+        Ok(())
+    }
+
+    /// Skips tokens up to the end of the statement or the block it opens.
+    fn skip_statement(&mut self) -> Result<(), CubeError> {
+        let mut depth = 0;
+        loop {
+            match self.next()? {
+                Token::Symbol('{') => depth += 1,
+                Token::Symbol('}') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                Token::Symbol(';') if depth == 0 => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// Encodes fields of a message. Varints are given as numbers, everything else as bytes.
+    pub fn encode(fields: Vec<(u64, WireField)>) -> Vec<u8> {
+        let mut res = Vec::new();
+        for (number, value) in fields {
+            match value {
+                WireField::Varint(v) => {
+                    encode_varint(&mut res, number << 3);
+                    encode_varint(&mut res, v);
+                }
+                WireField::Fixed64(v) => {
+                    encode_varint(&mut res, number << 3 | 1);
+                    res.extend_from_slice(&v.to_le_bytes());
+                }
+                WireField::Bytes(v) => {
+                    encode_varint(&mut res, number << 3 | 2);
+                    encode_varint(&mut res, v.len() as u64);
+                    res.extend_from_slice(&v);
+                }
+            }
+        }
+        res
+    }
+
+    pub enum WireField {
+        Varint(u64),
+        Fixed64(u64),
+        Bytes(Vec<u8>),
+    }
+
+    pub fn encode_varint(buf: &mut Vec<u8>, mut v: u64) {
+        while v >= 0x80 {
+            buf.push((v as u8) | 0x80);
+            v >>= 7;
+        }
+        buf.push(v as u8);
+    }
+
+    const SCHEMA: &str = r#"
+        syntax = "proto3";
+        package shop.events;
+
+        import "google/protobuf/timestamp.proto";
+        option java_package = "com.example.shop";
+
+        /* Not referenced by messages below. */
+        message Header {
+            string source = 1;
+        }
+
+        message Order {
+            enum Status {
+                option allow_alias = true;
+                UNKNOWN = 0;
+                CREATED = 1;
+                STARTED = 1;
+                SHIPPED = 2;
+            }
+            message Item {
+                string sku = 1;
+                sint32 delta = 2 [deprecated = true];
+            }
+            int64 id = 1;
+            string customer = 2;
+            Status status = 3;
+            double amount = 4;
+            repeated int32 quantities = 5;
+            Item first_item = 6;
+            map<string, int64> counters = 7;
+            google.protobuf.Timestamp created_at = 8;
+            optional string note = 9;
+            oneof payment {
+                string card = 10;
+                string voucher = 11;
+            }
+            reserved 12, 15 to 20;
+        }
+    "#;
+
+    #[test]
+    fn parse_and_decode() {
+        let schema = ProtoSchema::parse(SCHEMA).unwrap();
+        assert_eq!(
+            schema.messages_order,
+            vec!["shop.events.Header", "shop.events.Order"]
+        );
+        assert_eq!(
+            schema.messages["shop.events.Order"].nested_messages,
+            vec!["shop.events.Order.Item"]
+        );
+
+        let mut quantities = Vec::new();
+        encode_varint(&mut quantities, 3);
+        encode_varint(&mut quantities, 300);
+        let data = encode(vec![
+            (1, WireField::Varint(42)),
+            (2, WireField::Bytes(b"alice".to_vec())),
+            (3, WireField::Varint(2)),
+            (4, WireField::Fixed64(12.5f64.to_bits())),
+            (5, WireField::Bytes(quantities)),
+            (5, WireField::Varint(7)),
+            (
+                6,
+                WireField::Bytes(encode(vec![
+                    (1, WireField::Bytes(b"a-1".to_vec())),
+                    (2, WireField::Varint(3)),
+                ])),
+            ),
+            (
+                7,
+                WireField::Bytes(encode(vec![
+                    (1, WireField::Bytes(b"views".to_vec())),
+                    (2, WireField::Varint(10)),
+                ])),
+            ),
+            (
+                8,
+                WireField::Bytes(encode(vec![(1, WireField::Varint(1640995200))])),
+            ),
+            (11, WireField::Bytes(b"V1".to_vec())),
+            // Unknown fields are skipped.
+            (30, WireField::Varint(1)),
+        ]);
+        let value = schema.decode(&[1], &data).unwrap();
+        let expected = json::parse(
+            r#"{
+                "id": 42,
+                "customer": "alice",
+                "status": "SHIPPED",
+                "amount": 12.5,
+                "quantities": [3, 300, 7],
+                "first_item": { "sku": "a-1", "delta": -2 },
+                "counters": { "views": 10 },
+                "created_at": "2022-01-01T00:00:00Z",
+                "voucher": "V1"
+            }"#,
+        );
+        assert_eq!(value, expected.unwrap());
+
+        // Absent proto3 fields without presence are decoded as defaults.
+        let value = schema.decode(&[1], &[]).unwrap();
+        let expected =
+            json::parse(r#"{ "id": 0, "customer": "", "status": "UNKNOWN", "amount": 0 }"#);
+        assert_eq!(value, expected.unwrap());
+
+        let value = schema.decode(&[1, 0], &encode(vec![(1, WireField::Bytes(b"b".to_vec()))]));
+        let expected = json::parse(r#"{ "sku": "b", "delta": 0 }"#);
+        assert_eq!(value.unwrap(), expected.unwrap());
+        assert!(schema.decode(&[2], &[]).is_err());
+        assert!(schema.decode(&[1], &[0x08]).is_err());
+    }
+
+    #[test]
+    fn self_referencing_message() {
+        let schema = ProtoSchema::parse(
+            r#"
+            syntax = "proto3";
+            message Node {
+                int64 value = 1;
+                Node child = 2;
+            }
+        "#,
+        )
+        .unwrap();
+
+        let nested = |depth: usize| {
+            let mut data = encode(vec![(1, WireField::Varint(depth as u64))]);
+            for i in (0..depth).rev() {
+                data = encode(vec![
+                    (1, WireField::Varint(i as u64)),
+                    (2, WireField::Bytes(data)),
+                ]);
+            }
+            data
+        };
+        let value = schema.decode(&[0], &nested(2)).unwrap();
+        let expected =
+            json::parse(r#"{ "value": 0, "child": { "value": 1, "child": { "value": 2 } } }"#);
+        assert_eq!(value, expected.unwrap());
+
+        assert!(schema.decode(&[0], &nested(MAX_NESTING_DEPTH)).is_ok());
+        assert!(schema.decode(&[0], &nested(MAX_NESTING_DEPTH + 1)).is_err());
+    }
+
+    #[test]
+    fn message_indexes() {
+        let (indexes, rest) = split_message_indexes(&[0, 0x08]).unwrap();
+        assert_eq!(indexes, vec![0]);
+        assert_eq!(rest, &[0x08]);
+        // Count and indexes are zigzag encoded.
+        let (indexes, rest) = split_message_indexes(&[4, 2, 0, 0x08]).unwrap();
+        assert_eq!(indexes, vec![1, 0]);
+        assert_eq!(rest, &[0x08]);
+    }
+}
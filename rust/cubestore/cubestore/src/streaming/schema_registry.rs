@@ -0,0 +1,615 @@
+use crate::metastore::table::StreamValueFormat;
+use crate::streaming::protobuf::{split_message_indexes, ProtoSchema};
+use crate::CubeError;
+use apache_avro::types::Value as AvroValue;
+use apache_avro::Schema as AvroSchema;
+use chrono::{SecondsFormat, TimeZone, Utc};
+use json::object::Object;
+use json::JsonValue;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Delay before a failed schema is requested again. Doubles with every failure.
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+lazy_static! {
+    static ref HTTP_CLIENT: reqwest::Client = reqwest::ClientBuilder::new()
+        .use_rustls_tls()
+        .user_agent("cubestore")
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .unwrap();
+}
+
+/// Converts keys and payloads of Kafka messages into JSON values which are mapped to table
+/// columns by [crate::streaming::parse_json_payload_and_key].
+#[derive(Clone)]
+pub enum MessageDecoder {
+    Json,
+    /// Messages are in the Confluent wire format: a zero magic byte, a 4-byte big-endian schema id
+    /// and the encoded value.
+    SchemaRegistry {
+        format: StreamValueFormat,
+        registry: Arc<SchemaRegistryClient>,
+    },
+}
+
+impl MessageDecoder {
+    pub fn new(
+        format: StreamValueFormat,
+        schema_registry_url: Option<String>,
+    ) -> Result<Self, CubeError> {
+        match (format, schema_registry_url) {
+            (StreamValueFormat::Json, _) => Ok(MessageDecoder::Json),
+            (format, Some(url)) => Ok(MessageDecoder::SchemaRegistry {
+                format,
+                registry: Arc::new(SchemaRegistryClient::new(url)),
+            }),
+            (format, None) => Err(CubeError::user(format!(
+                "schema_registry_url should be set for the source to read {:?} messages",
+                format
+            ))),
+        }
+    }
+
+    /// Fetches schemas of the message which aren't cached yet. Must be called before decoding as
+    /// messages are decoded synchronously.
+    pub async fn load_schemas(
+        &self,
+        key: Option<&[u8]>,
+        payload: Option<&[u8]>,
+    ) -> Result<(), CubeError> {
+        match self {
+            MessageDecoder::Json => Ok(()),
+            MessageDecoder::SchemaRegistry { registry, .. } => {
+                for data in key.into_iter().chain(payload.into_iter()) {
+                    if let Some(id) = schema_id(data) {
+                        registry.load_schema(id).await?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    pub fn decode_payload(&self, payload: &[u8]) -> Result<JsonValue, CubeError> {
+        match self {
+            MessageDecoder::Json => {
+                let payload_str = String::from_utf8_lossy(payload);
+                json::parse(payload_str.as_ref()).map_err(|e| {
+                    CubeError::user(format!("Can't parse '{}' payload: {}", payload_str, e))
+                })
+            }
+            MessageDecoder::SchemaRegistry { format, registry } => {
+                registry.decode(payload, Some(*format))
+            }
+        }
+    }
+
+    /// Keys are decoded by the registry only if they're in the wire format: keys are often
+    /// written by a different serializer than payloads.
+    pub fn decode_key(&self, key: &[u8]) -> Result<JsonValue, CubeError> {
+        match self {
+            MessageDecoder::SchemaRegistry { registry, .. } if is_wire_format(key) => {
+                registry.decode(key, None)
+            }
+            _ => {
+                // Kafka can store additional metadata in suffix that contains information about window size for example
+                // Another use case is streams would usually don't have any keys
+                let key_str = String::from_utf8_lossy(key);
+                if key_str.starts_with('{') {
+                    if let Some(last_brace) = key_str.find('}') {
+                        return json::parse(&key_str.as_ref()[0..last_brace + 1]).map_err(|e| {
+                            CubeError::user(format!("Can't parse '{}' key: {}", key_str, e))
+                        });
+                    }
+                }
+                Ok(JsonValue::Object(Object::new()))
+            }
+        }
+    }
+}
+
+fn is_wire_format(data: &[u8]) -> bool {
+    data.len() >= 5 && data[0] == 0
+}
+
+fn schema_id(data: &[u8]) -> Option<u32> {
+    if is_wire_format(data) {
+        Some(u32::from_be_bytes([data[1], data[2], data[3], data[4]]))
+    } else {
+        None
+    }
+}
+
+enum RegisteredSchema {
+    Avro(AvroSchema),
+    Protobuf(ProtoSchema),
+}
+
+#[derive(Deserialize)]
+struct SchemaResponse {
+    schema: String,
+    /// Absent for Avro schemas.
+    #[serde(rename = "schemaType", default)]
+    schema_type: Option<String>,
+    /// Other schemas imported by this one.
+    #[serde(default)]
+    references: Vec<serde_json::Value>,
+}
+
+struct FailedFetch {
+    error: CubeError,
+    retry_at: Instant,
+    delay: Duration,
+}
+
+/// Fetches schemas by id from a Confluent compatible schema registry. Schemas are immutable, so
+/// they're cached for the lifetime of the client. Failed requests are retried with exponential
+/// backoff, messages of these schemas fail with the last error in the meantime.
+pub struct SchemaRegistryClient {
+    url: String,
+    schemas: RwLock<HashMap<u32, Arc<RegisteredSchema>>>,
+    failed: RwLock<HashMap<u32, FailedFetch>>,
+}
+
+impl SchemaRegistryClient {
+    pub fn new(url: String) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            schemas: RwLock::new(HashMap::new()),
+            failed: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Decodes the message in the wire format. If `format` is set, the schema must be of that type.
+    pub fn decode(
+        &self,
+        data: &[u8],
+        format: Option<StreamValueFormat>,
+    ) -> Result<JsonValue, CubeError> {
+        if !is_wire_format(data) {
+            return Err(CubeError::user(format!(
+                "Message isn't in the schema registry wire format: expected magic byte 0 but {:?} found",
+                data.first()
+            )));
+        }
+        let id = u32::from_be_bytes([data[1], data[2], data[3], data[4]]);
+        let schema = self.cached_schema(id)?;
+        match (schema.as_ref(), format) {
+            (RegisteredSchema::Avro(schema), None)
+            | (RegisteredSchema::Avro(schema), Some(StreamValueFormat::Avro)) => {
+                let value =
+                    apache_avro::from_avro_datum(schema, &mut &data[5..], None).map_err(|e| {
+                        CubeError::user(format!("Can't decode Avro message {}: {}", id, e))
+                    })?;
+                avro_to_json(value)
+            }
+            (RegisteredSchema::Protobuf(schema), None)
+            | (RegisteredSchema::Protobuf(schema), Some(StreamValueFormat::Protobuf)) => {
+                let (message_indexes, data) = split_message_indexes(&data[5..])?;
+                schema.decode(&message_indexes, data)
+            }
+            (_, Some(format)) => Err(CubeError::user(format!(
+                "Schema {} doesn't match {:?} value format",
+                id, format
+            ))),
+        }
+    }
+
+    fn cached_schema(&self, id: u32) -> Result<Arc<RegisteredSchema>, CubeError> {
+        if let Some(schema) = self.schemas.read().unwrap().get(&id) {
+            return Ok(schema.clone());
+        }
+        if let Some(failed) = self.failed.read().unwrap().get(&id) {
+            return Err(failed.error.clone());
+        }
+        Err(CubeError::internal(format!("Schema {} isn't loaded", id)))
+    }
+
+    async fn load_schema(&self, id: u32) -> Result<(), CubeError> {
+        if self.schemas.read().unwrap().contains_key(&id) {
+            return Ok(());
+        }
+        let delay = match self.failed.read().unwrap().get(&id) {
+            Some(failed) if failed.retry_at > Instant::now() => return Err(failed.error.clone()),
+            Some(failed) => (failed.delay * 2).min(MAX_RETRY_DELAY),
+            None => MIN_RETRY_DELAY,
+        };
+        match self.fetch(id).await {
+            Ok(schema) => {
+                self.failed.write().unwrap().remove(&id);
+                self.schemas.write().unwrap().insert(id, Arc::new(schema));
+                Ok(())
+            }
+            Err(error) => {
+                log::error!(
+                    "Can't load schema {} from {}, retrying in {:?}: {}",
+                    id,
+                    self.url,
+                    delay,
+                    error
+                );
+                self.failed.write().unwrap().insert(
+                    id,
+                    FailedFetch {
+                        error: error.clone(),
+                        retry_at: Instant::now() + delay,
+                        delay,
+                    },
+                );
+                Err(error)
+            }
+        }
+    }
+
+    async fn fetch(&self, id: u32) -> Result<RegisteredSchema, CubeError> {
+        let url = format!("{}/schemas/ids/{}", self.url, id);
+        let response = HTTP_CLIENT.get(&url).send().await?;
+        if !response.status().is_success() {
+            return Err(CubeError::user(format!(
+                "Can't fetch schema from {}: {}",
+                url,
+                response.status()
+            )));
+        }
+        parse_schema(id, response.json::<SchemaResponse>().await?)
+    }
+}
+
+fn parse_schema(id: u32, response: SchemaResponse) -> Result<RegisteredSchema, CubeError> {
+    if !response.references.is_empty() {
+        return Err(CubeError::user(format!(
+            "Schema {} references other schemas which isn't supported",
+            id
+        )));
+    }
+    match response.schema_type.as_deref().unwrap_or("AVRO") {
+        "AVRO" => Ok(RegisteredSchema::Avro(
+            AvroSchema::parse_str(&response.schema)
+                .map_err(|e| CubeError::user(format!("Can't parse Avro schema {}: {}", id, e)))?,
+        )),
+        "PROTOBUF" => Ok(RegisteredSchema::Protobuf(ProtoSchema::parse(
+            &response.schema,
+        )?)),
+        x => Err(CubeError::user(format!(
+            "Schema {} has unsupported type {}",
+            id, x
+        ))),
+    }
+}
+
+/// Dates and timestamps are converted to values understood by timestamp columns: numbers of
+/// milliseconds or RFC 3339 strings.
+fn avro_to_json(value: AvroValue) -> Result<JsonValue, CubeError> {
+    Ok(match value {
+        AvroValue::Null => JsonValue::Null,
+        AvroValue::Boolean(v) => JsonValue::Boolean(v),
+        AvroValue::Int(v) | AvroValue::TimeMillis(v) => JsonValue::from(v),
+        AvroValue::Long(v) | AvroValue::TimeMicros(v) => JsonValue::from(v),
+        AvroValue::Float(v) => JsonValue::from(v as f64),
+        AvroValue::Double(v) => JsonValue::from(v),
+        AvroValue::String(v) | AvroValue::Enum(_, v) => JsonValue::from(v),
+        AvroValue::Bytes(v) | AvroValue::Fixed(_, v) => {
+            JsonValue::from(String::from_utf8_lossy(&v).to_string())
+        }
+        AvroValue::Uuid(v) => JsonValue::from(v.to_string()),
+        AvroValue::Date(days) => JsonValue::from(days as i64 * 24 * 60 * 60 * 1000),
+        AvroValue::TimestampMillis(v) => JsonValue::from(v),
+        AvroValue::TimestampMicros(v) => {
+            let timestamp = Utc
+                .timestamp_opt(
+                    v.div_euclid(1_000_000),
+                    (v.rem_euclid(1_000_000) * 1000) as u32,
+                )
+                .single()
+                .ok_or_else(|| CubeError::user(format!("Invalid Avro timestamp: {}", v)))?;
+            JsonValue::from(timestamp.to_rfc3339_opts(SecondsFormat::Micros, true))
+        }
+        AvroValue::Union(_, v) => avro_to_json(*v)?,
+        AvroValue::Array(values) => JsonValue::Array(
+            values
+                .into_iter()
+                .map(avro_to_json)
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        AvroValue::Map(values) => {
+            let mut obj = Object::new();
+            for (k, v) in values {
+                obj.insert(&k, avro_to_json(v)?);
+            }
+            JsonValue::Object(obj)
+        }
+        AvroValue::Record(fields) => {
+            let mut obj = Object::new();
+            for (k, v) in fields {
+                obj.insert(&k, avro_to_json(v)?);
+            }
+            JsonValue::Object(obj)
+        }
+        x => {
+            return Err(CubeError::user(format!(
+                "Avro value {:?} isn't supported",
+                x
+            )))
+        }
+    })
+}
+
+/// Schema registry serving the given schemas by id.
+#[cfg(test)]
+pub mod mock {
+    use std::collections::HashMap;
+    use warp::http::StatusCode;
+    use warp::Filter;
+
+    /// Starts the registry on a random port and returns its url. Schemas are `(id, schema type,
+    /// schema)`. The server runs on its own thread so it can be used by tests on any runtime.
+    pub fn start_schema_registry(schemas: Vec<(u32, &'static str, String)>) -> String {
+        let schemas = schemas
+            .into_iter()
+            .map(|(id, schema_type, schema)| (id, (schema_type, schema)))
+            .collect::<HashMap<_, _>>();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            runtime.block_on(async move {
+                let route = warp::path!("schemas" / "ids" / u32).map(move |id: u32| {
+                    let (body, status) = match schemas.get(&id) {
+                        Some((schema_type, schema)) => (
+                            serde_json::json!({ "schema": schema, "schemaType": schema_type }),
+                            StatusCode::OK,
+                        ),
+                        None => (
+                            serde_json::json!({ "error_code": 40403, "message": "Schema not found" }),
+                            StatusCode::NOT_FOUND,
+                        ),
+                    };
+                    warp::reply::with_status(warp::reply::json(&body), status)
+                });
+                let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+                sender.send(addr).unwrap();
+                server.await
+            })
+        });
+        format!("http://{}", receiver.recv().unwrap())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::metastore::{Column, ColumnType};
+    use crate::streaming::parse_json_payload_and_key;
+    use crate::streaming::protobuf::tests::{encode, WireField};
+    use crate::table::{TableValue, TimestampValue};
+    use crate::util::decimal::Decimal;
+
+    pub const AVRO_SCHEMA: &str = r#"{
+        "type": "record",
+        "name": "Event",
+        "fields": [
+            {"name": "ANONYMOUSID", "type": "string"},
+            {"name": "FILTER_ID", "type": "long"},
+            {"name": "AMOUNT", "type": ["null", "double"]},
+            {"name": "TIMESTAMP", "type": {"type": "long", "logicalType": "timestamp-micros"}}
+        ]
+    }"#;
+
+    const PROTOBUF_SCHEMA: &str = r#"
+        syntax = "proto3";
+        message Key { string id = 1; }
+        message Event {
+            string anonymous_id = 1;
+            int64 filter_id = 2;
+            bool active = 3;
+        }
+    "#;
+
+    /// Avro payload in the wire format.
+    pub fn avro_message(schema_id: u32, value: AvroValue) -> Vec<u8> {
+        let schema = AvroSchema::parse_str(AVRO_SCHEMA).unwrap();
+        let mut res = vec![0];
+        res.extend_from_slice(&schema_id.to_be_bytes());
+        res.extend(apache_avro::to_avro_datum(&schema, value).unwrap());
+        res
+    }
+
+    pub fn avro_event(anonymous_id: &str, filter_id: i64, amount: Option<f64>) -> AvroValue {
+        AvroValue::Record(vec![
+            (
+                "ANONYMOUSID".to_string(),
+                AvroValue::String(anonymous_id.to_string()),
+            ),
+            ("FILTER_ID".to_string(), AvroValue::Long(filter_id)),
+            (
+                "AMOUNT".to_string(),
+                match amount {
+                    Some(amount) => AvroValue::Union(1, Box::new(AvroValue::Double(amount))),
+                    None => AvroValue::Union(0, Box::new(AvroValue::Null)),
+                },
+            ),
+            (
+                "TIMESTAMP".to_string(),
+                AvroValue::TimestampMicros(1_640_995_200_000_001),
+            ),
+        ])
+    }
+
+    fn protobuf_message(schema_id: u32, message_index: u8, data: Vec<u8>) -> Vec<u8> {
+        let mut res = vec![0];
+        res.extend_from_slice(&schema_id.to_be_bytes());
+        // Zigzag encoded count and index of the top level message.
+        res.extend_from_slice(&[2, message_index * 2]);
+        res.extend(data);
+        res
+    }
+
+    fn registry() -> String {
+        mock::start_schema_registry(vec![
+            (1, "AVRO", AVRO_SCHEMA.to_string()),
+            (2, "PROTOBUF", PROTOBUF_SCHEMA.to_string()),
+        ])
+    }
+
+    #[tokio::test]
+    async fn decode_avro() {
+        let decoder =
+            MessageDecoder::new(StreamValueFormat::Avro, Some(format!("{}/", registry()))).unwrap();
+        let message = avro_message(1, avro_event("a", 5, Some(1.5)));
+        assert!(decoder.decode_payload(&message).is_err());
+        decoder.load_schemas(None, Some(&message)).await.unwrap();
+        let columns = vec![
+            Column::new("ANONYMOUSID".to_string(), ColumnType::String, 0),
+            Column::new("FILTER_ID".to_string(), ColumnType::Int, 1),
+            Column::new(
+                "AMOUNT".to_string(),
+                ColumnType::Decimal {
+                    scale: 2,
+                    precision: 18,
+                },
+                2,
+            ),
+            Column::new("TIMESTAMP".to_string(), ColumnType::Timestamp, 3),
+        ];
+        let payload = decoder.decode_payload(&message).unwrap();
+        let values = parse_json_payload_and_key(
+            &columns,
+            &vec![],
+            payload,
+            &JsonValue::Object(Object::new()),
+        )
+        .unwrap();
+        assert_eq!(
+            values,
+            vec![
+                TableValue::String("a".to_string()),
+                TableValue::Int(5),
+                TableValue::Decimal(Decimal::new(150)),
+                TableValue::Timestamp(TimestampValue::new(1_640_995_200_000_001_000)),
+            ]
+        );
+
+        let payload = decoder
+            .decode_payload(&avro_message(1, avro_event("b", 6, None)))
+            .unwrap();
+        assert_eq!(payload["AMOUNT"], JsonValue::Null);
+
+        // Unknown schema.
+        let message = avro_message(3, avro_event("c", 7, None));
+        assert!(decoder.load_schemas(None, Some(&message)).await.is_err());
+        assert!(decoder.decode_payload(&message).is_err());
+        // Protobuf schema for Avro format.
+        let message = protobuf_message(2, 1, vec![]);
+        decoder.load_schemas(None, Some(&message)).await.unwrap();
+        assert!(decoder.decode_payload(&message).is_err());
+        assert!(decoder.decode_payload(b"{\"a\": 1}").is_err());
+    }
+
+    #[tokio::test]
+    async fn decode_protobuf() {
+        let decoder = MessageDecoder::new(StreamValueFormat::Protobuf, Some(registry())).unwrap();
+        let columns = vec![
+            Column::new("id".to_string(), ColumnType::String, 0),
+            Column::new("anonymous_id".to_string(), ColumnType::String, 1),
+            Column::new("filter_id".to_string(), ColumnType::Int, 2),
+            Column::new("active".to_string(), ColumnType::Boolean, 3),
+            Column::new("amount".to_string(), ColumnType::Float, 4),
+        ];
+        let key = protobuf_message(2, 0, encode(vec![(1, WireField::Bytes(b"k1".to_vec()))]));
+        let payload = protobuf_message(
+            2,
+            1,
+            encode(vec![
+                (1, WireField::Bytes(b"a".to_vec())),
+                (2, WireField::Varint(42)),
+            ]),
+        );
+        decoder
+            .load_schemas(Some(&key), Some(&payload))
+            .await
+            .unwrap();
+        let key = decoder.decode_key(&key).unwrap();
+        let payload = decoder.decode_payload(&payload).unwrap();
+        let values =
+            parse_json_payload_and_key(&columns, &vec![columns[0].clone()], payload, &key).unwrap();
+        assert_eq!(
+            values,
+            vec![
+                TableValue::String("k1".to_string()),
+                TableValue::String("a".to_string()),
+                TableValue::Int(42),
+                TableValue::Boolean(false),
+                TableValue::Null,
+            ]
+        );
+
+        // Keys which aren't in the wire format are parsed as JSON.
+        let key = decoder.decode_key(b"{\"id\": \"k2\"}window").unwrap();
+        assert_eq!(key["id"], "k2");
+        let key = decoder.decode_key(b"k3").unwrap();
+        assert_eq!(key, JsonValue::Object(Object::new()));
+    }
+
+    #[tokio::test]
+    async fn retry_failed_schemas_with_backoff() {
+        // Nothing listens on the port.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let registry = SchemaRegistryClient::new(url);
+
+        let error = registry.load_schema(1).await.unwrap_err();
+        let retry_at = registry.failed.read().unwrap()[&1].retry_at;
+        // The registry isn't requested again until the delay passes.
+        assert_eq!(registry.load_schema(1).await.unwrap_err(), error);
+        assert_eq!(registry.cached_schema(1).err().unwrap(), error);
+        assert_eq!(registry.failed.read().unwrap()[&1].retry_at, retry_at);
+
+        registry
+            .failed
+            .write()
+            .unwrap()
+            .get_mut(&1)
+            .unwrap()
+            .retry_at = Instant::now();
+        assert!(registry.load_schema(1).await.is_err());
+        assert_eq!(
+            registry.failed.read().unwrap()[&1].delay,
+            MIN_RETRY_DELAY * 2
+        );
+    }
+
+    #[test]
+    fn reject_schema_references() {
+        let response = serde_json::from_str::<SchemaResponse>(
+            r#"{
+                "schema": "syntax = \"proto3\"; import \"other.proto\"; message A { Other o = 1; }",
+                "schemaType": "PROTOBUF",
+                "references": [{"name": "other.proto", "subject": "other", "version": 1}]
+            }"#,
+        )
+        .unwrap();
+        let error = parse_schema(5, response).err().unwrap();
+        assert!(
+            error.message.contains("references other schemas"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn decoder_without_registry() {
+        assert!(MessageDecoder::new(StreamValueFormat::Avro, None).is_err());
+        let decoder = MessageDecoder::new(StreamValueFormat::Json, None).unwrap();
+        let payload = decoder.decode_payload(b"{\"a\": 1}").unwrap();
+        assert_eq!(payload["a"], 1);
+    }
+}
@@ -239,6 +239,14 @@ pub fn append_value(b: &mut dyn ArrayBuilder, c: &ColumnType, v: &TableValue) {
     match_column_type!(c, append)
 }
 
+pub fn null_array(t: &ColumnType, len: usize) -> ArrayRef {
+    let mut b = create_array_builder(t);
+    for _ in 0..len {
+        append_value(b.as_mut(), t, &TableValue::Null);
+    }
+    b.finish()
+}
+
 pub fn rows_to_columns(cols: &[Column], rows: &[Row]) -> Vec<ArrayRef> {
     let mut builders = create_array_builders(&cols);
     for r in rows {
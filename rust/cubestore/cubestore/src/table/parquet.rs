@@ -1,4 +1,5 @@
 use crate::config::injection::DIService;
+use crate::metastore::table::{ParquetCompression, ParquetEncoding, ParquetOptions};
 use crate::metastore::Index;
 use crate::CubeError;
 use arrow::array::ArrayRef;
@@ -6,7 +7,9 @@ use arrow::datatypes::Schema;
 use arrow::record_batch::RecordBatch;
 use datafusion::physical_plan::parquet::{NoopParquetMetadataCache, ParquetMetadataCache};
 use parquet::arrow::{ArrowReader, ArrowWriter, ParquetFileArrowReader};
-use parquet::file::properties::{WriterProperties, WriterVersion};
+use parquet::basic::{Compression, Encoding};
+use parquet::file::properties::{WriterProperties, WriterPropertiesBuilder, WriterVersion};
+use parquet::schema::types::ColumnPath;
 use std::fs::File;
 use std::sync::Arc;
 
@@ -77,10 +80,13 @@ impl ParquetTableStore {
     }
 
     pub fn writer_props(&self) -> WriterProperties {
-        WriterProperties::builder()
+        let mut builder = WriterProperties::builder()
             .set_max_row_group_size(self.row_group_size)
-            .set_writer_version(WriterVersion::PARQUET_2_0)
-            .build()
+            .set_writer_version(WriterVersion::PARQUET_2_0);
+        if let Some(options) = self.table.parquet_options() {
+            builder = apply_parquet_options(builder, &self.table, options);
+        }
+        builder.build()
     }
 
     pub fn write_data(&self, dest_file: &str, columns: Vec<ArrayRef>) -> Result<(), CubeError> {
@@ -100,11 +106,63 @@ pub fn arrow_schema(i: &Index) -> Schema {
     Schema::new(i.columns().iter().map(|c| c.into()).collect())
 }
 
+fn apply_parquet_options(
+    mut builder: WriterPropertiesBuilder,
+    index: &Index,
+    options: &ParquetOptions,
+) -> WriterPropertiesBuilder {
+    if let Some(compression) = options.compression {
+        builder = builder.set_compression(parquet_compression(compression));
+    }
+    if let Some(dictionary) = options.dictionary {
+        builder = builder.set_dictionary_enabled(dictionary);
+    }
+    for (name, column_options) in options.columns.iter() {
+        // Index may contain only part of the table columns.
+        if !index.columns().iter().any(|c| c.get_name() == name) {
+            continue;
+        }
+        let path = ColumnPath::from(name.as_str());
+        if let Some(compression) = column_options.compression {
+            builder =
+                builder.set_column_compression(path.clone(), parquet_compression(compression));
+        }
+        if let Some(encoding) = column_options.encoding {
+            builder = builder.set_column_encoding(path.clone(), parquet_encoding(encoding));
+        }
+        if let Some(dictionary) = column_options.dictionary {
+            builder = builder.set_column_dictionary_enabled(path, dictionary);
+        }
+    }
+    builder
+}
+
+fn parquet_compression(compression: ParquetCompression) -> Compression {
+    match compression {
+        ParquetCompression::Uncompressed => Compression::UNCOMPRESSED,
+        ParquetCompression::Snappy => Compression::SNAPPY,
+        ParquetCompression::Gzip => Compression::GZIP,
+        ParquetCompression::Brotli => Compression::BROTLI,
+        ParquetCompression::Lz4 => Compression::LZ4,
+        ParquetCompression::Zstd => Compression::ZSTD,
+    }
+}
+
+fn parquet_encoding(encoding: ParquetEncoding) -> Encoding {
+    match encoding {
+        ParquetEncoding::Plain => Encoding::PLAIN,
+        ParquetEncoding::DeltaBinaryPacked => Encoding::DELTA_BINARY_PACKED,
+        ParquetEncoding::DeltaLengthByteArray => Encoding::DELTA_LENGTH_BYTE_ARRAY,
+        ParquetEncoding::DeltaByteArray => Encoding::DELTA_BYTE_ARRAY,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     extern crate test;
 
     use crate::assert_eq_columns;
+    use crate::metastore::table::{ParquetCompression, ParquetEncoding, ParquetOptions};
     use crate::metastore::{Column, ColumnType, Index};
     use crate::store::{compaction, ROW_GROUP_SIZE};
     use crate::table::data::{cmp_row_key_heap, concat_record_batches, rows_to_columns, to_stream};
@@ -117,6 +175,7 @@ mod tests {
     };
     use arrow::record_batch::RecordBatch;
     use itertools::Itertools;
+    use parquet::basic::{Compression, Encoding};
     use parquet::data_type::DataType;
     use parquet::file::reader::FileReader;
     use parquet::file::reader::SerializedFileReader;
@@ -149,6 +208,7 @@ mod tests {
             None,
             None,
             Index::index_type_default(),
+            None,
         )
         .unwrap();
 
@@ -216,6 +276,62 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parquet_options() {
+        let mut options = ParquetOptions {
+            compression: Some(ParquetCompression::Gzip),
+            dictionary: Some(false),
+            columns: Vec::new(),
+        };
+        options.column_mut("str").compression = Some(ParquetCompression::Zstd);
+        options.column_mut("str").encoding = Some(ParquetEncoding::DeltaByteArray);
+        options.column_mut("int").encoding = Some(ParquetEncoding::DeltaBinaryPacked);
+        // Column which is absent in the index is ignored.
+        options.column_mut("other").compression = Some(ParquetCompression::Snappy);
+
+        let index = Index::try_new(
+            "table".to_string(),
+            1,
+            vec![
+                Column::new("str".to_string(), ColumnType::String, 0),
+                Column::new("int".to_string(), ColumnType::Int, 1),
+                Column::new("bool".to_string(), ColumnType::Boolean, 2),
+            ],
+            3,
+            None,
+            None,
+            Index::index_type_default(),
+            Some(options),
+        )
+        .unwrap();
+
+        let dest_file = NamedTempFile::new().unwrap();
+        let store = ParquetTableStore::new(index, ROW_GROUP_SIZE);
+        let data: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from(vec![Some("a"), Some("b"), None])),
+            Arc::new(Int64Array::from(vec![Some(1), None, Some(3)])),
+            Arc::new(BooleanArray::from(vec![Some(true), Some(false), None])),
+        ];
+        store
+            .write_data(dest_file.path().to_str().unwrap(), data)
+            .unwrap();
+
+        let r = SerializedFileReader::new(dest_file.into_file()).unwrap();
+        let metadata = r.metadata();
+        let columns = metadata.row_group(0).columns();
+        assert_eq!(
+            columns.iter().map(|c| c.compression()).collect_vec(),
+            vec![Compression::ZSTD, Compression::GZIP, Compression::GZIP]
+        );
+        assert!(columns[0].encodings().contains(&Encoding::DELTA_BYTE_ARRAY));
+        assert!(columns[1]
+            .encodings()
+            .contains(&Encoding::DELTA_BINARY_PACKED));
+        assert!(columns
+            .iter()
+            .all(|c| !c.encodings().contains(&Encoding::RLE_DICTIONARY)));
+    }
+
     #[tokio::test]
     async fn gutter() {
         let store = ParquetTableStore {
@@ -240,6 +356,7 @@ mod tests {
                 None,
                 None,
                 Index::index_type_default(),
+                None,
             )
             .unwrap(),
             row_group_size: 10,
@@ -361,6 +478,7 @@ mod tests {
                 None,
                 None,
                 Index::index_type_default(),
+                None,
             )
             .unwrap();
             let tmp_file = NamedTempFile::new().unwrap();
@@ -409,6 +527,7 @@ mod tests {
             None,
             None,
             Index::index_type_default(),
+            None,
         )
         .unwrap();
 
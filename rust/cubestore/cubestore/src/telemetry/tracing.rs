@@ -1,6 +1,17 @@
 use crate::config::injection::DIService;
 use crate::CubeError;
+use opentelemetry::global;
+use opentelemetry::sdk::propagation::TraceContextPropagator;
+use opentelemetry::sdk::{trace, Resource};
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
 use std::sync::Arc;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
 
 pub trait TracingHelper: DIService + Send + Sync {
     fn trace_and_span_id(&self) -> Option<(u64, u64)>;
@@ -21,3 +32,209 @@ impl TracingHelperImpl {
 }
 
 crate::di_service!(TracingHelperImpl, [TracingHelper]);
+
+/// Exports spans to the OTLP collector at 'CUBESTORE_OTLP_ENDPOINT' (or the standard
+/// 'OTEL_EXPORTER_OTLP_ENDPOINT'). Does nothing when neither is set.
+/// Spans below 'CUBESTORE_OTLP_TRACES_LEVEL' (info by default) are not exported.
+/// Must be called inside the tokio runtime.
+pub fn init_tracing_telemetry() -> Result<(), CubeError> {
+    let endpoint = match env::var("CUBESTORE_OTLP_ENDPOINT")
+        .or_else(|_| env::var("OTEL_EXPORTER_OTLP_ENDPOINT"))
+    {
+        Ok(e) => e,
+        Err(_) => return Ok(()),
+    };
+    let level = env::var("CUBESTORE_OTLP_TRACES_LEVEL")
+        .unwrap_or("info".to_string())
+        .parse::<LevelFilter>()
+        .map_err(|e| CubeError::user(format!("Invalid CUBESTORE_OTLP_TRACES_LEVEL: {}", e)))?;
+
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    let tracer = otlp_tracer(endpoint)?;
+    let subscriber = tracing_subscriber::registry()
+        .with(level)
+        .with(tracing_opentelemetry::layer().with_tracer(tracer));
+    tracing::subscriber::set_global_default(subscriber)
+        .map_err(|e| CubeError::internal(format!("Can't set tracing subscriber: {}", e)))?;
+    Ok(())
+}
+
+/// Installs the global tracer provider which exports spans in batches over gRPC.
+fn otlp_tracer(endpoint: String) -> Result<trace::Tracer, CubeError> {
+    opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint),
+        )
+        .with_trace_config(trace::config().with_resource(Resource::new(vec![
+            KeyValue::new("service.name", "cubestore"),
+            KeyValue::new("process.pid", std::process::id() as i64),
+        ])))
+        .install_batch(opentelemetry::runtime::Tokio)
+        .map_err(|e| CubeError::internal(format!("Can't start OTLP exporter: {}", e)))
+}
+
+/// Flushes spans which are not exported yet.
+pub fn shutdown_tracing_telemetry() {
+    global::shutdown_tracer_provider();
+}
+
+/// W3C trace context of a span, sent along with requests to other nodes and processes so their
+/// spans end up in the same trace.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TraceContext {
+    headers: HashMap<String, String>,
+}
+
+impl TraceContext {
+    /// Context of the current span. [None] when traces are not exported.
+    pub fn current() -> Option<TraceContext> {
+        let context = tracing::Span::current().context();
+        let mut headers = HashMap::new();
+        global::get_text_map_propagator(|p| p.inject_context(&context, &mut headers));
+        if headers.is_empty() {
+            None
+        } else {
+            Some(TraceContext { headers })
+        }
+    }
+
+    /// Makes `span` a child of the span this context was taken from.
+    pub fn attach(&self, span: &tracing::Span) {
+        let parent = global::get_text_map_propagator(|p| p.extract(&self.headers));
+        span.set_parent(parent);
+    }
+
+    pub fn attach_to(context: &Option<TraceContext>, span: tracing::Span) -> tracing::Span {
+        if let Some(context) = context {
+            context.attach(&span);
+        }
+        span
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::header::{HeaderMap, HeaderValue};
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Request, Response, Server};
+    use opentelemetry::sdk::propagation::TextMapCompositePropagator;
+    use opentelemetry::trace::{TraceContextExt, TracerProvider};
+    use std::sync::Mutex;
+
+    lazy_static::lazy_static! {
+        static ref GLOBAL_TELEMETRY_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+    }
+
+    /// Tests that use the global propagator or tracer provider hold this, so they don't run in
+    /// parallel. Both are reset to the defaults on drop.
+    struct GlobalTelemetry {
+        _lock: tokio::sync::MutexGuard<'static, ()>,
+    }
+
+    impl GlobalTelemetry {
+        fn install(lock: tokio::sync::MutexGuard<'static, ()>) -> Self {
+            global::set_text_map_propagator(TraceContextPropagator::new());
+            Self { _lock: lock }
+        }
+    }
+
+    impl Drop for GlobalTelemetry {
+        fn drop(&mut self) {
+            global::set_text_map_propagator(TextMapCompositePropagator::new(vec![]));
+            global::shutdown_tracer_provider();
+        }
+    }
+
+    #[test]
+    fn propagate_trace_context() {
+        let _telemetry = GlobalTelemetry::install(GLOBAL_TELEMETRY_LOCK.blocking_lock());
+        let provider = trace::TracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test", None)));
+        tracing::subscriber::with_default(subscriber, || {
+            assert_eq!(TraceContext::current(), None);
+
+            let router = tracing::info_span!("router");
+            let context = router.in_scope(|| TraceContext::current()).unwrap();
+            assert!(context.headers.contains_key("traceparent"));
+
+            let worker = TraceContext::attach_to(&Some(context), tracing::info_span!("worker"));
+            let trace_id = |s: &tracing::Span| s.context().span().span_context().trace_id();
+            assert_eq!(trace_id(&worker), trace_id(&router));
+            let unrelated = tracing::info_span!("unrelated");
+            assert_ne!(trace_id(&unrelated), trace_id(&router));
+        });
+    }
+
+    /// Accepts OTLP export requests over gRPC and keeps their bodies.
+    fn start_collector() -> (std::net::SocketAddr, Arc<Mutex<Vec<Vec<u8>>>>) {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = received.clone();
+        let make_service = make_service_fn(move |_| {
+            let requests = requests.clone();
+            async move {
+                Ok::<_, hyper::Error>(service_fn(move |req: Request<Body>| {
+                    let requests = requests.clone();
+                    async move {
+                        let body = hyper::body::to_bytes(req.into_body()).await?;
+                        requests.lock().unwrap().push(body.to_vec());
+                        // Empty ExportTraceServiceResponse in a gRPC frame followed by OK status.
+                        let (mut sender, body) = Body::channel();
+                        tokio::spawn(async move {
+                            sender.send_data(vec![0u8; 5].into()).await?;
+                            let mut trailers = HeaderMap::new();
+                            trailers.insert("grpc-status", HeaderValue::from_static("0"));
+                            sender.send_trailers(trailers).await
+                        });
+                        Ok::<_, hyper::Error>(
+                            Response::builder()
+                                .header("content-type", "application/grpc")
+                                .body(body)
+                                .unwrap(),
+                        )
+                    }
+                }))
+            }
+        });
+        let server = Server::from_tcp(listener)
+            .unwrap()
+            .http2_only(true)
+            .serve(make_service);
+        tokio::spawn(server);
+        (addr, received)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn export_spans_to_collector() {
+        let _telemetry = GlobalTelemetry::install(GLOBAL_TELEMETRY_LOCK.lock().await);
+        let (addr, received) = start_collector();
+        let tracer = otlp_tracer(format!("http://{}", addr)).unwrap();
+        let subscriber =
+            tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(tracer));
+        tracing::subscriber::with_default(subscriber, || {
+            let router = tracing::info_span!("router_select");
+            let context = router.in_scope(|| TraceContext::current());
+            assert!(context.is_some());
+            let worker = TraceContext::attach_to(&context, tracing::info_span!("worker_select"));
+            worker.in_scope(|| tracing::info!("executing on worker"));
+        });
+
+        // Shutdown blocks until the batch is exported.
+        tokio::task::spawn_blocking(shutdown_tracing_telemetry)
+            .await
+            .unwrap();
+
+        let received = received.lock().unwrap().concat();
+        let contains = |s: &str| received.windows(s.len()).any(|w| w == s.as_bytes());
+        assert!(contains("router_select"), "router span is not exported");
+        assert!(contains("worker_select"), "worker span is not exported");
+        assert!(contains("cubestore"), "service name is not exported");
+    }
+}
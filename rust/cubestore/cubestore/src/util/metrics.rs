@@ -14,6 +14,10 @@
 //!
 //! Note that misconfiguration (invalid port, address, etc) can cause metric updates to be silently
 //! ignored. This is by design to avoid interrupting normal operation.
+//!
+//! All updates are also recorded in-process and can be scraped in Prometheus format, see
+//! [crate::util::prometheus].
+use crate::util::prometheus;
 use crate::CubeError;
 use std::net::ToSocketAddrs;
 use std::net::UdpSocket;
@@ -75,6 +79,7 @@ impl Counter {
     }
 
     pub fn add_with_tags(&self, v: i64, tags: Option<&Vec<String>>) {
+        prometheus::record(self.metric.name, self.metric.kind, v, tags);
         if let Some(s) = sink() {
             s.send(&self.metric, v, tags)
         }
@@ -83,6 +88,10 @@ impl Counter {
     pub fn increment(&self) {
         self.add(1)
     }
+
+    pub fn register(&self) {
+        self.metric.register()
+    }
 }
 
 pub struct IntMetric {
@@ -95,17 +104,23 @@ impl IntMetric {
     }
 
     pub fn report_with_tags(&self, v: i64, tags: Option<&Vec<String>>) {
+        prometheus::record(self.metric.name, self.metric.kind, v, tags);
         if let Some(s) = sink() {
             s.send(&self.metric, v, tags)
         }
     }
+
+    pub fn register(&self) {
+        self.metric.register()
+    }
 }
 
 pub type Gauge = IntMetric;
 pub type Histogram = IntMetric;
 pub type Distribution = IntMetric;
 
-enum MetricType {
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum MetricType {
     Counter,
     Gauge,
     Histogram,
@@ -121,6 +136,11 @@ impl Metric {
     const fn new(name: &'static str, kind: MetricType) -> Metric {
         Metric { name, kind }
     }
+
+    /// Makes the metric visible in Prometheus format before its first update.
+    fn register(&self) {
+        prometheus::register(self.name, self.kind)
+    }
 }
 
 struct Sink {
@@ -0,0 +1,273 @@
+//! In-process registry of reported metrics, exposed in Prometheus text format.
+//!
+//! Every update made through [crate::util::metrics] is recorded here in addition to being sent
+//! to statsd. Tags in the `name:value` form become labels. Histograms and distributions share
+//! the same buckets, which cover both durations in milliseconds and row counts.
+use crate::util::metrics::MetricType;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::RwLock;
+
+/// Upper bounds of histogram buckets: 1, 2, 5, 10, 20, 50, ... 5_000_000.
+const BUCKETS: [i64; 21] = [
+    1, 2, 5, 10, 20, 50, 100, 200, 500, 1_000, 2_000, 5_000, 10_000, 20_000, 50_000, 100_000,
+    200_000, 500_000, 1_000_000, 2_000_000, 5_000_000,
+];
+
+type Labels = Vec<(String, String)>;
+
+/// Values of a single series. Counters and gauges use `value`, histograms use `buckets` and
+/// `sum`. All updates are lock-free.
+#[derive(Default)]
+struct Series {
+    value: AtomicI64,
+    /// Non-cumulative counts, the last one is for values above all bounds.
+    buckets: [AtomicU64; BUCKETS.len() + 1],
+    sum: AtomicI64,
+}
+
+impl Series {
+    fn update(&self, kind: MetricType, value: i64) {
+        match kind {
+            MetricType::Counter => {
+                self.value.fetch_add(value, Ordering::Relaxed);
+            }
+            MetricType::Gauge => self.value.store(value, Ordering::Relaxed),
+            MetricType::Histogram | MetricType::Distribution => {
+                let bucket = BUCKETS.iter().position(|b| value <= *b);
+                self.buckets[bucket.unwrap_or(BUCKETS.len())].fetch_add(1, Ordering::Relaxed);
+                self.sum.fetch_add(value, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+struct Family {
+    kind: MetricType,
+    series: RwLock<BTreeMap<Labels, Series>>,
+}
+
+impl Family {
+    fn new(kind: MetricType) -> Family {
+        // The unlabeled series is always present, so the metric renders as 0 before any update.
+        let mut series = BTreeMap::new();
+        series.insert(Labels::new(), Series::default());
+        Family {
+            kind,
+            series: RwLock::new(series),
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref REGISTRY: RwLock<BTreeMap<&'static str, Family>> = RwLock::new(BTreeMap::new());
+}
+
+/// Adds the metric to the registry, so it is rendered before its first update. Metrics that
+/// were not registered are added on their first update.
+pub(crate) fn register(name: &'static str, kind: MetricType) {
+    if REGISTRY.read().unwrap().contains_key(name) {
+        return;
+    }
+    REGISTRY
+        .write()
+        .unwrap()
+        .entry(name)
+        .or_insert_with(|| Family::new(kind));
+}
+
+pub(crate) fn record(name: &'static str, kind: MetricType, value: i64, tags: Option<&Vec<String>>) {
+    let labels = tags.map(|t| parse_labels(t)).unwrap_or_default();
+    // Updates of existing series only take read locks, write locks are needed for new series.
+    {
+        let registry = REGISTRY.read().unwrap();
+        if let Some(family) = registry.get(name) {
+            {
+                let series = family.series.read().unwrap();
+                if let Some(s) = series.get(&labels) {
+                    s.update(kind, value);
+                    return;
+                }
+            }
+            family
+                .series
+                .write()
+                .unwrap()
+                .entry(labels)
+                .or_default()
+                .update(kind, value);
+            return;
+        }
+    }
+    let mut registry = REGISTRY.write().unwrap();
+    let family = registry.entry(name).or_insert_with(|| Family::new(kind));
+    family
+        .series
+        .get_mut()
+        .unwrap()
+        .entry(labels)
+        .or_default()
+        .update(kind, value);
+}
+
+/// Renders all registered metrics in the Prometheus text exposition format.
+pub fn render() -> String {
+    let registry = REGISTRY.read().unwrap();
+    let mut out = String::new();
+    for (name, family) in registry.iter() {
+        let name = metric_name(name);
+        let series = family.series.read().unwrap();
+        match family.kind {
+            MetricType::Counter => {
+                let name = format!("{}_total", name);
+                writeln!(out, "# TYPE {} counter", name).unwrap();
+                for (labels, s) in series.iter() {
+                    let v = s.value.load(Ordering::Relaxed);
+                    writeln!(out, "{}{} {}", name, format_labels(labels, None), v).unwrap();
+                }
+            }
+            MetricType::Gauge => {
+                writeln!(out, "# TYPE {} gauge", name).unwrap();
+                for (labels, s) in series.iter() {
+                    let v = s.value.load(Ordering::Relaxed);
+                    writeln!(out, "{}{} {}", name, format_labels(labels, None), v).unwrap();
+                }
+            }
+            MetricType::Histogram | MetricType::Distribution => {
+                writeln!(out, "# TYPE {} histogram", name).unwrap();
+                for (labels, s) in series.iter() {
+                    let mut cumulative = 0;
+                    for (bound, bucket_count) in BUCKETS.iter().zip(s.buckets.iter()) {
+                        cumulative += bucket_count.load(Ordering::Relaxed);
+                        let le = bound.to_string();
+                        let labels = format_labels(labels, Some(&le));
+                        writeln!(out, "{}_bucket{} {}", name, labels, cumulative).unwrap();
+                    }
+                    let count = cumulative + s.buckets[BUCKETS.len()].load(Ordering::Relaxed);
+                    let labels_inf = format_labels(labels, Some("+Inf"));
+                    writeln!(out, "{}_bucket{} {}", name, labels_inf, count).unwrap();
+                    let labels = format_labels(labels, None);
+                    let sum = s.sum.load(Ordering::Relaxed);
+                    writeln!(out, "{}_sum{} {}", name, labels, sum).unwrap();
+                    writeln!(out, "{}_count{} {}", name, labels, count).unwrap();
+                }
+            }
+        }
+    }
+    out
+}
+
+fn parse_labels(tags: &[String]) -> Labels {
+    let mut labels = tags
+        .iter()
+        .map(|t| match t.split_once(':') {
+            Some((name, value)) => (metric_name(name), value.to_string()),
+            None => (metric_name(t), String::new()),
+        })
+        .collect::<Vec<_>>();
+    labels.sort();
+    labels
+}
+
+/// Prometheus names may only contain ASCII letters, digits, underscores and colons.
+fn metric_name(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == ':' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    name.trim_end_matches('_').to_string()
+}
+
+fn format_labels(labels: &Labels, le: Option<&str>) -> String {
+    let escape = |v: &str| {
+        v.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    };
+    let mut parts = labels
+        .iter()
+        .map(|(name, value)| format!("{}=\"{}\"", name, escape(value)))
+        .collect::<Vec<_>>();
+    if let Some(le) = le {
+        parts.push(format!("le=\"{}\"", le));
+    }
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", parts.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_metrics() {
+        let tags = vec!["command:select".to_string(), "user:a\"b".to_string()];
+        record(
+            "test.prometheus.queries",
+            MetricType::Counter,
+            2,
+            Some(&tags),
+        );
+        record(
+            "test.prometheus.queries",
+            MetricType::Counter,
+            3,
+            Some(&tags),
+        );
+        record("test.prometheus.queue)", MetricType::Gauge, 7, None);
+        record("test.prometheus.queue)", MetricType::Gauge, 5, None);
+        record("test.prometheus.time.ms", MetricType::Histogram, 3, None);
+        record(
+            "test.prometheus.time.ms",
+            MetricType::Histogram,
+            10_000_000,
+            None,
+        );
+
+        let out = render();
+        assert!(out.contains("# TYPE test_prometheus_queries_total counter\n"));
+        assert!(
+            out.contains("test_prometheus_queries_total{command=\"select\",user=\"a\\\"b\"} 5\n")
+        );
+        assert!(out.contains("# TYPE test_prometheus_queue gauge\ntest_prometheus_queue 5\n"));
+        assert!(out.contains("# TYPE test_prometheus_time_ms histogram\n"));
+        assert!(out.contains("test_prometheus_time_ms_bucket{le=\"2\"} 0\n"));
+        assert!(out.contains("test_prometheus_time_ms_bucket{le=\"5\"} 1\n"));
+        assert!(out.contains("test_prometheus_time_ms_bucket{le=\"5000000\"} 1\n"));
+        assert!(out.contains("test_prometheus_time_ms_bucket{le=\"+Inf\"} 2\n"));
+        assert!(out.contains("test_prometheus_time_ms_sum 10000003\n"));
+        assert!(out.contains("test_prometheus_time_ms_count 2\n"));
+    }
+
+    #[test]
+    fn render_registered_metrics() {
+        register("test.prometheus.registered.queries", MetricType::Counter);
+        register("test.prometheus.registered.time", MetricType::Histogram);
+
+        let out = render();
+        assert!(out.contains(
+            "# TYPE test_prometheus_registered_queries_total counter\n\
+             test_prometheus_registered_queries_total 0\n"
+        ));
+        assert!(out.contains("test_prometheus_registered_time_bucket{le=\"+Inf\"} 0\n"));
+        assert!(out.contains("test_prometheus_registered_time_count 0\n"));
+
+        record(
+            "test.prometheus.registered.queries",
+            MetricType::Counter,
+            4,
+            None,
+        );
+        assert!(render().contains("test_prometheus_registered_queries_total 4\n"));
+    }
+}